@@ -1,11 +1,19 @@
+use std::path::Path;
+use std::sync::{OnceLock, RwLock};
 use std::vec;
 
 use cosmic_text::{AttrsOwned, Color, Weight};
 use syntect::easy::HighlightLines;
-use syntect::highlighting::{FontStyle, ThemeSet};
-use syntect::parsing::SyntaxSet;
+use syntect::highlighting::{FontStyle, Theme, ThemeSet};
+use syntect::parsing::{SyntaxReference, SyntaxSet};
 use syntect::util::LinesWithEndings;
 
+mod math;
+mod mermaid;
+
+pub use math::latex_to_unicode;
+pub use mermaid::mermaid_to_text;
+
 pub struct BevyMarkdownTheme {
     pub code_theme: String,
     pub code_default_lang: String,
@@ -16,6 +24,8 @@ pub struct BevyMarkdownTheme {
 #[derive(Clone, Debug, Default)]
 pub struct TextSpanMetadata {
     pub link: Option<String>,
+    /// Byte offset of the task list checkbox mark (`x` or space) in the markdown text.
+    pub task: Option<usize>,
 }
 
 #[inline]
@@ -23,7 +33,7 @@ pub fn default<T: Default>() -> T {
     std::default::Default::default()
 }
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct TextSpan {
     pub text: String,
     pub font_size: Option<f32>,
@@ -45,10 +55,10 @@ enum InlineStyleType {
     Strong = 0x01,
     Emphasis = 0x02,
     StrongEmphasis = 0x03,
-    // StrikeThrough = 0x04,
-    // StrikeBold = 0x05,
-    // StrikeItalic = 0x06,
-    // StrikeBoldItalic = 0x07,
+    StrikeThrough = 0x04,
+    StrikeBold = 0x05,
+    StrikeItalic = 0x06,
+    StrikeBoldItalic = 0x07,
     None = 0x00,
 }
 
@@ -71,10 +81,10 @@ impl InlineStyleType {
             0x01 => InlineStyleType::Strong,
             0x02 => InlineStyleType::Emphasis,
             0x03 => InlineStyleType::StrongEmphasis,
-            // 0x04 => InlineStyleType::StrikeThrough,
-            // 0x05 => InlineStyleType::StrikeBold,
-            // 0x06 => InlineStyleType::StrikeItalic,
-            // 0x07 => InlineStyleType::StrikeBoldItalic,
+            0x04 => InlineStyleType::StrikeThrough,
+            0x05 => InlineStyleType::StrikeBold,
+            0x06 => InlineStyleType::StrikeItalic,
+            0x07 => InlineStyleType::StrikeBoldItalic,
             _ => InlineStyleType::None,
         }
     }
@@ -84,6 +94,199 @@ impl InlineStyleType {
 pub enum BevyMarkdownError {
     Transform { info: String },
     Parsing { info: String },
+    Highlighting { info: String },
+}
+
+/// Used when the code theme set in `BevyMarkdownTheme` doesn't exist.
+pub const DEFAULT_CODE_THEME: &str = "Solarized (light)";
+
+/// Syntax definitions and themes for code blocks, syntect defaults extended with registered ones.
+struct CodeHighlighting {
+    syntax_set: SyntaxSet,
+    theme_set: ThemeSet,
+}
+
+fn code_highlighting() -> &'static RwLock<CodeHighlighting> {
+    static CODE_HIGHLIGHTING: OnceLock<RwLock<CodeHighlighting>> = OnceLock::new();
+    CODE_HIGHLIGHTING.get_or_init(|| {
+        RwLock::new(CodeHighlighting {
+            syntax_set: SyntaxSet::load_defaults_newlines(),
+            theme_set: ThemeSet::load_defaults(),
+        })
+    })
+}
+
+/// Registers every `.sublime-syntax` definition found in `folder`, they are used for code blocks
+/// with matching language name or file extension.
+pub fn add_syntaxes_from_folder(folder: impl AsRef<Path>) -> Result<(), BevyMarkdownError> {
+    let mut highlighting = code_highlighting().write().unwrap();
+    let mut builder = highlighting.syntax_set.clone().into_builder();
+    builder
+        .add_from_folder(folder, true)
+        .map_err(|e| BevyMarkdownError::Highlighting {
+            info: e.to_string(),
+        })?;
+    highlighting.syntax_set = builder.build();
+    Ok(())
+}
+
+/// Registers every `.tmTheme` found in `folder`, theme name is the file name without extension.
+pub fn add_themes_from_folder(folder: impl AsRef<Path>) -> Result<(), BevyMarkdownError> {
+    code_highlighting()
+        .write()
+        .unwrap()
+        .theme_set
+        .add_from_folder(folder)
+        .map_err(|e| BevyMarkdownError::Highlighting {
+            info: e.to_string(),
+        })
+}
+
+/// Names of code themes that can be used as `BevyMarkdownTheme::code_theme`.
+pub fn code_theme_names() -> Vec<String> {
+    code_highlighting()
+        .read()
+        .unwrap()
+        .theme_set
+        .themes
+        .keys()
+        .cloned()
+        .collect()
+}
+
+fn find_syntax<'a>(syntax_set: &'a SyntaxSet, lang: &str) -> Option<&'a SyntaxReference> {
+    syntax_set
+        .find_syntax_by_name(lang)
+        .or_else(|| syntax_set.find_syntax_by_extension(lang))
+        .or_else(|| syntax_set.find_syntax_by_token(lang))
+}
+
+/// Highlighted code block, unknown languages fall back to the default language and then to plain text.
+fn handle_code_block(
+    code: &markdown::mdast::Code,
+    bevy_markdown: &BevyMarkdown,
+    text_spans: &mut Vec<TextSpan>,
+) {
+    let highlighting = code_highlighting().read().unwrap();
+    let syntax_set = &highlighting.syntax_set;
+    let markdown_theme = &bevy_markdown.markdown_theme;
+    let syntax = code
+        .lang
+        .as_deref()
+        .and_then(|lang| find_syntax(syntax_set, lang))
+        .or_else(|| find_syntax(syntax_set, &markdown_theme.code_default_lang))
+        .unwrap_or_else(|| syntax_set.find_syntax_plain_text());
+    let themes = &highlighting.theme_set.themes;
+    let theme: Option<&Theme> = themes
+        .get(&markdown_theme.code_theme)
+        .or_else(|| themes.get(DEFAULT_CODE_THEME));
+    text_spans.push(TextSpan {
+        text: "\n\n".to_string(),
+        ..default()
+    });
+    let mut h = theme.map(|theme| HighlightLines::new(syntax, theme));
+    for line in LinesWithEndings::from(code.value.as_str()) {
+        let ranges = h
+            .as_mut()
+            .and_then(|h| h.highlight_line(line, syntax_set).ok());
+        let Some(ranges) = ranges else {
+            text_spans.push(TextSpan {
+                text: line.to_string(),
+                color: Some(markdown_theme.inline_code),
+                ..default()
+            });
+            continue;
+        };
+        for &(style, text) in ranges.iter() {
+            let mut text_span = TextSpan {
+                text: text.to_string(),
+                ..default()
+            };
+            match style.font_style {
+                FontStyle::BOLD => text_span.weigth = Some(Weight::BOLD),
+                FontStyle::ITALIC => text_span.style = Some(cosmic_text::Style::Italic),
+                FontStyle::UNDERLINE => {
+                    text_span.weigth = Some(Weight::BOLD);
+                    text_span.style = Some(cosmic_text::Style::Italic);
+                }
+                _ => text_span.weigth = Some(Weight::SEMIBOLD),
+            };
+            let color = style.foreground;
+            text_span.color = Some(cosmic_text::Color::rgb(color.r, color.g, color.b));
+            text_spans.push(text_span);
+        }
+    }
+    text_spans.push(TextSpan {
+        text: "\n".to_string(),
+        ..default()
+    });
+}
+
+/// The markdown parser is still in alpha and panics on some malformed lists,
+/// such panics are turned into parsing errors.
+fn parse_markdown(text: &str) -> Result<markdown::mdast::Node, String> {
+    let mut options = markdown::ParseOptions::gfm();
+    options.constructs.math_flow = true;
+    options.constructs.math_text = true;
+    // `$$x$$` only, single dollars are common in text about prices
+    options.math_text_single_dollar = false;
+    // options only hold plain flags, mdx callbacks are not set
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        markdown::to_mdast(text, &options)
+    }))
+    .unwrap_or_else(|_| Err("markdown parser panicked".to_string()))
+}
+
+/// Math is converted to Unicode text, LaTeX that can't be converted is shown as written.
+fn math_span(latex: &str, bevy_markdown: &BevyMarkdown, force_size: Option<f32>) -> TextSpan {
+    match latex_to_unicode(latex) {
+        Some(text) => TextSpan {
+            text,
+            style: Some(cosmic_text::Style::Italic),
+            font_size: force_size,
+            ..default()
+        },
+        None => TextSpan {
+            text: format!("$${}$$", latex),
+            color: Some(bevy_markdown.markdown_theme.inline_code),
+            font_size: force_size,
+            ..default()
+        },
+    }
+}
+
+/// Mermaid diagrams drawn with text, unsupported diagrams are shown as code.
+fn handle_mermaid(
+    code: &markdown::mdast::Code,
+    bevy_markdown: &BevyMarkdown,
+    text_spans: &mut Vec<TextSpan>,
+) {
+    let (text, color) = match mermaid_to_text(&code.value) {
+        Some(lines) => (lines.join("\n"), None),
+        None => (
+            code.value.clone(),
+            Some(bevy_markdown.markdown_theme.inline_code),
+        ),
+    };
+    text_spans.push(TextSpan {
+        text: format!("\n\n{}\n", text),
+        color,
+        ..default()
+    });
+}
+
+/// Nodes without rendering support are shown as they are written in markdown.
+fn source_text_span(node: &markdown::mdast::Node, bevy_markdown: &BevyMarkdown) -> TextSpan {
+    let text = node
+        .position()
+        .and_then(|position| {
+            bevy_markdown
+                .text
+                .get(position.start.offset..position.end.offset)
+        })
+        .map(|text| text.to_string())
+        .unwrap_or_else(|| node.to_string());
+    TextSpan { text, ..default() }
 }
 
 pub fn get_bullet_for_indentation_level(level: u8) -> &'static str {
@@ -97,6 +300,186 @@ pub fn get_bullet_for_indentation_level(level: u8) -> &'static str {
     }
 }
 
+/// cosmic-text can't draw text decorations, so struck through text gets
+/// U+0336 COMBINING LONG STROKE OVERLAY after every character.
+/// Victor Mono doesn't have the glyph, it's taken from a fallback font.
+pub fn strike_through(text: &str) -> String {
+    text.chars().flat_map(|ch| [ch, '\u{0336}']).collect()
+}
+
+/// Number of columns the text takes in a monospace font.
+fn text_width(text: &str) -> usize {
+    text.chars().filter(|ch| *ch != '\u{0336}').count()
+}
+
+/// Splits spans into lines, empty lines at the start and the end are dropped.
+fn split_span_lines(text_spans: Vec<TextSpan>) -> Vec<Vec<TextSpan>> {
+    let mut lines: Vec<Vec<TextSpan>> = vec![vec![]];
+    for span in text_spans {
+        for (i, part) in span.text.split('\n').enumerate() {
+            if i > 0 {
+                lines.push(vec![]);
+            }
+            if !part.is_empty() {
+                lines.last_mut().unwrap().push(TextSpan {
+                    text: part.to_string(),
+                    ..span.clone()
+                });
+            }
+        }
+    }
+    while lines.first().is_some_and(|line| line.is_empty()) {
+        lines.remove(0);
+    }
+    while lines.last().is_some_and(|line| line.is_empty()) {
+        lines.pop();
+    }
+    lines
+}
+
+fn handle_blockquote(
+    quote: &markdown::mdast::BlockQuote,
+    bevy_markdown: &BevyMarkdown,
+    text_spans: &mut Vec<TextSpan>,
+    errors: &mut Vec<BevyMarkdownError>,
+) {
+    let mut quote_spans = vec![];
+    quote.children.iter().for_each(|child| match child {
+        markdown::mdast::Node::List(list) => {
+            let _ = handle_list_recursive(list, bevy_markdown, &mut quote_spans, errors, 0);
+        }
+        child => {
+            let _ = handle_block_styling(child, bevy_markdown, &mut quote_spans, errors);
+            quote_spans.push(TextSpan {
+                text: "\n".to_string(),
+                ..default()
+            });
+        }
+    });
+    text_spans.push(TextSpan {
+        text: "\n".to_string(),
+        ..default()
+    });
+    for line in split_span_lines(quote_spans) {
+        text_spans.push(TextSpan {
+            text: "│ ".to_string(),
+            color: Some(bevy_markdown.markdown_theme.inline_code),
+            ..default()
+        });
+        text_spans.extend(line);
+        text_spans.push(TextSpan {
+            text: "\n".to_string(),
+            ..default()
+        });
+    }
+}
+
+fn table_border(widths: &[usize], left: &str, middle: &str, right: &str) -> TextSpan {
+    let cells: Vec<String> = widths.iter().map(|width| "─".repeat(width + 2)).collect();
+    TextSpan {
+        text: format!("{}{}{}\n", left, cells.join(middle), right),
+        ..default()
+    }
+}
+
+/// Tables are drawn with box-drawing characters, columns line up only with monospace fonts.
+fn handle_table(
+    table: &markdown::mdast::Table,
+    bevy_markdown: &BevyMarkdown,
+    text_spans: &mut Vec<TextSpan>,
+    errors: &mut Vec<BevyMarkdownError>,
+) {
+    let mut rows: Vec<Vec<Vec<TextSpan>>> = vec![];
+    for (row_index, row) in table.children.iter().enumerate() {
+        let markdown::mdast::Node::TableRow(row) = row else {
+            errors.push(BevyMarkdownError::Transform {
+                info: "invalid table children".to_string(),
+            });
+            continue;
+        };
+        let header_style = if row_index == 0 {
+            InlineStyleType::Strong
+        } else {
+            InlineStyleType::None
+        };
+        let mut cells = vec![];
+        for cell in row.children.iter() {
+            let markdown::mdast::Node::TableCell(cell) = cell else {
+                errors.push(BevyMarkdownError::Transform {
+                    info: "invalid table row children".to_string(),
+                });
+                continue;
+            };
+            let mut cell_spans = vec![];
+            cell.children.iter().for_each(|child| {
+                let _ = handle_inline_styling(
+                    child,
+                    bevy_markdown,
+                    &mut cell_spans,
+                    errors,
+                    header_style.clone() as u8,
+                    None,
+                    None,
+                    &None,
+                );
+            });
+            cells.push(cell_spans);
+        }
+        rows.push(cells);
+    }
+
+    let columns = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+    let cell_width = |cell: &Vec<TextSpan>| {
+        cell.iter()
+            .map(|span| text_width(&span.text))
+            .sum::<usize>()
+    };
+    let widths: Vec<usize> = (0..columns)
+        .map(|column| {
+            rows.iter()
+                .filter_map(|row| row.get(column))
+                .map(cell_width)
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    text_spans.push(TextSpan {
+        text: "\n".to_string(),
+        ..default()
+    });
+    text_spans.push(table_border(&widths, "┌", "┬", "┐"));
+    let rows_count = rows.len();
+    for (row_index, mut row) in rows.into_iter().enumerate() {
+        row.resize_with(columns, Vec::new);
+        for (column, cell) in row.into_iter().enumerate() {
+            let padding = widths[column] - cell_width(&cell);
+            let (left, right) = match table.align.get(column) {
+                Some(markdown::mdast::AlignKind::Right) => (padding, 0),
+                Some(markdown::mdast::AlignKind::Center) => (padding / 2, padding - padding / 2),
+                _ => (0, padding),
+            };
+            text_spans.push(TextSpan {
+                text: format!("│ {}", " ".repeat(left)),
+                ..default()
+            });
+            text_spans.extend(cell);
+            text_spans.push(TextSpan {
+                text: format!("{} ", " ".repeat(right)),
+                ..default()
+            });
+        }
+        text_spans.push(TextSpan {
+            text: "│\n".to_string(),
+            ..default()
+        });
+        if row_index == 0 && rows_count > 1 {
+            text_spans.push(table_border(&widths, "├", "┼", "┤"));
+        }
+    }
+    text_spans.push(table_border(&widths, "└", "┴", "┘"));
+}
+
 pub fn handle_block_styling(
     node: &markdown::mdast::Node,
     bevy_markdown: &BevyMarkdown,
@@ -134,16 +517,13 @@ pub fn handle_block_styling(
                         ..default()
                     });
                 }
-                markdown::mdast::Node::Text(text) => {
-                    text_spans.push(TextSpan {
-                        text: text.value.to_string(),
-                        ..default()
-                    });
-                }
-                markdown::mdast::Node::Strong(_)
+                markdown::mdast::Node::Text(_)
+                | markdown::mdast::Node::Strong(_)
                 | markdown::mdast::Node::Emphasis(_)
                 | markdown::mdast::Node::InlineCode(_)
                 | markdown::mdast::Node::Delete(_)
+                | markdown::mdast::Node::Image(_)
+                | markdown::mdast::Node::InlineMath(_)
                 | markdown::mdast::Node::Link(_) => {
                     let _ = handle_inline_styling(
                         child,
@@ -156,18 +536,50 @@ pub fn handle_block_styling(
                         &None,
                     );
                 }
-                node => errors.push(BevyMarkdownError::Transform {
-                    info: format!("{:?} node is not implemented for paragraph", node),
-                }),
+                node => text_spans.push(source_text_span(node, bevy_markdown)),
+            });
+        }
+        markdown::mdast::Node::BlockQuote(quote) => {
+            handle_blockquote(quote, bevy_markdown, text_spans, errors);
+        }
+        markdown::mdast::Node::Table(table) => {
+            handle_table(table, bevy_markdown, text_spans, errors);
+        }
+        markdown::mdast::Node::ThematicBreak(_) => {
+            text_spans.push(TextSpan {
+                text: format!("\n{}\n", "─".repeat(24)),
+                color: Some(bevy_markdown.markdown_theme.inline_code),
+                ..default()
+            });
+        }
+        markdown::mdast::Node::Code(code) if code.lang.as_deref() == Some("mermaid") => {
+            handle_mermaid(code, bevy_markdown, text_spans)
+        }
+        markdown::mdast::Node::Code(code) => handle_code_block(code, bevy_markdown, text_spans),
+        markdown::mdast::Node::Math(math) => {
+            text_spans.push(TextSpan {
+                text: "\n".to_string(),
+                ..default()
+            });
+            text_spans.push(math_span(&math.value, bevy_markdown, None));
+            text_spans.push(TextSpan {
+                text: "\n".to_string(),
+                ..default()
+            });
+        }
+        node => {
+            text_spans.push(source_text_span(node, bevy_markdown));
+            text_spans.push(TextSpan {
+                text: "\n".to_string(),
+                ..default()
             });
         }
-        _ => errors.push(BevyMarkdownError::Transform {
-            info: "nesting is not implemented".to_string(),
-        }),
     }
     Ok(())
 }
 
+// inline nodes never fail, `errors` is kept to match block handlers
+#[allow(clippy::only_used_in_recursion)]
 pub fn handle_inline_styling(
     node: &markdown::mdast::Node,
     bevy_markdown: &BevyMarkdown,
@@ -189,6 +601,7 @@ pub fn handle_inline_styling(
             if let Some(link) = force_data {
                 text_span.metadata = Some(TextSpanMetadata {
                     link: Some(link.clone()),
+                    ..default()
                 })
             }
             text_spans.push(text_span);
@@ -217,35 +630,75 @@ pub fn handle_inline_styling(
                 force_data,
             );
         }),
-        markdown::mdast::Node::Text(text) => {
-            let mut text_span = TextSpan {
-                text: text.value.clone(),
-                font_size: force_size,
-                ..default()
-            };
-            if let Some(color) = force_color {
-                text_span.color = Some(color)
-            }
+        markdown::mdast::Node::Delete(delete) => delete.children.iter().for_each(|child| {
+            let _ = handle_inline_styling(
+                child,
+                bevy_markdown,
+                text_spans,
+                errors,
+                applied_style | InlineStyleType::StrikeThrough as u8,
+                force_color,
+                force_size,
+                force_data,
+            );
+        }),
+        markdown::mdast::Node::InlineMath(math) => {
+            let mut text_span = math_span(&math.value, bevy_markdown, force_size);
             if let Some(link) = force_data {
                 text_span.metadata = Some(TextSpanMetadata {
                     link: Some(link.clone()),
+                    ..default()
                 })
             }
-            match InlineStyleType::from_u8(applied_style) {
-                InlineStyleType::Strong => {
-                    text_span.weigth = Some(Weight::BOLD);
-                }
-                InlineStyleType::Emphasis => {
-                    text_span.style = Some(cosmic_text::Style::Italic);
-                }
-                InlineStyleType::StrongEmphasis => {
-                    text_span.weigth = Some(Weight::BOLD);
-                    text_span.style = Some(cosmic_text::Style::Italic);
-                }
-                _ => {}
-            }
             text_spans.push(text_span);
         }
+        markdown::mdast::Node::Break(_) => {
+            text_spans.push(TextSpan {
+                text: "\n".to_string(),
+                ..default()
+            });
+        }
+        // images can't be drawn inside text, they are shown as links to the image
+        markdown::mdast::Node::Image(image) => {
+            let alt = if image.alt.is_empty() {
+                &image.url
+            } else {
+                &image.alt
+            };
+            text_spans.push(TextSpan {
+                text: format!("[image: {}]", alt),
+                color: Some(bevy_markdown.markdown_theme.link),
+                font_size: force_size,
+                metadata: Some(TextSpanMetadata {
+                    link: Some(image.url.clone()),
+                    ..default()
+                }),
+                ..default()
+            });
+        }
+        // wiki links aren't markdown, they are found in text outside of other links
+        markdown::mdast::Node::Text(text) if force_data.is_none() => {
+            for (part, link) in split_wiki_links(&text.value) {
+                let color = match link {
+                    Some(_) => Some(bevy_markdown.markdown_theme.link),
+                    None => force_color,
+                };
+                text_spans.push(styled_text_span(
+                    part,
+                    applied_style,
+                    color,
+                    force_size,
+                    link,
+                ));
+            }
+        }
+        markdown::mdast::Node::Text(text) => text_spans.push(styled_text_span(
+            text.value.clone(),
+            applied_style,
+            force_color,
+            force_size,
+            force_data.clone(),
+        )),
         markdown::mdast::Node::Link(link) => link.children.iter().for_each(|child| {
             let _ = handle_inline_styling(
                 child,
@@ -258,15 +711,158 @@ pub fn handle_inline_styling(
                 &Some(link.url.clone()),
             );
         }),
-        _ => {
-            errors.push(BevyMarkdownError::Transform {
-                info: "nesting is not implemented".to_string(),
-            });
+        node => {
+            let mut text_span = source_text_span(node, bevy_markdown);
+            text_span.color = force_color;
+            text_span.font_size = force_size;
+            text_spans.push(text_span);
         }
     }
     Ok(())
 }
 
+fn styled_text_span(
+    text: String,
+    applied_style: u8,
+    color: Option<Color>,
+    font_size: Option<f32>,
+    link: Option<String>,
+) -> TextSpan {
+    let mut text_span = TextSpan {
+        text,
+        font_size,
+        color,
+        metadata: link.map(|link| TextSpanMetadata {
+            link: Some(link),
+            ..default()
+        }),
+        ..default()
+    };
+    match InlineStyleType::from_u8(applied_style) {
+        InlineStyleType::Strong | InlineStyleType::StrikeBold => {
+            text_span.weigth = Some(Weight::BOLD);
+        }
+        InlineStyleType::Emphasis | InlineStyleType::StrikeItalic => {
+            text_span.style = Some(cosmic_text::Style::Italic);
+        }
+        InlineStyleType::StrongEmphasis | InlineStyleType::StrikeBoldItalic => {
+            text_span.weigth = Some(Weight::BOLD);
+            text_span.style = Some(cosmic_text::Style::Italic);
+        }
+        InlineStyleType::StrikeThrough | InlineStyleType::None => {}
+    }
+    if applied_style & InlineStyleType::StrikeThrough as u8 != 0 {
+        text_span.text = strike_through(&text_span.text);
+    }
+    text_span
+}
+
+/// Splits text into plain parts and `[[target]]` or `[[target|label]]` wiki links.
+/// Each part comes with its link written as `[[target]]`, links are shown by their label or target.
+fn split_wiki_links(text: &str) -> Vec<(String, Option<String>)> {
+    let mut parts = vec![];
+    let mut plain = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("[[") {
+        let target = rest[start + 2..]
+            .find("]]")
+            .map(|len| &rest[start + 2..start + 2 + len])
+            .filter(|target| !target.trim().is_empty() && !target.contains(['[', '\n']));
+        let Some(target) = target else {
+            plain.push_str(&rest[..start + 2]);
+            rest = &rest[start + 2..];
+            continue;
+        };
+        plain.push_str(&rest[..start]);
+        if !plain.is_empty() {
+            parts.push((std::mem::take(&mut plain), None));
+        }
+        rest = &rest[start + 2 + target.len() + 2..];
+        let (target, label) = target.split_once('|').unwrap_or((target, target));
+        parts.push((
+            label.trim().to_string(),
+            Some(format!("[[{}]]", target.trim())),
+        ));
+    }
+    plain.push_str(rest);
+    if !plain.is_empty() {
+        parts.push((plain, None));
+    }
+    parts
+}
+
+/// Link targets in the markdown text, wiki links are returned as `[[target]]`.
+pub fn find_links(text: &str) -> Vec<String> {
+    fn find(node: &markdown::mdast::Node, links: &mut Vec<String>) {
+        match node {
+            markdown::mdast::Node::Link(link) => links.push(link.url.clone()),
+            markdown::mdast::Node::Text(text) => links.extend(
+                split_wiki_links(&text.value)
+                    .into_iter()
+                    .filter_map(|(_, link)| link),
+            ),
+            node => {
+                for child in node.children().into_iter().flatten() {
+                    find(child, links);
+                }
+            }
+        }
+    }
+    let mut links = vec![];
+    if let Ok(node) = parse_markdown(text) {
+        find(&node, &mut links);
+    }
+    links
+}
+
+/// Finds the checkbox mark of a task list item in the markdown text.
+fn task_checkbox_offset(text: &str, item: &markdown::mdast::ListItem) -> Option<usize> {
+    let start = item.position.as_ref()?.start.offset;
+    let offset = start + text.get(start..)?.find('[')? + 1;
+    text.get(offset + 1..)?.starts_with(']').then_some(offset)
+}
+
+/// Checks or unchecks the task list item with the checkbox mark at `offset`,
+/// `None` if there is no checkbox there.
+pub fn toggle_task(text: &str, offset: usize) -> Option<String> {
+    let mark = match text.get(offset..offset + 1)? {
+        " " => "x",
+        "x" | "X" => " ",
+        _ => return None,
+    };
+    if !text[..offset].ends_with('[') || !text[offset + 1..].starts_with(']') {
+        return None;
+    }
+    Some(format!(
+        "{}{}{}",
+        &text[..offset],
+        mark,
+        &text[offset + 1..]
+    ))
+}
+
+/// Number of checked and all task list items in the markdown text.
+pub fn count_tasks(text: &str) -> (usize, usize) {
+    fn count(node: &markdown::mdast::Node, tasks: &mut (usize, usize)) {
+        if let markdown::mdast::Node::ListItem(markdown::mdast::ListItem {
+            checked: Some(checked),
+            ..
+        }) = node
+        {
+            tasks.0 += *checked as usize;
+            tasks.1 += 1;
+        }
+        for child in node.children().into_iter().flatten() {
+            count(child, tasks);
+        }
+    }
+    let mut tasks = (0, 0);
+    if let Ok(node) = parse_markdown(text) {
+        count(&node, &mut tasks);
+    }
+    tasks
+}
+
 fn handle_list_recursive(
     list: &markdown::mdast::List,
     bevy_markdown: &BevyMarkdown,
@@ -293,9 +889,11 @@ fn handle_list_recursive(
                 }
 
                 let indent_char = if list.ordered {
-                    let index = list_index.unwrap();
+                    let index = list_index.unwrap_or(1);
                     list_index = Some(index + 1);
                     format!(" {}. ", index)
+                } else if item.checked.is_some() {
+                    " ".to_string()
                 } else {
                     get_bullet_for_indentation_level(indentation_level).to_string()
                 };
@@ -305,6 +903,17 @@ fn handle_list_recursive(
                     ..default()
                 });
 
+                if let Some(checked) = item.checked {
+                    text_spans.push(TextSpan {
+                        text: if checked { "[x] " } else { "[ ] " }.to_string(),
+                        metadata: Some(TextSpanMetadata {
+                            task: task_checkbox_offset(&bevy_markdown.text, &item),
+                            ..default()
+                        }),
+                        ..default()
+                    });
+                }
+
                 item.children.into_iter().for_each(|child| match child {
                     markdown::mdast::Node::Paragraph(paragraph) => {
                         paragraph.children.iter().for_each(|child| {
@@ -329,9 +938,9 @@ fn handle_list_recursive(
                             indentation_level + 1,
                         );
                     }
-                    node => errors.push(BevyMarkdownError::Transform {
-                        info: format!("{:?} node is not implemented for list item", node),
-                    }),
+                    child => {
+                        let _ = handle_block_styling(&child, bevy_markdown, text_spans, errors);
+                    }
                 });
 
                 text_spans.push(TextSpan {
@@ -357,10 +966,7 @@ pub struct BevyMarkdownLines {
 pub fn generate_markdown_lines(
     bevy_markdown: BevyMarkdown,
 ) -> Result<BevyMarkdownLines, Vec<BevyMarkdownError>> {
-    let node = markdown::to_mdast(bevy_markdown.text.as_str(), &markdown::ParseOptions::gfm());
-    let ps = SyntaxSet::load_defaults_newlines();
-    let ts = ThemeSet::load_defaults();
-
+    let node = parse_markdown(bevy_markdown.text.as_str());
     match node {
         Ok(node) => {
             let mut text_spans = Vec::new();
@@ -368,77 +974,23 @@ pub fn generate_markdown_lines(
             match node {
                 markdown::mdast::Node::Root(root) => {
                     root.children.iter().for_each(|child| match child {
-                        markdown::mdast::Node::Code(code) => {
-                            let default_lang =
-                                bevy_markdown.markdown_theme.code_default_lang.clone();
-                            let lang = code.lang.as_ref().unwrap_or(&default_lang);
-                            let syntax = [
-                                ps.find_syntax_by_name(lang.as_str()),
-                                ps.find_syntax_by_extension(lang.as_str()),
-                            ]
-                            .iter()
-                            .find(|&o| o.is_some())
-                            .unwrap()
-                            .unwrap();
-                            let mut h = HighlightLines::new(
-                                syntax,
-                                &ts.themes[&bevy_markdown.markdown_theme.code_theme.clone()],
-                            );
-                            text_spans.push(TextSpan {
-                                text: "\n\n".to_string(),
-                                ..default()
-                            });
-                            for line in LinesWithEndings::from(code.value.as_str()) {
-                                let ranges: Vec<(syntect::highlighting::Style, &str)> =
-                                    h.highlight_line(line, &ps).unwrap();
-
-                                for &(style, text) in ranges.iter() {
-                                    let mut text_span = TextSpan {
-                                        text: text.to_string(),
-                                        ..default()
-                                    };
-                                    match style.font_style {
-                                        FontStyle::BOLD => text_span.weigth = Some(Weight::BOLD),
-                                        FontStyle::ITALIC => {
-                                            text_span.style = Some(cosmic_text::Style::Italic)
-                                        }
-                                        FontStyle::UNDERLINE => {
-                                            text_span.weigth = Some(Weight::BOLD);
-                                            text_span.style = Some(cosmic_text::Style::Italic);
-                                        }
-                                        _ => text_span.weigth = Some(Weight::SEMIBOLD),
-                                    };
-                                    let color = style.foreground;
-                                    text_span.color =
-                                        Some(cosmic_text::Color::rgb(color.r, color.g, color.b));
-                                    text_spans.push(text_span);
-                                }
-                            }
-                            text_spans.push(TextSpan {
-                                text: "\n".to_string(),
-                                ..default()
-                            });
-                        }
-                        markdown::mdast::Node::Heading(_) | markdown::mdast::Node::Paragraph(_) => {
-                            let _ = handle_block_styling(
-                                child,
+                        markdown::mdast::Node::List(list) => {
+                            let _ = handle_list_recursive(
+                                list,
                                 &bevy_markdown,
                                 &mut text_spans,
                                 &mut errors,
+                                0,
                             );
                         }
-                        markdown::mdast::Node::List(list) => {
-                            let _ = handle_list_recursive(
-                                list,
+                        child => {
+                            let _ = handle_block_styling(
+                                child,
                                 &bevy_markdown,
                                 &mut text_spans,
                                 &mut errors,
-                                0,
                             );
                         }
-                        node => errors.push(BevyMarkdownError::Transform {
-                            info: format!("{:?} node is not implemented for root", node),
-                        }),
                     });
                 }
                 node => errors.push(BevyMarkdownError::Transform {
@@ -467,7 +1019,7 @@ pub fn generate_markdown_lines(
                     if let Some(metadata) = span.metadata.clone() {
                         spans_meta.push(metadata);
                     } else {
-                        spans_meta.push(TextSpanMetadata { link: None });
+                        spans_meta.push(TextSpanMetadata::default());
                     };
 
                     let mut temp = String::new();
@@ -651,4 +1203,307 @@ hello world
         .to_string();
         test_bevymarkdown(input, "test_render_nested_ordered_list".to_string())
     }
+
+    #[test]
+    pub fn test_render_strikethrough() {
+        let input = "~~gone~~ kept **~~bold gone~~**
+"
+        .to_string();
+        test_bevymarkdown(input, "test_render_strikethrough".to_string())
+    }
+
+    #[test]
+    pub fn test_render_blockquote() {
+        let input = "
+> Quoted *text*
+> on two lines
+>
+> - with a list
+"
+        .to_string();
+        test_bevymarkdown(input, "test_render_blockquote".to_string())
+    }
+
+    #[test]
+    pub fn test_render_table() {
+        let input = "
+| Name | Count | Note |
+| :--- | ----: | :--: |
+| apples | 3 | *fresh* |
+| kiwi | 12 | ok |
+"
+        .to_string();
+        test_bevymarkdown(input, "test_render_table".to_string())
+    }
+
+    #[test]
+    pub fn test_render_task_list() {
+        let input = "
+- [x] done task
+- [ ] open task
+1. [ ] numbered task
+"
+        .to_string();
+        test_bevymarkdown(input, "test_render_task_list".to_string())
+    }
+
+    #[test]
+    pub fn test_render_horizontal_rule() {
+        let input = "above
+
+---
+
+below
+"
+        .to_string();
+        test_bevymarkdown(input, "test_render_horizontal_rule".to_string())
+    }
+
+    #[test]
+    pub fn test_render_image() {
+        let input = "Look ![a cat](https://example.com/cat.png) and ![](cat.png)
+"
+        .to_string();
+        test_bevymarkdown(input, "test_render_image".to_string())
+    }
+
+    #[test]
+    pub fn test_toggle_task() {
+        let text = "- [ ] first\n- [X] second\n- plain [ ]";
+        assert_eq!(count_tasks(text), (1, 2));
+        let toggled = toggle_task(text, 3).unwrap();
+        assert_eq!(toggled, "- [x] first\n- [X] second\n- plain [ ]");
+        assert_eq!(count_tasks(&toggled), (2, 2));
+        assert_eq!(
+            toggle_task(&toggled, 15).unwrap(),
+            "- [x] first\n- [ ] second\n- plain [ ]"
+        );
+        assert_eq!(toggle_task(text, 0), None);
+        assert_eq!(toggle_task(text, 100), None);
+    }
+
+    #[test]
+    pub fn test_task_checkbox_metadata() {
+        let text = "1. [ ] one\n> - [x] quoted\n".to_string();
+        let markdown_lines = generate_markdown_lines(BevyMarkdown {
+            markdown_theme: BevyMarkdownTheme {
+                code_theme: "Solarized (light)".to_string(),
+                code_default_lang: "rs".to_string(),
+                link: Color::rgb(10, 10, 10),
+                inline_code: Color::rgb(100, 100, 100),
+            },
+            text: text.clone(),
+            attrs: AttrsOwned::new(Attrs::new()),
+        })
+        .unwrap();
+        let tasks: Vec<usize> = markdown_lines
+            .span_metadata
+            .iter()
+            .filter_map(|metadata| metadata.task)
+            .collect();
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(&text[tasks[0]..tasks[0] + 1], " ");
+        assert_eq!(&text[tasks[1]..tasks[1] + 1], "x");
+    }
+
+    #[test]
+    pub fn test_render_wiki_links() {
+        let input = "see [[Doc/Tab#note|the note]] and **[[Other doc]]**, not [[ ]] or `[[code]]`"
+            .to_string();
+        test_bevymarkdown(input, "test_render_wiki_links".to_string())
+    }
+
+    #[test]
+    pub fn test_find_links() {
+        let text =
+            "[[Doc/Tab#note|label]] [web](https://example.com) [[#other]]\n\n```\n[[code]]\n```";
+        assert_eq!(
+            find_links(text),
+            vec!["[[Doc/Tab#note]]", "https://example.com", "[[#other]]"]
+        );
+        assert!(find_links("[[unclosed and [[ ]]").is_empty());
+    }
+
+    #[test]
+    pub fn test_render_code_unknown_lang() {
+        let input = "```no-such-lang
+let x = 1;
+```
+> ```
+> quoted code
+> ```
+"
+        .to_string();
+        test_bevymarkdown(input, "test_render_code_unknown_lang".to_string())
+    }
+
+    #[test]
+    pub fn test_render_unsupported_nodes() {
+        let input = "<div>html block</div>
+
+text with <b>inline html</b> and footnote[^1]
+
+[^1]: footnote text
+"
+        .to_string();
+        test_bevymarkdown(input, "test_render_unsupported_nodes".to_string())
+    }
+
+    #[test]
+    pub fn test_render_math() {
+        let input = r"inline $$a^2 + b^2 = c^2$$ math
+
+$$
+\sum_{i=1}^{n} i = \frac{n(n+1)}{2}
+$$
+
+$$
+\begin{matrix} 1 & 0 \end{matrix}
+$$
+"
+        .to_string();
+        test_bevymarkdown(input, "test_render_math".to_string())
+    }
+
+    #[test]
+    pub fn test_render_mermaid() {
+        let input = "```mermaid
+graph TD
+    A[Start] --> B{Ok?}
+    B -->|yes| C(Done)
+```
+
+```mermaid
+pie title Pets
+    \"Dogs\" : 386
+```
+"
+        .to_string();
+        test_bevymarkdown(input, "test_render_mermaid".to_string())
+    }
+
+    #[test]
+    pub fn test_unknown_code_theme_falls_back() {
+        let markdown_lines = generate_markdown_lines(BevyMarkdown {
+            markdown_theme: BevyMarkdownTheme {
+                code_theme: "no such theme".to_string(),
+                code_default_lang: "no such lang".to_string(),
+                link: Color::rgb(10, 10, 10),
+                inline_code: Color::rgb(100, 100, 100),
+            },
+            text: "```\nfn main() {}\n```".to_string(),
+            attrs: AttrsOwned::new(Attrs::new()),
+        });
+        assert!(markdown_lines.is_ok());
+    }
+
+    #[test]
+    pub fn test_add_syntaxes_from_folder() {
+        let folder = std::env::temp_dir().join(format!("bevy_markdown_{}", std::process::id()));
+        std::fs::create_dir_all(&folder).unwrap();
+        std::fs::write(
+            folder.join("velotest.sublime-syntax"),
+            "%YAML 1.2
+---
+name: Velo Test
+file_extensions: [velotest]
+scope: source.velotest
+contexts:
+  main:
+    - match: '\\bkeyword\\b'
+      scope: keyword.control.velotest
+",
+        )
+        .unwrap();
+        let result = add_syntaxes_from_folder(&folder);
+        std::fs::remove_dir_all(&folder).unwrap();
+        assert!(result.is_ok());
+        let highlighting = code_highlighting().read().unwrap();
+        assert!(find_syntax(&highlighting.syntax_set, "velotest").is_some());
+        assert!(find_syntax(&highlighting.syntax_set, "rs").is_some());
+        drop(highlighting);
+        assert!(add_themes_from_folder("/no/such/folder").is_err());
+        assert!(code_theme_names().contains(&DEFAULT_CODE_THEME.to_string()));
+    }
+
+    /// Renders random markdown put together from snippets and random characters, nothing should panic.
+    #[test]
+    pub fn test_fuzz_generate_markdown_lines() {
+        let snippets = [
+            "# ",
+            "## ",
+            "- ",
+            "1. ",
+            "- [ ] ",
+            "- [x] ",
+            "> ",
+            "```",
+            "```rs\n",
+            "```???\n",
+            "`",
+            "*",
+            "**",
+            "_",
+            "~~",
+            "[",
+            "](",
+            ")",
+            "![",
+            "|",
+            "| --- |",
+            "---",
+            "\n",
+            "\n\n",
+            "    ",
+            "\t",
+            "<b>",
+            "</b>",
+            "[^1]",
+            "[^1]: ",
+            "\\",
+            "&amp;",
+            "text",
+            "ünïcödé 🦀",
+            "https://example.com",
+            "  \n",
+        ];
+        let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next = || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed
+        };
+        for _ in 0..2000 {
+            let mut input = String::new();
+            for _ in 0..(next() % 40) {
+                if next() % 5 == 0 {
+                    input.push(char::from_u32((next() % 0x2fff) as u32).unwrap_or('?'));
+                } else {
+                    input.push_str(snippets[(next() % snippets.len() as u64) as usize]);
+                }
+            }
+            let result = generate_markdown_lines(BevyMarkdown {
+                markdown_theme: BevyMarkdownTheme {
+                    code_theme: "Solarized (light)".to_string(),
+                    code_default_lang: "rs".to_string(),
+                    link: Color::rgb(10, 10, 10),
+                    inline_code: Color::rgb(100, 100, 100),
+                },
+                text: input.clone(),
+                attrs: AttrsOwned::new(Attrs::new()),
+            });
+            if let Err(errors) = result {
+                // only panics of the alpha parser itself are expected
+                assert!(
+                    errors
+                        .iter()
+                        .all(|error| matches!(error, BevyMarkdownError::Parsing { .. })),
+                    "failed to render {:?}: {:?}",
+                    input,
+                    errors
+                );
+            }
+        }
+    }
 }
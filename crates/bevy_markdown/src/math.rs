@@ -0,0 +1,364 @@
+//! LaTeX math shown as Unicode text, cosmic-text can't draw anything else inside a note.
+
+fn symbol(command: &str) -> Option<&'static str> {
+    let symbol = match command {
+        "alpha" => "α",
+        "beta" => "β",
+        "gamma" => "γ",
+        "delta" => "δ",
+        "epsilon" => "ϵ",
+        "varepsilon" => "ε",
+        "zeta" => "ζ",
+        "eta" => "η",
+        "theta" => "θ",
+        "vartheta" => "ϑ",
+        "iota" => "ι",
+        "kappa" => "κ",
+        "lambda" => "λ",
+        "mu" => "μ",
+        "nu" => "ν",
+        "xi" => "ξ",
+        "pi" => "π",
+        "rho" => "ρ",
+        "sigma" => "σ",
+        "tau" => "τ",
+        "upsilon" => "υ",
+        "phi" => "ϕ",
+        "varphi" => "φ",
+        "chi" => "χ",
+        "psi" => "ψ",
+        "omega" => "ω",
+        "Gamma" => "Γ",
+        "Delta" => "Δ",
+        "Theta" => "Θ",
+        "Lambda" => "Λ",
+        "Xi" => "Ξ",
+        "Pi" => "Π",
+        "Sigma" => "Σ",
+        "Upsilon" => "Υ",
+        "Phi" => "Φ",
+        "Psi" => "Ψ",
+        "Omega" => "Ω",
+        "sum" => "∑",
+        "prod" => "∏",
+        "int" => "∫",
+        "iint" => "∬",
+        "oint" => "∮",
+        "partial" => "∂",
+        "nabla" => "∇",
+        "infty" => "∞",
+        "pm" => "±",
+        "mp" => "∓",
+        "times" => "×",
+        "cdot" => "·",
+        "div" => "÷",
+        "le" | "leq" => "≤",
+        "ge" | "geq" => "≥",
+        "ne" | "neq" => "≠",
+        "approx" => "≈",
+        "equiv" => "≡",
+        "sim" => "∼",
+        "propto" => "∝",
+        "in" => "∈",
+        "notin" => "∉",
+        "subset" => "⊂",
+        "subseteq" => "⊆",
+        "supset" => "⊃",
+        "supseteq" => "⊇",
+        "cup" => "∪",
+        "cap" => "∩",
+        "emptyset" | "varnothing" => "∅",
+        "forall" => "∀",
+        "exists" => "∃",
+        "neg" | "lnot" => "¬",
+        "land" | "wedge" => "∧",
+        "lor" | "vee" => "∨",
+        "to" | "rightarrow" => "→",
+        "leftarrow" | "gets" => "←",
+        "leftrightarrow" => "↔",
+        "Rightarrow" | "implies" => "⇒",
+        "Leftarrow" => "⇐",
+        "Leftrightarrow" | "iff" => "⇔",
+        "mapsto" => "↦",
+        "ldots" | "dots" | "cdots" => "…",
+        "circ" => "∘",
+        "angle" => "∠",
+        "perp" => "⊥",
+        "parallel" => "∥",
+        "hbar" => "ℏ",
+        "ell" => "ℓ",
+        "Re" => "ℜ",
+        "Im" => "ℑ",
+        "aleph" => "ℵ",
+        "langle" => "⟨",
+        "rangle" => "⟩",
+        "lfloor" => "⌊",
+        "rfloor" => "⌋",
+        "lceil" => "⌈",
+        "rceil" => "⌉",
+        "{" => "{",
+        "}" => "}",
+        "|" => "‖",
+        "%" => "%",
+        "$" => "$",
+        "&" => "&",
+        "#" => "#",
+        "_" => "_",
+        "\\" => "\n",
+        "," | ":" | ";" | " " | "quad" => " ",
+        "qquad" => "  ",
+        "!" | "left" | "right" | "displaystyle" | "limits" => "",
+        "sin" => "sin",
+        "cos" => "cos",
+        "tan" => "tan",
+        "cot" => "cot",
+        "sec" => "sec",
+        "csc" => "csc",
+        "arcsin" => "arcsin",
+        "arccos" => "arccos",
+        "arctan" => "arctan",
+        "sinh" => "sinh",
+        "cosh" => "cosh",
+        "tanh" => "tanh",
+        "log" => "log",
+        "ln" => "ln",
+        "lg" => "lg",
+        "exp" => "exp",
+        "lim" => "lim",
+        "max" => "max",
+        "min" => "min",
+        "sup" => "sup",
+        "inf" => "inf",
+        "det" => "det",
+        "gcd" => "gcd",
+        "deg" => "deg",
+        "mod" | "bmod" => "mod",
+        _ => return None,
+    };
+    Some(symbol)
+}
+
+fn superscript(ch: char) -> Option<char> {
+    let script = match ch {
+        '0' => '⁰',
+        '1' => '¹',
+        '2' => '²',
+        '3' => '³',
+        '4' => '⁴',
+        '5' => '⁵',
+        '6' => '⁶',
+        '7' => '⁷',
+        '8' => '⁸',
+        '9' => '⁹',
+        '+' => '⁺',
+        '-' | '−' => '⁻',
+        '=' => '⁼',
+        '(' => '⁽',
+        ')' => '⁾',
+        'n' => 'ⁿ',
+        'i' => 'ⁱ',
+        _ => return None,
+    };
+    Some(script)
+}
+
+fn subscript(ch: char) -> Option<char> {
+    let script = match ch {
+        '0' => '₀',
+        '1' => '₁',
+        '2' => '₂',
+        '3' => '₃',
+        '4' => '₄',
+        '5' => '₅',
+        '6' => '₆',
+        '7' => '₇',
+        '8' => '₈',
+        '9' => '₉',
+        '+' => '₊',
+        '-' | '−' => '₋',
+        '=' => '₌',
+        '(' => '₍',
+        ')' => '₎',
+        'a' => 'ₐ',
+        'e' => 'ₑ',
+        'h' => 'ₕ',
+        'i' => 'ᵢ',
+        'j' => 'ⱼ',
+        'k' => 'ₖ',
+        'l' => 'ₗ',
+        'm' => 'ₘ',
+        'n' => 'ₙ',
+        'o' => 'ₒ',
+        'p' => 'ₚ',
+        'r' => 'ᵣ',
+        's' => 'ₛ',
+        't' => 'ₜ',
+        'u' => 'ᵤ',
+        'v' => 'ᵥ',
+        'x' => 'ₓ',
+        _ => return None,
+    };
+    Some(script)
+}
+
+/// Wraps text longer than one character in parentheses, e.g. for fractions.
+fn group(text: String) -> String {
+    if text.chars().count() > 1 {
+        format!("({})", text)
+    } else {
+        text
+    }
+}
+
+fn script(text: String, mark: char, convert: fn(char) -> Option<char>) -> String {
+    match text.chars().map(convert).collect::<Option<String>>() {
+        Some(script) if !text.is_empty() => script,
+        _ => format!("{}{}", mark, group(text)),
+    }
+}
+
+struct Parser<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+}
+
+impl<'a> Parser<'a> {
+    fn command(&mut self) -> Option<String> {
+        let mut name = String::new();
+        while let Some(ch) = self.chars.peek().filter(|ch| ch.is_ascii_alphabetic()) {
+            name.push(*ch);
+            self.chars.next();
+        }
+        if name.is_empty() {
+            name.push(self.chars.next()?);
+        }
+        Some(name)
+    }
+
+    /// Group in braces or a single token.
+    fn argument(&mut self) -> Option<String> {
+        while self.chars.peek() == Some(&' ') {
+            self.chars.next();
+        }
+        match self.chars.next()? {
+            '{' => self.parse(Some('}')),
+            '\\' => {
+                let command = self.command()?;
+                self.apply(&command)
+            }
+            '}' => None,
+            ch => Some(ch.to_string()),
+        }
+    }
+
+    fn optional_argument(&mut self) -> Option<Option<String>> {
+        if self.chars.peek() != Some(&'[') {
+            return Some(None);
+        }
+        self.chars.next();
+        self.parse(Some(']')).map(Some)
+    }
+
+    fn apply(&mut self, command: &str) -> Option<String> {
+        let text = match command {
+            "frac" | "dfrac" | "tfrac" => {
+                let numerator = self.argument()?;
+                let denominator = self.argument()?;
+                format!("{}/{}", group(numerator), group(denominator))
+            }
+            "sqrt" => {
+                let root = match self.optional_argument()?.as_deref() {
+                    None | Some("2") => "√".to_string(),
+                    Some("3") => "∛".to_string(),
+                    Some("4") => "∜".to_string(),
+                    Some(n) => format!("{}√", script(n.to_string(), '^', superscript)),
+                };
+                format!("{}{}", root, group(self.argument()?))
+            }
+            "text" | "mathrm" | "mathbf" | "mathit" | "mathsf" | "mathtt" | "mathcal"
+            | "mathbb" | "operatorname" | "textbf" | "textit" | "boldsymbol" => self.argument()?,
+            "overline" | "bar" => format!("{}\u{0305}", self.argument()?),
+            "hat" => format!("{}\u{0302}", self.argument()?),
+            "vec" => format!("{}\u{20d7}", self.argument()?),
+            "dot" => format!("{}\u{0307}", self.argument()?),
+            "tilde" => format!("{}\u{0303}", self.argument()?),
+            command => symbol(command)?.to_string(),
+        };
+        Some(text)
+    }
+
+    fn parse(&mut self, end: Option<char>) -> Option<String> {
+        let mut text = String::new();
+        while let Some(ch) = self.chars.next() {
+            match ch {
+                ch if Some(ch) == end => return Some(text),
+                '{' => text.push_str(&self.parse(Some('}'))?),
+                '}' => return None,
+                '\\' => {
+                    let command = self.command()?;
+                    text.push_str(&self.apply(&command)?);
+                }
+                '^' => text.push_str(&script(self.argument()?, '^', superscript)),
+                '_' => text.push_str(&script(self.argument()?, '_', subscript)),
+                '&' => text.push(' '),
+                '-' => text.push('−'),
+                '*' => text.push('∗'),
+                '\'' => text.push('′'),
+                ch if ch.is_whitespace() => {
+                    if !text.ends_with([' ', '\n']) && !text.is_empty() {
+                        text.push(' ');
+                    }
+                }
+                ch => text.push(ch),
+            }
+        }
+        end.is_none().then_some(text)
+    }
+}
+
+/// Converts LaTeX math to Unicode text, `None` if it uses commands or environments that can't be shown as text.
+pub fn latex_to_unicode(latex: &str) -> Option<String> {
+    let mut parser = Parser {
+        chars: latex.trim().chars().peekable(),
+    };
+    let text = parser.parse(None)?;
+    Some(
+        text.lines()
+            .map(|line| line.trim())
+            .collect::<Vec<_>>()
+            .join("\n"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latex_to_unicode() {
+        assert_eq!(latex_to_unicode("E = mc^2").as_deref(), Some("E = mc²"));
+        assert_eq!(
+            latex_to_unicode(r"\sum_{i=1}^{n} x_i \le \infty").as_deref(),
+            Some("∑ᵢ₌₁ⁿ xᵢ ≤ ∞")
+        );
+        assert_eq!(
+            latex_to_unicode(r"\frac{a+b}{2} \cdot \sqrt{x} + \sqrt[3]{y}").as_deref(),
+            Some("(a+b)/2 · √x + ∛y")
+        );
+        assert_eq!(
+            latex_to_unicode(r"x^{2y} - \alpha_{max}").as_deref(),
+            Some("x^(2y) − αₘₐₓ")
+        );
+        assert_eq!(
+            latex_to_unicode(r"\text{if } x \in \mathbb{R} \\ y \to 0").as_deref(),
+            Some("if x ∈ R\ny → 0")
+        );
+    }
+
+    #[test]
+    fn test_latex_to_unicode_unsupported() {
+        assert_eq!(latex_to_unicode(r"\begin{matrix} 1 \end{matrix}"), None);
+        assert_eq!(latex_to_unicode(r"\frac{1}"), None);
+        assert_eq!(latex_to_unicode(r"x^{2"), None);
+        assert_eq!(latex_to_unicode(r"x}"), None);
+    }
+}
@@ -0,0 +1,426 @@
+//! Simple mermaid flowcharts and sequence diagrams drawn with text.
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum LinkStyle {
+    Solid,
+    Dotted,
+    Thick,
+}
+
+impl LinkStyle {
+    fn line(&self) -> &'static str {
+        match self {
+            LinkStyle::Solid => "──",
+            LinkStyle::Dotted => "┄┄",
+            LinkStyle::Thick => "══",
+        }
+    }
+
+    fn vertical(&self) -> &'static str {
+        match self {
+            LinkStyle::Solid => "│",
+            LinkStyle::Dotted => "┆",
+            LinkStyle::Thick => "║",
+        }
+    }
+}
+
+struct Link {
+    label: Option<String>,
+    arrow: bool,
+    style: LinkStyle,
+}
+
+struct Edge {
+    from: String,
+    to: String,
+    link: Link,
+}
+
+/// Node id and its label drawn in a shape, e.g. `[Start]` or `{Ok?}` for decisions.
+fn parse_node(text: &str) -> Option<(String, String)> {
+    let text = text.trim();
+    let id_end = text
+        .find(|ch: char| !(ch.is_alphanumeric() || ch == '_'))
+        .unwrap_or(text.len());
+    let (id, shape) = text.split_at(id_end);
+    if id.is_empty() {
+        return None;
+    }
+    let shape = shape.trim();
+    if shape.is_empty() {
+        return Some((id.to_string(), format!("[{}]", id)));
+    }
+    let open = shape.chars().next()?;
+    let label = shape
+        .trim_start_matches(['[', '(', '{', '>', '/', '\\'])
+        .trim_end_matches([']', ')', '}', '/', '\\']);
+    if label.len() == shape.len() || !shape.ends_with([']', ')', '}']) {
+        return None;
+    }
+    let label = label.trim().trim_matches('"');
+    let drawn = match open {
+        '{' => format!("{{{}}}", label),
+        '(' => format!("({})", label),
+        _ => format!("[{}]", label),
+    };
+    Some((id.to_string(), drawn))
+}
+
+/// Link operator starting at `start`, returns the link and where the next node starts.
+fn parse_link(statement: &str, start: usize) -> Option<(Link, usize)> {
+    let rest = &statement[start..];
+    let op_len = rest
+        .find(|ch: char| !matches!(ch, '-' | '=' | '.'))
+        .unwrap_or(rest.len());
+    let op = &rest[..op_len];
+    if op_len < 2 || !(op.starts_with("--") || op.starts_with("==") || op.starts_with("-.")) {
+        return None;
+    }
+    let style = if op.contains('.') {
+        LinkStyle::Dotted
+    } else if op.contains('=') {
+        LinkStyle::Thick
+    } else {
+        LinkStyle::Solid
+    };
+    let mut end = start + op_len;
+    let mut arrow = false;
+    if statement[end..].starts_with(['>', 'x', 'o']) {
+        arrow = statement[end..].starts_with('>');
+        end += 1;
+    }
+    let mut label = None;
+    if !arrow && !statement[end..].starts_with(['|', ' ']) {
+        return None;
+    }
+    if !arrow && op_len == 2 {
+        // `A -- label --> B`
+        let label_end = statement[end..].find(op)?;
+        label = Some(statement[end..end + label_end].trim().to_string());
+        let (link, next) = parse_link(statement, end + label_end)?;
+        return Some((Link { label, ..link }, next));
+    }
+    if statement[end..].trim_start().starts_with('|') {
+        let label_start = end + statement[end..].find('|')? + 1;
+        let label_end = label_start + statement[label_start..].find('|')?;
+        label = Some(statement[label_start..label_end].trim().to_string());
+        end = label_end + 1;
+    }
+    Some((
+        Link {
+            label,
+            arrow,
+            style,
+        },
+        end,
+    ))
+}
+
+/// Node ids with their labels and the links between consecutive nodes.
+type Chain = (Vec<(String, String)>, Vec<Link>);
+
+/// Splits a statement like `A[Start] -->|go| B --> C` into nodes and links between them.
+fn parse_chain(statement: &str) -> Option<Chain> {
+    let (mut nodes, mut links) = (vec![], vec![]);
+    let mut node_start = 0;
+    let mut depth = 0;
+    let mut quoted = false;
+    let mut i = 0;
+    while i < statement.len() {
+        let ch = statement[i..].chars().next()?;
+        match ch {
+            '"' => quoted = !quoted,
+            '[' | '(' | '{' if !quoted => depth += 1,
+            ']' | ')' | '}' if !quoted => depth -= 1,
+            '-' | '=' if !quoted && depth == 0 => {
+                if let Some((link, next)) = parse_link(statement, i) {
+                    nodes.push(parse_node(&statement[node_start..i])?);
+                    links.push(link);
+                    node_start = next;
+                    i = next;
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        i += ch.len_utf8();
+    }
+    nodes.push(parse_node(&statement[node_start..])?);
+    Some((nodes, links))
+}
+
+fn link_text(link: &Link) -> String {
+    let line = link.style.line();
+    let head = if link.arrow { "▶" } else { "" };
+    match &link.label {
+        Some(label) => format!(" {}{}{}{} ", line, label, line, head),
+        None => format!(" {}{} ", line, head),
+    }
+}
+
+fn flowchart_to_text(direction: &str, statements: &[&str]) -> Option<Vec<String>> {
+    let mut nodes: Vec<(String, String)> = vec![];
+    let mut edges: Vec<Edge> = vec![];
+    for statement in statements {
+        let first_word = statement.split_whitespace().next().unwrap_or_default();
+        if matches!(
+            first_word,
+            "classDef"
+                | "class"
+                | "style"
+                | "linkStyle"
+                | "click"
+                | "subgraph"
+                | "end"
+                | "direction"
+        ) {
+            continue;
+        }
+        let (chain, links) = parse_chain(statement)?;
+        for (id, drawn) in chain.iter() {
+            match nodes.iter_mut().find(|(node_id, _)| node_id == id) {
+                // plain reference doesn't replace the shape given before
+                Some(node) if *drawn != format!("[{}]", id) => node.1 = drawn.clone(),
+                Some(_) => {}
+                None => nodes.push((id.clone(), drawn.clone())),
+            }
+        }
+        for (i, link) in links.into_iter().enumerate() {
+            edges.push(Edge {
+                from: chain[i].0.clone(),
+                to: chain[i + 1].0.clone(),
+                link,
+            });
+        }
+    }
+    let drawn = |id: &str| {
+        nodes
+            .iter()
+            .find(|(node_id, _)| node_id == id)
+            .map(|(_, drawn)| drawn.clone())
+            .unwrap_or_default()
+    };
+    let is_chain = !edges.is_empty()
+        && edges.len() + 1 == nodes.len()
+        && edges.windows(2).all(|pair| pair[0].to == pair[1].from)
+        && nodes.iter().all(|(id, _)| {
+            edges.iter().filter(|edge| edge.from == *id).count() <= 1
+                && edges.iter().filter(|edge| edge.to == *id).count() <= 1
+        });
+    let vertical = matches!(direction, "TD" | "TB" | "BT");
+    let mut lines = vec![];
+    if is_chain && vertical {
+        lines.push(drawn(&edges[0].from));
+        for edge in edges.iter() {
+            let line = edge.link.style.vertical();
+            match &edge.link.label {
+                Some(label) => lines.push(format!("  {} {}", line, label)),
+                None => lines.push(format!("  {}", line)),
+            }
+            lines.push(if edge.link.arrow { "  ▼" } else { "  │" }.to_string());
+            lines.push(drawn(&edge.to));
+        }
+    } else if is_chain {
+        let mut line = drawn(&edges[0].from);
+        for edge in edges.iter() {
+            line.push_str(&link_text(&edge.link));
+            line.push_str(&drawn(&edge.to));
+        }
+        lines.push(line);
+    } else {
+        for edge in edges.iter() {
+            lines.push(format!(
+                "{}{}{}",
+                drawn(&edge.from),
+                link_text(&edge.link),
+                drawn(&edge.to)
+            ));
+        }
+        for (id, drawn) in nodes.iter() {
+            if !edges.iter().any(|edge| edge.from == *id || edge.to == *id) {
+                lines.push(drawn.clone());
+            }
+        }
+    }
+    Some(lines)
+}
+
+fn sequence_to_text(statements: &[&str]) -> Option<Vec<String>> {
+    const ARROWS: [(&str, &str); 8] = [
+        ("-->>", "╌╌▶"),
+        ("->>", "──▶"),
+        ("--x", "╌╌x"),
+        ("-x", "──x"),
+        ("--)", "╌╌▶"),
+        ("-)", "──▶"),
+        ("-->", "╌╌"),
+        ("->", "──"),
+    ];
+    let mut aliases: Vec<(String, String)> = vec![];
+    let name = |aliases: &Vec<(String, String)>, id: &str| {
+        let id = id.trim().trim_start_matches(['+', '-']).trim();
+        aliases
+            .iter()
+            .find(|(alias_id, _)| alias_id == id)
+            .map(|(_, name)| name.clone())
+            .unwrap_or_else(|| id.to_string())
+    };
+    let mut lines = vec![];
+    let mut indent = 0;
+    for statement in statements {
+        let (first_word, rest) = statement
+            .split_once(char::is_whitespace)
+            .map(|(word, rest)| (word, rest.trim()))
+            .unwrap_or((statement, ""));
+        match first_word {
+            "participant" | "actor" => {
+                let (id, alias) = rest.split_once(" as ").unwrap_or((rest, rest));
+                aliases.push((id.trim().to_string(), alias.trim().to_string()));
+            }
+            "loop" | "alt" | "opt" | "par" | "critical" | "break" | "rect" => {
+                lines.push(format!("{}┌ {} {}", "  ".repeat(indent), first_word, rest));
+                indent += 1;
+            }
+            "else" | "and" | "option" => {
+                lines.push(format!(
+                    "{}├ {} {}",
+                    "  ".repeat(indent.saturating_sub(1)),
+                    first_word,
+                    rest
+                ));
+            }
+            "end" => {
+                indent = indent.checked_sub(1)?;
+                lines.push(format!("{}└", "  ".repeat(indent)));
+            }
+            "autonumber" | "activate" | "deactivate" | "title" => {}
+            "Note" | "note" => {
+                let (position, text) = rest.split_once(':')?;
+                lines.push(format!(
+                    "{}note {}: {}",
+                    "  ".repeat(indent),
+                    position.trim(),
+                    text.trim()
+                ));
+            }
+            _ => {
+                let (message, text) = statement.split_once(':').unwrap_or((statement, ""));
+                let (start, arrow, drawn) = ARROWS
+                    .iter()
+                    .filter_map(|(arrow, drawn)| {
+                        message.find(arrow).map(|start| (start, *arrow, *drawn))
+                    })
+                    .min_by_key(|(start, arrow, _)| (*start, usize::MAX - arrow.len()))?;
+                let from = name(&aliases, &message[..start]);
+                let to = name(&aliases, &message[start + arrow.len()..]);
+                if from.is_empty() || to.is_empty() {
+                    return None;
+                }
+                let text = text.trim();
+                let separator = if text.is_empty() { "" } else { ": " };
+                lines.push(format!(
+                    "{}{} {} {}{}{}",
+                    "  ".repeat(indent),
+                    from,
+                    drawn,
+                    to,
+                    separator,
+                    text
+                ));
+            }
+        }
+    }
+    Some(lines)
+}
+
+/// Lines of text drawing the diagram, `None` for diagram types or syntax that isn't supported.
+pub fn mermaid_to_text(source: &str) -> Option<Vec<String>> {
+    let statements: Vec<&str> = source
+        .lines()
+        .flat_map(|line| line.split(';'))
+        .map(|statement| statement.trim())
+        .filter(|statement| !statement.is_empty() && !statement.starts_with("%%"))
+        .collect();
+    let (header, statements) = statements.split_first()?;
+    let mut header = header.split_whitespace();
+    let lines = match header.next()? {
+        "graph" | "flowchart" => flowchart_to_text(header.next().unwrap_or("TD"), statements)?,
+        "sequenceDiagram" => sequence_to_text(statements)?,
+        _ => return None,
+    };
+    (!lines.is_empty()).then_some(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flowchart_chain() {
+        assert_eq!(
+            mermaid_to_text("graph LR\nA[Start] -->|go| B(Work) --> C{Done?}"),
+            Some(vec!["[Start] ──go──▶ (Work) ──▶ {Done?}".to_string()])
+        );
+        assert_eq!(
+            mermaid_to_text("flowchart TD\n  A --> B\n  B -.-> C[End]"),
+            Some(
+                ["[A]", "  │", "  ▼", "[B]", "  ┆", "  ▼", "[End]"]
+                    .map(String::from)
+                    .to_vec()
+            )
+        );
+    }
+
+    #[test]
+    fn test_flowchart_graph() {
+        assert_eq!(
+            mermaid_to_text(
+                "graph TD; A{Ok?} -- yes --> B; A -- no --> C; C == retry ==> A; D[Alone]"
+            ),
+            Some(
+                [
+                    "{Ok?} ──yes──▶ [B]",
+                    "{Ok?} ──no──▶ [C]",
+                    "[C] ══retry══▶ {Ok?}",
+                    "[Alone]"
+                ]
+                .map(String::from)
+                .to_vec()
+            )
+        );
+    }
+
+    #[test]
+    fn test_sequence_diagram() {
+        let source = "sequenceDiagram
+    participant A as Alice
+    A->>Bob: Hello
+    loop Every minute
+        Bob-->>A: Hi
+    end
+    Note over A,Bob: done";
+        assert_eq!(
+            mermaid_to_text(source),
+            Some(
+                [
+                    "Alice ──▶ Bob: Hello",
+                    "┌ loop Every minute",
+                    "  Bob ╌╌▶ Alice: Hi",
+                    "└",
+                    "note over A,Bob: done"
+                ]
+                .map(String::from)
+                .to_vec()
+            )
+        );
+    }
+
+    #[test]
+    fn test_unsupported_diagrams() {
+        assert_eq!(mermaid_to_text("pie title Pets\n\"Dogs\" : 386"), None);
+        assert_eq!(mermaid_to_text("graph LR\nA[Start --> B"), None);
+        assert_eq!(mermaid_to_text("sequenceDiagram\nend"), None);
+        assert_eq!(mermaid_to_text(""), None);
+    }
+}
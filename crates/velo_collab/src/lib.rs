@@ -0,0 +1,664 @@
+//! Conflict-free replicated state of a velo tab and the messages exchanged through the relay.
+//!
+//! Every element (node, arrow, drawing) is a map of last-writer-wins fields. Writes are ordered by
+//! Lamport stamps with the peer id as tie breaker, so replicas applying the same operations in any
+//! order end up with the same tab. The text of a note is a sequence of characters (RGA), so
+//! concurrent edits of the same note are merged instead of overwriting each other.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// String field of an element merged character by character.
+pub const TEXT_FIELD: &str = "text";
+
+/// Lamport timestamp of a write.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Stamp {
+    pub counter: u64,
+    pub peer: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ElementKind {
+    Node,
+    Arrow,
+    Drawing,
+    /// Image of an image note, replicated apart from the note so moving it doesn't resend the image
+    Image,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "op", rename_all = "kebab-case")]
+pub enum Op {
+    /// Sets one field of an element, creating the element when it is unknown
+    Set {
+        id: String,
+        kind: ElementKind,
+        field: String,
+        value: Value,
+        stamp: Stamp,
+    },
+    /// Removes the element, a newer write to any of its fields brings it back
+    Remove { id: String, stamp: Stamp },
+    /// Inserts characters into a text field after the character `after`, or at its start. The
+    /// characters are stamped with consecutive counters starting at `stamp`.
+    InsertText {
+        id: String,
+        field: String,
+        after: Option<Stamp>,
+        text: String,
+        stamp: Stamp,
+    },
+    /// Removes characters of a text field
+    DeleteText {
+        id: String,
+        field: String,
+        chars: Vec<Stamp>,
+        stamp: Stamp,
+    },
+}
+
+impl Op {
+    /// Newest stamp written by the operation.
+    pub fn stamp(&self) -> Stamp {
+        match self {
+            Op::Set { stamp, .. } | Op::Remove { stamp, .. } | Op::DeleteText { stamp, .. } => {
+                *stamp
+            }
+            Op::InsertText { text, stamp, .. } => Stamp {
+                counter: stamp.counter + text.chars().count().saturating_sub(1) as u64,
+                peer: stamp.peer,
+            },
+        }
+    }
+
+    /// Id of the changed element.
+    pub fn id(&self) -> &str {
+        match self {
+            Op::Set { id, .. }
+            | Op::Remove { id, .. }
+            | Op::InsertText { id, .. }
+            | Op::DeleteText { id, .. } => id,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Message {
+    /// First message of a peer after connecting
+    Hello {
+        peer: u64,
+        name: String,
+    },
+    /// Everything the room has seen so far, sent by the relay in reply to `Hello`
+    Welcome {
+        ops: Vec<Op>,
+    },
+    Ops {
+        peer: u64,
+        ops: Vec<Op>,
+    },
+    /// Cursor position on the canvas and selected note of a peer
+    Presence {
+        peer: u64,
+        name: String,
+        cursor: Option<(f32, f32)>,
+        selected: Option<String>,
+    },
+    /// Sent by the relay when a peer disconnects
+    Leave {
+        peer: u64,
+    },
+}
+
+#[derive(Clone, Debug)]
+struct Char {
+    id: Stamp,
+    after: Option<Stamp>,
+    value: char,
+    removed: bool,
+}
+
+/// Characters of a text field in document order, removed ones are kept as anchors of later inserts.
+#[derive(Clone, Debug, Default)]
+struct Text {
+    chars: Vec<Char>,
+    newest: Option<Stamp>,
+}
+
+impl Text {
+    fn position(&self, id: Stamp) -> Option<usize> {
+        self.chars.iter().position(|c| c.id == id)
+    }
+
+    /// Inserts a run of characters, returns whether any of them was new.
+    fn insert(&mut self, after: Option<Stamp>, stamp: Stamp, text: &str) -> bool {
+        let mut index = match after {
+            Some(after) => match self.position(after) {
+                Some(index) => index + 1,
+                None => return false,
+            },
+            None => 0,
+        };
+        let mut after = after;
+        let mut changed = false;
+        for (i, value) in text.chars().enumerate() {
+            let id = Stamp {
+                counter: stamp.counter + i as u64,
+                peer: stamp.peer,
+            };
+            if let Some(existing) = self.position(id) {
+                index = existing + 1;
+                after = Some(id);
+                continue;
+            }
+            // concurrent inserts at the same place are ordered by descending stamp, characters
+            // inserted after a newer one always have a newer stamp so they are skipped as well
+            while index < self.chars.len() && self.chars[index].id > id {
+                index += 1;
+            }
+            self.chars.insert(
+                index,
+                Char {
+                    id,
+                    after,
+                    value,
+                    removed: false,
+                },
+            );
+            index += 1;
+            after = Some(id);
+            changed = true;
+        }
+        changed
+    }
+
+    fn delete(&mut self, ids: &[Stamp]) -> bool {
+        let mut changed = false;
+        for c in self.chars.iter_mut() {
+            if !c.removed && ids.contains(&c.id) {
+                c.removed = true;
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    fn live(&self) -> impl Iterator<Item = &Char> {
+        self.chars.iter().filter(|c| !c.removed)
+    }
+
+    fn value(&self) -> String {
+        self.live().map(|c| c.value).collect()
+    }
+
+    /// Insertions recreating the characters in document order, which is also a causal order since
+    /// a character always follows the one it was inserted after.
+    fn runs(&self) -> Vec<(Option<Stamp>, Stamp, String)> {
+        let mut runs: Vec<(Option<Stamp>, Stamp, String)> = vec![];
+        let mut previous: Option<Stamp> = None;
+        for c in &self.chars {
+            let continues = previous.is_some_and(|previous| {
+                c.after == Some(previous)
+                    && c.id.peer == previous.peer
+                    && c.id.counter == previous.counter + 1
+            });
+            match runs.last_mut() {
+                Some((_, _, text)) if continues => text.push(c.value),
+                _ => runs.push((c.after, c.id, c.value.to_string())),
+            }
+            previous = Some(c.id);
+        }
+        runs
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Element {
+    kind: ElementKind,
+    fields: BTreeMap<String, (Stamp, Value)>,
+    texts: BTreeMap<String, Text>,
+    removed: Option<Stamp>,
+}
+
+impl Element {
+    fn new(kind: ElementKind) -> Self {
+        Element {
+            kind,
+            fields: BTreeMap::new(),
+            texts: BTreeMap::new(),
+            removed: None,
+        }
+    }
+
+    fn is_alive(&self) -> bool {
+        let newest = self
+            .fields
+            .values()
+            .map(|(stamp, _)| *stamp)
+            .chain(self.texts.values().filter_map(|text| text.newest))
+            .max();
+        match (newest, self.removed) {
+            (Some(newest), Some(removed)) => newest > removed,
+            (newest, None) => newest.is_some(),
+            (None, Some(_)) => false,
+        }
+    }
+
+    fn value(&self) -> Map<String, Value> {
+        let mut value: Map<String, Value> = self
+            .fields
+            .iter()
+            .map(|(field, (_, value))| (field.clone(), value.clone()))
+            .collect();
+        for (field, text) in &self.texts {
+            value.insert(field.clone(), Value::String(text.value()));
+        }
+        value
+    }
+}
+
+/// Replicated contents of one tab.
+#[derive(Clone, Debug)]
+pub struct TabCrdt {
+    peer: u64,
+    clock: u64,
+    elements: BTreeMap<String, Element>,
+}
+
+impl TabCrdt {
+    pub fn new(peer: u64) -> Self {
+        TabCrdt {
+            peer,
+            clock: 0,
+            elements: BTreeMap::new(),
+        }
+    }
+
+    fn next_stamp(&mut self) -> Stamp {
+        self.clock += 1;
+        Stamp {
+            counter: self.clock,
+            peer: self.peer,
+        }
+    }
+
+    /// Applies a local or remote operation, returns whether it changed the element.
+    pub fn apply(&mut self, op: &Op) -> bool {
+        self.clock = self.clock.max(op.stamp().counter);
+        match op {
+            Op::Set {
+                id,
+                kind,
+                field,
+                value,
+                stamp,
+            } => {
+                let element = self
+                    .elements
+                    .entry(id.clone())
+                    .or_insert(Element::new(*kind));
+                // the element may have been created by a removal that arrived first
+                if element.fields.is_empty() {
+                    element.kind = *kind;
+                }
+                match element.fields.get(field) {
+                    Some((current, _)) if current >= stamp => return false,
+                    _ => element
+                        .fields
+                        .insert(field.clone(), (*stamp, value.clone())),
+                };
+            }
+            Op::Remove { id, stamp } => {
+                let element = self
+                    .elements
+                    .entry(id.clone())
+                    .or_insert(Element::new(ElementKind::Node));
+                if element.removed.is_some_and(|removed| removed >= *stamp) {
+                    return false;
+                }
+                element.removed = Some(*stamp);
+            }
+            Op::InsertText {
+                id,
+                field,
+                after,
+                text,
+                stamp,
+            } => {
+                // only notes have text
+                let element = self
+                    .elements
+                    .entry(id.clone())
+                    .or_insert(Element::new(ElementKind::Node));
+                let content = element.texts.entry(field.clone()).or_default();
+                if !content.insert(*after, *stamp, text) {
+                    return false;
+                }
+                content.newest = content.newest.max(Some(op.stamp()));
+            }
+            Op::DeleteText {
+                id,
+                field,
+                chars,
+                stamp,
+            } => {
+                let Some(content) = self
+                    .elements
+                    .get_mut(id)
+                    .and_then(|element| element.texts.get_mut(field))
+                else {
+                    return false;
+                };
+                if !content.delete(chars) {
+                    return false;
+                }
+                content.newest = content.newest.max(Some(*stamp));
+            }
+        }
+        true
+    }
+
+    /// Current value of an element, `None` when it doesn't exist or was removed.
+    pub fn get(&self, id: &str) -> Option<(ElementKind, Map<String, Value>)> {
+        let element = self.elements.get(id).filter(|element| element.is_alive())?;
+        Some((element.kind, element.value()))
+    }
+
+    pub fn elements(&self) -> impl Iterator<Item = (&str, ElementKind, Map<String, Value>)> {
+        self.elements
+            .iter()
+            .filter(|(_, element)| element.is_alive())
+            .map(|(id, element)| (id.as_str(), element.kind, element.value()))
+    }
+
+    /// Operations setting the fields of an element that differ from `fields`, they are already applied.
+    pub fn update(&mut self, id: &str, kind: ElementKind, fields: &Map<String, Value>) -> Vec<Op> {
+        let current = self.get(id).map(|(_, fields)| fields).unwrap_or_default();
+        let mut ops = vec![];
+        for (field, value) in fields {
+            if current.get(field) == Some(value) {
+                continue;
+            }
+            match value {
+                Value::String(text) if field == TEXT_FIELD => {
+                    ops.extend(self.edit_text(id, kind, field, text))
+                }
+                _ => {
+                    let op = Op::Set {
+                        id: id.to_string(),
+                        kind,
+                        field: field.clone(),
+                        value: value.clone(),
+                        stamp: self.next_stamp(),
+                    };
+                    self.apply(&op);
+                    ops.push(op);
+                }
+            }
+        }
+        ops
+    }
+
+    /// Operations replacing the part of a text field that differs from `text`, they are already applied.
+    fn edit_text(&mut self, id: &str, kind: ElementKind, field: &str, text: &str) -> Vec<Op> {
+        let live: Vec<(Stamp, char)> = self
+            .elements
+            .entry(id.to_string())
+            .or_insert(Element::new(kind))
+            .texts
+            .entry(field.to_string())
+            .or_default()
+            .live()
+            .map(|c| (c.id, c.value))
+            .collect();
+        let new: Vec<char> = text.chars().collect();
+        let prefix = live
+            .iter()
+            .zip(&new)
+            .take_while(|((_, old), new)| old == *new)
+            .count();
+        let suffix = live[prefix..]
+            .iter()
+            .rev()
+            .zip(new[prefix..].iter().rev())
+            .take_while(|((_, old), new)| old == *new)
+            .count();
+        let mut ops = vec![];
+        let deleted: Vec<Stamp> = live[prefix..live.len() - suffix]
+            .iter()
+            .map(|(id, _)| *id)
+            .collect();
+        if !deleted.is_empty() {
+            ops.push(Op::DeleteText {
+                id: id.to_string(),
+                field: field.to_string(),
+                chars: deleted,
+                stamp: self.next_stamp(),
+            });
+        }
+        let inserted: String = new[prefix..new.len() - suffix].iter().collect();
+        if !inserted.is_empty() {
+            ops.push(Op::InsertText {
+                id: id.to_string(),
+                field: field.to_string(),
+                after: prefix.checked_sub(1).map(|i| live[i].0),
+                text: inserted,
+                stamp: self.next_stamp(),
+            });
+        }
+        for op in &ops {
+            self.apply(op);
+        }
+        ops
+    }
+
+    /// Removes an existing element, the operation is already applied.
+    pub fn remove(&mut self, id: &str) -> Option<Op> {
+        self.get(id)?;
+        let op = Op::Remove {
+            id: id.to_string(),
+            stamp: self.next_stamp(),
+        };
+        self.apply(&op);
+        Some(op)
+    }
+
+    /// Operations turning the replicated tab into `local`, they are already applied.
+    pub fn diff(&mut self, local: &BTreeMap<String, (ElementKind, Map<String, Value>)>) -> Vec<Op> {
+        let mut ops = vec![];
+        for (id, (kind, fields)) in local {
+            ops.extend(self.update(id, *kind, fields));
+        }
+        let removed: Vec<String> = self
+            .elements()
+            .map(|(id, ..)| id.to_string())
+            .filter(|id| !local.contains_key(id))
+            .collect();
+        for id in removed {
+            ops.extend(self.remove(&id));
+        }
+        ops
+    }
+
+    /// Operations recreating the whole state on another replica.
+    pub fn ops(&self) -> Vec<Op> {
+        let mut ops = vec![];
+        for (id, element) in &self.elements {
+            for (field, (stamp, value)) in &element.fields {
+                ops.push(Op::Set {
+                    id: id.clone(),
+                    kind: element.kind,
+                    field: field.clone(),
+                    value: value.clone(),
+                    stamp: *stamp,
+                });
+            }
+            for (field, text) in &element.texts {
+                for (after, stamp, run) in text.runs() {
+                    ops.push(Op::InsertText {
+                        id: id.clone(),
+                        field: field.clone(),
+                        after,
+                        text: run,
+                        stamp,
+                    });
+                }
+                let deleted: Vec<Stamp> = text
+                    .chars
+                    .iter()
+                    .filter(|c| c.removed)
+                    .map(|c| c.id)
+                    .collect();
+                if let (false, Some(stamp)) = (deleted.is_empty(), text.newest) {
+                    ops.push(Op::DeleteText {
+                        id: id.clone(),
+                        field: field.clone(),
+                        chars: deleted,
+                        stamp,
+                    });
+                }
+            }
+            if let Some(stamp) = element.removed {
+                ops.push(Op::Remove {
+                    id: id.clone(),
+                    stamp,
+                });
+            }
+        }
+        ops
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn tab(
+        elements: &[(&str, ElementKind, Value)],
+    ) -> BTreeMap<String, (ElementKind, Map<String, Value>)> {
+        elements
+            .iter()
+            .map(|(id, kind, value)| (id.to_string(), (*kind, value.as_object().unwrap().clone())))
+            .collect()
+    }
+
+    #[test]
+    fn test_concurrent_edits_converge() {
+        let mut alice = TabCrdt::new(1);
+        let mut bob = TabCrdt::new(2);
+        let ops = alice.diff(&tab(&[(
+            "n1",
+            ElementKind::Node,
+            json!({"x": 0., "text": "hello"}),
+        )]));
+        assert_eq!(ops.len(), 2);
+        for op in &ops {
+            bob.apply(op);
+        }
+
+        // both edit the text of the note while alice moves it and both set the color
+        let alice_ops = alice.diff(&tab(&[(
+            "n1",
+            ElementKind::Node,
+            json!({"x": 10., "text": "Hello", "bg_color": "red"}),
+        )]));
+        let bob_ops = bob.diff(&tab(&[(
+            "n1",
+            ElementKind::Node,
+            json!({"x": 0., "text": "hello world", "bg_color": "blue"}),
+        )]));
+        for op in &bob_ops {
+            alice.apply(op);
+        }
+        for op in &alice_ops {
+            bob.apply(op);
+        }
+        let expected = json!({"x": 10., "text": "Hello world", "bg_color": "blue"});
+        assert_eq!(Value::Object(alice.get("n1").unwrap().1), expected);
+        assert_eq!(Value::Object(bob.get("n1").unwrap().1), expected);
+
+        // applying the same operation again changes nothing
+        assert!(!alice.apply(&alice_ops[0]));
+    }
+
+    #[test]
+    fn test_concurrent_typing_converges() {
+        let note = |text: &str| tab(&[("n1", ElementKind::Node, json!({ "text": text }))]);
+        let mut alice = TabCrdt::new(1);
+        let mut bob = TabCrdt::new(2);
+        let mut carol = TabCrdt::new(3);
+        for op in alice.diff(&note("ab")) {
+            bob.apply(&op);
+            carol.apply(&op);
+        }
+
+        // all three type at the same place, carol also deletes a character
+        let alice_ops = alice.diff(&note("a12b"));
+        let bob_ops = bob.diff(&note("axyb"));
+        let carol_ops = carol.diff(&note("aZ"));
+        for op in bob_ops.iter().chain(&carol_ops) {
+            alice.apply(op);
+        }
+        for op in carol_ops.iter().chain(&alice_ops) {
+            bob.apply(op);
+        }
+        for op in alice_ops.iter().chain(&bob_ops) {
+            carol.apply(op);
+        }
+        let text = alice.get("n1").unwrap().1["text"].clone();
+        assert_eq!(bob.get("n1").unwrap().1["text"], text);
+        assert_eq!(carol.get("n1").unwrap().1["text"], text);
+        let text = text.as_str().unwrap();
+        assert_eq!(text.len(), 6);
+        for part in ["12", "xy", "Z"] {
+            assert!(text.contains(part), "{} misses {}", text, part);
+        }
+        assert!(text.starts_with('a') && !text.contains('b'));
+
+        // a new replica rebuilds the same text from the snapshot
+        let mut dave = TabCrdt::new(4);
+        for op in alice.ops() {
+            dave.apply(&op);
+        }
+        assert_eq!(dave.get("n1").unwrap().1["text"], text);
+        let ops = dave.diff(&note(&format!("{}!", text)));
+        assert!(matches!(&ops[..], [Op::InsertText { text, .. }] if text == "!"));
+    }
+
+    #[test]
+    fn test_remove_and_snapshot() {
+        let mut alice = TabCrdt::new(1);
+        alice.diff(&tab(&[
+            ("n1", ElementKind::Node, json!({"x": 0.})),
+            ("a1", ElementKind::Arrow, json!({"start": "n1"})),
+        ]));
+        let ops = alice.diff(&tab(&[("n1", ElementKind::Node, json!({"x": 0.}))]));
+        assert!(matches!(&ops[..], [Op::Remove { id, .. }] if id == "a1"));
+        assert!(alice.get("a1").is_none());
+
+        let mut bob = TabCrdt::new(2);
+        for op in alice.ops() {
+            bob.apply(&op);
+        }
+        assert_eq!(bob.elements().count(), 1);
+        // an update newer than the removal restores the element
+        bob.diff(&tab(&[
+            ("n1", ElementKind::Node, json!({"x": 0.})),
+            ("a1", ElementKind::Arrow, json!({"start": "n2"})),
+        ]));
+        assert_eq!(bob.get("a1").unwrap().1["start"], "n2");
+
+        let message = Message::Ops {
+            peer: 2,
+            ops: bob.ops(),
+        };
+        let json = serde_json::to_string(&message).unwrap();
+        assert_eq!(serde_json::from_str::<Message>(&json).unwrap(), message);
+    }
+}
@@ -0,0 +1,264 @@
+//! Relays collaboration messages between velo instances editing the same room.
+//!
+//! Usage: `velo_relay [address]`, peers connect to `ws://<address>/<room>`.
+// tungstenite callbacks and sends return its large error type
+#![allow(clippy::result_large_err)]
+
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use log::{info, warn};
+use tungstenite::handshake::server::{Request, Response};
+use tungstenite::{accept_hdr, Message as WsMessage};
+use velo_collab::{Message, TabCrdt};
+
+const DEFAULT_ADDRESS: &str = "127.0.0.1:9001";
+/// How long a connection waits for incoming messages before sending queued ones.
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+/// Peer id the relay uses for its own replica of the room.
+const RELAY_PEER: u64 = 0;
+
+struct Room {
+    /// Merged state of the room sent to peers joining later
+    crdt: TabCrdt,
+    connections: HashMap<usize, Sender<String>>,
+}
+
+type Rooms = Arc<Mutex<HashMap<String, Room>>>;
+
+fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    let address = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| DEFAULT_ADDRESS.to_string());
+    let listener = TcpListener::bind(&address).expect("failed to bind relay address");
+    info!("velo relay listening on ws://{}/<room>", address);
+    serve(listener, Arc::default());
+}
+
+/// Accepts connections until the listener fails, each one is handled on its own thread.
+fn serve(listener: TcpListener, rooms: Rooms) {
+    for (connection_id, stream) in listener.incoming().enumerate() {
+        match stream {
+            Ok(stream) => {
+                let rooms = rooms.clone();
+                thread::spawn(move || {
+                    if let Err(e) = handle_connection(stream, connection_id, rooms) {
+                        warn!("connection {} closed: {}", connection_id, e);
+                    }
+                });
+            }
+            Err(e) => warn!("failed to accept connection: {}", e),
+        }
+    }
+}
+
+fn handle_connection(
+    stream: TcpStream,
+    connection_id: usize,
+    rooms: Rooms,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (room_tx, room_rx) = mpsc::channel();
+    let mut socket = accept_hdr(stream, move |request: &Request, response: Response| {
+        let _ = room_tx.send(request.uri().path().trim_matches('/').to_string());
+        Ok(response)
+    })?;
+    let room_name = room_rx.try_recv().unwrap_or_default();
+    socket.get_mut().set_read_timeout(Some(POLL_INTERVAL))?;
+    let (tx, rx): (Sender<String>, Receiver<String>) = mpsc::channel();
+    rooms
+        .lock()
+        .unwrap()
+        .entry(room_name.clone())
+        .or_insert_with(|| Room {
+            crdt: TabCrdt::new(RELAY_PEER),
+            connections: HashMap::new(),
+        })
+        .connections
+        .insert(connection_id, tx.clone());
+    info!("connection {} joined room '{}'", connection_id, room_name);
+
+    let mut peer = None;
+    let result = loop {
+        match socket.read() {
+            Ok(WsMessage::Text(text)) => {
+                let Ok(message) = serde_json::from_str::<Message>(&text) else {
+                    warn!("connection {} sent invalid message", connection_id);
+                    continue;
+                };
+                let mut rooms = rooms.lock().unwrap();
+                let room = rooms.get_mut(&room_name).unwrap();
+                match &message {
+                    Message::Hello { peer: id, .. } => {
+                        peer = Some(*id);
+                        let welcome = Message::Welcome {
+                            ops: room.crdt.ops(),
+                        };
+                        let _ = tx.send(serde_json::to_string(&welcome)?);
+                        continue;
+                    }
+                    Message::Ops { ops, .. } => {
+                        for op in ops {
+                            room.crdt.apply(op);
+                        }
+                    }
+                    _ => {}
+                }
+                broadcast(room, connection_id, &text);
+            }
+            Ok(WsMessage::Close(_)) => break Ok(()),
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(e))
+                if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(e) => break Err(e.into()),
+        }
+        if let Err(e) = rx
+            .try_iter()
+            .try_for_each(|text| socket.send(WsMessage::Text(text)))
+        {
+            break Err(e.into());
+        }
+    };
+    leave(&rooms, &room_name, connection_id, peer)?;
+    result
+}
+
+fn broadcast(room: &Room, from: usize, text: &str) {
+    for (id, connection) in &room.connections {
+        if *id != from {
+            let _ = connection.send(text.to_string());
+        }
+    }
+}
+
+/// Removes the connection from its room and tells other peers to drop its cursor, the room is
+/// dropped with the last connection.
+fn leave(
+    rooms: &Rooms,
+    room_name: &str,
+    connection_id: usize,
+    peer: Option<u64>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut rooms = rooms.lock().unwrap();
+    if let Some(room) = rooms.get_mut(room_name) {
+        room.connections.remove(&connection_id);
+        if room.connections.is_empty() {
+            rooms.remove(room_name);
+            info!("room '{}' closed", room_name);
+        } else if let Some(peer) = peer {
+            let text = serde_json::to_string(&Message::Leave { peer })?;
+            broadcast(room, connection_id, &text);
+        }
+    }
+    info!("connection {} left room '{}'", connection_id, room_name);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::time::Instant;
+
+    use serde_json::json;
+    use tungstenite::WebSocket;
+    use velo_collab::ElementKind;
+
+    type Client = WebSocket<TcpStream>;
+
+    fn connect(address: &str, peer: u64) -> (Client, Vec<velo_collab::Op>) {
+        let stream = TcpStream::connect(address).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let (mut client, _) =
+            tungstenite::client(format!("ws://{}/board", address), stream).unwrap();
+        send(
+            &mut client,
+            &Message::Hello {
+                peer,
+                name: format!("peer {}", peer),
+            },
+        );
+        match receive(&mut client) {
+            Message::Welcome { ops } => (client, ops),
+            message => panic!("expected welcome, got {:?}", message),
+        }
+    }
+
+    fn send(client: &mut Client, message: &Message) {
+        let text = serde_json::to_string(message).unwrap();
+        client.send(WsMessage::Text(text)).unwrap();
+    }
+
+    fn receive(client: &mut Client) -> Message {
+        loop {
+            if let WsMessage::Text(text) = client.read().unwrap() {
+                return serde_json::from_str(&text).unwrap();
+            }
+        }
+    }
+
+    fn close(mut client: Client) {
+        client.close(None).unwrap();
+        while client.read().is_ok() {}
+    }
+
+    fn wait_for(rooms: &Rooms, condition: impl Fn(&HashMap<String, Room>) -> bool) {
+        let start = Instant::now();
+        while !condition(&rooms.lock().unwrap()) {
+            assert!(start.elapsed() < Duration::from_secs(5), "relay timed out");
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    #[test]
+    fn test_relay_round_trip() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let rooms: Rooms = Arc::default();
+        let server_rooms = rooms.clone();
+        thread::spawn(move || serve(listener, server_rooms));
+
+        let (mut alice, ops) = connect(&address, 1);
+        assert!(ops.is_empty());
+        let mut alice_crdt = TabCrdt::new(1);
+        let note = |text: &str| {
+            let fields = json!({"x": 0., "text": text}).as_object().unwrap().clone();
+            BTreeMap::from([("n1".to_string(), (ElementKind::Node, fields))])
+        };
+        let ops = alice_crdt.diff(&note("hello"));
+        send(&mut alice, &Message::Ops { peer: 1, ops });
+        wait_for(&rooms, |rooms| rooms["board"].crdt.get("n1").is_some());
+
+        // a peer joining later gets the state of the room
+        let (mut bob, ops) = connect(&address, 2);
+        let mut bob_crdt = TabCrdt::new(2);
+        for op in &ops {
+            bob_crdt.apply(op);
+        }
+        assert_eq!(bob_crdt.get("n1"), alice_crdt.get("n1"));
+
+        let ops = bob_crdt.diff(&note("hello world"));
+        send(&mut bob, &Message::Ops { peer: 2, ops });
+        match receive(&mut alice) {
+            Message::Ops { peer: 2, ops } => {
+                for op in &ops {
+                    alice_crdt.apply(op);
+                }
+            }
+            message => panic!("expected ops, got {:?}", message),
+        }
+        assert_eq!(alice_crdt.get("n1").unwrap().1["text"], "hello world");
+
+        close(bob);
+        assert_eq!(receive(&mut alice), Message::Leave { peer: 2 });
+        close(alice);
+        wait_for(&rooms, |rooms| rooms.is_empty());
+    }
+}
@@ -18,6 +18,7 @@ impl Plugin for GridPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(Material2dPlugin::<CustomGridMaterial>::default())
             .add_systems(Startup, grid)
+            .add_systems(Update, update_grid_theme)
             .add_systems(
                 PostUpdate,
                 (update_grid, grid_follows_camera)
@@ -39,6 +39,21 @@ pub fn grid(
         .insert(Grid);
 }
 
+pub fn update_grid_theme(
+    theme: Res<Theme>,
+    grid: Query<&Handle<CustomGridMaterial>, With<Grid>>,
+    mut materials: ResMut<Assets<CustomGridMaterial>>,
+) {
+    if !theme.is_changed() || theme.is_added() {
+        return;
+    }
+    for grid_handle in grid.iter() {
+        if let Some(material) = materials.get_mut(grid_handle) {
+            material.line_color = theme.canvas_bg_line_color;
+        }
+    }
+}
+
 pub fn update_grid(
     camera: Query<
         (&Camera, &GlobalTransform, &OrthographicProjection),
@@ -5,7 +5,7 @@ use bevy::{
     sprite::{Material2d, Material2dPlugin},
 };
 
-use self::systems::synchronise_shadow_sizes;
+use self::systems::{synchronise_shadow_sizes, update_shadows_theme};
 
 pub mod systems;
 pub struct ShadowsPlugin;
@@ -13,6 +13,7 @@ pub struct ShadowsPlugin;
 impl Plugin for ShadowsPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(Material2dPlugin::<CustomShadowMaterial>::default())
+            .add_systems(Update, update_shadows_theme)
             .add_systems(PostUpdate, synchronise_shadow_sizes);
     }
 }
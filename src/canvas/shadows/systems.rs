@@ -53,6 +53,21 @@ pub fn spawn_shadow(
         .id()
 }
 
+pub fn update_shadows_theme(
+    theme: Res<Theme>,
+    shadows: Query<&Handle<CustomShadowMaterial>>,
+    mut materials: ResMut<Assets<CustomShadowMaterial>>,
+) {
+    if !theme.is_changed() || theme.is_added() {
+        return;
+    }
+    for shadow_handle in shadows.iter() {
+        if let Some(material) = materials.get_mut(shadow_handle) {
+            material.color = theme.shadow;
+        }
+    }
+}
+
 pub fn synchronise_shadow_sizes(
     objects: Query<&Sprite, (Changed<Sprite>, With<Shadow>)>,
     shadows: Query<(&Parent, &Handle<CustomShadowMaterial>, &Mesh2dHandle)>,
@@ -15,6 +15,21 @@ pub struct Tab {
     pub name: String,
     pub checkpoints: VecDeque<String>,
     pub z_index: f32,
+    /// Viewpoints shown one by one in presentation mode
+    #[serde(default)]
+    pub slides: Vec<Slide>,
+}
+
+/// Camera view of a presentation slide.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Slide {
+    pub x: f32,
+    pub y: f32,
+    /// Camera projection scale, larger values show more of the canvas
+    pub scale: f32,
+    /// Note fitted to the screen instead of the stored view while it exists
+    #[serde(default)]
+    pub node_id: Option<ReflectableUuid>,
 }
 
 #[derive(Default, Serialize, Deserialize, Clone, Debug)]
@@ -23,4 +38,50 @@ pub struct Doc {
     pub id: ReflectableUuid,
     pub name: String,
     pub tags: Vec<String>,
+    #[serde(default)]
+    pub folder: Option<String>,
+    #[serde(default)]
+    pub pinned: bool,
+    /// Milliseconds since unix epoch, zero for documents created before timestamps were tracked
+    #[serde(default)]
+    pub created_at: f64,
+    #[serde(default)]
+    pub modified_at: f64,
+    /// Set while the document is shared, sharing again updates the same remote copy
+    #[serde(default)]
+    pub shared: Option<SharedRemote>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SharedRemote {
+    /// Provider specific id of the uploaded copy
+    pub remote_id: String,
+    /// Link to the document in the viewer
+    pub url: String,
+    /// Key of encrypted documents, reused so the link stays the same
+    #[serde(default)]
+    pub key: Option<String>,
+    /// Milliseconds since unix epoch
+    pub shared_at: f64,
+}
+
+impl Doc {
+    pub fn summary(&self) -> DocSummary {
+        DocSummary {
+            folder: self.folder.clone(),
+            pinned: self.pinned,
+            created_at: self.created_at,
+            modified_at: self.modified_at,
+        }
+    }
+}
+
+/// Document properties needed by the document list.
+/// Stored separately from documents so the list can be sorted without loading all of them.
+#[derive(Default, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DocSummary {
+    pub folder: Option<String>,
+    pub pinned: bool,
+    pub created_at: f64,
+    pub modified_at: f64,
 }
@@ -1,20 +1,58 @@
 use crate::components::Doc;
-#[cfg(not(target_arch = "wasm32"))]
-use crate::ui_plugin::SearchIndexState;
+use crate::ui_plugin::{HistoryConfig, NodeMetadata, SearchIndexState, ShareState};
 use crate::utils::ReflectableUuid;
 use bevy::prelude::*;
 use bevy_cosmic_edit::CosmicFont;
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 
+#[derive(Serialize, Deserialize, Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum DocSortMode {
+    #[default]
+    Name,
+    LastModified,
+    Created,
+}
+
+impl DocSortMode {
+    pub fn next(self) -> Self {
+        match self {
+            DocSortMode::Name => DocSortMode::LastModified,
+            DocSortMode::LastModified => DocSortMode::Created,
+            DocSortMode::Created => DocSortMode::Name,
+        }
+    }
+}
+
+impl std::fmt::Display for DocSortMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DocSortMode::Name => write!(f, "name"),
+            DocSortMode::LastModified => write!(f, "last modified"),
+            DocSortMode::Created => write!(f, "created"),
+        }
+    }
+}
+
 #[derive(Resource, Default)]
 pub struct AppState {
     pub current_document: Option<ReflectableUuid>,
     pub docs: HashMap<ReflectableUuid, Doc>,
-    pub github_token: Option<String>,
-    #[cfg(not(target_arch = "wasm32"))]
+    /// Configured place to upload shared documents to, sharing is disabled without it
+    pub share: Option<ShareState>,
     pub search_index: Option<SearchIndexState>,
     pub doc_list_ui: HashSet<ReflectableUuid>,
+    /// Only documents with this tag are shown in the doc list
+    pub tag_filter: Option<String>,
+    /// Only nodes having these tags and metadata fields are shown on the canvas
+    pub node_filter: Option<NodeMetadata>,
+    pub doc_sort: DocSortMode,
+    /// Recently opened documents, most recent first
+    pub recent_docs: Vec<ReflectableUuid>,
+    pub collapsed_folders: HashSet<String>,
+    /// Retention of the version history of tabs
+    pub history: HistoryConfig,
 }
 
 #[derive(Resource, Debug)]
@@ -41,5 +79,11 @@ pub struct LoadTabRequest {
     pub drop_last_checkpoint: bool, // Useful for undo functionality
 }
 
+/// Centers the camera on the node once it is on the canvas.
+#[derive(Resource, Debug)]
+pub struct FocusNodeRequest {
+    pub node_id: ReflectableUuid,
+}
+
 #[derive(Resource, Default)]
 pub struct FontSystemState(pub Option<Handle<CosmicFont>>);
@@ -1,6 +1,6 @@
 use crate::{
     components::{EffectsCamera, MainCamera},
-    themes::{get_theme_by_name, Theme},
+    themes::{velo_light, AvailableThemes, Theme},
     utils::get_theme_key,
 };
 use bevy::{
@@ -12,9 +12,13 @@ use bevy_pancam::PanCam;
 use bevy_pkv::PkvStore;
 
 pub fn setup_velo_theme(mut commands: Commands, pkv: Res<PkvStore>) {
+    #[cfg(not(target_arch = "wasm32"))]
+    crate::themes::load_user_code_highlighting();
     let theme_key = get_theme_key(&pkv);
-    let theme = get_theme_by_name(&theme_key);
+    let themes = AvailableThemes::load();
+    let theme = themes.get(&theme_key).cloned().unwrap_or_else(velo_light);
     commands.insert_resource(theme);
+    commands.insert_resource(themes);
 }
 
 pub fn setup_camera(mut commands: Commands, theme: Res<Theme>) {
@@ -1,7 +1,8 @@
 use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
-#[derive(Resource, Debug, Serialize, Deserialize)]
+#[derive(Resource, Clone, Debug, Serialize, Deserialize)]
 pub struct Theme {
     pub add_tab_bg: Color,
     pub arrow_btn_bg: Color,
@@ -204,6 +205,31 @@ pub fn velo_dark() -> Theme {
     }
 }
 
+/// Built-in themes followed by themes loaded from the themes directory.
+#[derive(Resource, Default)]
+pub struct AvailableThemes(pub Vec<(String, Theme)>);
+
+impl AvailableThemes {
+    pub fn load() -> Self {
+        let mut themes: Vec<_> = ["light", "dark"]
+            .into_iter()
+            .map(|name| (name.to_string(), get_theme_by_name(name)))
+            .collect();
+        for (name, theme) in user_themes() {
+            themes.retain(|(theme_name, _)| *theme_name != name);
+            themes.push((name, theme));
+        }
+        Self(themes)
+    }
+
+    pub fn get(&self, theme_name: &str) -> Option<&Theme> {
+        self.0
+            .iter()
+            .find(|(name, _)| name == theme_name)
+            .map(|(_, theme)| theme)
+    }
+}
+
 pub fn get_theme_by_name(theme_name: &str) -> Theme {
     match theme_name {
         "light" => velo_light(),
@@ -211,3 +237,393 @@ pub fn get_theme_by_name(theme_name: &str) -> Theme {
         _ => velo_light(),
     }
 }
+
+#[cfg(not(target_arch = "wasm32"))]
+fn user_themes() -> Vec<(String, Theme)> {
+    themes_dir()
+        .map(|dir| load_user_themes(&dir))
+        .unwrap_or_default()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn user_themes() -> Vec<(String, Theme)> {
+    vec![]
+}
+
+/// Directory with user-defined themes, e.g. `~/.config/velo/themes` on Linux.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn themes_dir() -> Option<std::path::PathBuf> {
+    use crate::{APP_NAME, ORG_NAME};
+
+    directories::ProjectDirs::from("", ORG_NAME, APP_NAME)
+        .map(|dirs| dirs.config_dir().join("themes"))
+}
+
+/// Registers code block syntax definitions (`.sublime-syntax`) from the `syntaxes` directory and
+/// code themes (`.tmTheme`, usable as `code_theme` of a theme) from the `code-themes` directory in the config directory.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_user_code_highlighting() {
+    use crate::{APP_NAME, ORG_NAME};
+
+    let Some(dirs) = directories::ProjectDirs::from("", ORG_NAME, APP_NAME) else {
+        return;
+    };
+    let syntaxes_dir = dirs.config_dir().join("syntaxes");
+    if syntaxes_dir.is_dir() {
+        if let Err(e) = bevy_markdown::add_syntaxes_from_folder(&syntaxes_dir) {
+            warn!(
+                "Failed to load syntaxes from {}: {:?}",
+                syntaxes_dir.display(),
+                e
+            );
+        }
+    }
+    let code_themes_dir = dirs.config_dir().join("code-themes");
+    if code_themes_dir.is_dir() {
+        if let Err(e) = bevy_markdown::add_themes_from_folder(&code_themes_dir) {
+            warn!(
+                "Failed to load code themes from {}: {:?}",
+                code_themes_dir.display(),
+                e
+            );
+        }
+    }
+}
+
+/// Loads every `.toml` and `.json` theme from `dir`, theme name is the file name without extension.
+/// Invalid files are skipped with a warning.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_user_themes(dir: &std::path::Path) -> Vec<(String, Theme)> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return vec![];
+    };
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .collect();
+    paths.sort();
+    let mut themes = vec![];
+    for path in paths {
+        let (Some(name), Some(extension)) = (path.file_stem(), path.extension()) else {
+            continue;
+        };
+        let value = match std::fs::read_to_string(&path) {
+            Ok(content) if extension == "toml" => {
+                toml::from_str::<Value>(&content).map_err(|e| e.to_string())
+            }
+            Ok(content) if extension == "json" => {
+                serde_json::from_str::<Value>(&content).map_err(|e| e.to_string())
+            }
+            Ok(_) => continue,
+            Err(e) => Err(e.to_string()),
+        };
+        match value.and_then(parse_theme) {
+            Ok(theme) => themes.push((name.to_string_lossy().to_string(), theme)),
+            Err(e) => warn!("Failed to load theme {}: {}", path.display(), e),
+        }
+    }
+    themes
+}
+
+/// Builds a theme from a partial definition, missing fields are taken from the theme named by
+/// the optional `base` field ("light" by default). Colors can be given as hex strings, e.g. `"#1e1e2e"`.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn parse_theme(value: Value) -> Result<Theme, String> {
+    let Value::Object(mut fields) = value else {
+        return Err("theme should be a table of fields".to_string());
+    };
+    let base = match fields.remove("base") {
+        Some(Value::String(base)) => base,
+        Some(_) => return Err("base should be a theme name".to_string()),
+        None => "light".to_string(),
+    };
+    let Ok(Value::Object(mut theme)) = serde_json::to_value(get_theme_by_name(&base)) else {
+        unreachable!("theme is serialized as an object");
+    };
+    for (key, value) in fields {
+        let Some(base_value) = theme.get(&key) else {
+            return Err(format!("unknown field {}", key));
+        };
+        let is_color = base_value.is_object() || key == "canvas_bg_color";
+        let value = match value {
+            Value::String(hex) if is_color => {
+                let color = Color::hex(hex.trim_start_matches('#'))
+                    .map_err(|_| format!("invalid color {} for {}", hex, key))?;
+                serde_json::to_value(color).unwrap()
+            }
+            value => value,
+        };
+        theme.insert(key, value);
+    }
+    serde_json::from_value(Value::Object(theme)).map_err(|e| e.to_string())
+}
+
+/// Pairs each color used by `old` theme with the color of the same field in `new` theme.
+/// When several fields share a color in `old` theme, the most common replacement wins.
+pub fn theme_color_map(old: &Theme, new: &Theme) -> Vec<(Color, Color)> {
+    let (Ok(Value::Object(old)), Ok(Value::Object(new))) =
+        (serde_json::to_value(old), serde_json::to_value(new))
+    else {
+        unreachable!("theme is serialized as an object");
+    };
+    let mut candidates: Vec<(Color, Vec<Color>)> = vec![];
+    for (key, old_value) in old.iter() {
+        let (Ok(old_color), Some(Ok(new_color))) = (
+            serde_json::from_value::<Color>(old_value.clone()),
+            new.get(key)
+                .map(|value| serde_json::from_value::<Color>(value.clone())),
+        ) else {
+            continue;
+        };
+        match candidates.iter_mut().find(|(color, _)| *color == old_color) {
+            Some((_, new_colors)) => new_colors.push(new_color),
+            None => candidates.push((old_color, vec![new_color])),
+        }
+    }
+    candidates
+        .into_iter()
+        .map(|(old_color, new_colors)| {
+            let new_color = new_colors
+                .iter()
+                .max_by_key(|color| {
+                    // prefer the first color on ties
+                    let count = new_colors.iter().filter(|c| c == color).count();
+                    let first = new_colors.iter().position(|c| c == *color).unwrap();
+                    (count, usize::MAX - first)
+                })
+                .copied()
+                .unwrap();
+            (old_color, new_color)
+        })
+        .collect()
+}
+
+/// Looks up a color by theme field name, e.g. `node_bg`, as stored in documents.
+pub fn get_theme_color(theme: &Theme, name: &str) -> Option<Color> {
+    match name {
+        "add_tab_bg" => Some(theme.add_tab_bg),
+        "arrow_btn_bg" => Some(theme.arrow_btn_bg),
+        "arrow_connector" => Some(theme.arrow_connector),
+        "arrow" => Some(theme.arrow),
+        "bottom_panel_bg" => Some(theme.bottom_panel_bg),
+        "btn_border" => Some(theme.btn_border),
+        "canvas_bg_color" => theme.canvas_bg_color,
+        "canvas_bg_line_color" => Some(theme.canvas_bg_line_color),
+        "celebrate_btn_bg" => Some(theme.celebrate_btn_bg),
+        "celebrate_btn" => Some(theme.celebrate_btn),
+        "drawing_pencil_btn_bg" => Some(theme.drawing_pencil_btn_bg),
+        "drawing_pencil_btn" => Some(theme.drawing_pencil_btn),
+        "drawing_two_points_btn_bg" => Some(theme.drawing_two_points_btn_bg),
+        "drawing_two_points_btn" => Some(theme.drawing_two_points_btn),
+        "add_text_btn_bg" => Some(theme.add_text_btn_bg),
+        "add_text_btn" => Some(theme.add_text_btn),
+        "drawing_selected" => Some(theme.drawing_selected),
+        "clipboard_image_bg" => Some(theme.clipboard_image_bg),
+        "color_change_1" => Some(theme.color_change_1),
+        "color_change_2" => Some(theme.color_change_2),
+        "color_change_3" => Some(theme.color_change_3),
+        "color_change_4" => Some(theme.color_change_4),
+        "color_change_5" => Some(theme.color_change_5),
+        "del_button" => Some(theme.del_button),
+        "doc_list_bg" => Some(theme.doc_list_bg),
+        "font" => Some(theme.font),
+        "front_back_btn_bg" => Some(theme.front_back_btn_bg),
+        "inline_code" => Some(theme.inline_code),
+        "left_panel_bg" => Some(theme.left_panel_bg),
+        "link" => Some(theme.link),
+        "menu_bg" => Some(theme.menu_bg),
+        "menu_btn_bg" => Some(theme.menu_btn_bg),
+        "menu_btn" => Some(theme.menu_btn),
+        "modal_bg" => Some(theme.modal_bg),
+        "modal_text_input_bg" => Some(theme.modal_text_input_bg),
+        "new_tab_btn_bg" => Some(theme.new_tab_btn_bg),
+        "node_bg" => Some(theme.node_bg),
+        "node_border" => Some(theme.node_border),
+        "node_found_color" => Some(theme.node_found_color),
+        "node_manipulation_bg" => Some(theme.node_manipulation_bg),
+        "node_manipulation" => Some(theme.node_manipulation),
+        "node_shadow" => Some(theme.node_shadow),
+        "ok_cancel_bg" => Some(theme.ok_cancel_bg),
+        "paper_node_bg" => Some(theme.paper_node_bg),
+        "search_box_bg" => Some(theme.search_box_bg),
+        "search_box_border" => Some(theme.search_box_border),
+        "selected_node_border" => Some(theme.selected_node_border),
+        "shadow" => Some(theme.shadow),
+        "tab_bg" => Some(theme.tab_bg),
+        "text_pos_btn_bg" => Some(theme.text_pos_btn_bg),
+        "visibility_btn_bg" => Some(theme.visibility_btn_bg),
+        "tooltip_bg" => Some(theme.tooltip_bg),
+        "color_none" => Some(theme.color_none),
+        _ => None,
+    }
+}
+
+/// Color of a node or drawing as stored in documents.
+#[derive(Clone, Debug, PartialEq)]
+pub enum NodeColor {
+    /// Theme color field like `node_bg`, stored as `node_bg` or with a fallback as `node_bg|#ffffffff`.
+    /// The fallback is used when the current theme doesn't have the field.
+    Theme {
+        name: String,
+        fallback: Option<Color>,
+    },
+    /// Arbitrary color, stored as `#rrggbbaa`.
+    Rgba(Color),
+}
+
+impl NodeColor {
+    pub fn parse(color: &str) -> Self {
+        let color = color.trim();
+        if let Some(hex) = color.strip_prefix('#') {
+            if let Ok(color) = Color::hex(hex) {
+                return NodeColor::Rgba(color);
+            }
+        }
+        let (name, fallback) = match color.split_once('|') {
+            Some((name, fallback)) => (name, Color::hex(fallback.trim_start_matches('#')).ok()),
+            None => (color, None),
+        };
+        NodeColor::Theme {
+            name: name.to_string(),
+            fallback,
+        }
+    }
+
+    pub fn resolve(&self, theme: &Theme) -> Option<Color> {
+        match self {
+            NodeColor::Theme { name, fallback } => get_theme_color(theme, name).or(*fallback),
+            NodeColor::Rgba(color) => Some(*color),
+        }
+    }
+
+    /// Remembers `color` as fallback, so the node keeps its color in themes without the field.
+    pub fn with_fallback(self, color: Color) -> Self {
+        match self {
+            NodeColor::Theme { name, .. } => NodeColor::Theme {
+                name,
+                fallback: Some(color),
+            },
+            NodeColor::Rgba(color) => NodeColor::Rgba(color),
+        }
+    }
+}
+
+impl std::fmt::Display for NodeColor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NodeColor::Theme {
+                name,
+                fallback: Some(fallback),
+            } => write!(f, "{}|{}", name, color_to_hex(*fallback)),
+            NodeColor::Theme { name, .. } => write!(f, "{}", name),
+            NodeColor::Rgba(color) => write!(f, "{}", color_to_hex(*color)),
+        }
+    }
+}
+
+pub fn color_to_hex(color: Color) -> String {
+    let [r, g, b, a] = color.as_rgba_u8();
+    format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a)
+}
+
+/// Resolves color stored in a document, unknown colors fall back to `default`.
+pub fn resolve_node_color(theme: &Theme, color: &str, default: Color) -> Color {
+    NodeColor::parse(color).resolve(theme).unwrap_or_else(|| {
+        warn!("Unknown color {}, using default color", color);
+        default
+    })
+}
+
+pub fn remap_color(color_map: &[(Color, Color)], color: Color) -> Color {
+    color_map
+        .iter()
+        .find(|(old, _)| *old == color)
+        .map_or(color, |(_, new)| *new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_theme() {
+        let value: Value = toml::from_str(
+            r##"
+            base = "dark"
+            node_bg = "#ff0000"
+            canvas_bg_color = "00ff00"
+            font_size = 16.0
+            "##,
+        )
+        .unwrap();
+        let theme = parse_theme(value).unwrap();
+        assert_eq!(theme.node_bg, Color::rgb(1., 0., 0.));
+        assert_eq!(theme.canvas_bg_color, Some(Color::rgb(0., 1., 0.)));
+        assert_eq!(theme.font_size, 16.);
+        assert_eq!(theme.code_theme, velo_dark().code_theme);
+
+        let value: Value = serde_json::from_str(r#"{"node_bg": "not a color"}"#).unwrap();
+        assert!(parse_theme(value).is_err());
+        let value: Value = serde_json::from_str(r#"{"unknown": 1}"#).unwrap();
+        assert!(parse_theme(value).is_err());
+    }
+
+    #[test]
+    fn test_node_color() {
+        let theme = velo_light();
+        let red = Color::rgba(1., 0., 0., 1.);
+        assert_eq!(
+            NodeColor::parse("node_bg").resolve(&theme),
+            Some(theme.node_bg)
+        );
+        assert_eq!(NodeColor::parse("#ff0000").resolve(&theme), Some(red));
+        assert_eq!(NodeColor::parse("unknown").resolve(&theme), None);
+        assert_eq!(
+            NodeColor::parse("unknown|#ff0000ff").resolve(&theme),
+            Some(red)
+        );
+        assert_eq!(
+            NodeColor::parse("node_bg|#ff0000ff").resolve(&theme),
+            Some(theme.node_bg)
+        );
+        assert_eq!(
+            NodeColor::parse("node_bg").with_fallback(red).to_string(),
+            "node_bg|#ff0000ff"
+        );
+        assert_eq!(NodeColor::parse("#FF0000").to_string(), "#ff0000ff");
+        assert_eq!(
+            resolve_node_color(&theme, "unknown", theme.node_bg),
+            theme.node_bg
+        );
+    }
+
+    #[test]
+    /// Every color field of the theme can be referenced by name
+    fn test_get_theme_color() {
+        let mut theme = velo_dark();
+        theme.canvas_bg_color = Some(Color::PINK);
+        let Ok(Value::Object(fields)) = serde_json::to_value(&theme) else {
+            unreachable!("theme is serialized as an object");
+        };
+        for (name, value) in fields {
+            let color = serde_json::from_value::<Color>(value).ok();
+            assert_eq!(get_theme_color(&theme, &name), color, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_theme_color_map() {
+        let light = velo_light();
+        let dark = velo_dark();
+        let color_map = theme_color_map(&light, &dark);
+        assert_eq!(remap_color(&color_map, light.font), dark.font);
+        assert_eq!(remap_color(&color_map, light.modal_bg), dark.modal_bg);
+        assert_eq!(
+            remap_color(&color_map, light.left_panel_bg),
+            dark.left_panel_bg
+        );
+        assert_eq!(remap_color(&color_map, Color::PINK), Color::PINK);
+        assert!(theme_color_map(&light, &light)
+            .iter()
+            .all(|(old, new)| old == new));
+    }
+}
@@ -8,6 +8,7 @@ use crate::resources::AppState;
 use crate::canvas::arrow::components::{ArrowConnect, ArrowType};
 use crate::canvas::arrow::events::{CreateArrow, RedrawArrow};
 use crate::utils::ReflectableUuid;
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 use uuid::Uuid;
 #[path = "ui_helpers/ui_helpers.rs"]
@@ -43,6 +44,27 @@ use tabs::*;
 #[path = "systems/doc_list.rs"]
 mod doc_list;
 use doc_list::*;
+#[path = "systems/notifications.rs"]
+mod notifications;
+pub use notifications::*;
+#[path = "systems/share_encryption.rs"]
+mod share_encryption;
+use share_encryption::*;
+#[path = "systems/share.rs"]
+mod share;
+pub use share::*;
+#[path = "systems/share_providers/github_gist.rs"]
+mod github_gist;
+use github_gist::*;
+#[path = "systems/share_providers/gitlab_snippet.rs"]
+mod gitlab_snippet;
+use gitlab_snippet::*;
+#[path = "systems/share_providers/http_put.rs"]
+mod http_put;
+use http_put::*;
+#[path = "systems/share_providers/local_dir.rs"]
+mod local_dir;
+use local_dir::*;
 #[path = "systems/clickable_links.rs"]
 mod clickable_links;
 use clickable_links::*;
@@ -61,12 +83,59 @@ use update_rectangle_position::*;
 #[path = "systems/create_new_node.rs"]
 mod create_new_node;
 use create_new_node::*;
-#[cfg(not(target_arch = "wasm32"))]
 #[path = "systems/search.rs"]
-#[cfg(not(target_arch = "wasm32"))]
 mod search;
-#[cfg(not(target_arch = "wasm32"))]
 pub use search::*;
+#[path = "systems/search_backend/memory_backend.rs"]
+mod memory_backend;
+pub use memory_backend::*;
+#[path = "systems/search_backend/tantivy_backend.rs"]
+#[cfg(not(target_arch = "wasm32"))]
+mod tantivy_backend;
+#[cfg(not(target_arch = "wasm32"))]
+pub use tantivy_backend::*;
+#[path = "systems/node_metadata.rs"]
+mod node_metadata;
+use node_metadata::*;
+#[path = "systems/task_list.rs"]
+mod task_list;
+use task_list::*;
+#[path = "systems/links.rs"]
+mod links;
+use links::*;
+#[path = "systems/text_style.rs"]
+mod text_style;
+use text_style::*;
+#[path = "systems/auto_size.rs"]
+mod auto_size;
+use auto_size::*;
+#[path = "systems/theme_picker.rs"]
+mod theme_picker;
+use theme_picker::*;
+#[path = "systems/viewer_mode.rs"]
+mod viewer_mode;
+pub use viewer_mode::*;
+#[path = "systems/presentation.rs"]
+mod presentation;
+use presentation::*;
+#[path = "systems/collab_socket.rs"]
+mod collab_socket;
+use collab_socket::*;
+#[path = "systems/collab.rs"]
+mod collab;
+pub use collab::*;
+#[path = "systems/diff_overlay.rs"]
+mod diff_overlay;
+use diff_overlay::*;
+#[path = "systems/doc_merge.rs"]
+mod doc_merge;
+use doc_merge::*;
+#[path = "systems/compare_versions.rs"]
+mod compare_versions;
+use compare_versions::*;
+#[path = "systems/version_history.rs"]
+mod version_history;
+pub use version_history::*;
 #[path = "systems/canvas_click.rs"]
 mod canvas_click;
 use canvas_click::*;
@@ -117,6 +186,26 @@ pub struct NodeInteraction {
 #[derive(Event)]
 pub struct UpdateDeleteDocBtn;
 
+/// Sets size of the node together with its text, markers and arrows.
+#[derive(Event)]
+pub struct ResizeNode {
+    pub id: ReflectableUuid,
+    pub size: Vec2,
+}
+
+/// Changes color of the node, or color of new drawings when no node is given.
+#[derive(Event)]
+pub struct ChangeNodeColor {
+    pub node_id: Option<ReflectableUuid>,
+    pub pair_color: (String, Color),
+}
+
+/// Opens a wiki link or `velo://` link, see `parse_internal_link`.
+#[derive(Event)]
+pub struct OpenInternalLink {
+    pub link: String,
+}
+
 #[derive(Resource, Clone)]
 pub struct CommChannels {
     pub tx: Sender<String>,
@@ -138,10 +227,45 @@ pub enum TextPos {
     TopLeft,
 }
 
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TextAlign {
+    Left,
+    Center,
+    Right,
+    Justify,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum VerticalAlign {
+    Top,
+    Middle,
+    Bottom,
+}
+
+/// Per-note text style, unset fields fall back to the theme and `TextPos`.
+#[derive(Serialize, Deserialize, Component, Clone, Default, PartialEq, Debug)]
+pub struct NodeTextStyle {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub font_size: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub font_family: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub font_weight: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub align: Option<TextAlign>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vertical_align: Option<VerticalAlign>,
+    /// Same format as node background colors, see `NodeColor`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Default)]
 pub struct JsonNodeText {
     pub text: String,
     pub pos: TextPos,
+    #[serde(default)]
+    pub style: NodeTextStyle,
 }
 
 #[derive(Serialize, Deserialize, Default)]
@@ -156,6 +280,12 @@ pub struct JsonNode<T> {
     pub text: JsonNodeText,
     pub bg_color: T,
     pub visible: bool,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub metadata: BTreeMap<String, String>,
+    #[serde(default)]
+    pub auto_size: bool,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -171,6 +301,7 @@ pub struct DrawingJsonNode<T> {
 
 pub const MAX_CHECKPOINTS: i32 = 7;
 pub const MAX_SAVED_DOCS_IN_MEMORY: i32 = 7;
+pub const MAX_RECENT_DOCS: usize = 5;
 
 #[derive(Resource, Default)]
 pub struct UiState {
@@ -195,22 +326,37 @@ impl Plugin for UiPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<UiState>();
         app.init_resource::<AppState>();
+        app.init_resource::<NotificationChannels>();
+        app.init_resource::<ShareChannels>();
+        if viewer_mode_requested() {
+            app.insert_resource(ViewerMode);
+        }
+        if let Some(config) = collab_requested() {
+            app.insert_resource(config);
+        }
 
         app.add_event::<AddRect<(String, Color)>>();
         app.add_event::<CreateArrow>();
         app.add_event::<RedrawArrow>();
         app.add_event::<SaveStore>();
         app.add_event::<UpdateDeleteDocBtn>();
+        app.add_event::<ChangeNodeColor>();
         app.add_event::<NodeInteraction>();
+        app.add_event::<ResizeNode>();
+        app.add_event::<OpenInternalLink>();
 
         #[cfg(not(target_arch = "wasm32"))]
-        app.add_systems(
-            Startup,
-            (read_native_config, init_search_index).before(init_layout),
-        );
+        app.add_systems(Startup, read_native_config.before(init_layout));
         #[cfg(target_arch = "wasm32")]
         app.add_systems(Startup, load_from_url.before(init_layout));
+        app.add_systems(Startup, init_search_index.before(init_layout));
         app.add_systems(Startup, init_layout);
+        app.add_systems(
+            Startup,
+            connect_collab
+                .after(init_layout)
+                .run_if(resource_exists::<CollabConfig>()),
+        );
 
         app.add_systems(
             Update,
@@ -219,8 +365,19 @@ impl Plugin for UiPlugin {
                 update_rectangle_position,
                 create_new_node,
                 resize_entity_start,
-                resize_entity_run,
                 resize_entity_end,
+            )
+                .distributive_run_if(editing_enabled),
+        );
+        app.add_systems(
+            Update,
+            (
+                (
+                    resize_entity_run.run_if(editing_enabled),
+                    auto_size_nodes,
+                    resize_node.run_if(editing_enabled),
+                )
+                    .chain(),
                 cancel_modal,
                 confirm_modal,
             ),
@@ -257,28 +414,66 @@ impl Plugin for UiPlugin {
         app.add_systems(
             Update,
             (
-                change_color_pallete,
+                (change_color_pallete, apply_node_color).chain(),
+                pick_color_handler,
                 change_arrow_type,
                 change_text_pos,
                 add_tab_handler,
                 delete_tab_handler,
                 rename_tab_handler,
-                mouse_scroll_list,
-                list_item_click,
                 new_doc_handler,
                 rename_doc_handler,
                 delete_doc_handler,
                 save_doc_handler,
+                edit_doc_tags_handler,
                 keyboard_input_system.before(bevy_cosmic_edit::cosmic_edit_bevy_events),
-            ),
+            )
+                .distributive_run_if(editing_enabled),
         );
+        app.add_systems(Update, (mouse_scroll_list, list_item_click));
         app.add_systems(
             Update,
             (doc_list_del_button_update, doc_list_ui_changed).chain(),
         );
+        app.add_systems(Update, (tag_facet_click, tag_facets_changed).chain());
+        app.add_systems(
+            Update,
+            (
+                (pin_doc_handler, move_doc_to_folder_handler).distributive_run_if(editing_enabled),
+                doc_list_folder_click,
+                recent_doc_click,
+                doc_sort_click,
+            ),
+        );
+        app.add_systems(
+            Update,
+            (
+                node_metadata_handler.run_if(editing_enabled),
+                update_node_badges,
+                apply_node_filter,
+                update_tab_task_counts,
+            ),
+        );
+        app.add_systems(
+            Update,
+            (
+                (text_style_handler, auto_size_handler).distributive_run_if(editing_enabled),
+                (
+                    apply_node_text_style.after(apply_theme),
+                    sync_node_text_layout,
+                )
+                    .chain(),
+            ),
+        );
 
-        #[cfg(not(target_arch = "wasm32"))]
-        app.add_systems(Update, (search_box_click, search_box_text_changed));
+        app.add_systems(
+            Update,
+            (
+                search_box_click,
+                search_box_text_changed,
+                rebuild_search_index_handler,
+            ),
+        );
 
         app.add_systems(
             Update,
@@ -286,35 +481,107 @@ impl Plugin for UiPlugin {
                 button_generic_handler,
                 select_tab_handler,
                 export_to_file,
-                import_from_file,
-                import_from_url,
+                (
+                    import_from_file,
+                    import_from_url,
+                    // the preview of a merge is not shared before it's applied
+                    shared_doc_handler.run_if(not(is_merging)),
+                )
+                    .distributive_run_if(editing_enabled),
                 load_doc_handler,
                 #[cfg(target_arch = "wasm32")]
                 set_window_property,
-                shared_doc_handler,
                 #[cfg(not(target_arch = "wasm32"))]
                 create_particles_effect,
                 #[cfg(not(target_arch = "wasm32"))]
                 update_particles_effect,
-                save_to_store.after(save_tab),
+                // the preview of a merge is only saved once it's applied
+                save_to_store.after(save_tab).run_if(not(is_merging)),
                 canvas_click,
                 active_editor_changed,
                 interactive_node.before(canvas_click),
                 change_theme,
-                enable_drawing_mode,
-                drawing,
+                theme_button_click,
+                apply_theme,
+                (enable_drawing_mode, drawing).distributive_run_if(editing_enabled),
                 update_drawing_position,
             ),
         );
         app.add_systems(
             Update,
-            (drawing_two_points, enable_two_points_draw_mode).chain(),
+            (drawing_two_points, enable_two_points_draw_mode)
+                .chain()
+                .distributive_run_if(editing_enabled),
         );
         app.add_systems(
             Update,
-            (set_focus_drawing, entity_to_draw_selected_changed).chain(),
+            (set_focus_drawing, entity_to_draw_selected_changed)
+                .chain()
+                .distributive_run_if(editing_enabled),
         );
         app.add_systems(Update, (set_focused_entity, clickable_links).chain());
+        app.add_systems(
+            Update,
+            (
+                open_internal_link.after(clickable_links),
+                focus_node.run_if(should_focus_node),
+                update_links_panel,
+                link_panel_click,
+                complete_link_on_tab
+                    .before(bevy_cosmic_edit::cosmic_edit_bevy_events)
+                    .run_if(editing_enabled),
+                show_notifications,
+                (
+                    revoke_share_handler.run_if(editing_enabled),
+                    apply_share_updates,
+                    update_share_tooltip,
+                ),
+            ),
+        );
+        app.add_systems(
+            Update,
+            (
+                (add_slide_handler, clear_slides_handler).distributive_run_if(editing_enabled),
+                start_presentation.run_if(not(is_presenting)),
+                (presentation_navigation, animate_presentation_camera)
+                    .chain()
+                    .distributive_run_if(is_presenting),
+            ),
+        );
+        app.add_systems(
+            Update,
+            (
+                (
+                    (
+                        sync_collab_tab,
+                        receive_collab_messages,
+                        apply_collab_changes,
+                    )
+                        .chain()
+                        .after(load_tab)
+                        .after(load_doc),
+                    send_collab_presence,
+                )
+                    .distributive_run_if(is_collaborating),
+                draw_remote_peers,
+            ),
+        );
+        app.add_systems(
+            Update,
+            (
+                (
+                    merge_button_handler.run_if(is_merging),
+                    compare_versions_handler,
+                    version_history_handler.run_if(not(is_merging)),
+                    history_button_handler.run_if(is_browsing_history),
+                )
+                    .distributive_run_if(editing_enabled),
+                update_merge_panel,
+                draw_diff_overlay,
+                close_diff_overlay.run_if(not(is_merging)),
+                update_history_panel,
+            ),
+        );
 
         app.add_systems(
             Update,
@@ -332,28 +599,27 @@ impl Plugin for UiPlugin {
 }
 
 #[cfg(target_arch = "wasm32")]
-fn load_from_url(mut commands: Commands) {
+fn load_from_url(mut commands: Commands, notifications: Res<NotificationChannels>) {
     let (tx, rx) = async_channel::bounded(1);
     commands.insert_resource(CommChannels { tx: tx.clone(), rx });
     let href = web_sys::window().unwrap().location().href().unwrap();
     let url = url::Url::parse(href.as_str()).unwrap();
     let query_pairs: std::collections::HashMap<_, _> = url.query_pairs().into_owned().collect();
+    let key = url
+        .fragment()
+        .and_then(key_from_fragment)
+        .map(str::to_owned);
     if let Some(url) = query_pairs.get("document") {
-        let pool = bevy::tasks::IoTaskPool::get();
         let mut finder = linkify::LinkFinder::new();
         finder.kinds(&[linkify::LinkKind::Url]);
         let links: Vec<_> = finder.links(url).collect();
         if links.len() == 1 {
-            let url = links.first().unwrap().as_str().to_owned();
-            let cc = tx.clone();
-            let task = pool.spawn(async move {
-                let request = ehttp::Request::get(url);
-                ehttp::fetch(request, move |result| {
-                    let json_string = result.unwrap().text().unwrap();
-                    cc.try_send(json_string).unwrap();
-                });
-            });
-            task.detach();
+            import_document_from_url(
+                links[0].as_str().to_owned(),
+                key,
+                tx,
+                notifications.notifier.clone(),
+            );
         }
     }
 }
@@ -363,7 +629,9 @@ fn read_native_config(mut app_state: ResMut<AppState>) {
     use crate::utils::read_config_file;
 
     let config = read_config_file().unwrap_or_default();
-    if let Some(github_token) = &config.github_access_token {
-        app_state.github_token = Some(github_token.clone());
-    }
+    app_state.share = config
+        .share
+        .or_else(|| config.github_access_token.map(ShareConfig::github_gist))
+        .map(ShareState::from);
+    app_state.history = config.history.unwrap_or_default();
 }
@@ -0,0 +1,173 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use bevy_cosmic_edit::{CosmicEdit, CosmicFont};
+use cosmic_text::{Buffer, Edit, FontSystem};
+
+use super::ui_helpers::{AutoSize, ButtonAction, ButtonTypes, RawText, VeloShape};
+use super::{node_size, NodeType, ResizeNode, UiState};
+
+/// Auto-sized notes wrap text instead of growing wider than this.
+const AUTO_SIZE_MAX_WIDTH: f32 = 600.;
+/// Space between text and note border on each side.
+const AUTO_SIZE_PADDING: f32 = 10.;
+const MIN_NODE_SIZE: f32 = 40.;
+
+/// Size of laid out text in logical pixels when wrapped at `max_width`.
+/// Lines are laid out separately, so the editor's own layout isn't touched.
+pub fn text_content_size(
+    buffer: &mut Buffer,
+    font_system: &mut FontSystem,
+    max_width: f32,
+    scale_factor: f32,
+) -> Vec2 {
+    let metrics = buffer.metrics();
+    let wrap = buffer.wrap();
+    let (mut width, mut lines) = (0.0_f32, 0);
+    for line in buffer.lines.iter_mut() {
+        let layout_lines =
+            line.shape(font_system)
+                .layout(metrics.font_size, max_width * scale_factor, wrap, None);
+        for layout_line in layout_lines.iter() {
+            width = width.max(layout_line.w);
+        }
+        lines += layout_lines.len();
+    }
+    Vec2::new(width, lines as f32 * metrics.line_height) / scale_factor
+}
+
+/// Smallest note size showing the whole text, circles have to fit the text box inside.
+pub fn fit_node_size(node_type: &NodeType, content: Vec2) -> Vec2 {
+    let size = (content + 2. * AUTO_SIZE_PADDING).max(Vec2::splat(MIN_NODE_SIZE));
+    let size = match node_type {
+        NodeType::Circle => Vec2::splat(size.length()),
+        NodeType::Rect | NodeType::Paper => size,
+    };
+    node_size(node_type, size.x.ceil(), size.y.ceil())
+}
+
+/// Grows auto-sized notes when their text changes, notes are never shrunk automatically
+/// so size set by hand is kept.
+pub fn auto_size_nodes(
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut cosmic_fonts: ResMut<Assets<CosmicFont>>,
+    mut editors: Query<(&RawText, &mut CosmicEdit, &Parent), (With<RawText>, With<AutoSize>)>,
+    shapes: Query<&VeloShape>,
+    mut resize_events: EventWriter<ResizeNode>,
+) {
+    let scale_factor = windows.single().scale_factor() as f32;
+    for (raw_text, mut cosmic_edit, parent) in editors.iter_mut() {
+        if !cosmic_edit.editor.buffer().redraw() || cosmic_edit.bg_image.is_some() {
+            continue;
+        }
+        let Ok(shape) = shapes.get(parent.get()) else {
+            continue;
+        };
+        let current_size = Vec2::new(cosmic_edit.width, cosmic_edit.height);
+        let max_width = AUTO_SIZE_MAX_WIDTH.max(current_size.x) - 2. * AUTO_SIZE_PADDING;
+        let font_system = cosmic_fonts.get_mut(&cosmic_edit.font_system).unwrap();
+        let content = text_content_size(
+            cosmic_edit.bypass_change_detection().editor.buffer_mut(),
+            &mut font_system.0,
+            max_width,
+            scale_factor,
+        );
+        let size = fit_node_size(&shape.node_type, content).max(current_size);
+        if size != current_size {
+            resize_events.send(ResizeNode {
+                id: raw_text.id,
+                size,
+            });
+        }
+    }
+}
+
+pub fn auto_size_handler(
+    mut commands: Commands,
+    mut interaction_query: Query<
+        (&Interaction, &ButtonAction),
+        (Changed<Interaction>, With<ButtonAction>),
+    >,
+    ui_state: Res<UiState>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut cosmic_fonts: ResMut<Assets<CosmicFont>>,
+    mut editors: Query<
+        (
+            Entity,
+            &RawText,
+            &mut CosmicEdit,
+            &Parent,
+            Option<&AutoSize>,
+        ),
+        With<RawText>,
+    >,
+    shapes: Query<&VeloShape>,
+    mut resize_events: EventWriter<ResizeNode>,
+) {
+    let scale_factor = windows.single().scale_factor() as f32;
+    for (interaction, button_action) in interaction_query.iter_mut() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let Some(id) = ui_state.entity_to_edit else {
+            continue;
+        };
+        let Some((entity, raw_text, mut cosmic_edit, parent, auto_size)) = editors
+            .iter_mut()
+            .find(|(_, raw_text, ..)| raw_text.id == id)
+        else {
+            continue;
+        };
+        match button_action.button_type {
+            ButtonTypes::AutoSize => {
+                if auto_size.is_some() {
+                    commands.entity(entity).remove::<AutoSize>();
+                } else {
+                    commands.entity(entity).insert(AutoSize);
+                    // measure on the next frame
+                    cosmic_edit.editor.buffer_mut().set_redraw(true);
+                }
+            }
+            ButtonTypes::ShrinkToFit => {
+                if cosmic_edit.bg_image.is_some() {
+                    continue;
+                }
+                let Ok(shape) = shapes.get(parent.get()) else {
+                    continue;
+                };
+                let max_width = cosmic_edit.width - 2. * AUTO_SIZE_PADDING;
+                let font_system = cosmic_fonts.get_mut(&cosmic_edit.font_system).unwrap();
+                let content = text_content_size(
+                    cosmic_edit.editor.buffer_mut(),
+                    &mut font_system.0,
+                    max_width,
+                    scale_factor,
+                );
+                resize_events.send(ResizeNode {
+                    id: raw_text.id,
+                    size: fit_node_size(&shape.node_type, content),
+                });
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fit_node_size() {
+        assert_eq!(
+            fit_node_size(&NodeType::Rect, Vec2::new(101., 35.)),
+            Vec2::new(122., 56.)
+        );
+        assert_eq!(
+            fit_node_size(&NodeType::Paper, Vec2::ZERO),
+            Vec2::splat(MIN_NODE_SIZE)
+        );
+        let circle = fit_node_size(&NodeType::Circle, Vec2::new(60., 20.));
+        assert_eq!(circle.x, circle.y);
+        assert!(circle.x >= Vec2::new(80., 40.).length());
+    }
+}
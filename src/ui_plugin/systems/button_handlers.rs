@@ -9,26 +9,24 @@ use bevy_cosmic_edit::{CosmicEdit, CosmicEditHistory, CosmicFont};
 use bevy_pkv::PkvStore;
 use bevy_prototype_lyon::prelude::{Fill, Stroke};
 use cosmic_text::{Cursor, Edit};
-use serde::Serialize;
-use serde_json::{json, Value};
+use serde_json::json;
 use uuid::Uuid;
 
-use crate::themes::Theme;
-use crate::{AddRect, JsonNode, JsonNodeText, NodeType, UiState};
+use crate::themes::{color_to_hex, Theme};
+use crate::{
+    AddRect, ChangeNodeColor, JsonNode, JsonNodeText, ModalAction, NodeTextStyle, NodeType, UiState,
+};
 
 use super::ui_helpers::{
-    spawn_modal, ButtonAction, ChangeColor, ChangeTheme, DeleteDoc, DocListItemButton, DrawPencil,
-    Drawing, GenericButton, NewDoc, RawText, SaveDoc, TextPosMode, Tooltip, TwoPointsDraw,
-    VeloNode, VeloShape,
+    spawn_modal, ButtonAction, ChangeColor, DeleteDoc, DocListItemButton, DrawPencil, Drawing,
+    EditDocTags, GenericButton, MoveDocToFolder, NewDoc, PickColor, PinDoc, RawText, SaveDoc,
+    TextPosMode, Tooltip, TwoPointsDraw, VeloNode, VeloShape,
 };
-use super::{ExportToFile, ImportFromFile, ImportFromUrl, MainPanel, ShareDoc};
+use super::{ExportToFile, ImportFromFile, ImportFromUrl, MainPanel};
 use crate::canvas::arrow::components::{ArrowMeta, ArrowMode};
 use crate::components::{Doc, MainCamera, Tab};
 use crate::resources::{AppState, FontSystemState, LoadDocRequest, SaveDocRequest};
-use crate::utils::{
-    bevy_color_to_cosmic, get_timestamp, load_doc_to_memory, ReflectableUuid, UserPreferences,
-    DARK_THEME_ICON_CODE, LIGHT_THEME_ICON_CODE,
-};
+use crate::utils::{bevy_color_to_cosmic, get_timestamp, ReflectableUuid};
 
 #[path = "../../macros.rs"]
 #[macro_use]
@@ -79,6 +77,7 @@ pub fn rec_button_handlers(
                             text: JsonNodeText {
                                 text: "".to_string(),
                                 pos: crate::TextPos::Center,
+                                ..default()
                             },
                             bg_color: pair_struct!(theme.node_bg),
                             ..default()
@@ -98,6 +97,7 @@ pub fn rec_button_handlers(
                             text: JsonNodeText {
                                 text: "".to_string(),
                                 pos: crate::TextPos::Center,
+                                ..default()
                             },
                             bg_color: pair_struct!(theme.node_bg),
                             ..default()
@@ -117,6 +117,7 @@ pub fn rec_button_handlers(
                             text: JsonNodeText {
                                 text: "".to_string(),
                                 pos: crate::TextPos::Center,
+                                ..default()
                             },
                             bg_color: pair_struct!(theme.paper_node_bg),
                             ..default()
@@ -264,6 +265,7 @@ pub fn rec_button_handlers(
                             text: JsonNodeText {
                                 text: "".to_string(),
                                 pos: crate::TextPos::Center,
+                                ..default()
                             },
                             bg_color: pair_struct!(theme.color_none),
                             ..default()
@@ -315,6 +317,12 @@ pub fn rec_button_handlers(
                         }
                     }
                 }
+                // handled by node_metadata_handler
+                super::ui_helpers::ButtonTypes::EditNodeMetadata => {}
+                super::ui_helpers::ButtonTypes::FilterNodes => {}
+                super::ui_helpers::ButtonTypes::EditTextStyle => {}
+                super::ui_helpers::ButtonTypes::AutoSize => {}
+                super::ui_helpers::ButtonTypes::ShrinkToFit => {}
                 super::ui_helpers::ButtonTypes::ShowRandom => {
                     let mut coords = VecDeque::new();
                     for (_, _, transform, visibility) in velo_node_query.iter() {
@@ -346,24 +354,16 @@ pub fn change_color_pallete(
         (&Interaction, &ChangeColor),
         (Changed<Interaction>, With<ChangeColor>),
     >,
-    mut velo_border: Query<(&mut Fill, &mut Stroke, &mut VeloShape), With<VeloShape>>,
-    mut ui_state: ResMut<UiState>,
+    mut change_node_color: EventWriter<ChangeNodeColor>,
+    ui_state: Res<UiState>,
 ) {
     for (interaction, change_color) in &mut interaction_query {
         match *interaction {
             Interaction::Pressed => {
-                let pair_color = change_color.pair_color.clone();
-                for (mut fill, mut stroke, mut velo_border) in velo_border.iter_mut() {
-                    if Some(velo_border.id) == ui_state.entity_to_edit {
-                        fill.color = pair_color.1;
-                        if fill.color == Color::NONE {
-                            stroke.color = Color::NONE;
-                        }
-                        velo_border.pair_color = pair_color;
-                        return;
-                    }
-                }
-                ui_state.draw_color_pair = Some(pair_color);
+                change_node_color.send(ChangeNodeColor {
+                    node_id: ui_state.entity_to_edit,
+                    pair_color: change_color.pair_color.clone(),
+                });
             }
             Interaction::Hovered => {}
             Interaction::None => {}
@@ -371,20 +371,94 @@ pub fn change_color_pallete(
     }
 }
 
+pub fn apply_node_color(
+    mut events: EventReader<ChangeNodeColor>,
+    mut velo_border: Query<(&mut Fill, &mut Stroke, &mut VeloShape), With<VeloShape>>,
+    mut ui_state: ResMut<UiState>,
+    app_state: Res<AppState>,
+    mut commands: Commands,
+) {
+    for event in events.iter() {
+        let pair_color = event.pair_color.clone();
+        match velo_border
+            .iter_mut()
+            .find(|(_, _, velo_border)| Some(velo_border.id) == event.node_id)
+        {
+            Some((mut fill, mut stroke, mut velo_border)) => {
+                fill.color = pair_color.1;
+                if fill.color == Color::NONE {
+                    stroke.color = Color::NONE;
+                }
+                velo_border.pair_color = pair_color;
+                if let Some(doc_id) = app_state.current_document {
+                    commands.insert_resource(SaveDocRequest { doc_id, path: None });
+                }
+            }
+            None => ui_state.draw_color_pair = Some(pair_color),
+        }
+    }
+}
+
+pub fn pick_color_handler(
+    mut commands: Commands,
+    interaction_query: Query<&Interaction, (Changed<Interaction>, With<PickColor>)>,
+    velo_border: Query<&VeloShape, With<VeloShape>>,
+    mut ui_state: ResMut<UiState>,
+    main_panel_query: Query<Entity, With<MainPanel>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut cosmic_fonts: ResMut<Assets<CosmicFont>>,
+    font_system_state: ResMut<FontSystemState>,
+    theme: Res<Theme>,
+) {
+    let window = windows.single();
+    for interaction in interaction_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let current_color = velo_border
+            .iter()
+            .find(|velo_border| Some(velo_border.id) == ui_state.entity_to_edit)
+            .map(|velo_border| velo_border.pair_color.clone())
+            .or(ui_state.draw_color_pair.clone())
+            .map(|(_, color)| color_to_hex(color))
+            .unwrap_or_else(|| "#".to_string());
+        let node_id = ui_state.entity_to_edit;
+        let id = ReflectableUuid::generate();
+        *ui_state = UiState::default();
+        commands.insert_resource(bevy_cosmic_edit::ActiveEditor { entity: None });
+        ui_state.modal_id = Some(id);
+        let entity = spawn_modal(
+            &mut commands,
+            &theme,
+            &mut cosmic_fonts,
+            font_system_state.0.clone().unwrap(),
+            window,
+            id,
+            ModalAction::PickColor(node_id, current_color),
+        );
+        commands.entity(main_panel_query.single()).add_child(entity);
+    }
+}
+
 pub fn change_text_pos(
     mut interaction_query: Query<
         (&Interaction, &TextPosMode),
         (Changed<Interaction>, With<TextPosMode>),
     >,
     state: Res<UiState>,
-    mut raw_text_node_query: Query<(&RawText, &mut CosmicEdit), With<RawText>>,
+    mut raw_text_node_query: Query<(&RawText, &mut CosmicEdit, &mut NodeTextStyle), With<RawText>>,
 ) {
     for (interaction, text_pos_mode) in &mut interaction_query {
         match *interaction {
             Interaction::Pressed => {
                 if let Some(entity_to_edit) = state.entity_to_edit {
-                    for (raw_text, mut cosmit_edit) in raw_text_node_query.iter_mut() {
+                    for (raw_text, mut cosmit_edit, mut text_style) in
+                        raw_text_node_query.iter_mut()
+                    {
                         if raw_text.id == entity_to_edit {
+                            // presets replace alignment set in the text style
+                            text_style.align = None;
+                            text_style.vertical_align = None;
                             cosmit_edit.text_pos = text_pos_mode.text_pos.clone().into();
                             cosmit_edit.editor.buffer_mut().set_redraw(true);
                         }
@@ -442,6 +516,7 @@ pub fn new_doc_handler(
                     checkpoints,
                     is_active: true,
                     z_index: 1.,
+                    slides: vec![],
                 }];
                 app_state.docs.insert(
                     doc_id,
@@ -450,6 +525,11 @@ pub fn new_doc_handler(
                         name: name.clone(),
                         tabs,
                         tags: vec![],
+                        folder: None,
+                        pinned: false,
+                        created_at: get_timestamp(),
+                        modified_at: get_timestamp(),
+                        shared: None,
                     },
                 );
                 commands.insert_resource(SaveDocRequest {
@@ -590,10 +670,11 @@ pub fn save_doc_handler(
     }
 }
 
-pub fn export_to_file(
+pub fn edit_doc_tags_handler(
     mut commands: Commands,
-    mut query: Query<&Interaction, (Changed<Interaction>, With<ExportToFile>)>,
+    mut query: Query<&Interaction, (Changed<Interaction>, With<EditDocTags>)>,
     mut ui_state: ResMut<UiState>,
+    app_state: Res<AppState>,
     main_panel_query: Query<Entity, With<MainPanel>>,
     windows: Query<&Window, With<PrimaryWindow>>,
     mut cosmic_fonts: ResMut<Assets<CosmicFont>>,
@@ -604,6 +685,12 @@ pub fn export_to_file(
     for interaction in &mut query.iter_mut() {
         match *interaction {
             Interaction::Pressed => {
+                let Some(doc) = app_state
+                    .current_document
+                    .and_then(|doc_id| app_state.docs.get(&doc_id))
+                else {
+                    continue;
+                };
                 let id = ReflectableUuid::generate();
                 *ui_state = UiState::default();
                 commands.insert_resource(bevy_cosmic_edit::ActiveEditor { entity: None });
@@ -615,7 +702,7 @@ pub fn export_to_file(
                     font_system_state.0.clone().unwrap(),
                     window,
                     id,
-                    super::ModalAction::SaveToFile,
+                    super::ModalAction::EditTags(doc.tags.clone()),
                 );
                 commands.entity(main_panel_query.single()).add_child(entity);
             }
@@ -625,89 +712,98 @@ pub fn export_to_file(
     }
 }
 
-#[cfg(target_arch = "wasm32")]
-pub fn set_window_property(mut app_state: ResMut<AppState>, mut pkv: ResMut<PkvStore>) {
-    if let Some(doc_id) = app_state.current_document {
-        load_doc_to_memory(doc_id, &mut app_state, &mut pkv);
-        let current_doc = app_state.docs.get(&doc_id).unwrap().clone();
-        let value = serde_json::to_string_pretty(&current_doc).unwrap();
-        let window = wasm_bindgen::JsValue::from(web_sys::window().unwrap());
-        let velo_var = wasm_bindgen::JsValue::from("velo");
-        let state = wasm_bindgen::JsValue::from(value);
-        js_sys::Reflect::set(&window, &velo_var, &state).unwrap();
+pub fn pin_doc_handler(
+    mut commands: Commands,
+    mut query: Query<&Interaction, (Changed<Interaction>, With<PinDoc>)>,
+    mut app_state: ResMut<AppState>,
+) {
+    for interaction in &mut query.iter_mut() {
+        match *interaction {
+            Interaction::Pressed => {
+                let Some(doc_id) = app_state.current_document else {
+                    continue;
+                };
+                if let Some(doc) = app_state.docs.get_mut(&doc_id) {
+                    doc.pinned = !doc.pinned;
+                    commands.insert_resource(SaveDocRequest { doc_id, path: None });
+                }
+            }
+            Interaction::Hovered => {}
+            Interaction::None => {}
+        }
     }
 }
 
-#[derive(Serialize)]
-struct GistFile {
-    content: String,
-}
-
-#[derive(Serialize)]
-struct GistCreateRequest {
-    description: String,
-    public: bool,
-    files: std::collections::HashMap<String, GistFile>,
+pub fn move_doc_to_folder_handler(
+    mut commands: Commands,
+    mut query: Query<&Interaction, (Changed<Interaction>, With<MoveDocToFolder>)>,
+    mut ui_state: ResMut<UiState>,
+    app_state: Res<AppState>,
+    main_panel_query: Query<Entity, With<MainPanel>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut cosmic_fonts: ResMut<Assets<CosmicFont>>,
+    font_system_state: ResMut<FontSystemState>,
+    theme: Res<Theme>,
+) {
+    let window = windows.single();
+    for interaction in &mut query.iter_mut() {
+        match *interaction {
+            Interaction::Pressed => {
+                let Some(doc) = app_state
+                    .current_document
+                    .and_then(|doc_id| app_state.docs.get(&doc_id))
+                else {
+                    continue;
+                };
+                let id = ReflectableUuid::generate();
+                *ui_state = UiState::default();
+                commands.insert_resource(bevy_cosmic_edit::ActiveEditor { entity: None });
+                ui_state.modal_id = Some(id);
+                let entity = spawn_modal(
+                    &mut commands,
+                    &theme,
+                    &mut cosmic_fonts,
+                    font_system_state.0.clone().unwrap(),
+                    window,
+                    id,
+                    super::ModalAction::MoveToFolder(doc.folder.clone().unwrap_or_default()),
+                );
+                commands.entity(main_panel_query.single()).add_child(entity);
+            }
+            Interaction::Hovered => {}
+            Interaction::None => {}
+        }
+    }
 }
 
-pub fn shared_doc_handler(
-    mut app_state: ResMut<AppState>,
-    mut query: Query<&Interaction, (Changed<Interaction>, With<ShareDoc>)>,
-    mut pkv: ResMut<PkvStore>,
+pub fn export_to_file(
+    mut commands: Commands,
+    mut query: Query<&Interaction, (Changed<Interaction>, With<ExportToFile>)>,
+    mut ui_state: ResMut<UiState>,
+    main_panel_query: Query<Entity, With<MainPanel>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut cosmic_fonts: ResMut<Assets<CosmicFont>>,
+    font_system_state: ResMut<FontSystemState>,
+    theme: Res<Theme>,
 ) {
+    let window = windows.single();
     for interaction in &mut query.iter_mut() {
         match *interaction {
             Interaction::Pressed => {
-                if let Some(doc_id) = app_state.current_document {
-                    load_doc_to_memory(doc_id, &mut app_state, &mut pkv);
-                    let current_doc = app_state.docs.get(&doc_id).unwrap().clone();
-                    let contents = serde_json::to_string_pretty(&current_doc).unwrap();
-                    let mut files = std::collections::HashMap::new();
-                    let filename = "velo.json";
-                    let file = GistFile {
-                        content: contents.to_string(),
-                    };
-                    files.insert(filename.to_string(), file);
-
-                    let request = GistCreateRequest {
-                        description: "Velo Document".to_string(),
-                        public: true,
-                        files,
-                    };
-
-                    let mut request = ehttp::Request::post(
-                        "https://api.github.com/gists",
-                        serde_json::to_string_pretty(&request).unwrap(),
-                    );
-                    request.headers.insert(
-                        "Accept".to_string(),
-                        "application/vnd.github.v3+json".to_string(),
-                    );
-                    request.headers.insert(
-                        "Authorization".to_string(),
-                        format!("token {}", app_state.github_token.as_ref().unwrap()),
-                    );
-                    #[cfg(not(target_arch = "wasm32"))]
-                    let mut clipboard = arboard::Clipboard::new().unwrap();
-                    ehttp::fetch(request, move |result| {
-                        let response = result.unwrap();
-                        if response.ok {
-                            let res_json: Value =
-                                serde_json::from_str(response.text().unwrap().as_str()).unwrap();
-                            let files: Value = res_json["files"].clone();
-                            let velo = files["velo.json"].clone();
-                            #[cfg(not(target_arch = "wasm32"))]
-                            clipboard
-                                .set_text(format!(
-                                    "https://staffengineer.github.io/velo?document={}",
-                                    velo["raw_url"].to_string().replace('\"', "")
-                                ))
-                                .unwrap();
-                        } else {
-                            error!("Error sharing document: {}", response.status_text);
-                        }
-                    });
-                }
+                let id = ReflectableUuid::generate();
+                *ui_state = UiState::default();
+                commands.insert_resource(bevy_cosmic_edit::ActiveEditor { entity: None });
+                ui_state.modal_id = Some(id);
+                let entity = spawn_modal(
+                    &mut commands,
+                    &theme,
+                    &mut cosmic_fonts,
+                    font_system_state.0.clone().unwrap(),
+                    window,
+                    id,
+                    super::ModalAction::SaveToFile,
+                );
+                commands.entity(main_panel_query.single()).add_child(entity);
             }
             Interaction::Hovered => {}
             Interaction::None => {}
@@ -715,6 +811,19 @@ pub fn shared_doc_handler(
     }
 }
 
+#[cfg(target_arch = "wasm32")]
+pub fn set_window_property(mut app_state: ResMut<AppState>, mut pkv: ResMut<PkvStore>) {
+    if let Some(doc_id) = app_state.current_document {
+        crate::utils::load_doc_to_memory(doc_id, &mut app_state, &mut pkv);
+        let current_doc = app_state.docs.get(&doc_id).unwrap().clone();
+        let value = serde_json::to_string_pretty(&current_doc).unwrap();
+        let window = wasm_bindgen::JsValue::from(web_sys::window().unwrap());
+        let velo_var = wasm_bindgen::JsValue::from("velo");
+        let state = wasm_bindgen::JsValue::from(value);
+        js_sys::Reflect::set(&window, &velo_var, &state).unwrap();
+    }
+}
+
 pub fn import_from_file(
     mut commands: Commands,
     mut query: Query<&Interaction, (Changed<Interaction>, With<ImportFromFile>)>,
@@ -876,58 +985,3 @@ pub fn enable_two_points_draw_mode(
         }
     }
 }
-
-pub fn change_theme(
-    mut pkv: ResMut<PkvStore>,
-    mut change_theme_button: Query<&Interaction, (Changed<Interaction>, With<ChangeTheme>)>,
-    mut change_theme_label: Query<&mut Text, (With<ChangeTheme>, Without<Tooltip>)>,
-    mut tooltip_label: Query<&mut Text, (With<Tooltip>, Without<ChangeTheme>)>,
-) {
-    for interaction in &mut change_theme_button.iter_mut() {
-        match *interaction {
-            Interaction::Pressed => {
-                for mut text in &mut change_theme_label.iter_mut() {
-                    let icon_code = text.sections[0].value.clone();
-                    if icon_code == DARK_THEME_ICON_CODE {
-                        for mut tooltip in &mut tooltip_label.iter_mut() {
-                            if tooltip.sections[0].value
-                                == "Enable dark theme (restart is required for now)"
-                            {
-                                tooltip.sections[0].value =
-                                    "Enable light theme (restart is required for now)".to_string();
-                                break;
-                            }
-                        }
-                        text.sections[0].value = LIGHT_THEME_ICON_CODE.to_string();
-                        let _ = pkv.set(
-                            "user_preferences",
-                            &UserPreferences {
-                                theme_name: Some("dark".to_string()),
-                            },
-                        );
-                    }
-                    if icon_code == LIGHT_THEME_ICON_CODE {
-                        for mut tooltip in &mut tooltip_label.iter_mut() {
-                            if tooltip.sections[0].value
-                                == "Enable light theme (restart is required for now)"
-                            {
-                                tooltip.sections[0].value =
-                                    "Enable dark theme (restart is required for now)".to_string();
-                                break;
-                            }
-                        }
-                        text.sections[0].value = DARK_THEME_ICON_CODE.to_string();
-                        let _ = pkv.set(
-                            "user_preferences",
-                            &UserPreferences {
-                                theme_name: Some("light".to_string()),
-                            },
-                        );
-                    }
-                }
-            }
-            Interaction::Hovered => {}
-            Interaction::None => {}
-        }
-    }
-}
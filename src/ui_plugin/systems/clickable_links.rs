@@ -1,20 +1,43 @@
 use bevy::{prelude::*, window::PrimaryWindow};
-use bevy_cosmic_edit::{get_node_cursor_pos, get_x_offset, get_y_offset, CosmicEdit};
-use cosmic_text::Edit;
+use bevy_cosmic_edit::{
+    cosmic_edit_set_text, get_node_cursor_pos, get_x_offset, get_y_offset, CosmicEdit, CosmicFont,
+    CosmicText, CosmicTextPos,
+};
+use bevy_markdown::toggle_task;
+use cosmic_text::{Buffer, Edit, LayoutRun};
 
 use crate::components::MainCamera;
+use crate::resources::{AppState, SaveDocRequest};
+use crate::themes::Theme;
+use crate::utils::get_markdown_lines;
 
-use super::{ui_helpers::BevyMarkdownView, NodeInteraction, NodeInteractionType, UiState};
+use super::{
+    parse_internal_link,
+    ui_helpers::{BevyMarkdownView, RawText},
+    NodeInteraction, NodeInteractionType, OpenInternalLink, UiState, ViewerMode,
+};
 
 pub fn clickable_links(
+    mut commands: Commands,
     mut windows: Query<&mut Window, With<PrimaryWindow>>,
     mut markdown_text_query: Query<
-        (&GlobalTransform, &mut CosmicEdit, &BevyMarkdownView),
+        (
+            Entity,
+            &GlobalTransform,
+            &mut CosmicEdit,
+            &BevyMarkdownView,
+            &mut RawText,
+        ),
         With<BevyMarkdownView>,
     >,
     mut node_interaction_events: EventReader<NodeInteraction>,
     ui_state: Res<UiState>,
+    app_state: Res<AppState>,
+    theme: Res<Theme>,
+    mut cosmic_fonts: ResMut<Assets<CosmicFont>>,
     camera_q: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut open_internal_link: EventWriter<OpenInternalLink>,
+    viewer_mode: Option<Res<ViewerMode>>,
 ) {
     if ui_state.hold_entity.is_some() {
         return;
@@ -23,7 +46,7 @@ pub fn clickable_links(
     let scale_factor = primary_window.scale_factor() as f32;
     let (camera, camera_transform) = camera_q.single();
     for event in node_interaction_events.iter() {
-        if let Ok((transform, cosmic_edit, bevy_markdown_view)) =
+        if let Ok((entity, transform, mut cosmic_edit, bevy_markdown_view, mut raw_text)) =
             markdown_text_query.get_mut(event.entity)
         {
             if event.node_interaction_type == NodeInteractionType::LeftClick {
@@ -39,33 +62,57 @@ pub fn clickable_links(
                     camera,
                     camera_transform,
                 ) {
-                    let font_size = cosmic_edit.editor.buffer().metrics().font_size;
-                    let line_height = cosmic_edit.editor.buffer().metrics().line_height;
-                    let y_start = get_y_offset(cosmic_edit.editor.buffer()) as f32;
-                    let x_start = get_x_offset(cosmic_edit.editor.buffer()) as f32;
-                    for layout_runs in cosmic_edit.editor.buffer().layout_runs() {
-                        let line_offset =
-                            (y_start + (layout_runs.line_y - font_size)) / scale_factor;
-                        if pos.1 < (line_offset + line_height / scale_factor) && pos.1 > line_offset
-                        {
-                            for glyph in layout_runs.glyphs {
-                                let start = (x_start + glyph.x) / scale_factor;
-                                let end = (x_start + glyph.x + glyph.w) / scale_factor;
-                                if pos.0 > start && pos.0 < end {
-                                    let idx = glyph.metadata;
-                                    if let Some(text_span) =
-                                        bevy_markdown_view.span_metadata.get(idx)
-                                    {
-                                        if let Some(link) = text_span.link.clone() {
-                                            #[cfg(not(target_arch = "wasm32"))]
-                                            open::that(link.clone()).unwrap();
-                                            #[cfg(target_arch = "wasm32")]
-                                            open_url_in_new_tab(link.clone().as_str()).unwrap();
-                                        }
-                                    }
-                                }
+                    let mut clicked_task = None;
+                    let idx = span_at(
+                        cosmic_edit.editor.buffer(),
+                        &cosmic_edit.text_pos,
+                        pos,
+                        scale_factor,
+                    );
+                    if let Some(text_span) =
+                        idx.and_then(|idx| bevy_markdown_view.span_metadata.get(idx))
+                    {
+                        if let Some(link) = text_span.link.clone() {
+                            if parse_internal_link(&link).is_some() {
+                                open_internal_link.send(OpenInternalLink { link });
+                            } else {
+                                #[cfg(not(target_arch = "wasm32"))]
+                                open::that(link.clone()).unwrap();
+                                #[cfg(target_arch = "wasm32")]
+                                open_url_in_new_tab(link.clone().as_str()).unwrap();
                             }
                         }
+                        if viewer_mode.is_none() {
+                            clicked_task = text_span.task;
+                        }
+                    }
+                    // toggle task list checkbox in the markdown and render it again
+                    let Some(text) =
+                        clicked_task.and_then(|offset| toggle_task(&raw_text.last_text, offset))
+                    else {
+                        continue;
+                    };
+                    raw_text.last_text = text.clone();
+                    let markdown_lines =
+                        get_markdown_lines(&theme, &text, cosmic_edit.attrs.clone());
+                    let cosmic_edit = cosmic_edit.as_mut();
+                    let font = cosmic_fonts.get_mut(&cosmic_edit.font_system).unwrap();
+                    cosmic_edit_set_text(
+                        CosmicText::MultiStyle(markdown_lines.lines),
+                        cosmic_edit.attrs.clone(),
+                        &mut cosmic_edit.editor,
+                        &mut font.0,
+                    );
+                    cosmic_edit.editor.buffer_mut().set_redraw(true);
+                    commands.entity(entity).insert(BevyMarkdownView {
+                        id: raw_text.id,
+                        span_metadata: markdown_lines.span_metadata,
+                    });
+                    if let Some(current_document) = app_state.current_document {
+                        commands.insert_resource(SaveDocRequest {
+                            doc_id: current_document,
+                            path: None,
+                        });
                     }
                 }
             }
@@ -73,6 +120,40 @@ pub fn clickable_links(
     }
 }
 
+/// Metadata index of the glyph under `pos`, given in logical pixels from the top left corner of the editor.
+/// Each line spans from its own top to the top of the next line, so lines of different height are hit correctly.
+pub fn span_at(
+    buffer: &Buffer,
+    text_pos: &CosmicTextPos,
+    pos: (f32, f32),
+    scale_factor: f32,
+) -> Option<usize> {
+    let (x_start, y_start) = match text_pos {
+        CosmicTextPos::Center => (get_x_offset(buffer) as f32, get_y_offset(buffer) as f32),
+        CosmicTextPos::TopLeft => (0., 0.),
+    };
+    let (x, y) = (
+        pos.0 * scale_factor - x_start,
+        pos.1 * scale_factor - y_start,
+    );
+    let runs: Vec<LayoutRun> = buffer.layout_runs().collect();
+    for (i, run) in runs.iter().enumerate() {
+        let line_bottom = runs
+            .get(i + 1)
+            .map(|next| next.line_top)
+            .unwrap_or(run.line_top + buffer.metrics().line_height);
+        if y < run.line_top || y >= line_bottom {
+            continue;
+        }
+        return run
+            .glyphs
+            .iter()
+            .find(|glyph| x >= glyph.x && x < glyph.x + glyph.w)
+            .map(|glyph| glyph.metadata);
+    }
+    None
+}
+
 #[cfg(target_arch = "wasm32")]
 pub fn open_url_in_new_tab(url: &str) -> Result<(), wasm_bindgen::prelude::JsValue> {
     use wasm_bindgen::prelude::*;
@@ -83,3 +164,47 @@ pub fn open_url_in_new_tab(url: &str) -> Result<(), wasm_bindgen::prelude::JsVal
     new_window.unwrap().focus()?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmic_text::{fontdb, Attrs, Family, FontSystem, Metrics, Shaping};
+
+    #[test]
+    fn test_span_at() {
+        let mut db = fontdb::Database::new();
+        db.load_font_data(include_bytes!("../../../assets/fonts/VictorMono-Regular.ttf").to_vec());
+        let mut font_system = FontSystem::new_with_locale_and_db("en-US".to_string(), db);
+        let mut buffer = Buffer::new(&mut font_system, Metrics::new(10., 20.));
+        buffer.set_size(&mut font_system, 200., 100.);
+        let attrs = Attrs::new().family(Family::Name("Victor Mono"));
+        buffer.set_text(&mut font_system, "ab\ncd", attrs, Shaping::Advanced);
+        buffer.lines[1].set_attrs_list(cosmic_text::AttrsList::new(attrs.metadata(7)));
+        buffer.shape_until_scroll(&mut font_system);
+
+        let x = {
+            let glyph = &buffer.layout_runs().next().unwrap().glyphs[0];
+            glyph.x + glyph.w / 2.
+        };
+        assert_eq!(
+            span_at(&buffer, &CosmicTextPos::TopLeft, (x, 5.), 1.),
+            Some(0)
+        );
+        assert_eq!(
+            span_at(&buffer, &CosmicTextPos::TopLeft, (x, 25.), 1.),
+            Some(7)
+        );
+        assert_eq!(
+            span_at(&buffer, &CosmicTextPos::TopLeft, (x / 2., 12.5), 2.),
+            Some(7)
+        );
+        assert_eq!(
+            span_at(&buffer, &CosmicTextPos::TopLeft, (x, 45.), 1.),
+            None
+        );
+        assert_eq!(
+            span_at(&buffer, &CosmicTextPos::TopLeft, (150., 5.), 1.),
+            None
+        );
+    }
+}
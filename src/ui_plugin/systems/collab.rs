@@ -0,0 +1,1103 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use async_channel::{Receiver, Sender};
+use bevy::{prelude::*, sprite::Anchor, window::PrimaryWindow};
+use bevy_cosmic_edit::{cosmic_edit_set_text, get_cosmic_text, CosmicEdit, CosmicFont, CosmicText};
+use bevy_prototype_lyon::prelude::{Fill, GeometryBuilder, Path, ShapeBundle, Stroke};
+use bevy_prototype_lyon::shapes;
+use cosmic_text::{Cursor, Edit, FontSystem};
+use serde_json::{json, Map, Value};
+use velo_collab::{ElementKind, Message, Op, TabCrdt, TEXT_FIELD};
+
+use super::ui_helpers::{
+    spawn_sprite_node, AutoSize, BevyMarkdownView, Drawing, FilteredOut, NodeMeta, NodeMetadata,
+    RawText, VeloNode, VeloShape,
+};
+use super::{
+    connect_socket, encode_node_image, load_node_image, spawn_drawing, DrawingJsonNode,
+    NodeTextStyle, NotificationChannels, SocketEvent, UiState, ViewerMode,
+};
+use crate::canvas::arrow::components::ArrowMeta;
+use crate::canvas::arrow::events::{CreateArrow, RedrawArrow};
+use crate::canvas::shadows::CustomShadowMaterial;
+use crate::components::MainCamera;
+use crate::resources::{AppState, FontSystemState, LoadDocRequest, LoadTabRequest};
+use crate::themes::{resolve_node_color, NodeColor, Theme};
+use crate::utils::{get_markdown_lines, ReflectableUuid};
+use crate::{JsonNode, JsonNodeText};
+
+const COLLAB_TASK: &str = "collab";
+#[cfg(not(target_arch = "wasm32"))]
+pub const COLLAB_FLAG: &str = "--collab";
+#[cfg(not(target_arch = "wasm32"))]
+pub const COLLAB_NAME_FLAG: &str = "--collab-name";
+const DEFAULT_NAME: &str = "Guest";
+/// Seconds between batches of local changes and presence updates sent to the relay.
+const SEND_INTERVAL: f32 = 0.1;
+/// Frames to wait for a reloaded tab to be spawned before comparing the canvas again.
+const SETTLE_FRAMES: usize = 10;
+/// Fields of a note that are updated in place, other remote changes spawn the note again.
+const IN_PLACE_FIELDS: [&str; 4] = ["x", "y", "z", TEXT_FIELD];
+/// Alignment and style of the text of a note, its content is kept in `TEXT_FIELD`.
+const TEXT_FORMAT_FIELD: &str = "text_format";
+/// Base64 PNG of an `ElementKind::Image` element.
+const IMAGE_FIELD: &str = "data";
+/// Remote cursors are drawn above notes.
+const CURSOR_Z: f32 = 900.;
+
+type TabElements = BTreeMap<String, (ElementKind, Map<String, Value>)>;
+
+/// Relay room to join, given with `--collab <url>` or `?collab=<url>`.
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct CollabConfig {
+    pub url: String,
+    /// Shown next to the cursor of this user on other screens
+    pub name: String,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn parse_collab_args(args: impl Iterator<Item = String>) -> Option<CollabConfig> {
+    let args: Vec<String> = args.collect();
+    let value = |flag: &str| {
+        let index = args.iter().position(|arg| arg == flag)?;
+        args.get(index + 1).cloned()
+    };
+    Some(CollabConfig {
+        url: value(COLLAB_FLAG)?,
+        name: value(COLLAB_NAME_FLAG).unwrap_or_else(|| DEFAULT_NAME.to_string()),
+    })
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn collab_requested() -> Option<CollabConfig> {
+    parse_collab_args(std::env::args())
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn collab_requested() -> Option<CollabConfig> {
+    let href = web_sys::window()?.location().href().ok()?;
+    let url = url::Url::parse(&href).ok()?;
+    let query: HashMap<_, _> = url.query_pairs().into_owned().collect();
+    Some(CollabConfig {
+        url: query.get("collab")?.clone(),
+        name: query
+            .get("name")
+            .cloned()
+            .unwrap_or_else(|| DEFAULT_NAME.to_string()),
+    })
+}
+
+struct RemotePeer {
+    name: String,
+    cursor: Option<(f32, f32)>,
+    selected: Option<String>,
+}
+
+/// Connection to the relay and the replicated contents of the synced tab.
+#[derive(Resource)]
+pub struct CollabSession {
+    peer: u64,
+    name: String,
+    crdt: TabCrdt,
+    outgoing: Sender<String>,
+    incoming: Receiver<SocketEvent>,
+    /// Tab kept in sync, set once the relay sent the room state
+    tab: Option<(ReflectableUuid, ReflectableUuid)>,
+    /// Whether `local` matches the canvas, it's rebuilt from all elements otherwise
+    in_tab: bool,
+    /// Canvas contents as last seen or updated, local changes are found by comparing with it
+    local: TabElements,
+    /// Elements changed by remote peers that are not yet shown on the canvas
+    touched: BTreeSet<String>,
+    pending: Vec<Op>,
+    since_sent: f32,
+    settle_frames: usize,
+    peers: HashMap<u64, RemotePeer>,
+}
+
+impl CollabSession {
+    fn send(&self, message: &Message) {
+        match serde_json::to_string(message) {
+            Ok(text) => {
+                let _ = self.outgoing.try_send(text);
+            }
+            Err(e) => error!("Failed to serialize collaboration message: {}", e),
+        }
+    }
+
+    /// Whether the canvas is being replaced by a loaded document or tab.
+    fn is_settling(
+        &self,
+        loading: &(Option<Res<LoadDocRequest>>, Option<Res<LoadTabRequest>>),
+    ) -> bool {
+        self.settle_frames > 0 || loading.0.is_some() || loading.1.is_some()
+    }
+}
+
+#[derive(Component)]
+pub struct RemoteCursor {
+    peer: u64,
+}
+
+#[derive(Component)]
+pub struct RemoteSelection;
+
+fn active_tab(app_state: &AppState) -> Option<(ReflectableUuid, ReflectableUuid)> {
+    let doc_id = app_state.current_document?;
+    let tab = app_state
+        .docs
+        .get(&doc_id)?
+        .tabs
+        .iter()
+        .find(|t| t.is_active)?;
+    Some((doc_id, tab.id))
+}
+
+fn peer_color(peer: u64) -> Color {
+    Color::hsl((peer % 360) as f32, 0.8, 0.45)
+}
+
+/// Splits the serialized element into its id and fields, the text of a note is moved out of its
+/// `text` object so it's merged character by character.
+fn element_fields(value: Value) -> Option<(String, Map<String, Value>)> {
+    let Value::Object(mut fields) = value else {
+        return None;
+    };
+    let id = fields.remove("id")?.as_str()?.to_string();
+    if let Some(Value::Object(mut text)) = fields.remove("text") {
+        let content = text.remove("text").unwrap_or_else(|| json!(""));
+        fields.insert(TEXT_FIELD.to_string(), content);
+        fields.insert(TEXT_FORMAT_FIELD.to_string(), Value::Object(text));
+    }
+    Some((id, fields))
+}
+
+/// Serialized element in the checkpoint format, the inverse of `element_fields`.
+fn element_value(id: &str, kind: ElementKind, fields: &Map<String, Value>) -> Value {
+    let mut value = fields.clone();
+    if kind == ElementKind::Node {
+        let mut text = match value.remove(TEXT_FORMAT_FIELD) {
+            Some(Value::Object(text)) => text,
+            _ => Map::new(),
+        };
+        let content = value.remove(TEXT_FIELD).unwrap_or_else(|| json!(""));
+        text.insert("text".to_string(), content);
+        value.insert("text".to_string(), Value::Object(text));
+    }
+    if kind != ElementKind::Arrow {
+        value.insert("id".to_string(), json!(id));
+    }
+    Value::Object(value)
+}
+
+/// Replicated value of an element, notes nobody typed in have an empty text.
+fn remote_element(crdt: &TabCrdt, id: &str) -> Option<(ElementKind, Map<String, Value>)> {
+    let (kind, mut fields) = crdt.get(id)?;
+    if kind == ElementKind::Node {
+        fields
+            .entry(TEXT_FIELD.to_string())
+            .or_insert_with(|| json!(""));
+    }
+    Some((kind, fields))
+}
+
+/// Images are elements of their own, identified by the note they belong to.
+fn image_id(node_id: &ReflectableUuid) -> String {
+    format!("image:{}", node_id.0)
+}
+
+/// Arrows have no id, they are identified by the notes and sides they connect.
+fn arrow_id(arrow: &ArrowMeta) -> String {
+    format!(
+        "{}:{:?}-{}:{:?}",
+        arrow.start.id.0, arrow.start.pos, arrow.end.id.0, arrow.end.pos
+    )
+}
+
+/// Note fields that differ between the canvas and the replicated tab, `None` when the note has to be spawned again.
+fn in_place_changes<'a>(
+    current: &'a Map<String, Value>,
+    remote: &'a Map<String, Value>,
+) -> Option<Vec<&'a str>> {
+    let mut fields = vec![];
+    for (field, value) in remote {
+        if current.get(field) == Some(value) {
+            continue;
+        }
+        if !IN_PLACE_FIELDS.contains(&field.as_str()) {
+            return None;
+        }
+        fields.push(field.as_str());
+    }
+    if current.keys().any(|field| !remote.contains_key(field)) {
+        return None;
+    }
+    Some(fields)
+}
+
+/// Byte offset of the cursor in the text of an editor.
+fn cursor_offset(text: &str, cursor: Cursor) -> usize {
+    let line_start: usize = text
+        .split('\n')
+        .take(cursor.line)
+        .map(|line| line.len() + 1)
+        .sum();
+    (line_start + cursor.index).min(text.len())
+}
+
+/// Line and byte index in the line of an offset in the text of an editor.
+fn cursor_position(text: &str, offset: usize) -> (usize, usize) {
+    let mut line_start = 0;
+    for (line, content) in text.split('\n').enumerate() {
+        if offset <= line_start + content.len() {
+            return (line, offset - line_start);
+        }
+        line_start += content.len() + 1;
+    }
+    (0, 0)
+}
+
+/// Moves an offset in `old` to the same place in `new`, an offset inside the replaced part of the
+/// text ends up after its replacement.
+fn shift_offset(old: &str, new: &str, offset: usize) -> usize {
+    let prefix: usize = old
+        .chars()
+        .zip(new.chars())
+        .take_while(|(old, new)| old == new)
+        .map(|(c, _)| c.len_utf8())
+        .sum();
+    if offset <= prefix {
+        return offset;
+    }
+    let suffix: usize = old[prefix..]
+        .chars()
+        .rev()
+        .zip(new[prefix..].chars().rev())
+        .take_while(|(old, new)| old == new)
+        .map(|(c, _)| c.len_utf8())
+        .sum();
+    let replaced_end = old.len() - suffix;
+    new.len() - suffix + offset.saturating_sub(replaced_end)
+}
+
+pub fn connect_collab(
+    mut commands: Commands,
+    config: Res<CollabConfig>,
+    notifications: Res<NotificationChannels>,
+) {
+    let (outgoing_tx, outgoing_rx) = async_channel::unbounded();
+    let (incoming_tx, incoming_rx) = async_channel::unbounded();
+    notifications
+        .notifier
+        .progress(COLLAB_TASK, format!("Connecting to {}...", config.url));
+    connect_socket(config.url.clone(), outgoing_rx, incoming_tx);
+    // zero is the peer id of the relay
+    let peer = rand::random::<u64>().max(1);
+    commands.insert_resource(CollabSession {
+        peer,
+        name: config.name.clone(),
+        crdt: TabCrdt::new(peer),
+        outgoing: outgoing_tx,
+        incoming: incoming_rx,
+        tab: None,
+        in_tab: false,
+        local: BTreeMap::new(),
+        touched: BTreeSet::new(),
+        pending: vec![],
+        since_sent: 0.,
+        settle_frames: 0,
+        peers: HashMap::new(),
+    });
+}
+
+/// Turns local changes of the active tab into CRDT operations. Only elements whose components
+/// changed since the last frame are compared, and the edited note while typing.
+pub fn sync_collab_tab(
+    mut session: ResMut<CollabSession>,
+    app_state: Res<AppState>,
+    (viewer_mode, images): (Option<Res<ViewerMode>>, Res<Assets<Image>>),
+    loading: (Option<Res<LoadDocRequest>>, Option<Res<LoadTabRequest>>),
+    raw_text_query: Query<(
+        Entity,
+        Ref<RawText>,
+        &CosmicEdit,
+        &Parent,
+        Ref<NodeTextStyle>,
+        Option<Ref<AutoSize>>,
+        Ref<Sprite>,
+    )>,
+    border_query: Query<(&Parent, Ref<VeloShape>, Ref<NodeMetadata>, Ref<Path>)>,
+    velo_node_query: Query<(
+        &VeloNode,
+        Ref<Transform>,
+        Ref<Visibility>,
+        Option<&FilteredOut>,
+    )>,
+    (arrows, drawings): (
+        Query<(Ref<ArrowMeta>, Ref<Visibility>, Option<&FilteredOut>)>,
+        Query<(Ref<Transform>, Ref<Drawing<(String, Color)>>, &Stroke), Without<VeloNode>>,
+    ),
+    mut removed: (
+        RemovedComponents<VeloNode>,
+        RemovedComponents<ArrowMeta>,
+        RemovedComponents<Drawing<(String, Color)>>,
+        RemovedComponents<AutoSize>,
+    ),
+) {
+    let session = session.as_mut();
+    if loading.0.is_some() || loading.1.is_some() {
+        session.settle_frames = SETTLE_FRAMES;
+        session.in_tab = false;
+        return;
+    }
+    if session.settle_frames > 0 {
+        session.settle_frames -= 1;
+        return;
+    }
+    if session.tab.is_some() && session.tab != active_tab(&app_state) {
+        session.in_tab = false;
+        return;
+    }
+
+    let full = !session.in_tab;
+    let auto_size_removed: HashSet<Entity> = removed.3.iter().collect();
+    let mut check_removed = removed.0.iter().count() > 0;
+    check_removed |= removed.1.iter().count() > 0;
+    check_removed |= removed.2.iter().count() > 0;
+    let mut changed = TabElements::new();
+    for (entity, raw_text, cosmic_edit, parent, text_style, auto_size, sprite) in
+        raw_text_query.iter()
+    {
+        let Ok((border_parent, border, metadata, path)) = border_query.get(parent.get()) else {
+            continue;
+        };
+        let Ok((_, transform, visibility, filtered_out)) = velo_node_query.get(border_parent.get())
+        else {
+            continue;
+        };
+        // the edited note is compared every frame while typing
+        let is_changed = full
+            || !cosmic_edit.readonly
+            || raw_text.is_changed()
+            || text_style.is_changed()
+            || sprite.is_changed()
+            || auto_size
+                .as_ref()
+                .is_some_and(|auto_size| auto_size.is_changed())
+            || auto_size_removed.contains(&entity)
+            || border.is_changed()
+            || metadata.is_changed()
+            || path.is_changed()
+            || transform.is_changed()
+            || visibility.is_changed();
+        if !is_changed {
+            continue;
+        }
+        let text = if cosmic_edit.readonly {
+            raw_text.last_text.clone()
+        } else {
+            get_cosmic_text(cosmic_edit.editor.buffer())
+        };
+        let node = JsonNode {
+            visible: match filtered_out {
+                Some(filtered_out) => filtered_out.was_visible,
+                None => *visibility == Visibility::Visible,
+            },
+            node_type: border.node_type.clone(),
+            id: raw_text.id.0,
+            x: transform.translation.x,
+            y: transform.translation.y,
+            z: transform.translation.z,
+            width: cosmic_edit.width,
+            height: cosmic_edit.height,
+            bg_color: NodeColor::parse(&border.pair_color.0)
+                .with_fallback(border.pair_color.1)
+                .to_string(),
+            text: JsonNodeText {
+                text,
+                pos: cosmic_edit.text_pos.clone().into(),
+                style: text_style.clone(),
+            },
+            tags: metadata.tags.clone(),
+            metadata: metadata.fields.clone(),
+            auto_size: auto_size.is_some(),
+        };
+        if let Some((id, fields)) = serde_json::to_value(node).ok().and_then(element_fields) {
+            changed.insert(id, (ElementKind::Node, fields));
+        }
+        // images of notes don't change, they are only encoded once
+        let image_id = image_id(&raw_text.id);
+        if let Some(handle) = &cosmic_edit.bg_image {
+            if full || !session.local.contains_key(&image_id) {
+                if let Some(data) = encode_node_image(&images, handle) {
+                    let mut fields = Map::new();
+                    fields.insert(IMAGE_FIELD.to_string(), json!(data));
+                    changed.insert(image_id, (ElementKind::Image, fields));
+                }
+            }
+        }
+    }
+    let arrows_changed = arrows
+        .iter()
+        .any(|(arrow, visibility, _)| arrow.is_changed() || visibility.is_changed());
+    if full || arrows_changed {
+        // redrawing an arrow may connect it to other sides of the notes, which changes its id
+        check_removed = true;
+        for (arrow, visibility, filtered_out) in arrows.iter() {
+            let mut meta = *arrow;
+            meta.visible = match filtered_out {
+                Some(filtered_out) => filtered_out.was_visible,
+                None => *visibility == Visibility::Visible,
+            };
+            if let Ok(Value::Object(fields)) = serde_json::to_value(meta) {
+                changed.insert(arrow_id(&arrow), (ElementKind::Arrow, fields));
+            }
+        }
+    }
+    for (transform, drawing, stroke) in drawings.iter() {
+        if !full && !transform.is_changed() && !drawing.is_changed() {
+            continue;
+        }
+        let drawing = DrawingJsonNode {
+            x: transform.translation.x,
+            y: transform.translation.y,
+            z: transform.translation.z,
+            width: stroke.options.line_width,
+            id: drawing.id,
+            points: drawing.points.clone(),
+            drawing_color: NodeColor::parse(&drawing.drawing_color.0)
+                .with_fallback(drawing.drawing_color.1)
+                .to_string(),
+        };
+        if let Some((id, fields)) = serde_json::to_value(drawing).ok().and_then(element_fields) {
+            changed.insert(id, (ElementKind::Drawing, fields));
+        }
+    }
+
+    if full {
+        session.local = changed;
+        session.in_tab = true;
+        if session.tab.is_some() {
+            // the tab was loaded from its checkpoint, everything replicated meanwhile has to be shown
+            let ids: Vec<String> = session
+                .crdt
+                .elements()
+                .map(|(id, ..)| id.to_string())
+                .chain(session.local.keys().cloned())
+                .collect();
+            session.touched.extend(ids);
+        }
+        return;
+    }
+    // changes are only sent once the relay sent the room state
+    let send = session.tab.is_some() && viewer_mode.is_none();
+    for (id, element) in changed {
+        if session.local.get(&id) == Some(&element) {
+            continue;
+        }
+        if send {
+            let ops = session.crdt.update(&id, element.0, &element.1);
+            session.pending.extend(ops);
+        }
+        session.local.insert(id, element);
+    }
+    if check_removed {
+        let present: HashSet<String> = velo_node_query
+            .iter()
+            .flat_map(|(node, ..)| [node.id.0.to_string(), image_id(&node.id)])
+            .chain(arrows.iter().map(|(arrow, ..)| arrow_id(&arrow)))
+            .chain(
+                drawings
+                    .iter()
+                    .map(|(_, drawing, _)| drawing.id.0.to_string()),
+            )
+            .collect();
+        let removed: Vec<String> = session
+            .local
+            .keys()
+            .filter(|id| !present.contains(*id))
+            .cloned()
+            .collect();
+        for id in removed {
+            session.local.remove(&id);
+            if send {
+                let op = session.crdt.remove(&id);
+                session.pending.extend(op);
+            }
+        }
+    }
+}
+
+/// Handles messages of the relay and sends batches of local changes.
+pub fn receive_collab_messages(
+    mut commands: Commands,
+    mut session: ResMut<CollabSession>,
+    app_state: Res<AppState>,
+    (time, notifications, viewer_mode): (
+        Res<Time>,
+        Res<NotificationChannels>,
+        Option<Res<ViewerMode>>,
+    ),
+    loading: (Option<Res<LoadDocRequest>>, Option<Res<LoadTabRequest>>),
+) {
+    let session = session.as_mut();
+    // remote changes wait until the loaded canvas is compared with the replicated tab
+    while !session.is_settling(&loading) {
+        let Ok(event) = session.incoming.try_recv() else {
+            break;
+        };
+        match event {
+            SocketEvent::Connected => session.send(&Message::Hello {
+                peer: session.peer,
+                name: session.name.clone(),
+            }),
+            SocketEvent::Closed(error) => {
+                notifications
+                    .notifier
+                    .error(COLLAB_TASK, format!("Collaboration stopped: {}", error));
+                commands.remove_resource::<CollabSession>();
+                return;
+            }
+            SocketEvent::Message(Message::Welcome { ops }) => {
+                for op in &ops {
+                    session.crdt.apply(op);
+                }
+                // notes on the canvas are added to the room, they win over older remote values
+                if viewer_mode.is_none() {
+                    for (id, (kind, fields)) in &session.local {
+                        let ops = session.crdt.update(id, *kind, fields);
+                        session.pending.extend(ops);
+                    }
+                }
+                session.tab = active_tab(&app_state);
+                session.in_tab = false;
+                notifications
+                    .notifier
+                    .success(COLLAB_TASK, "Joined the collaboration session");
+            }
+            SocketEvent::Message(Message::Ops { ops, .. }) => {
+                for op in &ops {
+                    if session.crdt.apply(op) {
+                        session.touched.insert(op.id().to_string());
+                    }
+                }
+            }
+            SocketEvent::Message(Message::Presence {
+                peer,
+                name,
+                cursor,
+                selected,
+            }) => {
+                session.peers.insert(
+                    peer,
+                    RemotePeer {
+                        name,
+                        cursor,
+                        selected,
+                    },
+                );
+            }
+            SocketEvent::Message(Message::Leave { peer }) => {
+                session.peers.remove(&peer);
+            }
+            SocketEvent::Message(Message::Hello { .. }) => {}
+        }
+    }
+
+    session.since_sent += time.delta_seconds();
+    if session.since_sent >= SEND_INTERVAL && !session.pending.is_empty() {
+        session.since_sent = 0.;
+        let ops = std::mem::take(&mut session.pending);
+        session.send(&Message::Ops {
+            peer: session.peer,
+            ops,
+        });
+    }
+}
+
+/// Shows elements changed by remote peers: notes are moved and their text is replaced in place,
+/// other changed elements are spawned again on their own.
+pub fn apply_collab_changes(
+    mut commands: Commands,
+    mut session: ResMut<CollabSession>,
+    app_state: Res<AppState>,
+    ui_state: Res<UiState>,
+    (theme, font_system_state, windows): (
+        Res<Theme>,
+        Res<FontSystemState>,
+        Query<&Window, With<PrimaryWindow>>,
+    ),
+    mut assets: (
+        ResMut<Assets<CosmicFont>>,
+        ResMut<Assets<CustomShadowMaterial>>,
+        ResMut<Assets<Mesh>>,
+        ResMut<Assets<Image>>,
+    ),
+    mut raw_text_query: Query<(Entity, &mut RawText, &mut CosmicEdit)>,
+    mut velo_node_query: Query<(Entity, &VeloNode, &mut Transform)>,
+    (arrows, drawings): (
+        Query<(Entity, &ArrowMeta)>,
+        Query<(Entity, &Drawing<(String, Color)>)>,
+    ),
+    (mut create_arrow, mut redraw_arrow): (EventWriter<CreateArrow>, EventWriter<RedrawArrow>),
+) {
+    let session = session.as_mut();
+    if session.touched.is_empty() || !session.in_tab || session.tab != active_tab(&app_state) {
+        return;
+    }
+    let scale_factor = windows.single().scale_factor() as f32;
+    for id in std::mem::take(&mut session.touched) {
+        let remote = remote_element(&session.crdt, &id);
+        let current = session.local.get(&id);
+        if remote.as_ref() == current {
+            continue;
+        }
+        let Some((kind, remote)) = remote else {
+            match current.map(|(kind, _)| *kind) {
+                Some(ElementKind::Node) => {
+                    for (entity, node, _) in velo_node_query.iter() {
+                        if Some(node.id) == remote_node_id(&id) {
+                            commands.entity(entity).despawn_recursive();
+                        }
+                    }
+                }
+                Some(ElementKind::Arrow) => {
+                    for (entity, arrow) in arrows.iter() {
+                        if arrow_id(arrow) == id {
+                            commands.entity(entity).despawn_recursive();
+                        }
+                    }
+                }
+                Some(ElementKind::Drawing) => {
+                    for (entity, drawing) in drawings.iter() {
+                        if drawing.id.0.to_string() == id {
+                            commands.entity(entity).despawn_recursive();
+                        }
+                    }
+                }
+                Some(ElementKind::Image) => {
+                    for (_, raw_text, mut cosmic_edit) in raw_text_query.iter_mut() {
+                        if image_id(&raw_text.id) == id {
+                            cosmic_edit.bg_image = None;
+                        }
+                    }
+                }
+                None => {}
+            }
+            session.local.remove(&id);
+            continue;
+        };
+        let value = element_value(&id, kind, &remote);
+        match kind {
+            ElementKind::Node => {
+                let Ok(node) = serde_json::from_value::<JsonNode<String>>(value) else {
+                    warn!("Skipping incomplete collaboration element {}", id);
+                    continue;
+                };
+                let node_id = ReflectableUuid(node.id);
+                let in_place = current.and_then(|(_, current)| in_place_changes(current, &remote));
+                match in_place {
+                    Some(fields) => {
+                        if fields.iter().any(|field| *field != TEXT_FIELD) {
+                            for (_, velo_node, mut transform) in velo_node_query.iter_mut() {
+                                if velo_node.id == node_id {
+                                    transform.translation = Vec3::new(node.x, node.y, node.z);
+                                    redraw_arrow.send(RedrawArrow { id: node_id });
+                                }
+                            }
+                        }
+                        if fields.contains(&TEXT_FIELD) {
+                            for (entity, mut raw_text, mut cosmic_edit) in raw_text_query.iter_mut()
+                            {
+                                if raw_text.id == node_id {
+                                    let font = assets.0.get_mut(&cosmic_edit.font_system).unwrap();
+                                    set_remote_text(
+                                        &mut commands,
+                                        &theme,
+                                        &mut font.0,
+                                        entity,
+                                        &mut raw_text,
+                                        &mut cosmic_edit,
+                                        &node.text.text,
+                                    );
+                                }
+                            }
+                        }
+                    }
+                    None => {
+                        let mut image = None;
+                        for (_, raw_text, cosmic_edit) in raw_text_query.iter() {
+                            if raw_text.id == node_id {
+                                image = cosmic_edit.bg_image.clone();
+                            }
+                        }
+                        for (entity, velo_node, _) in velo_node_query.iter() {
+                            if velo_node.id == node_id {
+                                commands.entity(entity).despawn_recursive();
+                            }
+                        }
+                        if image.is_none() {
+                            image =
+                                session
+                                    .crdt
+                                    .get(&image_id(&node_id))
+                                    .and_then(|(_, fields)| {
+                                        load_node_image(
+                                            &mut assets.3,
+                                            fields[IMAGE_FIELD].as_str()?,
+                                        )
+                                    });
+                        }
+                        let bg_color = resolve_node_color(&theme, &node.bg_color, theme.node_bg);
+                        spawn_sprite_node(
+                            &mut commands,
+                            &mut assets.1,
+                            &mut assets.2,
+                            &theme,
+                            &mut assets.0,
+                            font_system_state.0.clone().unwrap(),
+                            scale_factor,
+                            NodeMeta {
+                                size: (node.width, node.height),
+                                node_type: node.node_type,
+                                id: node_id,
+                                image,
+                                text: node.text.text,
+                                pair_bg_color: (node.bg_color, bg_color),
+                                position: (node.x, node.y, node.z),
+                                text_pos: node.text.pos,
+                                text_style: node.text.style,
+                                auto_size: node.auto_size,
+                                is_active: ui_state.entity_to_edit == Some(node_id),
+                                visible: node.visible,
+                                metadata: NodeMetadata {
+                                    tags: node.tags,
+                                    fields: node.metadata,
+                                },
+                            },
+                        );
+                        redraw_arrow.send(RedrawArrow { id: node_id });
+                    }
+                }
+            }
+            ElementKind::Arrow => {
+                let Ok(meta) = serde_json::from_value::<ArrowMeta>(value) else {
+                    warn!("Skipping incomplete collaboration element {}", id);
+                    continue;
+                };
+                for (entity, arrow) in arrows.iter() {
+                    if arrow_id(arrow) == id {
+                        commands.entity(entity).despawn_recursive();
+                    }
+                }
+                create_arrow.send(CreateArrow {
+                    visible: meta.visible,
+                    start: meta.start,
+                    end: meta.end,
+                    arrow_type: meta.arrow_type,
+                });
+            }
+            ElementKind::Drawing => {
+                let Ok(drawing) = serde_json::from_value::<DrawingJsonNode<String>>(value) else {
+                    warn!("Skipping incomplete collaboration element {}", id);
+                    continue;
+                };
+                if drawing.points.is_empty() {
+                    warn!("Skipping collaboration drawing without points {}", id);
+                    continue;
+                }
+                for (entity, existing) in drawings.iter() {
+                    if existing.id == drawing.id {
+                        commands.entity(entity).despawn_recursive();
+                    }
+                }
+                spawn_drawing(&mut commands, &theme, drawing);
+            }
+            ElementKind::Image => {
+                let Some(image) = remote
+                    .get(IMAGE_FIELD)
+                    .and_then(Value::as_str)
+                    .and_then(|data| load_node_image(&mut assets.3, data))
+                else {
+                    warn!("Skipping invalid collaboration image {}", id);
+                    continue;
+                };
+                for (_, raw_text, mut cosmic_edit) in raw_text_query.iter_mut() {
+                    if image_id(&raw_text.id) == id {
+                        cosmic_edit.bg_image = Some(image.clone());
+                    }
+                }
+            }
+        }
+        session.local.insert(id, (kind, remote));
+    }
+}
+
+/// Replaces the text of a note, the cursor of the edited note stays where the local user types.
+fn set_remote_text(
+    commands: &mut Commands,
+    theme: &Theme,
+    font_system: &mut FontSystem,
+    entity: Entity,
+    raw_text: &mut RawText,
+    cosmic_edit: &mut CosmicEdit,
+    text: &str,
+) {
+    if cosmic_edit.readonly {
+        raw_text.last_text = text.to_string();
+        let markdown_lines = get_markdown_lines(theme, text, cosmic_edit.attrs.clone());
+        cosmic_edit_set_text(
+            CosmicText::MultiStyle(markdown_lines.lines),
+            cosmic_edit.attrs.clone(),
+            &mut cosmic_edit.editor,
+            font_system,
+        );
+        commands.entity(entity).insert(BevyMarkdownView {
+            id: raw_text.id,
+            span_metadata: markdown_lines.span_metadata,
+        });
+    } else {
+        let old = get_cosmic_text(cosmic_edit.editor.buffer());
+        let cursor = cosmic_edit.editor.cursor();
+        let offset = shift_offset(&old, text, cursor_offset(&old, cursor));
+        cosmic_edit_set_text(
+            CosmicText::OneStyle(text.to_string()),
+            cosmic_edit.attrs.clone(),
+            &mut cosmic_edit.editor,
+            font_system,
+        );
+        let (line, index) = cursor_position(text, offset);
+        cosmic_edit.editor.set_cursor(Cursor {
+            line,
+            index,
+            ..cursor
+        });
+        cosmic_edit.editor.set_select_opt(None);
+    }
+    cosmic_edit.editor.buffer_mut().set_redraw(true);
+}
+
+fn remote_node_id(id: &str) -> Option<ReflectableUuid> {
+    id.parse().ok().map(ReflectableUuid)
+}
+
+/// Sends the cursor position and the edited note of the local user.
+pub fn send_collab_presence(
+    session: Res<CollabSession>,
+    ui_state: Res<UiState>,
+    time: Res<Time>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut last_sent: Local<(f32, Option<(Option<(f32, f32)>, Option<String>)>)>,
+) {
+    last_sent.0 += time.delta_seconds();
+    if session.tab.is_none() || last_sent.0 < SEND_INTERVAL {
+        return;
+    }
+    last_sent.0 = 0.;
+    let (camera, camera_transform) = camera_query.single();
+    let cursor = windows
+        .single()
+        .cursor_position()
+        .and_then(|position| camera.viewport_to_world_2d(camera_transform, position))
+        .map(|position| (position.x, position.y));
+    let selected = ui_state.entity_to_edit.map(|id| id.0.to_string());
+    let presence = Some((cursor, selected.clone()));
+    if last_sent.1 == presence {
+        return;
+    }
+    last_sent.1 = presence;
+    session.send(&Message::Presence {
+        peer: session.peer,
+        name: session.name.clone(),
+        cursor,
+        selected,
+    });
+}
+
+/// Draws cursors of other peers and outlines the notes they edit.
+pub fn draw_remote_peers(
+    mut commands: Commands,
+    session: Option<Res<CollabSession>>,
+    mut cursors: Query<(Entity, &RemoteCursor, &mut Transform, &mut Visibility)>,
+    selections: Query<Entity, With<RemoteSelection>>,
+    camera_query: Query<&OrthographicProjection, With<MainCamera>>,
+    raw_text_query: Query<(&RawText, &CosmicEdit)>,
+    velo_node_query: Query<(&VeloNode, &GlobalTransform)>,
+) {
+    for entity in selections.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    let peers = session.as_ref().map(|session| &session.peers);
+    let scale = camera_query.single().scale;
+    for (entity, cursor, mut transform, mut visibility) in cursors.iter_mut() {
+        match peers.and_then(|peers| peers.get(&cursor.peer)) {
+            Some(peer) => {
+                if let Some((x, y)) = peer.cursor {
+                    transform.translation = Vec3::new(x, y, CURSOR_Z);
+                    transform.scale = Vec3::splat(scale);
+                    *visibility = Visibility::Visible;
+                } else {
+                    *visibility = Visibility::Hidden;
+                }
+            }
+            None => commands.entity(entity).despawn_recursive(),
+        }
+    }
+    let Some(peers) = peers else {
+        return;
+    };
+    for (&peer_id, peer) in peers {
+        let color = peer_color(peer_id);
+        if !cursors.iter().any(|(_, cursor, ..)| cursor.peer == peer_id) {
+            commands
+                .spawn((
+                    ShapeBundle {
+                        path: GeometryBuilder::build_as(&shapes::Circle {
+                            radius: 5.,
+                            center: Vec2::ZERO,
+                        }),
+                        transform: Transform::from_xyz(0., 0., CURSOR_Z),
+                        visibility: Visibility::Hidden,
+                        ..default()
+                    },
+                    Fill::color(color),
+                    RemoteCursor { peer: peer_id },
+                ))
+                .with_children(|builder| {
+                    builder.spawn(Text2dBundle {
+                        text: Text::from_section(
+                            peer.name.clone(),
+                            TextStyle {
+                                font_size: 14.,
+                                color,
+                                ..default()
+                            },
+                        ),
+                        text_anchor: Anchor::TopLeft,
+                        transform: Transform::from_xyz(6., -6., 0.),
+                        ..default()
+                    });
+                });
+        }
+        let Some(selected) = peer.selected.as_deref().and_then(remote_node_id) else {
+            continue;
+        };
+        let size = raw_text_query
+            .iter()
+            .find(|(raw_text, _)| raw_text.id == selected)
+            .map(|(_, cosmic_edit)| Vec2::new(cosmic_edit.width, cosmic_edit.height));
+        let position = velo_node_query
+            .iter()
+            .find(|(node, _)| node.id == selected)
+            .map(|(_, transform)| transform.translation());
+        if let (Some(size), Some(position)) = (size, position) {
+            commands.spawn((
+                ShapeBundle {
+                    path: GeometryBuilder::build_as(&shapes::Rectangle {
+                        extents: size + Vec2::splat(8.),
+                        origin: shapes::RectangleOrigin::Center,
+                    }),
+                    transform: Transform::from_xyz(position.x, position.y, CURSOR_Z - 1.),
+                    ..default()
+                },
+                Stroke::new(color, 3.),
+                RemoteSelection,
+            ));
+        }
+    }
+}
+
+pub fn is_collaborating(session: Option<Res<CollabSession>>) -> bool {
+    session.is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_in_place_changes() {
+        let fields = |value: Value| element_fields(value).unwrap().1;
+        let current = fields(
+            json!({"id": "n1", "x": 0., "y": 0., "text": {"text": "a", "pos": "Center"}, "bg_color": "red"}),
+        );
+        let moved = fields(
+            json!({"id": "n1", "x": 5., "y": 0., "text": {"text": "b", "pos": "Center"}, "bg_color": "red"}),
+        );
+        let recolored = fields(
+            json!({"id": "n1", "x": 0., "y": 0., "text": {"text": "a", "pos": "Center"}, "bg_color": "blue"}),
+        );
+        let aligned = fields(
+            json!({"id": "n1", "x": 0., "y": 0., "text": {"text": "a", "pos": "TopLeft"}, "bg_color": "red"}),
+        );
+        assert_eq!(moved[TEXT_FIELD], "b");
+        assert_eq!(
+            element_value("n1", ElementKind::Node, &moved)["text"],
+            json!({"text": "b", "pos": "Center"})
+        );
+        assert_eq!(
+            in_place_changes(&current, &moved),
+            Some(vec![TEXT_FIELD, "x"])
+        );
+        assert_eq!(in_place_changes(&current, &recolored), None);
+        assert_eq!(in_place_changes(&current, &aligned), None);
+
+        // the cursor of the edited note keeps its place when remote text is added before it
+        let old = "hello\nworld";
+        let new = "oh hello\nbig world";
+        let cursor = Cursor::new(1, 2);
+        let offset = shift_offset(old, new, cursor_offset(old, cursor));
+        assert_eq!(cursor_position(new, offset), (1, 6));
+        assert_eq!(shift_offset(old, new, 0), 0);
+        assert_eq!(shift_offset("abcd", "aXd", 2), 2);
+
+        #[cfg(not(target_arch = "wasm32"))]
+        assert_eq!(
+            parse_collab_args(
+                [
+                    "velo",
+                    "--collab",
+                    "ws://127.0.0.1:9001/board",
+                    "--collab-name",
+                    "Ann"
+                ]
+                .into_iter()
+                .map(String::from)
+            ),
+            Some(CollabConfig {
+                url: "ws://127.0.0.1:9001/board".to_string(),
+                name: "Ann".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_invalid_remote_elements() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .add_asset::<Image>();
+        let mut images = app.world.resource_mut::<Assets<Image>>();
+        assert!(load_node_image(&mut images, "not an image").is_none());
+        let handle = images.add(Image::default());
+        let data = encode_node_image(&images, &handle).unwrap();
+        let loaded = load_node_image(&mut images, &data).unwrap();
+        assert_eq!(images.get(&loaded).unwrap().size(), Vec2::ONE);
+
+        let mut world = World::new();
+        let mut queue = bevy::ecs::system::CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, &world);
+        let drawing = DrawingJsonNode {
+            x: 0.,
+            y: 0.,
+            z: 0.,
+            id: ReflectableUuid::generate(),
+            points: vec![],
+            drawing_color: "red".to_string(),
+            width: 2.,
+        };
+        assert!(spawn_drawing(&mut commands, &crate::themes::velo_light(), drawing).is_none());
+        queue.apply(&mut world);
+        assert!(world.entities().is_empty());
+    }
+}
@@ -0,0 +1,124 @@
+use async_channel::{Receiver, Sender};
+use velo_collab::Message;
+
+/// What the socket reports back to the collaboration systems.
+pub enum SocketEvent {
+    Connected,
+    Message(Message),
+    Closed(String),
+}
+
+fn parse_message(text: &str) -> Option<Message> {
+    match serde_json::from_str(text) {
+        Ok(message) => Some(message),
+        Err(e) => {
+            bevy::log::warn!("Invalid collaboration message: {}", e);
+            None
+        }
+    }
+}
+
+/// Connects to the relay on a separate thread, messages are exchanged through the channels until either side closes.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn connect_socket(url: String, outgoing: Receiver<String>, incoming: Sender<SocketEvent>) {
+    use std::io::ErrorKind;
+    use tungstenite::stream::MaybeTlsStream;
+    use tungstenite::Message as WsMessage;
+
+    std::thread::spawn(move || {
+        let mut socket = match tungstenite::connect(url.as_str()) {
+            Ok((socket, _)) => socket,
+            Err(e) => {
+                let _ = incoming.try_send(SocketEvent::Closed(e.to_string()));
+                return;
+            }
+        };
+        if let MaybeTlsStream::Plain(stream) = socket.get_mut() {
+            let _ = stream.set_read_timeout(Some(std::time::Duration::from_millis(20)));
+        }
+        let _ = incoming.try_send(SocketEvent::Connected);
+        let error = loop {
+            match socket.read() {
+                Ok(WsMessage::Text(text)) => {
+                    if let Some(message) = parse_message(&text) {
+                        if incoming.try_send(SocketEvent::Message(message)).is_err() {
+                            break None;
+                        }
+                    }
+                }
+                Ok(WsMessage::Close(_)) => break Some("relay closed the connection".to_string()),
+                Ok(_) => {}
+                Err(tungstenite::Error::Io(e))
+                    if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+                Err(e) => break Some(e.to_string()),
+            }
+            if let Err(e) = std::iter::from_fn(|| outgoing.try_recv().ok())
+                .try_for_each(|text| socket.send(WsMessage::Text(text)).map_err(Box::new))
+            {
+                break Some(e.to_string());
+            }
+            if incoming.is_closed() {
+                break None;
+            }
+        };
+        if let Some(error) = error {
+            let _ = incoming.try_send(SocketEvent::Closed(error));
+        }
+    });
+}
+
+/// Connects with the browser WebSocket, queued messages are sent from a timer.
+#[cfg(target_arch = "wasm32")]
+pub fn connect_socket(url: String, outgoing: Receiver<String>, incoming: Sender<SocketEvent>) {
+    use wasm_bindgen::{closure::Closure, JsCast};
+    use web_sys::{MessageEvent, WebSocket};
+
+    let socket = match WebSocket::new(&url) {
+        Ok(socket) => socket,
+        Err(e) => {
+            let _ = incoming.try_send(SocketEvent::Closed(format!("{:?}", e)));
+            return;
+        }
+    };
+    let tx = incoming.clone();
+    let on_open = Closure::<dyn FnMut()>::new(move || {
+        let _ = tx.try_send(SocketEvent::Connected);
+    });
+    socket.set_onopen(Some(on_open.as_ref().unchecked_ref()));
+    on_open.forget();
+
+    let tx = incoming.clone();
+    let on_message = Closure::<dyn FnMut(_)>::new(move |event: MessageEvent| {
+        if let Some(message) = event.data().as_string().as_deref().and_then(parse_message) {
+            let _ = tx.try_send(SocketEvent::Message(message));
+        }
+    });
+    socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+    on_message.forget();
+
+    let tx = incoming;
+    let on_close = Closure::<dyn FnMut()>::new(move || {
+        let _ = tx.try_send(SocketEvent::Closed(
+            "connection to the relay closed".to_string(),
+        ));
+    });
+    socket.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+    on_close.forget();
+
+    let sender = socket.clone();
+    let send_queued = Closure::<dyn FnMut()>::new(move || {
+        if sender.ready_state() != WebSocket::OPEN {
+            return;
+        }
+        while let Ok(text) = outgoing.try_recv() {
+            let _ = sender.send_with_str(&text);
+        }
+    });
+    let _ = web_sys::window()
+        .unwrap()
+        .set_interval_with_callback_and_timeout_and_arguments_0(
+            send_queued.as_ref().unchecked_ref(),
+            20,
+        );
+    send_queued.forget();
+}
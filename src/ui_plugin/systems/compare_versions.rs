@@ -0,0 +1,184 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+use bevy_cosmic_edit::CosmicFont;
+
+use super::ui_helpers::{spawn_modal, CompareVersions, MainPanel};
+use super::{DiffOverlay, ElementChange, ModalAction, Notifier, TabDiff, TabSnapshot, UiState};
+use crate::components::Tab;
+use crate::resources::{AppState, FontSystemState};
+use crate::themes::Theme;
+use crate::utils::ReflectableUuid;
+
+const COMPARE_TASK: &str = "compare";
+
+/// Version of the active tab to compare.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CompareSource {
+    /// Checkpoint number, 1 is the oldest one
+    Checkpoint(usize),
+    /// Document exported to a file, its tab with the same id as the active one is compared
+    File(String),
+}
+
+/// Two checkpoint numbers or file paths separated by whitespace.
+pub fn parse_compare_input(text: &str) -> Result<(CompareSource, CompareSource), String> {
+    let sources: Vec<CompareSource> = text
+        .split_whitespace()
+        .map(|part| match part.parse() {
+            Ok(number) => CompareSource::Checkpoint(number),
+            Err(_) => CompareSource::File(part.to_string()),
+        })
+        .collect();
+    match &sources[..] {
+        [old, new] => Ok((old.clone(), new.clone())),
+        _ => Err("Enter two checkpoint numbers or files to compare".to_string()),
+    }
+}
+
+pub fn active_tab(app_state: &AppState) -> Option<(ReflectableUuid, &Tab)> {
+    let doc_id = app_state.current_document?;
+    let tab = app_state
+        .docs
+        .get(&doc_id)?
+        .tabs
+        .iter()
+        .find(|tab| tab.is_active)?;
+    Some((doc_id, tab))
+}
+
+fn load_version(tab: &Tab, source: &CompareSource) -> Result<TabSnapshot, String> {
+    match source {
+        CompareSource::Checkpoint(number) => number
+            .checked_sub(1)
+            .and_then(|index| tab.checkpoints.get(index))
+            .and_then(|json| TabSnapshot::parse(json))
+            .ok_or(format!(
+                "Checkpoint {} not found, the tab has {}",
+                number,
+                tab.checkpoints.len()
+            )),
+        #[cfg(not(target_arch = "wasm32"))]
+        CompareSource::File(path) => {
+            let json = std::fs::read_to_string(path)
+                .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+            let doc: crate::components::Doc = serde_json::from_str(&json)
+                .map_err(|e| format!("{} is not a Velo document: {}", path, e))?;
+            let file_tab = doc
+                .tabs
+                .iter()
+                .find(|t| t.id == tab.id)
+                .or(doc.tabs.first())
+                .ok_or(format!("{} has no tabs", path))?;
+            Ok(TabSnapshot::of_tab(file_tab))
+        }
+        #[cfg(target_arch = "wasm32")]
+        CompareSource::File(path) => Err(format!("{} is not a checkpoint number", path)),
+    }
+}
+
+/// Highlights differences between two versions of the active tab over the canvas.
+pub fn compare_versions(
+    commands: &mut Commands,
+    app_state: &AppState,
+    notifier: &Notifier,
+    text: &str,
+) {
+    let Some((doc_id, tab)) = active_tab(app_state) else {
+        return;
+    };
+    let result = parse_compare_input(text)
+        .and_then(|(old, new)| Ok((load_version(tab, &old)?, load_version(tab, &new)?)));
+    let (old, new) = match result {
+        Ok(versions) => versions,
+        Err(e) => {
+            notifier.error(COMPARE_TASK, e);
+            return;
+        }
+    };
+    let diff = TabDiff::new(old, new);
+    let count = |change: ElementChange| diff.changes.values().filter(|c| **c == change).count();
+    let summary = [
+        ElementChange::Added,
+        ElementChange::Removed,
+        ElementChange::Moved,
+        ElementChange::Changed,
+    ]
+    .map(|change| format!("{} {}", count(change), change))
+    .join(", ");
+    notifier.success(
+        COMPARE_TASK,
+        format!("{}, press Escape to hide the differences", summary),
+    );
+    let mut overlay = DiffOverlay {
+        doc_id,
+        ..default()
+    };
+    overlay.tabs.insert(tab.id, diff);
+    commands.insert_resource(overlay);
+}
+
+pub fn compare_versions_handler(
+    mut commands: Commands,
+    query: Query<&Interaction, (Changed<Interaction>, With<CompareVersions>)>,
+    mut ui_state: ResMut<UiState>,
+    app_state: Res<AppState>,
+    main_panel_query: Query<Entity, With<MainPanel>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut cosmic_fonts: ResMut<Assets<CosmicFont>>,
+    font_system_state: ResMut<FontSystemState>,
+    theme: Res<Theme>,
+) {
+    let window = windows.single();
+    for interaction in query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let checkpoints = active_tab(&app_state)
+            .map(|(_, tab)| tab.checkpoints.len())
+            .unwrap_or_default();
+        // previous and latest checkpoint
+        let default = format!("{} {}", checkpoints.saturating_sub(1).max(1), checkpoints);
+        let id = ReflectableUuid::generate();
+        *ui_state = UiState::default();
+        commands.insert_resource(bevy_cosmic_edit::ActiveEditor { entity: None });
+        ui_state.modal_id = Some(id);
+        let entity = spawn_modal(
+            &mut commands,
+            &theme,
+            &mut cosmic_fonts,
+            font_system_state.0.clone().unwrap(),
+            window,
+            id,
+            ModalAction::CompareVersions(default),
+        );
+        commands.entity(main_panel_query.single()).add_child(entity);
+    }
+}
+
+/// Escape hides the differences, merges are closed from their panel.
+pub fn close_diff_overlay(mut commands: Commands, input: Res<Input<KeyCode>>) {
+    if input.just_pressed(KeyCode::Escape) {
+        commands.remove_resource::<DiffOverlay>();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_compare_input() {
+        assert_eq!(
+            parse_compare_input(" 2  7 "),
+            Ok((CompareSource::Checkpoint(2), CompareSource::Checkpoint(7)))
+        );
+        assert_eq!(
+            parse_compare_input("./old.json ./new.json"),
+            Ok((
+                CompareSource::File("./old.json".to_string()),
+                CompareSource::File("./new.json".to_string())
+            ))
+        );
+        assert!(parse_compare_input("7").is_err());
+        assert!(parse_compare_input("1 2 3").is_err());
+    }
+}
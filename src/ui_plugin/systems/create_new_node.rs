@@ -8,7 +8,7 @@ use crate::{
     utils::ReflectableUuid,
 };
 
-use super::{ui_helpers::spawn_sprite_node, AddRect, NodeMeta, UiState};
+use super::{ui_helpers::spawn_sprite_node, AddRect, NodeMeta, NodeMetadata, UiState};
 
 pub fn create_new_node(
     mut commands: Commands,
@@ -55,8 +55,14 @@ pub fn create_new_node(
                 pair_bg_color: event.node.bg_color.clone(),
                 position: (event.node.x, event.node.y, tab.z_index),
                 text_pos: event.node.text.pos.clone(),
+                text_style: event.node.text.style.clone(),
+                auto_size: event.node.auto_size,
                 is_active: true,
                 visible: true,
+                metadata: NodeMetadata {
+                    tags: event.node.tags.clone(),
+                    fields: event.node.metadata.clone(),
+                },
             },
         );
     }
@@ -0,0 +1,404 @@
+use std::collections::{BTreeMap, HashMap};
+
+use bevy::{prelude::*, sprite::Anchor, text::Text2dBounds};
+use bevy_cosmic_edit::CosmicEdit;
+use bevy_prototype_lyon::prelude::{GeometryBuilder, ShapeBundle, Stroke};
+use bevy_prototype_lyon::shapes;
+use serde_json::{json, Map, Value};
+use similar::{ChangeTag, TextDiff};
+use velo_collab::ElementKind;
+
+use super::ui_helpers::{DiffMark, RawText, VeloNode};
+use crate::components::Tab;
+use crate::resources::AppState;
+use crate::themes::Theme;
+use crate::utils::ReflectableUuid;
+
+/// Fields of a note that only change when it is moved or brought to front/back.
+const POSITION_FIELDS: [&str; 3] = ["x", "y", "z"];
+/// Highlights are drawn above notes and below remote cursors.
+const OVERLAY_Z: f32 = 800.;
+/// Space between a note and its highlight.
+const OVERLAY_MARGIN: f32 = 6.;
+/// Narrow notes get a wider text diff below them.
+const MIN_TEXT_DIFF_WIDTH: f32 = 200.;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ElementChange {
+    Added,
+    Removed,
+    Moved,
+    Changed,
+    /// Changed differently on both sides of a merge
+    Conflict,
+}
+
+impl ElementChange {
+    pub fn color(self) -> Color {
+        match self {
+            ElementChange::Added => Color::rgb(0.2, 0.7, 0.3),
+            ElementChange::Removed => Color::rgb(0.85, 0.2, 0.2),
+            ElementChange::Moved => Color::rgb(0.2, 0.5, 0.9),
+            ElementChange::Changed => Color::rgb(0.95, 0.6, 0.1),
+            ElementChange::Conflict => Color::rgb(0.8, 0.2, 0.8),
+        }
+    }
+}
+
+impl std::fmt::Display for ElementChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ElementChange::Added => write!(f, "added"),
+            ElementChange::Removed => write!(f, "removed"),
+            ElementChange::Moved => write!(f, "moved"),
+            ElementChange::Changed => write!(f, "changed"),
+            ElementChange::Conflict => write!(f, "conflict"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextChange {
+    Same,
+    Removed,
+    Inserted,
+}
+
+/// Word level changes turning `old` into `new`, neighbouring words with the same change are joined.
+pub fn text_changes(old: &str, new: &str) -> Vec<(TextChange, String)> {
+    let mut changes: Vec<(TextChange, String)> = vec![];
+    for change in TextDiff::from_words(old, new).iter_all_changes() {
+        let kind = match change.tag() {
+            ChangeTag::Equal => TextChange::Same,
+            ChangeTag::Delete => TextChange::Removed,
+            ChangeTag::Insert => TextChange::Inserted,
+        };
+        match changes.last_mut() {
+            Some((last, text)) if *last == kind => text.push_str(change.value()),
+            _ => changes.push((kind, change.value().to_string())),
+        }
+    }
+    changes
+}
+
+/// Contents of a tab checkpoint with elements keyed by id, arrows by the notes they connect.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TabSnapshot {
+    pub images: Map<String, Value>,
+    pub elements: BTreeMap<String, (ElementKind, Map<String, Value>)>,
+}
+
+impl TabSnapshot {
+    pub fn parse(json: &str) -> Option<Self> {
+        let json: Value = serde_json::from_str(json).ok()?;
+        let mut snapshot = TabSnapshot {
+            images: json["images"].as_object().cloned().unwrap_or_default(),
+            ..default()
+        };
+        for (key, kind) in [
+            ("nodes", ElementKind::Node),
+            ("arrows", ElementKind::Arrow),
+            ("drawings", ElementKind::Drawing),
+        ] {
+            for value in json[key].as_array().into_iter().flatten() {
+                let Value::Object(fields) = value else {
+                    continue;
+                };
+                let id = match kind {
+                    ElementKind::Arrow => arrow_key(fields),
+                    _ => match fields.get("id").and_then(Value::as_str) {
+                        Some(id) => id.to_string(),
+                        None => continue,
+                    },
+                };
+                snapshot.elements.insert(id, (kind, fields.clone()));
+            }
+        }
+        Some(snapshot)
+    }
+
+    /// Latest checkpoint of the tab, empty for tabs that were never saved.
+    pub fn of_tab(tab: &Tab) -> Self {
+        tab.checkpoints
+            .back()
+            .and_then(|json| TabSnapshot::parse(json))
+            .unwrap_or_default()
+    }
+
+    /// Checkpoint JSON in the format written by `save_tab`.
+    pub fn to_json(&self) -> String {
+        let mut nodes = vec![];
+        let mut arrows = vec![];
+        let mut drawings = vec![];
+        for (kind, fields) in self.elements.values() {
+            let value = Value::Object(fields.clone());
+            match kind {
+                ElementKind::Node => nodes.push(value),
+                ElementKind::Arrow => arrows.push(value),
+                ElementKind::Drawing => drawings.push(value),
+                // snapshots keep images apart in `images`
+                ElementKind::Image => {}
+            }
+        }
+        json!({
+            "images": self.images,
+            "nodes": nodes,
+            "arrows": arrows,
+            "drawings": drawings,
+        })
+        .to_string()
+    }
+}
+
+/// Arrows have no id, they are identified by the notes and sides they connect.
+pub fn arrow_key(fields: &Map<String, Value>) -> String {
+    let connector = |side: &str| fields.get(side).map(Value::to_string).unwrap_or_default();
+    format!("{}-{}", connector("start"), connector("end"))
+}
+
+/// How elements changed from `old` to `new`, unchanged elements are left out.
+pub fn diff_snapshots(old: &TabSnapshot, new: &TabSnapshot) -> BTreeMap<String, ElementChange> {
+    let mut changes = BTreeMap::new();
+    for (id, (_, fields)) in &new.elements {
+        let change = match old.elements.get(id) {
+            None => ElementChange::Added,
+            Some((_, old_fields)) if old_fields == fields => continue,
+            Some((_, old_fields)) if only_moved(old_fields, fields) => ElementChange::Moved,
+            Some(_) => ElementChange::Changed,
+        };
+        changes.insert(id.clone(), change);
+    }
+    for id in old.elements.keys() {
+        if !new.elements.contains_key(id) {
+            changes.insert(id.clone(), ElementChange::Removed);
+        }
+    }
+    changes
+}
+
+fn only_moved(old: &Map<String, Value>, new: &Map<String, Value>) -> bool {
+    old.keys()
+        .chain(new.keys())
+        .all(|field| POSITION_FIELDS.contains(&field.as_str()) || old.get(field) == new.get(field))
+}
+
+/// Changes of one tab and its contents before and after them.
+#[derive(Clone, Debug, Default)]
+pub struct TabDiff {
+    pub changes: BTreeMap<String, ElementChange>,
+    /// Removed and moved notes are outlined where they were
+    pub old: TabSnapshot,
+    /// Notes missing on the canvas are outlined where they are in this snapshot
+    pub new: TabSnapshot,
+}
+
+impl TabDiff {
+    pub fn new(old: TabSnapshot, new: TabSnapshot) -> Self {
+        TabDiff {
+            changes: diff_snapshots(&old, &new),
+            old,
+            new,
+        }
+    }
+}
+
+/// Highlights of changed notes drawn over the canvas while the active tab has a diff.
+#[derive(Resource, Default)]
+pub struct DiffOverlay {
+    pub doc_id: ReflectableUuid,
+    pub tabs: HashMap<ReflectableUuid, TabDiff>,
+}
+
+/// Center and size of a note stored in a checkpoint.
+fn node_rect(fields: &Map<String, Value>) -> Option<(Vec2, Vec2)> {
+    let number = |field: &str| fields.get(field).and_then(Value::as_f64).map(|v| v as f32);
+    Some((
+        Vec2::new(number("x")?, number("y")?),
+        Vec2::new(number("width")?, number("height")?),
+    ))
+}
+
+fn node_text(snapshot: &TabSnapshot, id: &str) -> Option<String> {
+    let (_, fields) = snapshot.elements.get(id)?;
+    fields.get("text")?["text"].as_str().map(str::to_string)
+}
+
+fn spawn_outline(
+    commands: &mut Commands,
+    (position, size): (Vec2, Vec2),
+    color: Color,
+    width: f32,
+) {
+    commands.spawn((
+        ShapeBundle {
+            path: GeometryBuilder::build_as(&shapes::Rectangle {
+                extents: size + Vec2::splat(2. * OVERLAY_MARGIN),
+                origin: shapes::RectangleOrigin::Center,
+            }),
+            transform: Transform::from_xyz(position.x, position.y, OVERLAY_Z),
+            ..default()
+        },
+        Stroke::new(color, width),
+        DiffMark,
+    ));
+}
+
+/// Removed words in red and inserted ones in green below the note.
+fn spawn_text_diff(
+    commands: &mut Commands,
+    theme: &Theme,
+    (position, size): (Vec2, Vec2),
+    changes: Vec<(TextChange, String)>,
+) {
+    let sections = changes.into_iter().map(|(change, text)| {
+        let color = match change {
+            TextChange::Same => theme.font,
+            TextChange::Removed => ElementChange::Removed.color(),
+            TextChange::Inserted => ElementChange::Added.color(),
+        };
+        TextSection::new(
+            text,
+            TextStyle {
+                font_size: 14.,
+                color,
+                ..default()
+            },
+        )
+    });
+    commands.spawn((
+        Text2dBundle {
+            text: Text::from_sections(sections),
+            text_anchor: Anchor::TopCenter,
+            text_2d_bounds: Text2dBounds {
+                size: Vec2::new(size.x.max(MIN_TEXT_DIFF_WIDTH), f32::INFINITY),
+            },
+            transform: Transform::from_xyz(
+                position.x,
+                position.y - size.y / 2. - 2. * OVERLAY_MARGIN,
+                OVERLAY_Z,
+            ),
+            ..default()
+        },
+        DiffMark,
+    ));
+}
+
+/// Outlines changed notes of the active tab, moved notes also where they were and changed text is
+/// shown as a word diff.
+pub fn draw_diff_overlay(
+    mut commands: Commands,
+    overlay: Option<Res<DiffOverlay>>,
+    app_state: Res<AppState>,
+    theme: Res<Theme>,
+    marks: Query<Entity, With<DiffMark>>,
+    raw_text_query: Query<(&RawText, &CosmicEdit)>,
+    velo_node_query: Query<(&VeloNode, &GlobalTransform)>,
+) {
+    for entity in marks.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    let Some(overlay) = overlay else {
+        return;
+    };
+    if app_state.current_document != Some(overlay.doc_id) {
+        return;
+    }
+    let Some(diff) = app_state
+        .docs
+        .get(&overlay.doc_id)
+        .and_then(|doc| doc.tabs.iter().find(|tab| tab.is_active))
+        .and_then(|tab| overlay.tabs.get(&tab.id))
+    else {
+        return;
+    };
+    let snapshot_rect = |snapshot: &TabSnapshot, id: &str| match snapshot.elements.get(id) {
+        Some((ElementKind::Node, fields)) => node_rect(fields),
+        _ => None,
+    };
+    for (id, change) in &diff.changes {
+        let old_rect = snapshot_rect(&diff.old, id);
+        if *change == ElementChange::Removed {
+            if let Some(rect) = old_rect {
+                spawn_outline(&mut commands, rect, change.color(), 3.);
+            }
+            continue;
+        }
+        // notes on the canvas are outlined where they are now
+        let live_rect = id.parse().ok().map(ReflectableUuid).and_then(|node_id| {
+            let size = raw_text_query
+                .iter()
+                .find(|(raw_text, _)| raw_text.id == node_id)
+                .map(|(_, cosmic_edit)| Vec2::new(cosmic_edit.width, cosmic_edit.height))?;
+            let position = velo_node_query
+                .iter()
+                .find(|(node, _)| node.id == node_id)
+                .map(|(_, transform)| transform.translation().truncate())?;
+            Some((position, size))
+        });
+        let Some(rect) = live_rect.or_else(|| snapshot_rect(&diff.new, id)) else {
+            continue;
+        };
+        spawn_outline(&mut commands, rect, change.color(), 3.);
+        if let Some(old_rect) = old_rect.filter(|old_rect| old_rect.0 != rect.0) {
+            spawn_outline(&mut commands, old_rect, change.color().with_a(0.4), 1.5);
+        }
+        if let (Some(old), Some(new)) = (node_text(&diff.old, id), node_text(&diff.new, id)) {
+            if old != new {
+                spawn_text_diff(&mut commands, &theme, rect, text_changes(&old, &new));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_snapshots() {
+        let old = TabSnapshot::parse(
+            &json!({
+                "images": {},
+                "nodes": [
+                    {"id": "a", "x": 0., "y": 0., "width": 10., "height": 10., "text": "a"},
+                    {"id": "b", "x": 0., "y": 0., "width": 10., "height": 10., "text": "b"},
+                    {"id": "c", "x": 0., "y": 0., "width": 10., "height": 10., "text": "c"},
+                    {"id": "d", "x": 0., "y": 0., "width": 10., "height": 10., "text": "d"}
+                ],
+                "arrows": [{"start": {"id": "a", "pos": "Top"}, "end": {"id": "b", "pos": "Top"}}],
+                "drawings": []
+            })
+            .to_string(),
+        )
+        .unwrap();
+        let mut new = old.clone();
+        new.elements.remove("b");
+        new.elements.get_mut("c").unwrap().1["x"] = json!(50.);
+        new.elements.get_mut("d").unwrap().1["text"] = json!("changed");
+        let mut added = new.elements["a"].clone();
+        added.1["id"] = json!("e");
+        new.elements.insert("e".to_string(), added);
+
+        let changes = diff_snapshots(&old, &new);
+        assert_eq!(changes.len(), 4);
+        assert_eq!(changes["b"], ElementChange::Removed);
+        assert_eq!(changes["c"], ElementChange::Moved);
+        assert_eq!(changes["d"], ElementChange::Changed);
+        assert_eq!(changes["e"], ElementChange::Added);
+        assert_eq!(
+            node_rect(&old.elements["b"].1),
+            Some((Vec2::ZERO, Vec2::splat(10.)))
+        );
+        assert_eq!(TabSnapshot::parse(&new.to_json()).unwrap(), new);
+
+        assert_eq!(
+            text_changes("buy milk today", "buy oat milk"),
+            vec![
+                (TextChange::Same, "buy ".to_string()),
+                (TextChange::Inserted, "oat ".to_string()),
+                (TextChange::Same, "milk".to_string()),
+                (TextChange::Removed, " today".to_string()),
+            ]
+        );
+    }
+}
@@ -8,17 +8,21 @@ use bevy_cosmic_edit::CosmicFont;
 use super::ui_helpers::ScrollingList;
 use crate::{resources::FontSystemState, themes::Theme, ui_plugin::ui_helpers::DocListItemButton};
 
-use crate::resources::{AppState, LoadDocRequest, SaveDocRequest};
+use crate::components::DocSummary;
+use crate::resources::{AppState, DocSortMode, LoadDocRequest, SaveDocRequest};
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 
 use bevy_pkv::PkvStore;
 
 use crate::{ui_plugin::ui_helpers::add_list_item, utils::ReflectableUuid};
 
 use super::{
-    ui_helpers::{DeleteDoc, DocList, DocListItemContainer},
-    UpdateDeleteDocBtn,
+    ui_helpers::{
+        DeleteDoc, DocList, DocListFolder, DocListItemContainer, DocListSection, DocSortButton,
+        GenericButton, RecentDocButton, TagFacet, TagFacetList,
+    },
+    UpdateDeleteDocBtn, MAX_RECENT_DOCS,
 };
 
 pub fn list_item_click(
@@ -32,16 +36,7 @@ pub fn list_item_click(
     for (interaction, doc_list_item) in &mut interaction_query.iter_mut() {
         match *interaction {
             Interaction::Pressed => {
-                if Some(doc_list_item.id) != state.current_document {
-                    commands.insert_resource(SaveDocRequest {
-                        doc_id: state.current_document.unwrap(),
-                        path: None,
-                    });
-                    state.current_document = Some(doc_list_item.id);
-                    commands.insert_resource(LoadDocRequest {
-                        doc_id: doc_list_item.id,
-                    });
-                }
+                open_doc(&mut state, &mut commands, doc_list_item.id);
             }
             Interaction::Hovered => {}
             Interaction::None => {}
@@ -49,6 +44,17 @@ pub fn list_item_click(
     }
 }
 
+fn open_doc(state: &mut ResMut<AppState>, commands: &mut Commands, doc_id: ReflectableUuid) {
+    if Some(doc_id) != state.current_document {
+        commands.insert_resource(SaveDocRequest {
+            doc_id: state.current_document.unwrap(),
+            path: None,
+        });
+        state.current_document = Some(doc_id);
+        commands.insert_resource(LoadDocRequest { doc_id });
+    }
+}
+
 pub fn mouse_scroll_list(
     mut mouse_wheel_events: EventReader<MouseWheel>,
     mut query_list: Query<(&mut ScrollingList, &mut Style, &Parent, &Node)>,
@@ -89,14 +95,111 @@ pub fn doc_list_del_button_update(
     }
 }
 
+/// Row of the document list, rows are compared to decide whether the list has to be re-created.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DocListRow {
+    Header(String),
+    Folder {
+        folder: String,
+        collapsed: bool,
+        count: usize,
+    },
+    Recent(ReflectableUuid),
+    Doc(ReflectableUuid),
+}
+
+#[derive(Clone)]
+pub struct DocListEntry {
+    pub id: ReflectableUuid,
+    pub name: String,
+    pub summary: DocSummary,
+}
+
+/// Lays out the document list: recently opened documents, pinned documents,
+/// folders and then the rest of documents, each section sorted by `doc_sort`.
+pub fn get_doc_list_rows(
+    mut entries: Vec<DocListEntry>,
+    doc_sort: DocSortMode,
+    recent_docs: &[ReflectableUuid],
+    current_document: Option<ReflectableUuid>,
+    collapsed_folders: &HashSet<String>,
+) -> Vec<DocListRow> {
+    match doc_sort {
+        DocSortMode::Name => entries.sort_by(|a, b| a.name.cmp(&b.name).then(a.id.0.cmp(&b.id.0))),
+        DocSortMode::LastModified => entries.sort_by(|a, b| {
+            b.summary
+                .modified_at
+                .total_cmp(&a.summary.modified_at)
+                .then(a.id.0.cmp(&b.id.0))
+        }),
+        DocSortMode::Created => entries.sort_by(|a, b| {
+            b.summary
+                .created_at
+                .total_cmp(&a.summary.created_at)
+                .then(a.id.0.cmp(&b.id.0))
+        }),
+    }
+    let mut rows = vec![];
+
+    let recent: Vec<DocListRow> = recent_docs
+        .iter()
+        .filter(|id| Some(**id) != current_document && entries.iter().any(|e| e.id == **id))
+        .take(MAX_RECENT_DOCS)
+        .map(|id| DocListRow::Recent(*id))
+        .collect();
+    if !recent.is_empty() {
+        rows.push(DocListRow::Header("Recent".to_string()));
+        rows.extend(recent);
+    }
+
+    let pinned: Vec<DocListRow> = entries
+        .iter()
+        .filter(|entry| entry.summary.pinned)
+        .map(|entry| DocListRow::Doc(entry.id))
+        .collect();
+    if !pinned.is_empty() {
+        rows.push(DocListRow::Header("Pinned".to_string()));
+        rows.extend(pinned);
+    }
+
+    let mut folders: BTreeMap<&String, Vec<ReflectableUuid>> = BTreeMap::new();
+    for entry in entries.iter().filter(|entry| !entry.summary.pinned) {
+        if let Some(folder) = &entry.summary.folder {
+            folders.entry(folder).or_default().push(entry.id);
+        }
+    }
+    for (folder, ids) in folders {
+        let collapsed = collapsed_folders.contains(folder);
+        rows.push(DocListRow::Folder {
+            folder: folder.clone(),
+            collapsed,
+            count: ids.len(),
+        });
+        if !collapsed {
+            rows.extend(ids.into_iter().map(DocListRow::Doc));
+        }
+    }
+
+    let other: Vec<DocListRow> = entries
+        .iter()
+        .filter(|entry| !entry.summary.pinned && entry.summary.folder.is_none())
+        .map(|entry| DocListRow::Doc(entry.id))
+        .collect();
+    if !rows.is_empty() && !other.is_empty() {
+        rows.push(DocListRow::Header("Documents".to_string()));
+    }
+    rows.extend(other);
+    rows
+}
+
 pub fn doc_list_ui_changed(
     mut commands: Commands,
     app_state: Res<AppState>,
-    mut last_doc_list: Local<HashSet<ReflectableUuid>>,
+    mut last_doc_list: Local<Vec<DocListRow>>,
     mut doc_list_query: Query<Entity, With<DocList>>,
     asset_server: Res<AssetServer>,
     pkv: Res<PkvStore>,
-    mut query_container: Query<Entity, With<DocListItemContainer>>,
+    mut query_container: Query<Entity, Or<(With<DocListItemContainer>, With<DocListSection>)>>,
     mut event_writer: EventWriter<UpdateDeleteDocBtn>,
     theme: Res<Theme>,
     mut cosmic_fonts: ResMut<Assets<CosmicFont>>,
@@ -105,40 +208,301 @@ pub fn doc_list_ui_changed(
 ) {
     let primary_window = windows.single();
     let scale_factor = primary_window.scale_factor() as f32;
-    if app_state.is_changed() && app_state.doc_list_ui != *last_doc_list {
+    if !app_state.is_changed() {
+        return;
+    }
+    let doc_list_ui: HashSet<ReflectableUuid> = match &app_state.tag_filter {
+        Some(tag) => {
+            let docs_tags = get_docs_tags(&pkv, &app_state);
+            app_state
+                .doc_list_ui
+                .iter()
+                .filter(|doc_id| docs_tags.get(doc_id).is_some_and(|tags| tags.contains(tag)))
+                .cloned()
+                .collect()
+        }
+        None => app_state.doc_list_ui.clone(),
+    };
+    let summaries = get_docs_summaries(&pkv, &app_state);
+    let entries = doc_list_ui
+        .iter()
+        .map(|doc_id| DocListEntry {
+            id: *doc_id,
+            name: get_doc_name(*doc_id, &pkv, &app_state),
+            summary: summaries.get(doc_id).cloned().unwrap_or_default(),
+        })
+        .collect();
+    let rows = get_doc_list_rows(
+        entries,
+        app_state.doc_sort,
+        &app_state.recent_docs,
+        app_state.current_document,
+        &app_state.collapsed_folders,
+    );
+    if rows != *last_doc_list {
         // Think about re-using UI elements instead of destroying and re-creating them
         for entity in query_container.iter_mut() {
             commands.entity(entity).despawn_recursive();
         }
         let doc_list = doc_list_query.single_mut();
-        let mut doc_tuples: Vec<(String, ReflectableUuid)> = app_state
-            .doc_list_ui
-            .iter()
-            .map(|doc_id| {
-                let doc_name = get_doc_name(*doc_id, &pkv, &app_state);
-                (doc_name, *doc_id)
-            })
-            .collect();
-        // Sort the tuples alphabetically based on doc_name
-        doc_tuples.sort_by(|(name1, _), (name2, _)| name1.cmp(name2));
-        for (doc_name, doc_id) in doc_tuples {
-            let doc_list_item = add_list_item(
-                &mut commands,
-                &mut cosmic_fonts,
-                font_system_state.0.clone().unwrap(),
-                &theme,
-                &asset_server,
-                doc_id,
-                doc_name,
-                scale_factor,
-            );
-            commands.entity(doc_list).add_child(doc_list_item);
+        for row in rows.iter() {
+            let entity = match row {
+                DocListRow::Header(title) => add_doc_list_header(&mut commands, &theme, title),
+                DocListRow::Folder {
+                    folder,
+                    collapsed,
+                    count,
+                } => add_doc_list_button(
+                    &mut commands,
+                    &theme,
+                    format!(
+                        "{} {} ({})",
+                        if *collapsed { "+" } else { "-" },
+                        folder,
+                        count
+                    ),
+                    DocListFolder {
+                        folder: folder.clone(),
+                    },
+                ),
+                DocListRow::Recent(doc_id) => add_doc_list_button(
+                    &mut commands,
+                    &theme,
+                    get_doc_name(*doc_id, &pkv, &app_state),
+                    RecentDocButton { id: *doc_id },
+                ),
+                DocListRow::Doc(doc_id) => add_list_item(
+                    &mut commands,
+                    &mut cosmic_fonts,
+                    font_system_state.0.clone().unwrap(),
+                    &theme,
+                    &asset_server,
+                    *doc_id,
+                    get_doc_name(*doc_id, &pkv, &app_state),
+                    scale_factor,
+                ),
+            };
+            commands.entity(doc_list).add_child(entity);
         }
         event_writer.send(UpdateDeleteDocBtn);
-        *last_doc_list = app_state.doc_list_ui.clone();
+        *last_doc_list = rows;
+    }
+}
+
+fn add_doc_list_header(commands: &mut Commands, theme: &Res<Theme>, title: &str) -> Entity {
+    commands
+        .spawn((
+            TextBundle::from_section(
+                title,
+                TextStyle {
+                    font_size: 14.,
+                    color: theme.font,
+                    ..default()
+                },
+            )
+            .with_style(Style {
+                align_self: AlignSelf::Start,
+                margin: UiRect::new(Val::Px(5.), Val::Px(5.), Val::Px(8.), Val::Px(2.)),
+                ..default()
+            }),
+            DocListSection,
+        ))
+        .id()
+}
+
+fn add_doc_list_button(
+    commands: &mut Commands,
+    theme: &Res<Theme>,
+    label: String,
+    component: impl Component,
+) -> Entity {
+    commands
+        .spawn((
+            ButtonBundle {
+                border_color: theme.btn_border.into(),
+                background_color: theme.doc_list_bg.into(),
+                style: Style {
+                    width: Val::Percent(100.),
+                    border: UiRect::all(Val::Px(1.)),
+                    padding: UiRect::all(Val::Px(4.)),
+                    ..default()
+                },
+                ..default()
+            },
+            GenericButton,
+            DocListSection,
+            component,
+        ))
+        .with_children(|builder| {
+            builder.spawn(TextBundle::from_section(
+                label,
+                TextStyle {
+                    font_size: theme.font_size,
+                    color: theme.font,
+                    ..default()
+                },
+            ));
+        })
+        .id()
+}
+
+pub fn doc_list_folder_click(
+    mut interaction_query: Query<
+        (&Interaction, &DocListFolder),
+        (Changed<Interaction>, With<DocListFolder>),
+    >,
+    mut app_state: ResMut<AppState>,
+) {
+    for (interaction, folder) in &mut interaction_query.iter_mut() {
+        if let Interaction::Pressed = interaction {
+            if !app_state.collapsed_folders.remove(&folder.folder) {
+                app_state.collapsed_folders.insert(folder.folder.clone());
+            }
+        }
+    }
+}
+
+pub fn recent_doc_click(
+    mut interaction_query: Query<
+        (&Interaction, &RecentDocButton),
+        (Changed<Interaction>, With<RecentDocButton>),
+    >,
+    mut state: ResMut<AppState>,
+    mut commands: Commands,
+) {
+    for (interaction, recent_doc) in &mut interaction_query.iter_mut() {
+        if let Interaction::Pressed = interaction {
+            open_doc(&mut state, &mut commands, recent_doc.id);
+        }
     }
 }
 
+pub fn doc_sort_click(
+    mut interaction_query: Query<
+        (&Interaction, &Children),
+        (Changed<Interaction>, With<DocSortButton>),
+    >,
+    mut text_query: Query<&mut Text>,
+    mut app_state: ResMut<AppState>,
+    mut pkv: ResMut<PkvStore>,
+) {
+    for (interaction, children) in &mut interaction_query.iter_mut() {
+        if let Interaction::Pressed = interaction {
+            app_state.doc_sort = app_state.doc_sort.next();
+            pkv.set("doc_sort", &app_state.doc_sort).unwrap();
+            for child in children.iter() {
+                if let Ok(mut text) = text_query.get_mut(*child) {
+                    text.sections[0].value = format!("Sort by {}", app_state.doc_sort);
+                }
+            }
+        }
+    }
+}
+
+pub fn tag_facets_changed(
+    mut commands: Commands,
+    app_state: Res<AppState>,
+    pkv: Res<PkvStore>,
+    mut last_facets: Local<Option<(BTreeSet<String>, Option<String>)>>,
+    tag_facet_list: Query<Entity, With<TagFacetList>>,
+    theme: Res<Theme>,
+) {
+    if !app_state.is_changed() {
+        return;
+    }
+    let tags: BTreeSet<String> = get_docs_tags(&pkv, &app_state)
+        .into_values()
+        .flatten()
+        .collect();
+    let facets = Some((tags, app_state.tag_filter.clone()));
+    if facets == *last_facets {
+        return;
+    }
+    let Ok(tag_facet_list) = tag_facet_list.get_single() else {
+        return;
+    };
+    commands.entity(tag_facet_list).despawn_descendants();
+    for tag in facets.as_ref().unwrap().0.iter() {
+        let is_selected = app_state.tag_filter.as_ref() == Some(tag);
+        let facet = commands
+            .spawn((
+                ButtonBundle {
+                    border_color: theme.btn_border.into(),
+                    background_color: if is_selected {
+                        theme.node_found_color.into()
+                    } else {
+                        theme.ok_cancel_bg.into()
+                    },
+                    style: Style {
+                        border: UiRect::all(Val::Px(1.)),
+                        padding: UiRect::horizontal(Val::Px(4.)),
+                        margin: UiRect::all(Val::Px(2.)),
+                        ..default()
+                    },
+                    ..default()
+                },
+                GenericButton,
+                TagFacet { tag: tag.clone() },
+            ))
+            .with_children(|builder| {
+                builder.spawn(TextBundle::from_section(
+                    format!("#{}", tag),
+                    TextStyle {
+                        font_size: 14.,
+                        color: theme.font,
+                        ..default()
+                    },
+                ));
+            })
+            .id();
+        commands.entity(tag_facet_list).add_child(facet);
+    }
+    *last_facets = facets;
+}
+
+pub fn tag_facet_click(
+    mut interaction_query: Query<(&Interaction, &TagFacet), (Changed<Interaction>, With<TagFacet>)>,
+    mut app_state: ResMut<AppState>,
+) {
+    for (interaction, facet) in &mut interaction_query.iter_mut() {
+        if let Interaction::Pressed = interaction {
+            if app_state.tag_filter.as_ref() == Some(&facet.tag) {
+                app_state.tag_filter = None;
+            } else {
+                app_state.tag_filter = Some(facet.tag.clone());
+            }
+        }
+    }
+}
+
+/// Returns tags of all documents, tags of documents loaded to memory take precedence over stored ones.
+pub fn get_docs_tags(
+    pkv: &Res<PkvStore>,
+    app_state: &Res<AppState>,
+) -> HashMap<ReflectableUuid, Vec<String>> {
+    let mut tags = pkv
+        .get::<HashMap<ReflectableUuid, Vec<String>>>("tags")
+        .unwrap_or_default();
+    for (doc_id, doc) in app_state.docs.iter() {
+        tags.insert(*doc_id, doc.tags.clone());
+    }
+    tags
+}
+
+/// Returns summaries of all documents, documents loaded to memory take precedence over stored ones.
+pub fn get_docs_summaries(
+    pkv: &Res<PkvStore>,
+    app_state: &Res<AppState>,
+) -> HashMap<ReflectableUuid, DocSummary> {
+    let mut summaries = pkv
+        .get::<HashMap<ReflectableUuid, DocSummary>>("doc_summaries")
+        .unwrap_or_default();
+    for (doc_id, doc) in app_state.docs.iter() {
+        summaries.insert(*doc_id, doc.summary());
+    }
+    summaries
+}
+
 pub fn get_doc_name(
     doc_id: ReflectableUuid,
     pkv: &Res<PkvStore>,
@@ -155,3 +519,95 @@ pub fn get_doc_name(
 
     "Unknown".to_string()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, folder: Option<&str>, pinned: bool, modified_at: f64) -> DocListEntry {
+        DocListEntry {
+            id: ReflectableUuid::generate(),
+            name: name.to_string(),
+            summary: DocSummary {
+                folder: folder.map(|folder| folder.to_string()),
+                pinned,
+                created_at: 0.,
+                modified_at,
+            },
+        }
+    }
+
+    #[test]
+    fn test_doc_list_rows() {
+        let a = entry("a", None, false, 3.);
+        let b = entry("b", None, false, 1.);
+        let c = entry("c", Some("work"), false, 2.);
+        let d = entry("d", Some("work"), true, 4.);
+        let (a_id, b_id, c_id, d_id) = (a.id, b.id, c.id, d.id);
+        let entries = || vec![b.clone(), d.clone(), a.clone(), c.clone()];
+
+        let rows = get_doc_list_rows(
+            entries(),
+            DocSortMode::Name,
+            &[b_id, a_id, ReflectableUuid::generate()],
+            Some(b_id),
+            &HashSet::new(),
+        );
+        assert_eq!(
+            rows,
+            vec![
+                DocListRow::Header("Recent".to_string()),
+                DocListRow::Recent(a_id),
+                DocListRow::Header("Pinned".to_string()),
+                DocListRow::Doc(d_id),
+                DocListRow::Folder {
+                    folder: "work".to_string(),
+                    collapsed: false,
+                    count: 1
+                },
+                DocListRow::Doc(c_id),
+                DocListRow::Header("Documents".to_string()),
+                DocListRow::Doc(a_id),
+                DocListRow::Doc(b_id),
+            ]
+        );
+
+        let rows = get_doc_list_rows(
+            entries(),
+            DocSortMode::LastModified,
+            &[],
+            None,
+            &HashSet::from(["work".to_string()]),
+        );
+        assert_eq!(
+            rows,
+            vec![
+                DocListRow::Header("Pinned".to_string()),
+                DocListRow::Doc(d_id),
+                DocListRow::Folder {
+                    folder: "work".to_string(),
+                    collapsed: true,
+                    count: 1
+                },
+                DocListRow::Header("Documents".to_string()),
+                DocListRow::Doc(a_id),
+                DocListRow::Doc(b_id),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_doc_list_rows_without_sections() {
+        let a = entry("a", None, false, 1.);
+        let b = entry("b", None, false, 2.);
+        let (a_id, b_id) = (a.id, b.id);
+        let rows = get_doc_list_rows(
+            vec![a, b],
+            DocSortMode::LastModified,
+            &[],
+            None,
+            &HashSet::new(),
+        );
+        assert_eq!(rows, vec![DocListRow::Doc(b_id), DocListRow::Doc(a_id)]);
+    }
+}
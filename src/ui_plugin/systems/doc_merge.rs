@@ -0,0 +1,741 @@
+use std::collections::{BTreeSet, HashMap};
+
+use bevy::prelude::*;
+use bevy_pkv::PkvStore;
+use serde_json::{Map, Value};
+use velo_collab::ElementKind;
+
+use super::ui_helpers::{GenericButton, MergeButton, MergePanel};
+use super::{DiffOverlay, ElementChange, NotificationChannels, Notifier};
+use super::{TabDiff, TabSnapshot};
+use crate::components::{Doc, Tab};
+use crate::resources::{AppState, LoadDocRequest, SaveDocRequest};
+use crate::themes::Theme;
+use crate::utils::ReflectableUuid;
+use crate::MAX_CHECKPOINTS;
+
+const MERGE_TASK: &str = "merge";
+/// Store key of documents as they were last exchanged with others, the common ancestor of merges.
+const MERGE_BASES_KEY: &str = "merge_bases";
+/// Characters of note text shown in the merge panel.
+const LABEL_LENGTH: usize = 30;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MergeSide {
+    Ours,
+    Theirs,
+}
+
+/// Element changed differently in both documents.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MergeConflict {
+    pub tab_id: ReflectableUuid,
+    /// Element id, empty when the whole tab was removed on one side and changed on the other
+    pub id: String,
+    /// Fields changed on both sides, empty when one side removed the element
+    pub fields: Vec<String>,
+    pub label: String,
+}
+
+pub struct MergeResult {
+    pub doc: Doc,
+    pub conflicts: Vec<MergeConflict>,
+}
+
+type MergeChoices = HashMap<(ReflectableUuid, String), MergeSide>;
+
+/// Merge of an imported copy into the local document, previewed on the canvas until applied.
+#[derive(Resource)]
+pub struct DocMerge {
+    base: Option<Doc>,
+    ours: Doc,
+    theirs: Doc,
+    choices: MergeChoices,
+    conflicts: Vec<MergeConflict>,
+}
+
+/// Theirs when only they changed the value, ours otherwise.
+fn pick<'a, T: PartialEq>(base: Option<&T>, ours: &'a T, theirs: &'a T) -> &'a T {
+    if base == Some(ours) {
+        theirs
+    } else {
+        ours
+    }
+}
+
+/// Three-way merge of one element, returns the merged element and conflicting fields if any.
+fn merge_element(
+    base: Option<&Map<String, Value>>,
+    ours: Option<&Map<String, Value>>,
+    theirs: Option<&Map<String, Value>>,
+    side: MergeSide,
+) -> (Option<Map<String, Value>>, Option<Vec<String>>) {
+    if ours == theirs || theirs == base {
+        return (ours.cloned(), None);
+    }
+    if ours == base {
+        return (theirs.cloned(), None);
+    }
+    let (Some(ours), Some(theirs)) = (ours, theirs) else {
+        // removed on one side and changed on the other
+        let kept = match side {
+            MergeSide::Ours => ours,
+            MergeSide::Theirs => theirs,
+        };
+        return (kept.cloned(), Some(vec![]));
+    };
+    let empty = Map::new();
+    let base = base.unwrap_or(&empty);
+    let mut merged = Map::new();
+    let mut conflicts = vec![];
+    let fields: BTreeSet<&String> = ours.keys().chain(theirs.keys()).collect();
+    for field in fields {
+        let (b, o, t) = (base.get(field), ours.get(field), theirs.get(field));
+        let value = if o == t || t == b {
+            o
+        } else if o == b {
+            t
+        } else {
+            conflicts.push(field.clone());
+            match side {
+                MergeSide::Ours => o,
+                MergeSide::Theirs => t,
+            }
+        };
+        if let Some(value) = value {
+            merged.insert(field.clone(), value.clone());
+        }
+    }
+    (Some(merged), (!conflicts.is_empty()).then_some(conflicts))
+}
+
+fn element_label(kind: ElementKind, fields: Option<&Map<String, Value>>) -> String {
+    match kind {
+        ElementKind::Node => {
+            let text = fields
+                .and_then(|fields| fields.get("text"))
+                .and_then(|text| text["text"].as_str())
+                .and_then(|text| text.lines().find(|line| !line.trim().is_empty()))
+                .unwrap_or_default()
+                .trim();
+            let mut label: String = text.chars().take(LABEL_LENGTH).collect();
+            if label.len() < text.len() {
+                label.push('…');
+            }
+            format!("Note \"{}\"", label)
+        }
+        ElementKind::Arrow => "Arrow".to_string(),
+        ElementKind::Drawing => "Drawing".to_string(),
+        ElementKind::Image => "Image".to_string(),
+    }
+}
+
+fn merge_tab(
+    tab_id: ReflectableUuid,
+    base: Option<&TabSnapshot>,
+    ours: &TabSnapshot,
+    theirs: &TabSnapshot,
+    choices: &MergeChoices,
+    conflicts: &mut Vec<MergeConflict>,
+) -> TabSnapshot {
+    let empty = TabSnapshot::default();
+    let base = base.unwrap_or(&empty);
+    let mut merged = TabSnapshot::default();
+    let ids: BTreeSet<&String> = base
+        .elements
+        .keys()
+        .chain(ours.elements.keys())
+        .chain(theirs.elements.keys())
+        .collect();
+    for id in ids {
+        let get = |snapshot: &'_ TabSnapshot| snapshot.elements.get(id).cloned();
+        let (base_element, our_element, their_element) = (get(base), get(ours), get(theirs));
+        let kind = our_element
+            .as_ref()
+            .or(their_element.as_ref())
+            .or(base_element.as_ref())
+            .map(|(kind, _)| *kind)
+            .unwrap();
+        let side = choices
+            .get(&(tab_id, id.clone()))
+            .copied()
+            .unwrap_or(MergeSide::Ours);
+        let fields = |element: &Option<(ElementKind, Map<String, Value>)>| {
+            element.as_ref().map(|(_, fields)| fields.clone())
+        };
+        let (our_fields, their_fields) = (fields(&our_element), fields(&their_element));
+        let (element, conflict) = merge_element(
+            fields(&base_element).as_ref(),
+            our_fields.as_ref(),
+            their_fields.as_ref(),
+            side,
+        );
+        if let Some(fields) = conflict {
+            conflicts.push(MergeConflict {
+                tab_id,
+                id: id.clone(),
+                fields,
+                label: element_label(kind, our_fields.as_ref().or(their_fields.as_ref())),
+            });
+        }
+        if let Some(fields) = element {
+            merged.elements.insert(id.clone(), (kind, fields));
+        }
+    }
+    // arrows of removed notes can't be drawn
+    let nodes: BTreeSet<String> = merged
+        .elements
+        .iter()
+        .filter(|(_, (kind, _))| *kind == ElementKind::Node)
+        .map(|(id, _)| id.clone())
+        .collect();
+    merged.elements.retain(|_, (kind, fields)| {
+        *kind != ElementKind::Arrow
+            || ["start", "end"].iter().all(|side| {
+                fields[*side]["id"]
+                    .as_str()
+                    .is_some_and(|id| nodes.contains(id))
+            })
+    });
+    for id in nodes {
+        if let Some(image) = ours.images.get(&id).or(theirs.images.get(&id)) {
+            merged.images.insert(id, image.clone());
+        }
+    }
+    merged
+}
+
+/// Conflict of a tab removed on one side and changed on the other, returns the side to keep.
+fn tab_conflict(
+    tab_id: ReflectableUuid,
+    choices: &MergeChoices,
+    conflicts: &mut Vec<MergeConflict>,
+) -> MergeSide {
+    conflicts.push(MergeConflict {
+        tab_id,
+        id: String::new(),
+        fields: vec![],
+        label: "Tab".to_string(),
+    });
+    choices
+        .get(&(tab_id, String::new()))
+        .copied()
+        .unwrap_or(MergeSide::Ours)
+}
+
+pub fn push_checkpoint(tab: &mut Tab, snapshot: &TabSnapshot) {
+    if TabSnapshot::of_tab(tab) == *snapshot {
+        return;
+    }
+    if (tab.checkpoints.len() as i32) > MAX_CHECKPOINTS {
+        tab.checkpoints.pop_front();
+    }
+    tab.checkpoints.push_back(snapshot.to_json());
+}
+
+/// Three-way merge of documents keyed on tab and element ids, conflicts are resolved with `choices`
+/// and in favor of `ours` without a choice.
+pub fn merge_docs(
+    base: Option<&Doc>,
+    ours: &Doc,
+    theirs: &Doc,
+    choices: &MergeChoices,
+) -> MergeResult {
+    let mut doc = ours.clone();
+    let mut conflicts = vec![];
+    doc.name = pick(base.map(|base| &base.name), &ours.name, &theirs.name).clone();
+    doc.tags = pick(base.map(|base| &base.tags), &ours.tags, &theirs.tags).clone();
+    let base_tab =
+        |id: ReflectableUuid| base.and_then(|base| base.tabs.iter().find(|t| t.id == id));
+    doc.tabs = vec![];
+    for tab in &ours.tabs {
+        let base_tab = base_tab(tab.id);
+        match theirs.tabs.iter().find(|t| t.id == tab.id) {
+            Some(their_tab) => {
+                let snapshot = merge_tab(
+                    tab.id,
+                    base_tab.map(TabSnapshot::of_tab).as_ref(),
+                    &TabSnapshot::of_tab(tab),
+                    &TabSnapshot::of_tab(their_tab),
+                    choices,
+                    &mut conflicts,
+                );
+                let mut tab = tab.clone();
+                tab.name = pick(base_tab.map(|t| &t.name), &tab.name, &their_tab.name).clone();
+                tab.slides =
+                    pick(base_tab.map(|t| &t.slides), &tab.slides, &their_tab.slides).clone();
+                push_checkpoint(&mut tab, &snapshot);
+                doc.tabs.push(tab);
+            }
+            None => match base_tab {
+                // removed by them and unchanged here
+                Some(base_tab) if TabSnapshot::of_tab(base_tab) == TabSnapshot::of_tab(tab) => {}
+                // removed by them and changed here
+                Some(_) => {
+                    if tab_conflict(tab.id, choices, &mut conflicts) == MergeSide::Ours {
+                        doc.tabs.push(tab.clone());
+                    }
+                }
+                None => doc.tabs.push(tab.clone()),
+            },
+        }
+    }
+    for tab in &theirs.tabs {
+        if ours.tabs.iter().any(|t| t.id == tab.id) {
+            continue;
+        }
+        let keep = match base_tab(tab.id) {
+            None => true,
+            // removed here and unchanged by them
+            Some(base_tab) if TabSnapshot::of_tab(base_tab) == TabSnapshot::of_tab(tab) => false,
+            // removed here and changed by them
+            Some(_) => tab_conflict(tab.id, choices, &mut conflicts) == MergeSide::Theirs,
+        };
+        if keep {
+            let mut tab = tab.clone();
+            tab.is_active = false;
+            doc.tabs.push(tab);
+        }
+    }
+    if doc.tabs.is_empty() {
+        doc.tabs = ours.tabs.clone();
+    }
+    if !doc.tabs.iter().any(|tab| tab.is_active) {
+        doc.tabs[0].is_active = true;
+    }
+    MergeResult { doc, conflicts }
+}
+
+/// Remembers the document as last exchanged with others, only the latest checkpoint of tabs is kept.
+pub fn record_merge_base(pkv: &mut PkvStore, doc: &Doc) {
+    let mut doc = doc.clone();
+    doc.shared = None;
+    for tab in &mut doc.tabs {
+        tab.checkpoints = tab.checkpoints.pop_back().into_iter().collect();
+    }
+    let mut bases = pkv
+        .get::<HashMap<ReflectableUuid, Doc>>(MERGE_BASES_KEY)
+        .unwrap_or_default();
+    bases.insert(doc.id, doc);
+    if let Err(e) = pkv.set(MERGE_BASES_KEY, &bases) {
+        error!("Failed to save merge base: {}", e);
+    }
+}
+
+pub fn remove_merge_base(pkv: &mut PkvStore, doc_id: ReflectableUuid) {
+    if let Ok(mut bases) = pkv.get::<HashMap<ReflectableUuid, Doc>>(MERGE_BASES_KEY) {
+        if bases.remove(&doc_id).is_some() {
+            let _ = pkv.set(MERGE_BASES_KEY, &bases);
+        }
+    }
+}
+
+/// Shows the merged document on the canvas with changes to the local one highlighted.
+fn preview_merge(commands: &mut Commands, app_state: &mut AppState, merge: &mut DocMerge) {
+    let result = merge_docs(
+        merge.base.as_ref(),
+        &merge.ours,
+        &merge.theirs,
+        &merge.choices,
+    );
+    let mut overlay = DiffOverlay {
+        doc_id: merge.ours.id,
+        ..default()
+    };
+    for tab in &result.doc.tabs {
+        let old = merge
+            .ours
+            .tabs
+            .iter()
+            .find(|t| t.id == tab.id)
+            .map(TabSnapshot::of_tab)
+            .unwrap_or_default();
+        let mut diff = TabDiff::new(old, TabSnapshot::of_tab(tab));
+        for conflict in result.conflicts.iter().filter(|c| c.tab_id == tab.id) {
+            diff.changes
+                .insert(conflict.id.clone(), ElementChange::Conflict);
+        }
+        overlay.tabs.insert(tab.id, diff);
+    }
+    commands.insert_resource(overlay);
+    app_state.current_document = Some(merge.ours.id);
+    app_state.doc_list_ui.insert(merge.ours.id);
+    app_state.docs.insert(merge.ours.id, result.doc);
+    commands.insert_resource(LoadDocRequest {
+        doc_id: merge.ours.id,
+    });
+    merge.conflicts = result.conflicts;
+}
+
+/// Starts merging an imported copy of a document that already exists.
+pub fn start_doc_merge(
+    commands: &mut Commands,
+    app_state: &mut AppState,
+    pkv: &PkvStore,
+    ours: Doc,
+    theirs: Doc,
+    notifier: &Notifier,
+) {
+    let base = pkv
+        .get::<HashMap<ReflectableUuid, Doc>>(MERGE_BASES_KEY)
+        .ok()
+        .and_then(|mut bases| bases.remove(&ours.id));
+    let mut merge = DocMerge {
+        base,
+        ours,
+        theirs,
+        choices: HashMap::new(),
+        conflicts: vec![],
+    };
+    let result = merge_docs(
+        merge.base.as_ref(),
+        &merge.ours,
+        &merge.theirs,
+        &merge.choices,
+    );
+    let unchanged = result.doc.tabs.len() == merge.ours.tabs.len()
+        && result
+            .doc
+            .tabs
+            .iter()
+            .zip(&merge.ours.tabs)
+            .all(|(merged, ours)| {
+                merged.id == ours.id && TabSnapshot::of_tab(merged) == TabSnapshot::of_tab(ours)
+            });
+    if unchanged && result.conflicts.is_empty() {
+        notifier.success(
+            MERGE_TASK,
+            format!("\"{}\" is already up to date", merge.ours.name),
+        );
+        return;
+    }
+    preview_merge(commands, app_state, &mut merge);
+    let message = match merge.conflicts.len() {
+        0 => format!(
+            "Review changes to \"{}\" and apply the merge",
+            merge.ours.name
+        ),
+        n => format!(
+            "Merging \"{}\": pick sides for {} conflict{}",
+            merge.ours.name,
+            n,
+            if n == 1 { "" } else { "s" }
+        ),
+    };
+    notifier.progress(MERGE_TASK, message);
+    commands.insert_resource(merge);
+}
+
+pub fn merge_button_handler(
+    mut commands: Commands,
+    mut app_state: ResMut<AppState>,
+    merge: Option<ResMut<DocMerge>>,
+    interaction_query: Query<(&Interaction, &MergeButton), Changed<Interaction>>,
+    mut pkv: ResMut<PkvStore>,
+    notifications: Res<NotificationChannels>,
+) {
+    let Some(mut merge) = merge else {
+        return;
+    };
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let doc_id = merge.ours.id;
+        match button {
+            MergeButton::Choose(index, side) => {
+                let Some(conflict) = merge.conflicts.get(*index).cloned() else {
+                    continue;
+                };
+                let merge = merge.as_mut();
+                merge.choices.insert((conflict.tab_id, conflict.id), *side);
+                // the tab of the conflict is shown after the preview is reloaded
+                for doc in [&mut merge.ours, &mut merge.theirs] {
+                    for tab in doc.tabs.iter_mut() {
+                        tab.is_active = tab.id == conflict.tab_id;
+                    }
+                }
+                preview_merge(&mut commands, &mut app_state, merge);
+            }
+            MergeButton::Apply => {
+                // the next merge starts from the copy merged now
+                record_merge_base(&mut pkv, &merge.theirs);
+                commands.insert_resource(SaveDocRequest { doc_id, path: None });
+                commands.remove_resource::<DocMerge>();
+                commands.remove_resource::<DiffOverlay>();
+                notifications
+                    .notifier
+                    .success(MERGE_TASK, format!("Merged \"{}\"", merge.ours.name));
+            }
+            MergeButton::Cancel => {
+                app_state.docs.insert(doc_id, merge.ours.clone());
+                commands.insert_resource(LoadDocRequest { doc_id });
+                commands.remove_resource::<DocMerge>();
+                commands.remove_resource::<DiffOverlay>();
+                notifications.notifier.success(MERGE_TASK, "Merge canceled");
+            }
+        }
+        break;
+    }
+}
+
+pub fn spawn_panel_button(
+    builder: &mut ChildBuilder,
+    theme: &Theme,
+    label: &str,
+    button: impl Component,
+    selected: bool,
+) {
+    builder
+        .spawn((
+            ButtonBundle {
+                border_color: theme.btn_border.into(),
+                background_color: if selected {
+                    theme.ok_cancel_bg.into()
+                } else {
+                    theme.modal_bg.into()
+                },
+                style: Style {
+                    border: UiRect::all(Val::Px(1.)),
+                    padding: UiRect::all(Val::Px(4.)),
+                    margin: UiRect::left(Val::Px(5.)),
+                    ..default()
+                },
+                ..default()
+            },
+            GenericButton,
+            button,
+        ))
+        .with_children(|builder| {
+            builder.spawn(TextBundle::from_section(
+                label,
+                TextStyle {
+                    font_size: 14.,
+                    color: theme.font,
+                    ..default()
+                },
+            ));
+        });
+}
+
+/// Lists conflicts of the merge with buttons to pick a side, hidden while nothing is merged.
+pub fn update_merge_panel(
+    mut commands: Commands,
+    merge: Option<Res<DocMerge>>,
+    theme: Res<Theme>,
+    mut panel_query: Query<(Entity, &mut Style), With<MergePanel>>,
+) {
+    let Ok((panel, mut style)) = panel_query.get_single_mut() else {
+        return;
+    };
+    let Some(merge) = merge else {
+        if style.display != Display::None {
+            style.display = Display::None;
+            commands.entity(panel).despawn_descendants();
+        }
+        return;
+    };
+    if !merge.is_changed() {
+        return;
+    }
+    style.display = Display::Flex;
+    commands.entity(panel).despawn_descendants();
+    let text_style = TextStyle {
+        font_size: 14.,
+        color: theme.font,
+        ..default()
+    };
+    commands.entity(panel).with_children(|builder| {
+        builder.spawn(TextBundle::from_section(
+            format!("Merge \"{}\"", merge.ours.name),
+            TextStyle {
+                font_size: 16.,
+                ..text_style.clone()
+            },
+        ));
+        builder.spawn(TextBundle::from_sections(
+            [
+                ElementChange::Added,
+                ElementChange::Removed,
+                ElementChange::Moved,
+                ElementChange::Changed,
+                ElementChange::Conflict,
+            ]
+            .map(|change| {
+                TextSection::new(
+                    format!("{} ", change),
+                    TextStyle {
+                        color: change.color(),
+                        ..text_style.clone()
+                    },
+                )
+            }),
+        ));
+        for (index, conflict) in merge.conflicts.iter().enumerate() {
+            let tab_name = merge
+                .ours
+                .tabs
+                .iter()
+                .chain(&merge.theirs.tabs)
+                .find(|tab| tab.id == conflict.tab_id)
+                .map(|tab| tab.name.as_str())
+                .unwrap_or_default();
+            let details = if conflict.fields.is_empty() {
+                "removed on one side".to_string()
+            } else {
+                conflict.fields.join(", ")
+            };
+            let side = merge
+                .choices
+                .get(&(conflict.tab_id, conflict.id.clone()))
+                .copied()
+                .unwrap_or(MergeSide::Ours);
+            builder
+                .spawn(NodeBundle {
+                    style: Style {
+                        align_items: AlignItems::Center,
+                        margin: UiRect::top(Val::Px(5.)),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|builder| {
+                    builder.spawn(TextBundle::from_section(
+                        format!("{} / {}: {}", tab_name, conflict.label, details),
+                        text_style.clone(),
+                    ));
+                    spawn_panel_button(
+                        builder,
+                        &theme,
+                        "Mine",
+                        MergeButton::Choose(index, MergeSide::Ours),
+                        side == MergeSide::Ours,
+                    );
+                    spawn_panel_button(
+                        builder,
+                        &theme,
+                        "Theirs",
+                        MergeButton::Choose(index, MergeSide::Theirs),
+                        side == MergeSide::Theirs,
+                    );
+                });
+        }
+        builder
+            .spawn(NodeBundle {
+                style: Style {
+                    justify_content: JustifyContent::End,
+                    margin: UiRect::top(Val::Px(8.)),
+                    ..default()
+                },
+                ..default()
+            })
+            .with_children(|builder| {
+                spawn_panel_button(builder, &theme, "Apply Merge", MergeButton::Apply, true);
+                spawn_panel_button(builder, &theme, "Cancel", MergeButton::Cancel, false);
+            });
+    });
+}
+
+pub fn is_merging(merge: Option<Res<DocMerge>>) -> bool {
+    merge.is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn doc(id: ReflectableUuid, tab_id: ReflectableUuid, nodes: Value) -> Doc {
+        Doc {
+            id,
+            name: "Doc".to_string(),
+            tabs: vec![Tab {
+                is_active: true,
+                id: tab_id,
+                name: "Tab 1".to_string(),
+                checkpoints: [
+                    json!({"images": {}, "nodes": nodes, "arrows": [], "drawings": []}).to_string(),
+                ]
+                .into(),
+                z_index: 1.,
+                slides: vec![],
+            }],
+            ..default()
+        }
+    }
+
+    #[test]
+    fn test_merge_docs() {
+        let (doc_id, tab_id) = (ReflectableUuid::generate(), ReflectableUuid::generate());
+        let base = doc(
+            doc_id,
+            tab_id,
+            json!([
+                {"id": "a", "x": 0., "text": "a"},
+                {"id": "b", "x": 0., "text": "b"},
+                {"id": "c", "x": 0., "text": "c"}
+            ]),
+        );
+        // we move a and edit b, they edit a and b and remove c
+        let ours = doc(
+            doc_id,
+            tab_id,
+            json!([
+                {"id": "a", "x": 5., "text": "a"},
+                {"id": "b", "x": 0., "text": "mine"},
+                {"id": "c", "x": 0., "text": "c"}
+            ]),
+        );
+        let theirs = doc(
+            doc_id,
+            tab_id,
+            json!([
+                {"id": "a", "x": 0., "text": "A"},
+                {"id": "b", "x": 0., "text": "theirs"},
+                {"id": "d", "x": 0., "text": "d"}
+            ]),
+        );
+        let result = merge_docs(Some(&base), &ours, &theirs, &HashMap::new());
+        assert_eq!(
+            result.conflicts,
+            vec![MergeConflict {
+                tab_id,
+                id: "b".to_string(),
+                fields: vec!["text".to_string()],
+                label: "Note \"\"".to_string(),
+            }]
+        );
+        let merged = TabSnapshot::of_tab(&result.doc.tabs[0]);
+        let value = |id: &str| Value::Object(merged.elements[id].1.clone());
+        assert_eq!(value("a"), json!({"id": "a", "x": 5., "text": "A"}));
+        assert_eq!(value("b")["text"], "mine");
+        assert!(!merged.elements.contains_key("c"));
+        assert_eq!(value("d")["text"], "d");
+
+        let choices = HashMap::from([((tab_id, "b".to_string()), MergeSide::Theirs)]);
+        let result = merge_docs(Some(&base), &ours, &theirs, &choices);
+        let merged = TabSnapshot::of_tab(&result.doc.tabs[0]);
+        assert_eq!(merged.elements["b"].1["text"], "theirs");
+
+        // without a common ancestor notes changed on both sides conflict
+        let result = merge_docs(None, &ours, &theirs, &HashMap::new());
+        assert_eq!(result.conflicts.len(), 2);
+
+        // a tab we removed that they changed is a conflict, it stays removed unless theirs is picked
+        let removed = doc(doc_id, ReflectableUuid::generate(), json!([]));
+        let result = merge_docs(Some(&base), &removed, &theirs, &HashMap::new());
+        assert_eq!(result.conflicts.len(), 1);
+        assert_eq!(result.conflicts[0].id, "");
+        assert!(result.doc.tabs.iter().all(|tab| tab.id != tab_id));
+        let choices = HashMap::from([((tab_id, String::new()), MergeSide::Theirs)]);
+        let result = merge_docs(Some(&base), &removed, &theirs, &choices);
+        assert_eq!(result.doc.tabs[1].id, tab_id);
+        assert_eq!(
+            element_label(
+                ElementKind::Node,
+                json!({"text": {"text": "\nA long first line of the merged note"}}).as_object()
+            ),
+            "Note \"A long first line of the merge…\""
+        );
+    }
+}
@@ -5,14 +5,13 @@ use bevy_cosmic_edit::{
     cosmic_edit_set_text, get_cosmic_text, get_text_spans, ActiveEditor, CosmicEdit,
     CosmicEditHistory, CosmicFont, CosmicText, EditHistoryItem,
 };
-use bevy_markdown::{generate_markdown_lines, BevyMarkdown, BevyMarkdownTheme};
 use bevy_prototype_lyon::prelude::{Fill, Stroke};
 use cosmic_text::{Cursor, Edit};
 
 use crate::{
     resources::{AppState, SaveDocRequest},
     themes::Theme,
-    utils::{bevy_color_to_cosmic, ReflectableUuid},
+    utils::{bevy_color_to_cosmic, get_markdown_lines, ReflectableUuid},
 };
 
 use super::{ui_helpers::VeloShape, BevyMarkdownView, NodeType, RawText, UiState};
@@ -107,18 +106,8 @@ pub fn entity_to_edit_changed(
                         };
                         let text = get_cosmic_text(cosmic_edit.editor.buffer());
                         raw_text.last_text = text.clone();
-                        let markdown_theme = BevyMarkdownTheme {
-                            code_theme: theme.code_theme.clone(),
-                            code_default_lang: theme.code_default_lang.clone(),
-                            link: bevy_color_to_cosmic(theme.link),
-                            inline_code: bevy_color_to_cosmic(theme.inline_code),
-                        };
-                        let markdown_lines = generate_markdown_lines(BevyMarkdown {
-                            text,
-                            markdown_theme,
-                            attrs: cosmic_edit.attrs.clone(),
-                        })
-                        .expect("should handle markdown convertion");
+                        let markdown_lines =
+                            get_markdown_lines(&theme, &text, cosmic_edit.attrs.clone());
                         let font = cosmic_fonts
                             .get_mut(&cosmic_edit.font_system.clone())
                             .unwrap();
@@ -174,18 +163,8 @@ pub fn entity_to_edit_changed(
                         };
                         let text = get_cosmic_text(cosmic_edit.editor.buffer());
                         raw_text.last_text = text.clone();
-                        let markdown_theme = BevyMarkdownTheme {
-                            code_theme: theme.code_theme.clone(),
-                            code_default_lang: theme.code_default_lang.clone(),
-                            link: bevy_color_to_cosmic(theme.link),
-                            inline_code: bevy_color_to_cosmic(theme.inline_code),
-                        };
-                        let markdown_lines = generate_markdown_lines(BevyMarkdown {
-                            text,
-                            markdown_theme,
-                            attrs: cosmic_edit.attrs.clone(),
-                        })
-                        .expect("should handle markdown convertion");
+                        let markdown_lines =
+                            get_markdown_lines(&theme, &text, cosmic_edit.attrs.clone());
                         let font = cosmic_fonts
                             .get_mut(&cosmic_edit.font_system.clone())
                             .unwrap();
@@ -0,0 +1,30 @@
+use bevy::prelude::*;
+
+use crate::{themes::Theme, ui_plugin::ui_helpers::LinksPanel};
+
+/// Hidden until there are link suggestions or backlinks to show.
+pub fn add_links_panel(commands: &mut Commands, theme: &Res<Theme>) -> Entity {
+    commands
+        .spawn((
+            NodeBundle {
+                background_color: theme.modal_bg.into(),
+                border_color: theme.btn_border.into(),
+                style: Style {
+                    display: Display::None,
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(10.),
+                    right: Val::Px(10.),
+                    max_width: Val::Percent(40.),
+                    border: UiRect::all(Val::Px(1.)),
+                    padding: UiRect::all(Val::Px(5.)),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Start,
+                    ..default()
+                },
+                z_index: ZIndex::Global(1),
+                ..default()
+            },
+            LinksPanel,
+        ))
+        .id()
+}
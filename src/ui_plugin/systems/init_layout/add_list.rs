@@ -10,9 +10,9 @@ use bevy::{
 use bevy_pkv::PkvStore;
 
 use super::ui_helpers::ScrollingList;
-use crate::resources::{AppState, LoadDocRequest};
-use crate::ui_plugin::ui_helpers::DocList;
-use crate::utils::ReflectableUuid;
+use crate::resources::{AppState, DocSortMode, LoadDocRequest};
+use crate::ui_plugin::ui_helpers::{DocList, DocSortButton, GenericButton};
+use crate::utils::{get_timestamp, ReflectableUuid};
 use crate::{
     components::{Doc, Tab},
     themes::Theme,
@@ -28,6 +28,12 @@ pub fn add_list(
         app_state.current_document = Some(last_saved);
         commands.insert_resource(LoadDocRequest { doc_id: last_saved });
     }
+    if let Ok(doc_sort) = pkv.get::<DocSortMode>("doc_sort") {
+        app_state.doc_sort = doc_sort;
+    }
+    if let Ok(recent_docs) = pkv.get::<Vec<ReflectableUuid>>("recent_docs") {
+        app_state.recent_docs = recent_docs;
+    }
 
     let top = commands
         .spawn(NodeBundle {
@@ -69,6 +75,7 @@ pub fn add_list(
             name: tab_name,
             checkpoints: VecDeque::new(),
             z_index: 1.,
+            slides: vec![],
             is_active: true,
         }];
         let doc_id = ReflectableUuid::generate();
@@ -79,6 +86,11 @@ pub fn add_list(
                 name: "Untitled".to_string(),
                 tabs,
                 tags: vec![],
+                folder: None,
+                pinned: false,
+                created_at: get_timestamp(),
+                modified_at: get_timestamp(),
+                shared: None,
             },
         );
         app_state.current_document = Some(doc_id);
@@ -88,3 +100,38 @@ pub fn add_list(
     commands.entity(top).add_child(node);
     top
 }
+
+pub fn add_doc_sort_button(
+    commands: &mut Commands,
+    theme: &Res<Theme>,
+    doc_sort: DocSortMode,
+) -> Entity {
+    commands
+        .spawn((
+            ButtonBundle {
+                border_color: theme.btn_border.into(),
+                background_color: theme.ok_cancel_bg.into(),
+                style: Style {
+                    align_self: AlignSelf::Center,
+                    border: UiRect::all(Val::Px(1.)),
+                    padding: UiRect::horizontal(Val::Px(4.)),
+                    margin: UiRect::bottom(Val::Px(5.)),
+                    ..default()
+                },
+                ..default()
+            },
+            GenericButton,
+            DocSortButton,
+        ))
+        .with_children(|builder| {
+            builder.spawn(TextBundle::from_section(
+                format!("Sort by {}", doc_sort),
+                TextStyle {
+                    font_size: 14.,
+                    color: theme.font,
+                    ..default()
+                },
+            ));
+        })
+        .id()
+}
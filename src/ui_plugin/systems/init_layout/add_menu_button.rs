@@ -3,7 +3,6 @@ use bevy::{prelude::*, text::BreakLineOn};
 use crate::{
     themes::Theme,
     ui_plugin::ui_helpers::{get_tooltip, GenericButton, Tooltip, TooltipPosition},
-    utils::{DARK_THEME_ICON_CODE, LIGHT_THEME_ICON_CODE},
 };
 
 pub fn add_menu_button(
@@ -17,13 +16,22 @@ pub fn add_menu_button(
         "New Tab" => "\u{e3ba}",
         "New Document" => "\u{e89c}",
         "Save Document" => "\u{e161}",
+        "Edit Document Tags" => "\u{e54e}",
+        "Pin/Unpin Document" => "\u{f10d}",
+        "Move Document to Folder" => "\u{e2c7}",
         "Export To File" => "\u{e2c6}",
         "Import From File" => "\u{e255}",
         "Import From URL" => "\u{e902}",
+        "Rebuild Search Index" => "\u{e5d5}",
         "Save Document to window.velo object" => "\u{e866}",
         "Share Document (copy URL to clipboard)" => "\u{e80d}",
-        "Enable dark theme (restart is required for now)" => DARK_THEME_ICON_CODE,
-        "Enable light theme (restart is required for now)" => LIGHT_THEME_ICON_CODE,
+        "Revoke Shared Document" => "\u{e16f}",
+        "Add Slide (current view or edited note)" => "\u{e41b}",
+        "Remove Slides of the Tab" => "\u{e53c}",
+        "Present Slides" => "\u{e037}",
+        "Compare Checkpoints or Files" => "\u{e3b9}",
+        "Version History" => "\u{e889}",
+        "Change Theme" => "\u{e40a}",
         _ => panic!("Unknown menu button tooltip label: {}", label),
     };
     match label.as_str() {
@@ -0,0 +1,39 @@
+use bevy::prelude::*;
+
+use crate::themes::Theme;
+use crate::ui_plugin::ui_helpers::{HistoryPanel, MergePanel};
+
+/// Top right corner of the canvas, shown while a document is merged.
+pub fn add_merge_panel(commands: &mut Commands, theme: &Res<Theme>) -> Entity {
+    add_corner_panel(commands, theme, MergePanel)
+}
+
+/// Same corner as the merge panel, shown while the history of a tab is browsed.
+pub fn add_history_panel(commands: &mut Commands, theme: &Res<Theme>) -> Entity {
+    add_corner_panel(commands, theme, HistoryPanel)
+}
+
+fn add_corner_panel(commands: &mut Commands, theme: &Res<Theme>, marker: impl Component) -> Entity {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    display: Display::None,
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(10.),
+                    right: Val::Px(10.),
+                    max_width: Val::Percent(45.),
+                    flex_direction: FlexDirection::Column,
+                    border: UiRect::all(Val::Px(1.)),
+                    padding: UiRect::all(Val::Px(8.)),
+                    ..default()
+                },
+                border_color: theme.btn_border.into(),
+                background_color: theme.modal_bg.into(),
+                z_index: ZIndex::Global(2),
+                ..default()
+            },
+            marker,
+        ))
+        .id()
+}
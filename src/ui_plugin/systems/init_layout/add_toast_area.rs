@@ -0,0 +1,25 @@
+use bevy::prelude::*;
+
+use crate::ui_plugin::ui_helpers::ToastArea;
+
+/// Bottom right corner of the canvas, filled by `show_notifications`.
+pub fn add_toast_area(commands: &mut Commands) -> Entity {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Px(10.),
+                    right: Val::Px(10.),
+                    max_width: Val::Percent(40.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::End,
+                    ..default()
+                },
+                z_index: ZIndex::Global(2),
+                ..default()
+            },
+            ToastArea,
+        ))
+        .id()
+}
@@ -16,6 +16,11 @@ pub fn add_visibility(
         crate::ui_plugin::ui_helpers::ButtonTypes::ShowChildren => "\u{e8f4}".to_string(),
         crate::ui_plugin::ui_helpers::ButtonTypes::HideChildren => "\u{e8f5}".to_string(),
         crate::ui_plugin::ui_helpers::ButtonTypes::ShowRandom => "\u{e043}".to_string(),
+        crate::ui_plugin::ui_helpers::ButtonTypes::EditNodeMetadata => "\u{e892}".to_string(),
+        crate::ui_plugin::ui_helpers::ButtonTypes::FilterNodes => "\u{ef4f}".to_string(),
+        crate::ui_plugin::ui_helpers::ButtonTypes::EditTextStyle => "\u{e245}".to_string(),
+        crate::ui_plugin::ui_helpers::ButtonTypes::AutoSize => "\u{f1ce}".to_string(),
+        crate::ui_plugin::ui_helpers::ButtonTypes::ShrinkToFit => "\u{f1cf}".to_string(),
         _ => panic!("unexpected button type"),
     };
 
@@ -7,15 +7,17 @@ use bevy_cosmic_edit::{create_cosmic_font_system, CosmicFont, CosmicFontConfig};
 use bevy_pkv::PkvStore;
 
 use super::ui_helpers::{
-    self, AddTab, BottomPanel, ButtonAction, ChangeTheme, DrawPencil, LeftPanel, LeftPanelControls,
-    LeftPanelExplorer, MainPanel, Menu, NewDoc, ParticlesEffect, Root, SaveDoc, TextPosMode,
-    TwoPointsDraw,
+    self, AddSlide, AddTab, BottomPanel, ButtonAction, ChangeTheme, ClearSlides, CompareVersions,
+    DrawPencil, EditDocTags, LeftPanel, LeftPanelControls, LeftPanelExplorer, MainPanel, Menu,
+    MoveDocToFolder, NewDoc, ParticlesEffect, PinDoc, Root, SaveDoc, StartPresentation,
+    TagFacetList, TextPosMode, TwoPointsDraw, VersionHistory,
+};
+use super::{
+    CommChannels, ExportToFile, ImportFromFile, ImportFromUrl, RevokeShare, ShareDoc, ViewerMode,
 };
-use super::{CommChannels, ExportToFile, ImportFromFile, ImportFromUrl, ShareDoc};
 use crate::canvas::arrow::components::{ArrowMode, ArrowType};
 use crate::resources::{AppState, FontSystemState};
 use crate::themes::Theme;
-use crate::utils::get_theme_key;
 use crate::TextPos;
 
 #[path = "../../../macros.rs"]
@@ -74,6 +76,16 @@ use add_search_box::*;
 mod add_visibility;
 use add_visibility::*;
 
+#[path = "add_links_panel.rs"]
+mod add_links_panel;
+use add_links_panel::*;
+#[path = "add_toast_area.rs"]
+mod add_toast_area;
+use add_toast_area::*;
+#[path = "add_merge_panel.rs"]
+mod add_merge_panel;
+use add_merge_panel::*;
+
 // Think about splitting this function to wasm and native
 pub fn init_layout(
     mut commands: Commands,
@@ -84,7 +96,10 @@ pub fn init_layout(
     windows: Query<&Window, With<PrimaryWindow>>,
     mut fonts: ResMut<Assets<Font>>,
     theme: Res<Theme>,
+    viewer_mode: Option<Res<ViewerMode>>,
 ) {
+    // editing controls are left out in viewer mode, the systems behind them don't run either
+    let editable = viewer_mode.is_none();
     // font setup
     let font_bytes_regular = include_bytes!("../../../../assets/fonts/VictorMono-Regular.ttf");
     let font_bytes_bold = include_bytes!("../../../../assets/fonts/VictorMono-Bold.ttf");
@@ -147,14 +162,16 @@ pub fn init_layout(
             BottomPanel,
         ))
         .id();
-    let add_tab = add_menu_button(
-        &mut commands,
-        &theme,
-        "New Tab".to_string(),
-        &icon_font,
-        AddTab,
-    );
-    commands.entity(bottom_panel).add_child(add_tab);
+    if editable {
+        let add_tab = add_menu_button(
+            &mut commands,
+            &theme,
+            "New Tab".to_string(),
+            &icon_font,
+            AddTab,
+        );
+        commands.entity(bottom_panel).add_child(add_tab);
+    }
 
     let docs = add_list(&mut commands, &theme, &mut app_state, &mut pkv);
 
@@ -199,80 +216,169 @@ pub fn init_layout(
             Menu,
         ))
         .id();
-    let new_doc = add_menu_button(
-        &mut commands,
-        &theme,
-        "New Document".to_string(),
-        &icon_font,
-        NewDoc,
-    );
-    let save_doc = add_menu_button(
-        &mut commands,
-        &theme,
-        "Save Document".to_string(),
-        &icon_font,
-        SaveDoc,
-    );
+    if editable {
+        let new_doc = add_menu_button(
+            &mut commands,
+            &theme,
+            "New Document".to_string(),
+            &icon_font,
+            NewDoc,
+        );
+        let save_doc = add_menu_button(
+            &mut commands,
+            &theme,
+            "Save Document".to_string(),
+            &icon_font,
+            SaveDoc,
+        );
+        let edit_tags = add_menu_button(
+            &mut commands,
+            &theme,
+            "Edit Document Tags".to_string(),
+            &icon_font,
+            EditDocTags,
+        );
+        let pin_doc = add_menu_button(
+            &mut commands,
+            &theme,
+            "Pin/Unpin Document".to_string(),
+            &icon_font,
+            PinDoc,
+        );
+        let move_doc_to_folder = add_menu_button(
+            &mut commands,
+            &theme,
+            "Move Document to Folder".to_string(),
+            &icon_font,
+            MoveDocToFolder,
+        );
+        commands.entity(menu).add_child(new_doc);
+        commands.entity(menu).add_child(save_doc);
+        commands.entity(menu).add_child(edit_tags);
+        commands.entity(menu).add_child(pin_doc);
+        commands.entity(menu).add_child(move_doc_to_folder);
+    }
     #[cfg(not(target_arch = "wasm32"))]
-    let export_file = add_menu_button(
-        &mut commands,
-        &theme,
-        "Export To File".to_string(),
-        &icon_font,
-        ExportToFile,
-    );
+    {
+        let export_file = add_menu_button(
+            &mut commands,
+            &theme,
+            "Export To File".to_string(),
+            &icon_font,
+            ExportToFile,
+        );
+        commands.entity(menu).add_child(export_file);
+    }
     #[cfg(not(target_arch = "wasm32"))]
-    let import_file = add_menu_button(
-        &mut commands,
-        &theme,
-        "Import From File".to_string(),
-        &icon_font,
-        ImportFromFile,
-    );
+    if editable {
+        let import_file = add_menu_button(
+            &mut commands,
+            &theme,
+            "Import From File".to_string(),
+            &icon_font,
+            ImportFromFile,
+        );
+        commands.entity(menu).add_child(import_file);
+        let import_url = add_menu_button(
+            &mut commands,
+            &theme,
+            "Import From URL".to_string(),
+            &icon_font,
+            ImportFromUrl,
+        );
+        commands.entity(menu).add_child(import_url);
+    }
     #[cfg(not(target_arch = "wasm32"))]
-    let import_url = add_menu_button(
+    {
+        let rebuild_search_index = add_menu_button(
+            &mut commands,
+            &theme,
+            "Rebuild Search Index".to_string(),
+            &icon_font,
+            super::RebuildSearchIndex,
+        );
+        commands.entity(menu).add_child(rebuild_search_index);
+    }
+    if editable && app_state.share.is_some() {
+        let share_doc = add_menu_button(
+            &mut commands,
+            &theme,
+            "Share Document (copy URL to clipboard)".to_string(),
+            &icon_font,
+            ShareDoc,
+        );
+        commands.entity(menu).add_child(share_doc);
+        let revoke_share = add_menu_button(
+            &mut commands,
+            &theme,
+            "Revoke Shared Document".to_string(),
+            &icon_font,
+            RevokeShare,
+        );
+        commands.entity(menu).add_child(revoke_share);
+    }
+    if editable {
+        let add_slide = add_menu_button(
+            &mut commands,
+            &theme,
+            "Add Slide (current view or edited note)".to_string(),
+            &icon_font,
+            AddSlide,
+        );
+        commands.entity(menu).add_child(add_slide);
+        let clear_slides = add_menu_button(
+            &mut commands,
+            &theme,
+            "Remove Slides of the Tab".to_string(),
+            &icon_font,
+            ClearSlides,
+        );
+        commands.entity(menu).add_child(clear_slides);
+    }
+    let start_presentation = add_menu_button(
         &mut commands,
         &theme,
-        "Import From URL".to_string(),
+        "Present Slides".to_string(),
         &icon_font,
-        ImportFromUrl,
+        StartPresentation,
     );
-    #[cfg(target_arch = "wasm32")]
-    let set_window_prop = add_menu_button(
+    commands.entity(menu).add_child(start_presentation);
+    let compare_versions = add_menu_button(
         &mut commands,
         &theme,
-        "Save Document to window.velo object".to_string(),
+        "Compare Checkpoints or Files".to_string(),
         &icon_font,
-        super::SetWindowProperty,
+        CompareVersions,
     );
-    commands.entity(menu).add_child(new_doc);
-    commands.entity(menu).add_child(save_doc);
-    #[cfg(not(target_arch = "wasm32"))]
-    commands.entity(menu).add_child(export_file);
-    #[cfg(not(target_arch = "wasm32"))]
-    commands.entity(menu).add_child(import_file);
-    #[cfg(not(target_arch = "wasm32"))]
-    commands.entity(menu).add_child(import_url);
-    if app_state.github_token.is_some() {
-        let share_doc = add_menu_button(
+    commands.entity(menu).add_child(compare_versions);
+    if editable {
+        let version_history = add_menu_button(
             &mut commands,
             &theme,
-            "Share Document (copy URL to clipboard)".to_string(),
+            "Version History".to_string(),
             &icon_font,
-            ShareDoc,
+            VersionHistory,
         );
-        commands.entity(menu).add_child(share_doc);
+        commands.entity(menu).add_child(version_history);
     }
     #[cfg(target_arch = "wasm32")]
-    commands.entity(menu).add_child(set_window_prop);
-    let theme_key = get_theme_key(&pkv);
-    let theme_msg = if theme_key == "light" {
-        "Enable dark theme (restart is required for now)".to_string()
-    } else {
-        "Enable light theme (restart is required for now)".to_string()
-    };
-
-    let change_theme = add_menu_button(&mut commands, &theme, theme_msg, &icon_font, ChangeTheme);
+    {
+        let set_window_prop = add_menu_button(
+            &mut commands,
+            &theme,
+            "Save Document to window.velo object".to_string(),
+            &icon_font,
+            super::SetWindowProperty,
+        );
+        commands.entity(menu).add_child(set_window_prop);
+    }
+    let change_theme = add_menu_button(
+        &mut commands,
+        &theme,
+        "Change Theme".to_string(),
+        &icon_font,
+        ChangeTheme,
+    );
     commands.entity(menu).add_child(change_theme);
 
     let main_bottom = commands
@@ -337,6 +443,14 @@ pub fn init_layout(
         ))
         .id();
 
+    let links_panel = add_links_panel(&mut commands, &theme);
+    commands.entity(main_panel).add_child(links_panel);
+    let toast_area = add_toast_area(&mut commands);
+    commands.entity(main_panel).add_child(toast_area);
+    let merge_panel = add_merge_panel(&mut commands, &theme);
+    commands.entity(main_panel).add_child(merge_panel);
+    let history_panel = add_history_panel(&mut commands, &theme);
+    commands.entity(main_panel).add_child(history_panel);
     commands.entity(right_panel).add_child(main_panel);
     commands.entity(right_panel).add_child(bottom_panel);
 
@@ -347,6 +461,11 @@ pub fn init_layout(
                     padding: UiRect::all(Val::Px(10.)),
                     width: Val::Percent(100.),
                     height: Val::Percent(40.),
+                    display: if editable {
+                        Display::Flex
+                    } else {
+                        Display::None
+                    },
                     flex_direction: FlexDirection::Column,
                     align_items: AlignItems::Center,
                     justify_content: JustifyContent::Center,
@@ -357,7 +476,6 @@ pub fn init_layout(
             LeftPanelControls,
         ))
         .id();
-    #[cfg(not(target_arch = "wasm32"))]
     let search_box = add_search_box(
         &mut commands,
         &theme,
@@ -370,7 +488,7 @@ pub fn init_layout(
             NodeBundle {
                 style: Style {
                     width: Val::Percent(100.),
-                    height: Val::Percent(60.),
+                    height: Val::Percent(if editable { 60. } else { 100. }),
                     flex_direction: FlexDirection::Column,
                     align_items: AlignItems::Center,
                     justify_content: JustifyContent::Center,
@@ -381,8 +499,25 @@ pub fn init_layout(
             LeftPanelExplorer,
         ))
         .id();
-    #[cfg(not(target_arch = "wasm32"))]
+    let tag_facets = commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(80.),
+                    flex_direction: FlexDirection::Row,
+                    flex_wrap: FlexWrap::Wrap,
+                    margin: UiRect::vertical(Val::Px(5.)),
+                    ..default()
+                },
+                ..default()
+            },
+            TagFacetList,
+        ))
+        .id();
     commands.entity(left_panel_explorer).add_child(search_box);
+    let doc_sort = add_doc_sort_button(&mut commands, &theme, app_state.doc_sort);
+    commands.entity(left_panel_explorer).add_child(tag_facets);
+    commands.entity(left_panel_explorer).add_child(doc_sort);
     commands.entity(left_panel_explorer).add_child(docs);
 
     commands.entity(left_panel).add_child(left_panel_controls);
@@ -455,12 +590,14 @@ pub fn init_layout(
     let color3 = add_color(&mut commands, &theme, pair_struct!(theme.color_change_3));
     let color4 = add_color(&mut commands, &theme, pair_struct!(theme.color_change_4));
     let color5 = add_color(&mut commands, &theme, pair_struct!(theme.color_change_5));
+    let custom_color = add_custom_color(&mut commands, &theme);
 
     commands.entity(color_picker).add_child(color1);
     commands.entity(color_picker).add_child(color2);
     commands.entity(color_picker).add_child(color3);
     commands.entity(color_picker).add_child(color4);
     commands.entity(color_picker).add_child(color5);
+    commands.entity(color_picker).add_child(custom_color);
 
     let arrow_modes = commands
         .spawn((NodeBundle {
@@ -561,8 +698,38 @@ pub fn init_layout(
         "Top Left Text".to_string(),
         &icon_font,
     );
+    let text_style = add_visibility(
+        &mut commands,
+        &theme,
+        ButtonAction {
+            button_type: ui_helpers::ButtonTypes::EditTextStyle,
+        },
+        "Text style (size, font, alignment, color)".to_string(),
+        &icon_font,
+    );
+    let auto_size = add_visibility(
+        &mut commands,
+        &theme,
+        ButtonAction {
+            button_type: ui_helpers::ButtonTypes::AutoSize,
+        },
+        "Grow note to fit text while typing (toggle)".to_string(),
+        &icon_font,
+    );
+    let shrink_to_fit = add_visibility(
+        &mut commands,
+        &theme,
+        ButtonAction {
+            button_type: ui_helpers::ButtonTypes::ShrinkToFit,
+        },
+        "Shrink note to fit text".to_string(),
+        &icon_font,
+    );
     commands.entity(text_modes).add_child(text_pos1);
     commands.entity(text_modes).add_child(text_pos2);
+    commands.entity(text_modes).add_child(text_style);
+    commands.entity(text_modes).add_child(auto_size);
+    commands.entity(text_modes).add_child(shrink_to_fit);
 
     let visibility = commands
         .spawn((NodeBundle {
@@ -604,9 +771,29 @@ pub fn init_layout(
         "Show random note".to_string(),
         &icon_font,
     );
+    let edit_metadata = add_visibility(
+        &mut commands,
+        &theme,
+        ButtonAction {
+            button_type: ui_helpers::ButtonTypes::EditNodeMetadata,
+        },
+        "Edit note tags and metadata".to_string(),
+        &icon_font,
+    );
+    let filter_notes = add_visibility(
+        &mut commands,
+        &theme,
+        ButtonAction {
+            button_type: ui_helpers::ButtonTypes::FilterNodes,
+        },
+        "Filter notes by tags and metadata".to_string(),
+        &icon_font,
+    );
     commands.entity(visibility).add_child(show_children);
     commands.entity(visibility).add_child(hide_notes);
     commands.entity(visibility).add_child(show_random);
+    commands.entity(visibility).add_child(edit_metadata);
+    commands.entity(visibility).add_child(filter_notes);
 
     let left_panel_bottom = commands
         .spawn((NodeBundle {
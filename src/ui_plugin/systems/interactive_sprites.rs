@@ -21,7 +21,13 @@ pub fn interactive_node(
     buttons: Res<Input<MouseButton>>,
     res_images: Res<Assets<Image>>,
     mut sprite_query: Query<
-        (&Sprite, &Handle<Image>, &GlobalTransform, Entity),
+        (
+            &Sprite,
+            &Handle<Image>,
+            &GlobalTransform,
+            &Transform,
+            Entity,
+        ),
         With<InteractiveNode>,
     >,
     drawing_query: Query<
@@ -67,7 +73,7 @@ pub fn interactive_node(
             };
         }
     }
-    for (sprite, handle, node_transform, entity) in &mut sprite_query.iter_mut() {
+    for (sprite, handle, node_transform, transform, entity) in &mut sprite_query.iter_mut() {
         let size = match sprite.custom_size {
             Some(size) => (size.x, size.y),
             None => {
@@ -82,10 +88,12 @@ pub fn interactive_node(
             }
         };
 
+        // vertically aligned text moves the sprite, the node itself stays in place
+        let y_center = node_transform.affine().translation.y - transform.translation.y;
         let x_min = node_transform.affine().translation.x - size.0 / 2.;
-        let y_min = node_transform.affine().translation.y - size.1 / 2.;
+        let y_min = y_center - size.1 / 2.;
         let x_max = node_transform.affine().translation.x + size.0 / 2.;
-        let y_max = node_transform.affine().translation.y + size.1 / 2.;
+        let y_max = y_center + size.1 / 2.;
         let z_current = node_transform.affine().translation.z;
 
         if let Some(pos) = primary_window.cursor_position() {
@@ -137,6 +137,7 @@ pub fn keyboard_input_system(
                 text: JsonNodeText {
                     text: "".to_string(),
                     pos: crate::TextPos::Center,
+                    ..default()
                 },
                 bg_color: pair_struct!(theme.paper_node_bg),
                 ..Default::default()
@@ -156,6 +157,7 @@ pub fn keyboard_input_system(
                 text: JsonNodeText {
                     text: "".to_string(),
                     pos: crate::TextPos::Center,
+                    ..default()
                 },
                 bg_color: pair_struct!(theme.node_bg),
                 ..default()
@@ -175,6 +177,7 @@ pub fn keyboard_input_system(
                 text: JsonNodeText {
                     text: "".to_string(),
                     pos: crate::TextPos::Center,
+                    ..default()
                 },
                 bg_color: pair_struct!(theme.node_bg),
                 ..default()
@@ -283,9 +286,11 @@ pub fn insert_from_clipboard(
                     text: crate::JsonNodeText {
                         text: "".to_string(),
                         pos: crate::TextPos::Center,
+                        ..default()
                     },
                     bg_color: pair_struct!(theme.clipboard_image_bg),
                     z: 0.,
+                    ..Default::default()
                 },
                 image: Some(image),
             });
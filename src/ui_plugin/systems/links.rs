@@ -0,0 +1,801 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_cosmic_edit::{ActiveEditor, CosmicEdit, CosmicFont};
+use bevy_markdown::find_links;
+use bevy_pkv::PkvStore;
+use cosmic_text::{Action, Edit};
+use serde_json::Value;
+
+use super::ui_helpers::{
+    GenericButton, LinkPanelAction, LinkPanelItem, LinksPanel, RawText, VeloNode,
+};
+use super::{OpenInternalLink, UiState};
+use crate::components::{Doc, MainCamera};
+use crate::resources::{
+    AppState, FocusNodeRequest, LoadDocRequest, LoadTabRequest, SaveDocRequest, SaveTabRequest,
+};
+use crate::themes::Theme;
+use crate::utils::{load_doc_to_memory, ReflectableUuid};
+
+pub const INTERNAL_LINK_SCHEME: &str = "velo://";
+pub const MAX_LINK_SUGGESTIONS: usize = 5;
+/// Frames to wait for the linked note to be spawned after its tab is loaded.
+const FOCUS_NODE_FRAMES: usize = 3;
+
+/// Target of an internal link, each part is either an id or a name.
+/// Missing document or tab means the one the link is in.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LinkTarget {
+    pub doc: Option<String>,
+    pub tab: Option<String>,
+    /// Note id or its title, the first line of the note text
+    pub note: Option<String>,
+}
+
+/// Parses `[[Doc/Tab#note]]` wiki links and `velo://doc/tab/node` links, `None` for external links.
+pub fn parse_internal_link(link: &str) -> Option<LinkTarget> {
+    fn part(text: &str) -> Option<String> {
+        let text = text.trim();
+        (!text.is_empty()).then(|| text.to_string())
+    }
+    if let Some(path) = link.strip_prefix(INTERNAL_LINK_SCHEME) {
+        let mut parts = path.trim_end_matches('/').splitn(3, '/');
+        let target = LinkTarget {
+            doc: parts.next().and_then(part),
+            tab: parts.next().and_then(part),
+            note: parts.next().and_then(part),
+        };
+        return target.doc.is_some().then_some(target);
+    }
+    let target = link.strip_prefix("[[")?.strip_suffix("]]")?;
+    let (path, note) = match target.split_once('#') {
+        Some((path, note)) => (path, part(note)),
+        None => (target, None),
+    };
+    let (doc, tab) = match path.split_once('/') {
+        Some((doc, tab)) => (part(doc), part(tab)),
+        None => (part(path), None),
+    };
+    Some(LinkTarget { doc, tab, note })
+}
+
+/// Partly typed wiki link target at the end of the text before the cursor, e.g. `Doc/Ta` for `see [[Doc/Ta`.
+pub fn typed_link(text_before_cursor: &str) -> Option<&str> {
+    let typed = &text_before_cursor[text_before_cursor.rfind("[[")? + 2..];
+    (!typed.contains(['[', ']'])).then_some(typed)
+}
+
+/// First line of the note text without heading marks.
+pub fn note_title(text: &str) -> String {
+    text.lines()
+        .map(|line| line.trim().trim_start_matches('#').trim())
+        .find(|line| !line.is_empty())
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn matches_name(query: &str, id: &ReflectableUuid, name: &str) -> bool {
+    query == id.0.to_string() || query.to_lowercase() == name.to_lowercase()
+}
+
+fn contains_ignore_case(text: &str, query: &str) -> bool {
+    text.to_lowercase().contains(&query.to_lowercase())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LinkLocation {
+    pub doc_id: ReflectableUuid,
+    /// `None` keeps the tab the document opens with
+    pub tab_id: Option<ReflectableUuid>,
+    pub node_id: Option<ReflectableUuid>,
+}
+
+struct IndexedTab {
+    doc_id: ReflectableUuid,
+    doc_name: String,
+    tab_id: ReflectableUuid,
+    tab_name: String,
+}
+
+pub struct IndexedNote {
+    pub doc_id: ReflectableUuid,
+    pub tab_id: ReflectableUuid,
+    pub node_id: ReflectableUuid,
+    pub title: String,
+    text: String,
+}
+
+/// Documents, tabs and notes internal links can point to.
+/// Notes are read from the latest checkpoint of each tab.
+#[derive(Default)]
+pub struct LinkIndex {
+    tabs: Vec<IndexedTab>,
+    notes: Vec<IndexedNote>,
+}
+
+impl LinkIndex {
+    pub fn new<'a>(docs: impl IntoIterator<Item = &'a Doc>) -> Self {
+        let mut index = LinkIndex::default();
+        for doc in docs {
+            for tab in doc.tabs.iter() {
+                index.tabs.push(IndexedTab {
+                    doc_id: doc.id,
+                    doc_name: doc.name.clone(),
+                    tab_id: tab.id,
+                    tab_name: tab.name.clone(),
+                });
+                let notes = tab
+                    .checkpoints
+                    .back()
+                    .map(|checkpoint| checkpoint_notes(checkpoint))
+                    .unwrap_or_default();
+                index.set_tab_notes(doc.id, tab.id, notes);
+            }
+        }
+        index.tabs.sort_by(|a, b| a.doc_name.cmp(&b.doc_name));
+        index
+    }
+
+    /// Replaces notes of the tab, e.g. with the ones currently on the canvas.
+    pub fn set_tab_notes(
+        &mut self,
+        doc_id: ReflectableUuid,
+        tab_id: ReflectableUuid,
+        notes: impl IntoIterator<Item = (ReflectableUuid, String)>,
+    ) {
+        self.notes
+            .retain(|note| note.doc_id != doc_id || note.tab_id != tab_id);
+        self.notes
+            .extend(notes.into_iter().map(|(node_id, text)| IndexedNote {
+                doc_id,
+                tab_id,
+                node_id,
+                title: note_title(&text),
+                text,
+            }));
+    }
+
+    pub fn resolve(
+        &self,
+        target: &LinkTarget,
+        from_doc: ReflectableUuid,
+        from_tab: ReflectableUuid,
+    ) -> Option<LinkLocation> {
+        let doc_id = match &target.doc {
+            Some(doc) => {
+                self.tabs
+                    .iter()
+                    .find(|tab| matches_name(doc, &tab.doc_id, &tab.doc_name))?
+                    .doc_id
+            }
+            None => from_doc,
+        };
+        let tab_id = match &target.tab {
+            Some(tab) => Some(
+                self.tabs
+                    .iter()
+                    .find(|t| t.doc_id == doc_id && matches_name(tab, &t.tab_id, &t.tab_name))?
+                    .tab_id,
+            ),
+            None if doc_id == from_doc && target.note.is_none() => Some(from_tab),
+            None => None,
+        };
+        let Some(note) = &target.note else {
+            return Some(LinkLocation {
+                doc_id,
+                tab_id,
+                node_id: None,
+            });
+        };
+        // a note without a tab is looked up in all tabs, the one the link is in first
+        let note = self
+            .notes
+            .iter()
+            .filter(|n| n.doc_id == doc_id && tab_id.is_none_or(|tab_id| n.tab_id == tab_id))
+            .filter(|n| matches_name(note, &n.node_id, &n.title))
+            .min_by_key(|n| n.tab_id != from_tab)?;
+        Some(LinkLocation {
+            doc_id,
+            tab_id: Some(note.tab_id),
+            node_id: Some(note.node_id),
+        })
+    }
+
+    /// Link targets completing the typed one, to be written as `[[target]]`.
+    pub fn suggestions(
+        &self,
+        typed: &str,
+        from_doc: ReflectableUuid,
+        from_tab: ReflectableUuid,
+    ) -> Vec<String> {
+        let mut suggestions: Vec<String> = if let Some((path, note)) = typed.split_once('#') {
+            let target = parse_internal_link(&format!("[[{}]]", path)).unwrap_or_default();
+            let Some(location) = self.resolve(&target, from_doc, from_tab) else {
+                return vec![];
+            };
+            self.notes
+                .iter()
+                .filter(|n| n.doc_id == location.doc_id)
+                .filter(|n| location.tab_id.is_none_or(|tab_id| n.tab_id == tab_id))
+                .filter(|n| !n.title.is_empty() && contains_ignore_case(&n.title, note))
+                .map(|n| format!("{}#{}", path, n.title))
+                .collect()
+        } else if let Some((doc, tab)) = typed.split_once('/') {
+            let doc_id = if doc.trim().is_empty() {
+                Some(from_doc)
+            } else {
+                self.tabs
+                    .iter()
+                    .find(|t| matches_name(doc.trim(), &t.doc_id, &t.doc_name))
+                    .map(|t| t.doc_id)
+            };
+            self.tabs
+                .iter()
+                .filter(|t| Some(t.doc_id) == doc_id && contains_ignore_case(&t.tab_name, tab))
+                .map(|t| format!("{}/{}", doc, t.tab_name))
+                .collect()
+        } else {
+            let docs = self
+                .tabs
+                .iter()
+                .filter(|t| contains_ignore_case(&t.doc_name, typed))
+                .map(|t| t.doc_name.clone());
+            let notes = self
+                .notes
+                .iter()
+                .filter(|n| n.doc_id == from_doc && n.tab_id == from_tab)
+                .filter(|n| !n.title.is_empty() && contains_ignore_case(&n.title, typed))
+                .map(|n| format!("#{}", n.title));
+            docs.chain(notes).collect()
+        };
+        let typed = typed.to_lowercase();
+        suggestions.sort_by_key(|s| (!s.to_lowercase().starts_with(&typed), s.to_lowercase()));
+        suggestions.dedup();
+        suggestions.truncate(MAX_LINK_SUGGESTIONS);
+        suggestions
+    }
+
+    /// Notes with internal links to the given note.
+    pub fn backlinks(
+        &self,
+        doc_id: ReflectableUuid,
+        node_id: ReflectableUuid,
+    ) -> Vec<&IndexedNote> {
+        self.notes
+            .iter()
+            .filter(|note| note.node_id != node_id)
+            .filter(|note| note.text.contains("[[") || note.text.contains(INTERNAL_LINK_SCHEME))
+            .filter(|note| {
+                find_links(&note.text).iter().any(|link| {
+                    parse_internal_link(link)
+                        .and_then(|target| self.resolve(&target, note.doc_id, note.tab_id))
+                        .is_some_and(|location| {
+                            location.doc_id == doc_id && location.node_id == Some(node_id)
+                        })
+                })
+            })
+            .collect()
+    }
+
+    /// "Doc / Tab: title" of the note.
+    pub fn note_location_name(&self, note: &IndexedNote) -> String {
+        let tab = self.tabs.iter().find(|tab| tab.tab_id == note.tab_id);
+        let (doc_name, tab_name) = tab
+            .map(|tab| (tab.doc_name.as_str(), tab.tab_name.as_str()))
+            .unwrap_or_default();
+        format!("{} / {}: {}", doc_name, tab_name, note.title)
+    }
+}
+
+/// Ids and texts of notes saved in the checkpoint.
+fn checkpoint_notes(checkpoint: &str) -> Vec<(ReflectableUuid, String)> {
+    let Ok(json) = serde_json::from_str::<Value>(checkpoint) else {
+        return vec![];
+    };
+    let Some(nodes) = json["nodes"].as_array() else {
+        return vec![];
+    };
+    nodes
+        .iter()
+        .filter_map(|node| {
+            let id = node["id"].as_str()?.parse().ok()?;
+            let text = node["text"]["text"].as_str()?;
+            Some((ReflectableUuid(id), text.to_string()))
+        })
+        .collect()
+}
+
+fn active_tab(app_state: &AppState) -> Option<(ReflectableUuid, ReflectableUuid)> {
+    let doc_id = app_state.current_document?;
+    let tab = app_state
+        .docs
+        .get(&doc_id)?
+        .tabs
+        .iter()
+        .find(|t| t.is_active)?;
+    Some((doc_id, tab.id))
+}
+
+/// Index of all stored documents, documents loaded to memory and notes on the canvas take precedence.
+fn build_link_index<'a>(
+    app_state: &AppState,
+    pkv: &PkvStore,
+    canvas_notes: impl Iterator<Item = &'a RawText>,
+) -> LinkIndex {
+    let mut docs = pkv
+        .get::<HashMap<ReflectableUuid, Doc>>("docs")
+        .unwrap_or_default();
+    docs.retain(|id, _| !app_state.docs.contains_key(id));
+    let mut index = LinkIndex::new(docs.values().chain(app_state.docs.values()));
+    if let Some((doc_id, tab_id)) = active_tab(app_state) {
+        index.set_tab_notes(
+            doc_id,
+            tab_id,
+            canvas_notes.map(|raw_text| (raw_text.id, raw_text.last_text.clone())),
+        );
+    }
+    index
+}
+
+/// Opens the document and tab the link points to and centers the camera on the linked note.
+pub fn open_internal_link(
+    mut commands: Commands,
+    mut events: EventReader<OpenInternalLink>,
+    mut app_state: ResMut<AppState>,
+    mut pkv: ResMut<PkvStore>,
+    raw_text_query: Query<&RawText>,
+) {
+    for event in events.iter() {
+        let Some((current_doc, current_tab)) = active_tab(&app_state) else {
+            continue;
+        };
+        let index = build_link_index(&app_state, &pkv, raw_text_query.iter());
+        let Some(location) = parse_internal_link(&event.link)
+            .and_then(|target| index.resolve(&target, current_doc, current_tab))
+        else {
+            warn!("Link target not found: {}", event.link);
+            continue;
+        };
+        if location.doc_id != current_doc {
+            commands.insert_resource(SaveDocRequest {
+                doc_id: current_doc,
+                path: None,
+            });
+            load_doc_to_memory(location.doc_id, &mut app_state, &mut pkv);
+            if let (Some(tab_id), Some(doc)) =
+                (location.tab_id, app_state.docs.get_mut(&location.doc_id))
+            {
+                for tab in doc.tabs.iter_mut() {
+                    tab.is_active = tab.id == tab_id;
+                }
+            }
+            app_state.current_document = Some(location.doc_id);
+            commands.insert_resource(LoadDocRequest {
+                doc_id: location.doc_id,
+            });
+        } else if let Some(tab_id) = location.tab_id.filter(|tab_id| *tab_id != current_tab) {
+            commands.insert_resource(SaveTabRequest {
+                doc_id: current_doc,
+                tab_id: current_tab,
+            });
+            for tab in app_state
+                .docs
+                .get_mut(&current_doc)
+                .unwrap()
+                .tabs
+                .iter_mut()
+            {
+                tab.is_active = tab.id == tab_id;
+            }
+            commands.insert_resource(LoadTabRequest {
+                doc_id: current_doc,
+                tab_id,
+                drop_last_checkpoint: false,
+            });
+        }
+        if let Some(node_id) = location.node_id {
+            commands.insert_resource(FocusNodeRequest { node_id });
+        }
+    }
+}
+
+pub fn should_focus_node(request: Option<Res<FocusNodeRequest>>) -> bool {
+    request.is_some()
+}
+
+/// Centers the camera on the requested note once its tab is loaded.
+pub fn focus_node(
+    mut commands: Commands,
+    request: Res<FocusNodeRequest>,
+    loading: (Option<Res<LoadDocRequest>>, Option<Res<LoadTabRequest>>),
+    velo_node_query: Query<(&VeloNode, &Transform)>,
+    mut camera_query: Query<&mut Transform, (With<MainCamera>, Without<VeloNode>)>,
+    mut frames_waited: Local<usize>,
+) {
+    if loading.0.is_some() || loading.1.is_some() {
+        *frames_waited = 0;
+        return;
+    }
+    if let Some((_, transform)) = velo_node_query
+        .iter()
+        .find(|(node, _)| node.id == request.node_id)
+    {
+        let mut camera_transform = camera_query.single_mut();
+        camera_transform.translation.x = transform.translation.x;
+        camera_transform.translation.y = transform.translation.y;
+    } else if *frames_waited < FOCUS_NODE_FRAMES {
+        *frames_waited += 1;
+        return;
+    } else {
+        warn!("Linked note not found: {}", request.node_id.0);
+    }
+    *frames_waited = 0;
+    commands.remove_resource::<FocusNodeRequest>();
+}
+
+/// Shows link suggestions while a wiki link is typed in the edited note, and backlinks of the selected note otherwise.
+pub fn update_links_panel(
+    mut commands: Commands,
+    ui_state: Res<UiState>,
+    app_state: Res<AppState>,
+    pkv: Res<PkvStore>,
+    theme: Res<Theme>,
+    raw_text_query: Query<(&RawText, &CosmicEdit)>,
+    changed_raw_text: Query<(), Changed<RawText>>,
+    mut links_panel: Query<(Entity, &mut Style), With<LinksPanel>>,
+    mut index: Local<Option<(ReflectableUuid, LinkIndex)>>,
+    mut last_items: Local<(String, Vec<(String, LinkPanelAction)>)>,
+) {
+    let Ok((links_panel, mut style)) = links_panel.get_single_mut() else {
+        return;
+    };
+    let (Some(node_id), Some((doc_id, tab_id))) = (ui_state.entity_to_edit, active_tab(&app_state))
+    else {
+        *index = None;
+        if !last_items.1.is_empty() {
+            *last_items = default();
+            style.display = Display::None;
+            commands.entity(links_panel).despawn_descendants();
+        }
+        return;
+    };
+    if index.as_ref().map(|(id, _)| *id) != Some(node_id) || !changed_raw_text.is_empty() {
+        let link_index = build_link_index(
+            &app_state,
+            &pkv,
+            raw_text_query.iter().map(|(raw_text, _)| raw_text),
+        );
+        *index = Some((node_id, link_index));
+    }
+    let link_index = &index.as_ref().unwrap().1;
+
+    let typed = raw_text_query
+        .iter()
+        .find(|(raw_text, cosmic_edit)| raw_text.id == node_id && !cosmic_edit.readonly)
+        .and_then(|(_, cosmic_edit)| {
+            let cursor = cosmic_edit.editor.cursor();
+            let line = cosmic_edit.editor.buffer().lines.get(cursor.line)?;
+            typed_link(line.text().get(..cursor.index)?).map(|typed| typed.to_string())
+        });
+    let items = match typed {
+        Some(typed) => (
+            "Link to".to_string(),
+            link_index
+                .suggestions(&typed, doc_id, tab_id)
+                .into_iter()
+                .map(|link| {
+                    let action = LinkPanelAction::Complete {
+                        typed: typed.clone(),
+                        link: link.clone(),
+                    };
+                    (link, action)
+                })
+                .collect(),
+        ),
+        None => (
+            "Backlinks".to_string(),
+            link_index
+                .backlinks(doc_id, node_id)
+                .into_iter()
+                .map(|note| {
+                    let link = format!(
+                        "{}{}/{}/{}",
+                        INTERNAL_LINK_SCHEME, note.doc_id.0, note.tab_id.0, note.node_id.0
+                    );
+                    (
+                        link_index.note_location_name(note),
+                        LinkPanelAction::Open(link),
+                    )
+                })
+                .collect(),
+        ),
+    };
+    if items == *last_items {
+        return;
+    }
+    commands.entity(links_panel).despawn_descendants();
+    style.display = if items.1.is_empty() {
+        Display::None
+    } else {
+        Display::Flex
+    };
+    if !items.1.is_empty() {
+        let header = commands
+            .spawn(TextBundle::from_section(
+                items.0.clone(),
+                TextStyle {
+                    font_size: 14.,
+                    color: theme.font,
+                    ..default()
+                },
+            ))
+            .id();
+        commands.entity(links_panel).add_child(header);
+    }
+    for (label, action) in items.1.iter() {
+        let item = commands
+            .spawn((
+                ButtonBundle {
+                    border_color: theme.btn_border.into(),
+                    background_color: theme.ok_cancel_bg.into(),
+                    style: Style {
+                        border: UiRect::all(Val::Px(1.)),
+                        padding: UiRect::horizontal(Val::Px(4.)),
+                        margin: UiRect::top(Val::Px(2.)),
+                        ..default()
+                    },
+                    ..default()
+                },
+                GenericButton,
+                LinkPanelItem {
+                    action: action.clone(),
+                },
+            ))
+            .with_children(|builder| {
+                builder.spawn(TextBundle::from_section(
+                    label.clone(),
+                    TextStyle {
+                        font_size: 14.,
+                        color: theme.link,
+                        ..default()
+                    },
+                ));
+            })
+            .id();
+        commands.entity(links_panel).add_child(item);
+    }
+    *last_items = items;
+}
+
+/// Replaces the typed part of the wiki link before the cursor with the chosen link.
+fn complete_link(cosmic_edit: &mut CosmicEdit, font: &mut CosmicFont, typed: &str, link: &str) {
+    for _ in typed.chars() {
+        cosmic_edit.editor.action(&mut font.0, Action::Backspace);
+    }
+    for ch in format!("{}]]", link).chars() {
+        cosmic_edit.editor.action(&mut font.0, Action::Insert(ch));
+    }
+    cosmic_edit.editor.buffer_mut().set_redraw(true);
+}
+
+pub fn link_panel_click(
+    interaction_query: Query<(&Interaction, &LinkPanelItem), Changed<Interaction>>,
+    active_editor: Res<ActiveEditor>,
+    mut cosmic_edit_query: Query<&mut CosmicEdit>,
+    mut cosmic_fonts: ResMut<Assets<CosmicFont>>,
+    mut open_link: EventWriter<OpenInternalLink>,
+) {
+    for (interaction, item) in interaction_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match &item.action {
+            LinkPanelAction::Complete { typed, link } => {
+                let Some(mut cosmic_edit) = active_editor
+                    .entity
+                    .and_then(|entity| cosmic_edit_query.get_mut(entity).ok())
+                else {
+                    continue;
+                };
+                let font = cosmic_fonts.get_mut(&cosmic_edit.font_system).unwrap();
+                complete_link(&mut cosmic_edit, font, typed, link);
+            }
+            LinkPanelAction::Open(link) => open_link.send(OpenInternalLink { link: link.clone() }),
+        }
+    }
+}
+
+/// Tab accepts the first link suggestion instead of being typed into the note.
+pub fn complete_link_on_tab(
+    keys: Res<Input<KeyCode>>,
+    mut char_events: ResMut<Events<ReceivedCharacter>>,
+    links_panel: Query<&Children, With<LinksPanel>>,
+    items: Query<&LinkPanelItem>,
+    active_editor: Res<ActiveEditor>,
+    mut cosmic_edit_query: Query<&mut CosmicEdit>,
+    mut cosmic_fonts: ResMut<Assets<CosmicFont>>,
+) {
+    if !keys.just_pressed(KeyCode::Tab) {
+        return;
+    }
+    let Some((typed, link)) = links_panel
+        .iter()
+        .flat_map(|children| children.iter())
+        .find_map(|child| match items.get(*child).map(|item| &item.action) {
+            Ok(LinkPanelAction::Complete { typed, link }) => Some((typed, link)),
+            _ => None,
+        })
+    else {
+        return;
+    };
+    let Some(mut cosmic_edit) = active_editor
+        .entity
+        .and_then(|entity| cosmic_edit_query.get_mut(entity).ok())
+    else {
+        return;
+    };
+    let font = cosmic_fonts.get_mut(&cosmic_edit.font_system).unwrap();
+    complete_link(&mut cosmic_edit, font, typed, link);
+    char_events.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use super::*;
+    use crate::components::Tab;
+
+    fn doc(name: &str, tabs: Vec<(&str, Vec<(ReflectableUuid, &str)>)>) -> Doc {
+        Doc {
+            id: ReflectableUuid::generate(),
+            name: name.to_string(),
+            tabs: tabs
+                .into_iter()
+                .enumerate()
+                .map(|(i, (tab_name, notes))| {
+                    let nodes: Vec<Value> = notes
+                        .into_iter()
+                        .map(|(id, text)| serde_json::json!({ "id": id.0, "text": { "text": text } }))
+                        .collect();
+                    Tab {
+                        is_active: i == 0,
+                        id: ReflectableUuid::generate(),
+                        name: tab_name.to_string(),
+                        checkpoints: VecDeque::from([serde_json::json!({ "nodes": nodes }).to_string()]),
+                        z_index: 1.,
+                        slides: vec![],
+                    }
+                })
+                .collect(),
+            ..default()
+        }
+    }
+
+    #[test]
+    fn test_parse_internal_link() {
+        assert_eq!(
+            parse_internal_link("[[Doc/Tab#note]]"),
+            Some(LinkTarget {
+                doc: Some("Doc".to_string()),
+                tab: Some("Tab".to_string()),
+                note: Some("note".to_string()),
+            })
+        );
+        assert_eq!(
+            parse_internal_link("[[#note]]"),
+            Some(LinkTarget {
+                note: Some("note".to_string()),
+                ..default()
+            })
+        );
+        assert_eq!(
+            parse_internal_link("velo://doc-id/tab-id"),
+            Some(LinkTarget {
+                doc: Some("doc-id".to_string()),
+                tab: Some("tab-id".to_string()),
+                note: None,
+            })
+        );
+        assert_eq!(parse_internal_link("https://example.com"), None);
+        assert_eq!(parse_internal_link("velo://"), None);
+        assert_eq!(typed_link("see [[Doc/Ta"), Some("Doc/Ta"));
+        assert_eq!(typed_link("see [[Doc]] and"), None);
+        assert_eq!(note_title("\n## Plan \nsteps"), "Plan");
+    }
+
+    #[test]
+    fn test_resolve_links_and_backlinks() {
+        let (plan, steps, other) = (
+            ReflectableUuid::generate(),
+            ReflectableUuid::generate(),
+            ReflectableUuid::generate(),
+        );
+        let project = doc(
+            "Project",
+            vec![
+                ("Main", vec![(plan, "# Plan\nsee [[#Steps]]")]),
+                ("Details", vec![(steps, "Steps")]),
+            ],
+        );
+        let notes = doc(
+            "Notes",
+            vec![("Tab 1", vec![(other, "[[project/details#steps|steps]]")])],
+        );
+        let index = LinkIndex::new([&project, &notes]);
+        let (main_tab, details_tab) = (project.tabs[0].id, project.tabs[1].id);
+
+        let location = |link: &str, from: &Doc| {
+            index.resolve(
+                &parse_internal_link(link).unwrap(),
+                from.id,
+                from.tabs[0].id,
+            )
+        };
+        assert_eq!(
+            location("[[#Steps]]", &project),
+            Some(LinkLocation {
+                doc_id: project.id,
+                tab_id: Some(details_tab),
+                node_id: Some(steps),
+            })
+        );
+        assert_eq!(
+            location("[[Project]]", &notes),
+            Some(LinkLocation {
+                doc_id: project.id,
+                tab_id: None,
+                node_id: None,
+            })
+        );
+        assert_eq!(
+            location(
+                &format!("velo://{}/{}/{}", project.id.0, main_tab.0, plan.0),
+                &notes
+            )
+            .and_then(|location| location.node_id),
+            Some(plan)
+        );
+        assert_eq!(location("[[Missing]]", &notes), None);
+        assert_eq!(location("[[Project/Main#Steps]]", &notes), None);
+
+        let backlinks: Vec<ReflectableUuid> = index
+            .backlinks(project.id, steps)
+            .iter()
+            .map(|note| note.node_id)
+            .collect();
+        assert_eq!(backlinks.len(), 2);
+        assert!(backlinks.contains(&plan) && backlinks.contains(&other));
+        assert_eq!(
+            index.note_location_name(index.backlinks(project.id, steps)[0]),
+            "Project / Main: Plan"
+        );
+    }
+
+    #[test]
+    fn test_link_suggestions() {
+        let note = ReflectableUuid::generate();
+        let project = doc(
+            "Project",
+            vec![("Main", vec![(note, "Plan")]), ("Details", vec![])],
+        );
+        let notes = doc("Notes", vec![("Tab 1", vec![])]);
+        let index = LinkIndex::new([&project, &notes]);
+        let (doc_id, tab_id) = (project.id, project.tabs[0].id);
+        assert_eq!(
+            index.suggestions("", doc_id, tab_id),
+            vec!["#Plan", "Notes", "Project"]
+        );
+        assert_eq!(index.suggestions("pro", doc_id, tab_id), vec!["Project"]);
+        assert_eq!(
+            index.suggestions("Project/", doc_id, tab_id),
+            vec!["Project/Details", "Project/Main"]
+        );
+        assert_eq!(index.suggestions("#pl", doc_id, tab_id), vec!["#Plan"]);
+        assert_eq!(
+            index.suggestions("Project#", notes.id, notes.tabs[0].id),
+            vec!["Project#Plan"]
+        );
+    }
+}
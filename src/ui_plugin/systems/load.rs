@@ -9,16 +9,16 @@ use bevy_prototype_lyon::prelude::{PathBuilder, ShapeBundle, Stroke};
 
 use super::{
     ui_helpers::{
-        add_tab, spawn_sprite_node, BottomPanel, Drawing, InteractiveNode, NodeMeta, TabContainer,
-        VeloNode,
+        add_tab, spawn_sprite_node, BottomPanel, Drawing, InteractiveNode, NodeMeta, NodeMetadata,
+        TabContainer, VeloNode,
     },
-    DeleteDoc, DeleteTab, DrawingJsonNode,
+    DeleteDoc, DeleteTab, DrawingJsonNode, MAX_RECENT_DOCS,
 };
 use crate::{canvas::arrow::events::CreateArrow, utils::load_doc_to_memory};
 use crate::{
     canvas::{arrow::components::ArrowMeta, shadows::CustomShadowMaterial},
     resources::{FontSystemState, LoadTabRequest},
-    themes::Theme,
+    themes::{resolve_node_color, Theme},
 };
 
 use crate::resources::{AppState, LoadDocRequest};
@@ -26,7 +26,7 @@ use crate::utils::ReflectableUuid;
 use crate::{JsonNode, UiState};
 use bevy_pkv::PkvStore;
 use image::{load_from_memory_with_format, ImageFormat};
-use serde_json::{Map, Value};
+use serde_json::Value;
 
 pub fn should_load_doc(request: Option<Res<LoadDocRequest>>) -> bool {
     request.is_some()
@@ -70,6 +70,11 @@ pub fn load_doc(
         }
     }
     load_doc_to_memory(doc_id, &mut app_state, &mut pkv);
+    // current document is not shown among recent ones, so keep one more
+    app_state.recent_docs.retain(|id| *id != doc_id);
+    app_state.recent_docs.insert(0, doc_id);
+    app_state.recent_docs.truncate(MAX_RECENT_DOCS + 1);
+    pkv.set("recent_docs", &app_state.recent_docs).unwrap();
 
     let mut tabs = vec![];
     for entity in tabs_query.iter_mut() {
@@ -114,14 +119,9 @@ pub fn load_tab(
     font_system_state: ResMut<FontSystemState>,
     mut windows: Query<&mut Window, With<PrimaryWindow>>,
     theme: Res<Theme>,
-    mut local_theme: Local<Option<Map<String, Value>>>,
     mut materials_meshes: (ResMut<Assets<CustomShadowMaterial>>, ResMut<Assets<Mesh>>),
 ) {
     *ui_state = UiState::default();
-    let value = serde_json::to_value(&*theme).unwrap();
-    if local_theme.is_none() || theme.is_changed() {
-        *local_theme = Some(value.as_object().unwrap().clone());
-    }
 
     commands.insert_resource(bevy_cosmic_edit::ActiveEditor { entity: None });
     let primary_window = windows.single_mut();
@@ -161,38 +161,11 @@ pub fn load_tab(
             let nodes = json["nodes"].as_array().unwrap();
             for node in nodes.iter() {
                 let json_node: JsonNode<String> = serde_json::from_value(node.clone()).unwrap();
-                let image: Option<Handle<Image>> = match images.get(&json_node.id.to_string()) {
-                    Some(image) => {
-                        let image_bytes = general_purpose::STANDARD
-                            .decode(image.as_str().unwrap().as_bytes())
-                            .unwrap();
-                        let img =
-                            load_from_memory_with_format(&image_bytes, ImageFormat::Png).unwrap();
-                        let size: Extent3d = Extent3d {
-                            width: img.width(),
-                            height: img.height(),
-                            ..Default::default()
-                        };
-                        let image = Image::new(
-                            size,
-                            TextureDimension::D2,
-                            img.into_bytes(),
-                            TextureFormat::Rgba8UnormSrgb,
-                        );
-                        let image_handle = res_images.add(image);
-                        Some(image_handle)
-                    }
-                    None => None,
-                };
-                let theme_color = local_theme
-                    .as_ref()
-                    .unwrap()
-                    .get(json_node.bg_color.as_str())
-                    .unwrap();
-                let pair_bg_color = (
-                    json_node.bg_color,
-                    serde_json::from_value(theme_color.clone()).unwrap(),
-                );
+                let image: Option<Handle<Image>> = images
+                    .get(&json_node.id.to_string())
+                    .and_then(|image| load_node_image(&mut res_images, image.as_str()?));
+                let bg_color = resolve_node_color(&theme, &json_node.bg_color, theme.node_bg);
+                let pair_bg_color = (json_node.bg_color, bg_color);
                 let _ = spawn_sprite_node(
                     &mut commands,
                     &mut materials_meshes.0,
@@ -210,8 +183,14 @@ pub fn load_tab(
                         pair_bg_color,
                         position: (json_node.x, json_node.y, json_node.z),
                         text_pos: json_node.text.pos,
+                        text_style: json_node.text.style,
+                        auto_size: json_node.auto_size,
                         is_active: false,
                         visible: json_node.visible,
+                        metadata: NodeMetadata {
+                            tags: json_node.tags,
+                            fields: json_node.metadata,
+                        },
                     },
                 );
             }
@@ -230,44 +209,71 @@ pub fn load_tab(
             for drawing in drawings.iter() {
                 let drawing_json_node: DrawingJsonNode<String> =
                     serde_json::from_value(drawing.clone()).unwrap();
-                let mut path_builder = PathBuilder::new();
-                let mut points_iter = drawing_json_node.points.iter();
-                let start = points_iter.next().unwrap();
-                path_builder.move_to(*start);
-                path_builder.line_to(*start);
-                for point in points_iter {
-                    path_builder.line_to(*point);
-                }
-                let path = path_builder.build();
-                let theme_color = local_theme
-                    .as_ref()
-                    .unwrap()
-                    .get(drawing_json_node.drawing_color.as_str())
-                    .unwrap();
-                let pair_color = (
-                    drawing_json_node.drawing_color,
-                    serde_json::from_value(theme_color.clone()).unwrap(),
-                );
-                commands.spawn((
-                    ShapeBundle {
-                        path,
-                        transform: Transform::from_xyz(
-                            drawing_json_node.x,
-                            drawing_json_node.y,
-                            drawing_json_node.z,
-                        ),
-                        ..Default::default()
-                    },
-                    Stroke::new(pair_color.1, 2.),
-                    Drawing {
-                        id: drawing_json_node.id,
-                        points: drawing_json_node.points.clone(),
-                        drawing_color: pair_color,
-                    },
-                    InteractiveNode,
-                ));
+                spawn_drawing(&mut commands, &theme, drawing_json_node);
             }
             break;
         }
     }
 }
+
+/// Decodes the base64 encoded PNG of an image note, `None` when it isn't a valid PNG.
+pub fn load_node_image(res_images: &mut Assets<Image>, image: &str) -> Option<Handle<Image>> {
+    let image_bytes = general_purpose::STANDARD.decode(image.as_bytes()).ok()?;
+    let img = load_from_memory_with_format(&image_bytes, ImageFormat::Png).ok()?;
+    let size: Extent3d = Extent3d {
+        width: img.width(),
+        height: img.height(),
+        ..Default::default()
+    };
+    let image = Image::new(
+        size,
+        TextureDimension::D2,
+        img.into_rgba8().into_raw(),
+        TextureFormat::Rgba8UnormSrgb,
+    );
+    Some(res_images.add(image))
+}
+
+/// Spawns a pencil drawing, drawings without points are skipped.
+pub fn spawn_drawing(
+    commands: &mut Commands,
+    theme: &Theme,
+    drawing_json_node: DrawingJsonNode<String>,
+) -> Option<Entity> {
+    let mut path_builder = PathBuilder::new();
+    let mut points_iter = drawing_json_node.points.iter();
+    let start = points_iter.next()?;
+    path_builder.move_to(*start);
+    path_builder.line_to(*start);
+    for point in points_iter {
+        path_builder.line_to(*point);
+    }
+    let path = path_builder.build();
+    let drawing_color = resolve_node_color(
+        theme,
+        &drawing_json_node.drawing_color,
+        theme.drawing_pencil_btn,
+    );
+    let pair_color = (drawing_json_node.drawing_color, drawing_color);
+    let entity = commands
+        .spawn((
+            ShapeBundle {
+                path,
+                transform: Transform::from_xyz(
+                    drawing_json_node.x,
+                    drawing_json_node.y,
+                    drawing_json_node.z,
+                ),
+                ..Default::default()
+            },
+            Stroke::new(pair_color.1, 2.),
+            Drawing {
+                id: drawing_json_node.id,
+                points: drawing_json_node.points,
+                drawing_color: pair_color,
+            },
+            InteractiveNode,
+        ))
+        .id();
+    Some(entity)
+}
@@ -2,6 +2,7 @@ use std::collections::HashMap;
 use std::fs::canonicalize;
 use std::path::PathBuf;
 
+use async_channel::Sender;
 use bevy::prelude::*;
 use bevy::tasks::IoTaskPool;
 use bevy_cosmic_edit::{get_cosmic_text, ActiveEditor, CosmicEdit};
@@ -9,13 +10,110 @@ use bevy_pkv::PkvStore;
 use cosmic_text::Edit;
 use linkify::{LinkFinder, LinkKind};
 
-use super::ui_helpers::{ModalCancel, ModalConfirm, ModalTop};
-use super::{CommChannels, EditableText, ModalAction, TabContainer};
-use crate::components::Doc;
+use super::ui_helpers::{ModalCancel, ModalConfirm, ModalTop, NodeMetadata, RawText, VeloShape};
+use super::{
+    compare_versions, decrypt_document, index_document, is_encrypted, name_version,
+    parse_node_metadata, parse_node_text_style, record_merge_base, remove_history,
+    remove_merge_base, split_key_fragment, start_doc_merge, ChangeNodeColor, CommChannels,
+    EditableText, HistoryBrowser, ModalAction, NodeTextStyle, NotificationChannels, Notifier,
+    TabContainer, ViewerMode,
+};
+use crate::components::{Doc, DocSummary};
 use crate::resources::{AppState, LoadDocRequest, LoadTabRequest, SaveDocRequest};
-use crate::utils::ReflectableUuid;
+use crate::themes::{NodeColor, Theme};
+use crate::utils::{parse_tags, ReflectableUuid};
 use crate::UiState;
 
+const IMPORT_TASK: &str = "import";
+
+/// Decrypts the document if needed and hands it over to `load_doc_handler`.
+fn send_document(tx: &Sender<String>, notifier: &Notifier, json: String, key: Option<&str>) {
+    let json = match (is_encrypted(&json), key) {
+        (false, _) => json,
+        (true, Some(key)) => match decrypt_document(&json, key) {
+            Ok(json) => json,
+            Err(e) => {
+                notifier.error(IMPORT_TASK, format!("Failed to decrypt document: {}", e));
+                return;
+            }
+        },
+        (true, None) => {
+            notifier.error(
+                IMPORT_TASK,
+                "Document is encrypted, open it with the full share link including #key=...",
+            );
+            return;
+        }
+    };
+    if tx.try_send(json).is_err() {
+        notifier.error(IMPORT_TASK, "Another document is being imported, try again");
+    }
+}
+
+/// Downloads the document in the background, failures are reported as notifications.
+pub fn import_document_from_url(
+    url: String,
+    key: Option<String>,
+    tx: Sender<String>,
+    notifier: Notifier,
+) {
+    notifier.progress(IMPORT_TASK, format!("Downloading {}...", url));
+    let task = IoTaskPool::get().spawn(async move {
+        ehttp::fetch(ehttp::Request::get(&url), move |result| {
+            let json = match result {
+                Ok(response) if response.ok => response.text().map(|text| text.to_string()),
+                Ok(response) => {
+                    notifier.error(
+                        IMPORT_TASK,
+                        format!(
+                            "Failed to download {}: {} {}",
+                            url, response.status, response.status_text
+                        ),
+                    );
+                    return;
+                }
+                Err(e) => {
+                    notifier.error(IMPORT_TASK, format!("Failed to download {}: {}", url, e));
+                    return;
+                }
+            };
+            match json {
+                Some(json) => send_document(&tx, &notifier, json, key.as_deref()),
+                None => notifier.error(IMPORT_TASK, format!("{} is not a text document", url)),
+            }
+        });
+    });
+    task.detach();
+}
+
+fn import_document_from_file(path: &str, tx: &Sender<String>, notifier: &Notifier) {
+    match canonicalize(PathBuf::from(path.trim())).and_then(std::fs::read_to_string) {
+        Ok(json) => send_document(tx, notifier, json, None),
+        Err(e) => notifier.error(
+            IMPORT_TASK,
+            format!("Failed to read {}: {}", path.trim(), e),
+        ),
+    }
+}
+
+/// Accepts document links with the key of encrypted documents in the fragment.
+fn import_document_from_link(text: &str, tx: &Sender<String>, notifier: &Notifier) {
+    let (url, key) = split_key_fragment(text.trim());
+    let mut finder = LinkFinder::new();
+    finder.kinds(&[LinkKind::Url]);
+    let links: Vec<_> = finder.links(url).collect();
+    if links.len() == 1 {
+        import_document_from_url(
+            links[0].as_str().to_owned(),
+            key.map(str::to_owned),
+            tx.clone(),
+            notifier.clone(),
+        );
+    } else {
+        notifier.error(IMPORT_TASK, format!("{} is not a URL", text.trim()));
+    }
+}
+
 pub fn cancel_modal(
     mut commands: Commands,
     mut interaction_query: Query<
@@ -48,26 +146,27 @@ fn delete_doc(
     remove_from_storage(pkv, id_to_remove, app_state.current_document.unwrap());
     app_state.current_document = app_state.docs.keys().next().cloned();
     app_state.doc_list_ui.remove(&id_to_remove);
+    app_state.recent_docs.retain(|id| *id != id_to_remove);
     commands.insert_resource(LoadDocRequest {
         doc_id: app_state.current_document.unwrap(),
     });
-    #[cfg(not(target_arch = "wasm32"))]
-    {
-        if let Some(index) = &app_state.search_index {
-            let index = std::sync::Arc::new(index.index.clone());
-            let pool = IoTaskPool::get();
-            let id_to_remove = std::sync::Arc::new(id_to_remove);
-            pool.spawn(async move {
-                let _ = super::clear_doc_index(&index, &id_to_remove.0);
-            })
-            .detach();
-        }
+    if let Some(index) = &app_state.search_index {
+        let index = index.index.clone();
+        let pool = IoTaskPool::get();
+        let id_to_remove = std::sync::Arc::new(id_to_remove);
+        pool.spawn(async move {
+            if let Err(e) = index.clear_doc(&id_to_remove.0) {
+                error!("Error removing document from search index {:?}", e);
+            }
+        })
+        .detach();
     }
 }
 
 fn delete_tab(
     app_state: &mut ResMut<AppState>,
     commands: &mut Commands,
+    pkv: &mut ResMut<PkvStore>,
     query_container: &mut Query<(Entity, &TabContainer), With<TabContainer>>,
 ) {
     let current_document = app_state.current_document.unwrap();
@@ -81,10 +180,10 @@ fn delete_tab(
         .unwrap()
         .id;
 
-    #[cfg(not(target_arch = "wasm32"))]
     if let Some(index) = &mut app_state.search_index {
         index.tabs_to_delete.insert(tab_id.0);
     }
+    remove_history(pkv, [tab_id]);
 
     for (entity, tab) in query_container.iter_mut() {
         if tab.id == tab_id {
@@ -125,23 +224,55 @@ pub fn load_doc_handler(
     mut commands: Commands,
     mut app_state: ResMut<AppState>,
     comm_channels: Res<CommChannels>,
-    pkv: Res<PkvStore>,
+    mut pkv: ResMut<PkvStore>,
+    notifications: Res<NotificationChannels>,
+    viewer_mode: Option<Res<ViewerMode>>,
 ) {
-    if comm_channels.rx.is_empty() {
+    let Ok(r) = comm_channels.rx.try_recv() else {
         return;
-    }
-    let r = comm_channels
-        .rx
-        .try_recv()
-        .expect("Failed to receive document string");
-    let import_document: Doc = serde_json::from_str(&r).expect("Failed to deserialize document");
-    if let Ok(docs) = pkv.get::<HashMap<ReflectableUuid, Doc>>("docs") {
-        if docs.contains_key(&import_document.id) {
+    };
+    let notifier = &notifications.notifier;
+    let import_document: Doc = match serde_json::from_str(&r) {
+        Ok(doc) => doc,
+        Err(e) => {
+            notifier.error(IMPORT_TASK, format!("Not a Velo document: {}", e));
+            return;
+        }
+    };
+    if let Ok(mut docs) = pkv.get::<HashMap<ReflectableUuid, Doc>>("docs") {
+        if let Some(stored) = docs.remove(&import_document.id) {
+            if viewer_mode.is_some() {
+                notifier.error(
+                    IMPORT_TASK,
+                    format!("Document \"{}\" is already imported", import_document.name),
+                );
+                return;
+            }
+            // the copy in memory may be newer than the stored one
+            let ours = app_state
+                .docs
+                .get(&import_document.id)
+                .cloned()
+                .unwrap_or(stored);
+            start_doc_merge(
+                &mut commands,
+                &mut app_state,
+                &pkv,
+                ours,
+                import_document,
+                notifier,
+            );
             return;
         }
     }
+    record_merge_base(&mut pkv, &import_document);
+    notifier.success(
+        IMPORT_TASK,
+        format!("Imported \"{}\"", import_document.name),
+    );
     app_state.current_document = Some(import_document.id);
     app_state.doc_list_ui.insert(import_document.id);
+    index_document(&app_state, &import_document);
     app_state
         .docs
         .insert(import_document.id, import_document.clone());
@@ -163,7 +294,13 @@ pub fn confirm_modal(
     mut pkv: ResMut<PkvStore>,
     input: Res<Input<KeyCode>>,
     mut query_path: Query<(&CosmicEdit, &EditableText), With<EditableText>>,
+    mut node_metadata_query: Query<(&VeloShape, &mut NodeMetadata), With<VeloShape>>,
     comm_channels: Res<CommChannels>,
+    notifications: Res<NotificationChannels>,
+    mut change_node_color: EventWriter<ChangeNodeColor>,
+    mut text_style_query: Query<(&RawText, &CosmicEdit, &mut NodeTextStyle), With<RawText>>,
+    theme: Res<Theme>,
+    mut history_browser: Option<ResMut<HistoryBrowser>>,
 ) {
     for (interaction, path_modal_confirm) in interaction_query.iter_mut() {
         if let Interaction::Pressed = interaction {
@@ -181,31 +318,53 @@ pub fn confirm_modal(
                                     break;
                                 }
                                 ModalAction::LoadFromFile => {
-                                    if let Ok(path) = canonicalize(PathBuf::from(text.trim())) {
-                                        let json = std::fs::read_to_string(path)
-                                            .expect("Error reading document from file");
-                                        let cc = comm_channels.tx.clone();
-                                        cc.try_send(json).unwrap()
-                                    }
+                                    import_document_from_file(
+                                        &text,
+                                        &comm_channels.tx,
+                                        &notifications.notifier,
+                                    );
                                 }
                                 ModalAction::LoadFromUrl => {
-                                    let pool = IoTaskPool::get();
-                                    let url = text.trim();
-                                    let mut finder = LinkFinder::new();
-                                    finder.kinds(&[LinkKind::Url]);
-                                    let links: Vec<_> = finder.links(url).collect();
-                                    if links.len() == 1 {
-                                        let url = links.first().unwrap().as_str().to_owned();
-                                        let cc = comm_channels.tx.clone();
-                                        let task = pool.spawn(async move {
-                                            let request = ehttp::Request::get(url);
-                                            ehttp::fetch(request, move |result| {
-                                                let json_string = result.unwrap().text().unwrap();
-                                                cc.try_send(json_string).unwrap();
-                                            });
-                                        });
-                                        task.detach();
-                                    }
+                                    import_document_from_link(
+                                        &text,
+                                        &comm_channels.tx,
+                                        &notifications.notifier,
+                                    );
+                                }
+                                ModalAction::EditTags(_) => {
+                                    edit_tags(&mut app_state, &mut commands, &text);
+                                }
+                                ModalAction::EditNodeMetadata(id, _) => {
+                                    edit_node_metadata(&mut node_metadata_query, id, &text);
+                                }
+                                ModalAction::FilterNodes(_) => {
+                                    filter_nodes(&mut app_state, &text);
+                                }
+                                ModalAction::MoveToFolder(_) => {
+                                    move_to_folder(&mut app_state, &mut commands, &text);
+                                }
+                                ModalAction::PickColor(node_id, _) => {
+                                    pick_color(&theme, &mut change_node_color, node_id, &text);
+                                }
+                                ModalAction::EditTextStyle(id, _) => {
+                                    edit_node_text_style(
+                                        &mut commands,
+                                        &app_state,
+                                        &mut text_style_query,
+                                        id,
+                                        &text,
+                                    );
+                                }
+                                ModalAction::CompareVersions(_) => {
+                                    compare_versions(
+                                        &mut commands,
+                                        &app_state,
+                                        &notifications.notifier,
+                                        &text,
+                                    );
+                                }
+                                ModalAction::NameVersion(_) => {
+                                    name_version(&mut pkv, history_browser.as_deref_mut(), &text);
                                 }
                                 ModalAction::DeleteDocument => {}
                                 ModalAction::DeleteTab => {}
@@ -216,11 +375,24 @@ pub fn confirm_modal(
                         ModalAction::SaveToFile => {}
                         ModalAction::LoadFromFile => {}
                         ModalAction::LoadFromUrl => {}
+                        ModalAction::EditTags(_) => {}
+                        ModalAction::EditNodeMetadata(..) => {}
+                        ModalAction::FilterNodes(_) => {}
+                        ModalAction::MoveToFolder(_) => {}
+                        ModalAction::PickColor(..) => {}
+                        ModalAction::EditTextStyle(..) => {}
+                        ModalAction::CompareVersions(_) => {}
+                        ModalAction::NameVersion(_) => {}
                         ModalAction::DeleteDocument => {
                             delete_doc(&mut app_state, &mut commands, &mut pkv);
                         }
                         ModalAction::DeleteTab => {
-                            delete_tab(&mut app_state, &mut commands, &mut tab_query_container);
+                            delete_tab(
+                                &mut app_state,
+                                &mut commands,
+                                &mut pkv,
+                                &mut tab_query_container,
+                            );
                         }
                     }
                 }
@@ -245,31 +417,53 @@ pub fn confirm_modal(
                                 break;
                             }
                             ModalAction::LoadFromFile => {
-                                if let Ok(path) = canonicalize(PathBuf::from(text.trim())) {
-                                    let json = std::fs::read_to_string(path)
-                                        .expect("Error reading document from file");
-                                    let cc = comm_channels.tx.clone();
-                                    cc.try_send(json).unwrap()
-                                }
+                                import_document_from_file(
+                                    &text,
+                                    &comm_channels.tx,
+                                    &notifications.notifier,
+                                );
                             }
                             ModalAction::LoadFromUrl => {
-                                let pool = IoTaskPool::get();
-                                let url = text.trim();
-                                let mut finder = LinkFinder::new();
-                                finder.kinds(&[LinkKind::Url]);
-                                let links: Vec<_> = finder.links(url).collect();
-                                if links.len() == 1 {
-                                    let url = links.first().unwrap().as_str().to_owned();
-                                    let cc = comm_channels.tx.clone();
-                                    let task = pool.spawn(async move {
-                                        let request = ehttp::Request::get(url);
-                                        ehttp::fetch(request, move |result| {
-                                            let json_string = result.unwrap().text().unwrap();
-                                            cc.try_send(json_string).unwrap();
-                                        });
-                                    });
-                                    task.detach();
-                                }
+                                import_document_from_link(
+                                    &text,
+                                    &comm_channels.tx,
+                                    &notifications.notifier,
+                                );
+                            }
+                            ModalAction::EditTags(_) => {
+                                edit_tags(&mut app_state, &mut commands, &text);
+                            }
+                            ModalAction::EditNodeMetadata(id, _) => {
+                                edit_node_metadata(&mut node_metadata_query, id, &text);
+                            }
+                            ModalAction::FilterNodes(_) => {
+                                filter_nodes(&mut app_state, &text);
+                            }
+                            ModalAction::MoveToFolder(_) => {
+                                move_to_folder(&mut app_state, &mut commands, &text);
+                            }
+                            ModalAction::PickColor(node_id, _) => {
+                                pick_color(&theme, &mut change_node_color, node_id, &text);
+                            }
+                            ModalAction::EditTextStyle(id, _) => {
+                                edit_node_text_style(
+                                    &mut commands,
+                                    &app_state,
+                                    &mut text_style_query,
+                                    id,
+                                    &text,
+                                );
+                            }
+                            ModalAction::CompareVersions(_) => {
+                                compare_versions(
+                                    &mut commands,
+                                    &app_state,
+                                    &notifications.notifier,
+                                    &text,
+                                );
+                            }
+                            ModalAction::NameVersion(_) => {
+                                name_version(&mut pkv, history_browser.as_deref_mut(), &text);
                             }
                             ModalAction::DeleteDocument => {}
                             ModalAction::DeleteTab => {}
@@ -280,12 +474,23 @@ pub fn confirm_modal(
                     ModalAction::SaveToFile => {}
                     ModalAction::LoadFromFile => {}
                     ModalAction::LoadFromUrl => {}
+                    ModalAction::EditTags(_) => {}
+                    ModalAction::EditNodeMetadata(..) => {}
+                    ModalAction::FilterNodes(_) => {}
+                    ModalAction::MoveToFolder(_) => {}
+                    ModalAction::PickColor(..) => {}
+                    ModalAction::EditTextStyle(..) => {}
+                    ModalAction::CompareVersions(_) => {}
+                    ModalAction::NameVersion(_) => {}
                     ModalAction::DeleteDocument => {
                         delete_doc(&mut app_state, &mut commands, &mut pkv);
                     }
-                    ModalAction::DeleteTab => {
-                        delete_tab(&mut app_state, &mut commands, &mut tab_query_container)
-                    }
+                    ModalAction::DeleteTab => delete_tab(
+                        &mut app_state,
+                        &mut commands,
+                        &mut pkv,
+                        &mut tab_query_container,
+                    ),
                 }
             }
             commands.entity(entity).despawn_recursive();
@@ -295,16 +500,94 @@ pub fn confirm_modal(
     }
 }
 
+fn edit_tags(app_state: &mut ResMut<AppState>, commands: &mut Commands, text: &str) {
+    let doc_id = app_state.current_document.unwrap();
+    app_state.docs.get_mut(&doc_id).unwrap().tags = parse_tags(text);
+    commands.insert_resource(SaveDocRequest { doc_id, path: None });
+}
+
+fn move_to_folder(app_state: &mut ResMut<AppState>, commands: &mut Commands, text: &str) {
+    let doc_id = app_state.current_document.unwrap();
+    let folder = text.trim();
+    app_state.docs.get_mut(&doc_id).unwrap().folder = if folder.is_empty() {
+        None
+    } else {
+        Some(folder.to_string())
+    };
+    commands.insert_resource(SaveDocRequest { doc_id, path: None });
+}
+
+fn edit_node_metadata(
+    node_metadata_query: &mut Query<(&VeloShape, &mut NodeMetadata), With<VeloShape>>,
+    id: ReflectableUuid,
+    text: &str,
+) {
+    for (shape, mut metadata) in node_metadata_query.iter_mut() {
+        if shape.id == id {
+            *metadata = parse_node_metadata(text);
+            break;
+        }
+    }
+}
+
+fn pick_color(
+    theme: &Res<Theme>,
+    change_node_color: &mut EventWriter<ChangeNodeColor>,
+    node_id: Option<ReflectableUuid>,
+    text: &str,
+) {
+    let node_color = NodeColor::parse(text);
+    if let Some(color) = node_color.resolve(theme) {
+        change_node_color.send(ChangeNodeColor {
+            node_id,
+            pair_color: (node_color.to_string(), color),
+        });
+    } else {
+        warn!("Unknown color {}", text.trim());
+    }
+}
+
+fn edit_node_text_style(
+    commands: &mut Commands,
+    app_state: &ResMut<AppState>,
+    text_style_query: &mut Query<(&RawText, &CosmicEdit, &mut NodeTextStyle), With<RawText>>,
+    id: ReflectableUuid,
+    text: &str,
+) {
+    for (raw_text, cosmic_edit, mut style) in text_style_query.iter_mut() {
+        if raw_text.id == id {
+            let mut new_style = parse_node_text_style(text);
+            new_style.fill_layout(&cosmic_edit.text_pos.clone().into());
+            *style = new_style;
+            if let Some(doc_id) = app_state.current_document {
+                commands.insert_resource(SaveDocRequest { doc_id, path: None });
+            }
+            break;
+        }
+    }
+}
+
+fn filter_nodes(app_state: &mut ResMut<AppState>, text: &str) {
+    let filter = parse_node_metadata(text);
+    app_state.node_filter = if filter == NodeMetadata::default() {
+        None
+    } else {
+        Some(filter)
+    };
+}
+
 fn remove_from_storage(
     pkv: &mut ResMut<PkvStore>,
     id_to_remove: ReflectableUuid,
     new_id: ReflectableUuid,
 ) {
     if let Ok(mut docs) = pkv.get::<HashMap<ReflectableUuid, Doc>>("docs") {
-        if docs.remove(&id_to_remove).is_some() {
+        if let Some(doc) = docs.remove(&id_to_remove) {
             pkv.set("docs", &docs).unwrap();
+            remove_history(pkv, doc.tabs.iter().map(|tab| tab.id));
         }
     }
+    remove_merge_base(pkv, id_to_remove);
     if let Ok(mut tags) = pkv.get::<HashMap<ReflectableUuid, Vec<String>>>("tags") {
         if tags.remove(&id_to_remove).is_some() {
             pkv.set("tags", &tags).unwrap();
@@ -315,6 +598,15 @@ fn remove_from_storage(
             pkv.set("names", &names).unwrap();
         }
     }
+    if let Ok(mut summaries) = pkv.get::<HashMap<ReflectableUuid, DocSummary>>("doc_summaries") {
+        if summaries.remove(&id_to_remove).is_some() {
+            pkv.set("doc_summaries", &summaries).unwrap();
+        }
+    }
+    if let Ok(mut recent_docs) = pkv.get::<Vec<ReflectableUuid>>("recent_docs") {
+        recent_docs.retain(|id| *id != id_to_remove);
+        pkv.set("recent_docs", &recent_docs).unwrap();
+    }
     if let Ok(last_saved) = pkv.get::<ReflectableUuid>("last_saved") {
         if last_saved == id_to_remove {
             pkv.set("last_saved", &new_id).unwrap();
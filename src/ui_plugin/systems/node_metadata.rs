@@ -0,0 +1,284 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use bevy_cosmic_edit::CosmicFont;
+use bevy_markdown::count_tasks;
+
+use super::ui_helpers::{
+    spawn_modal, ButtonAction, ButtonTypes, FilteredOut, MainPanel, NodeBadge, NodeMetadata,
+    RawText, VeloNode, VeloShape,
+};
+use super::{task_progress_text, ModalAction, UiState};
+use crate::canvas::arrow::components::ArrowMeta;
+use crate::resources::{AppState, FontSystemState};
+use crate::themes::Theme;
+use crate::utils::{dedup_tags, parse_tags, ReflectableUuid};
+
+/// Parses text like `tags: bug, ui; owner: alice; status: done`.
+/// Parts without a key are treated as tags, keys are case insensitive.
+pub fn parse_node_metadata(text: &str) -> NodeMetadata {
+    let mut metadata = NodeMetadata::default();
+    for part in text.split(';') {
+        match part.split_once(':') {
+            Some((key, value)) => {
+                let key = key.trim().to_lowercase();
+                let value = value.trim();
+                if key == "tags" {
+                    metadata.tags.extend(parse_tags(value));
+                } else if !key.is_empty() && !value.is_empty() {
+                    metadata.fields.insert(key, value.to_string());
+                }
+            }
+            None => metadata.tags.extend(parse_tags(part)),
+        }
+    }
+    metadata.tags = dedup_tags(
+        metadata
+            .tags
+            .iter()
+            .map(|tag| tag.trim_start_matches('#').to_string()),
+    );
+    metadata
+}
+
+/// Inverse of `parse_node_metadata`.
+pub fn format_node_metadata(metadata: &NodeMetadata) -> String {
+    let mut parts = vec![];
+    if !metadata.tags.is_empty() {
+        parts.push(format!("tags: {}", metadata.tags.join(", ")));
+    }
+    for (key, value) in metadata.fields.iter() {
+        parts.push(format!("{}: {}", key, value));
+    }
+    parts.join("; ")
+}
+
+/// Badge shows task progress of the note followed by its tags and metadata fields.
+pub fn node_badge_text(metadata: &NodeMetadata, tasks: (usize, usize)) -> String {
+    let mut parts: Vec<String> = task_progress_text(tasks).into_iter().collect();
+    parts.extend(metadata.tags.iter().map(|tag| format!("#{}", tag)));
+    for (key, value) in metadata.fields.iter() {
+        parts.push(format!("{}: {}", key, value));
+    }
+    parts.join("  ")
+}
+
+/// Node matches when it has every tag and field value of the filter, ignoring case.
+pub fn node_matches_filter(metadata: &NodeMetadata, filter: &NodeMetadata) -> bool {
+    let has_tags = filter.tags.iter().all(|tag| {
+        metadata
+            .tags
+            .iter()
+            .any(|node_tag| node_tag.eq_ignore_ascii_case(tag))
+    });
+    let has_fields = filter.fields.iter().all(|(key, value)| {
+        metadata
+            .fields
+            .get(key)
+            .is_some_and(|node_value| node_value.eq_ignore_ascii_case(value))
+    });
+    has_tags && has_fields
+}
+
+pub fn node_metadata_handler(
+    mut commands: Commands,
+    mut interaction_query: Query<
+        (&Interaction, &ButtonAction),
+        (Changed<Interaction>, With<ButtonAction>),
+    >,
+    metadata_query: Query<(&VeloShape, &NodeMetadata), With<VeloShape>>,
+    mut ui_state: ResMut<UiState>,
+    app_state: Res<AppState>,
+    main_panel_query: Query<Entity, With<MainPanel>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut cosmic_fonts: ResMut<Assets<CosmicFont>>,
+    font_system_state: ResMut<FontSystemState>,
+    theme: Res<Theme>,
+) {
+    let window = windows.single();
+    for (interaction, button_action) in &mut interaction_query.iter_mut() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let action = match button_action.button_type {
+            ButtonTypes::EditNodeMetadata => {
+                let Some(id) = ui_state.entity_to_edit else {
+                    continue;
+                };
+                let Some((_, metadata)) = metadata_query.iter().find(|(shape, _)| shape.id == id)
+                else {
+                    continue;
+                };
+                ModalAction::EditNodeMetadata(id, format_node_metadata(metadata))
+            }
+            ButtonTypes::FilterNodes => ModalAction::FilterNodes(
+                app_state
+                    .node_filter
+                    .as_ref()
+                    .map(format_node_metadata)
+                    .unwrap_or_default(),
+            ),
+            _ => continue,
+        };
+        let id = ReflectableUuid::generate();
+        *ui_state = UiState::default();
+        commands.insert_resource(bevy_cosmic_edit::ActiveEditor { entity: None });
+        ui_state.modal_id = Some(id);
+        let entity = spawn_modal(
+            &mut commands,
+            &theme,
+            &mut cosmic_fonts,
+            font_system_state.0.clone().unwrap(),
+            window,
+            id,
+            action,
+        );
+        commands.entity(main_panel_query.single()).add_child(entity);
+    }
+}
+
+pub fn update_node_badges(
+    metadata_query: Query<(Ref<NodeMetadata>, &Children)>,
+    raw_text_query: Query<Ref<RawText>>,
+    mut badge_query: Query<&mut Text, With<NodeBadge>>,
+) {
+    for (metadata, children) in metadata_query.iter() {
+        let raw_text = children
+            .iter()
+            .find_map(|child| raw_text_query.get(*child).ok());
+        let text_changed = raw_text
+            .as_ref()
+            .is_some_and(|raw_text| raw_text.is_changed());
+        if !metadata.is_changed() && !text_changed {
+            continue;
+        }
+        let tasks = raw_text
+            .map(|raw_text| count_tasks(&raw_text.last_text))
+            .unwrap_or_default();
+        for child in children.iter() {
+            if let Ok(mut text) = badge_query.get_mut(*child) {
+                text.sections[0].value = node_badge_text(&metadata, tasks);
+            }
+        }
+    }
+}
+
+/// Hides nodes not matching `AppState::node_filter` together with their arrows.
+/// Visibility from before filtering is kept in `FilteredOut` and restored once the node matches again.
+pub fn apply_node_filter(
+    mut commands: Commands,
+    app_state: Res<AppState>,
+    mut last_filter: Local<Option<NodeMetadata>>,
+    changed_metadata: Query<(), Changed<NodeMetadata>>,
+    added_arrows: Query<(), Added<ArrowMeta>>,
+    shape_query: Query<(&Parent, &VeloShape, &NodeMetadata), With<VeloShape>>,
+    mut velo_node_query: Query<
+        (Entity, &mut Visibility, Option<&FilteredOut>),
+        (With<VeloNode>, Without<ArrowMeta>),
+    >,
+    mut arrow_query: Query<
+        (Entity, &ArrowMeta, &mut Visibility, Option<&FilteredOut>),
+        (With<ArrowMeta>, Without<VeloNode>),
+    >,
+) {
+    if app_state.node_filter == *last_filter
+        && changed_metadata.is_empty()
+        && added_arrows.is_empty()
+    {
+        return;
+    }
+    *last_filter = app_state.node_filter.clone();
+
+    let mut hidden_nodes = HashSet::new();
+    for (parent, shape, metadata) in shape_query.iter() {
+        let hide = app_state
+            .node_filter
+            .as_ref()
+            .is_some_and(|filter| !node_matches_filter(metadata, filter));
+        if hide {
+            hidden_nodes.insert(shape.id);
+        }
+        if let Ok((entity, mut visibility, filtered_out)) = velo_node_query.get_mut(parent.get()) {
+            set_filtered_out(&mut commands, entity, &mut visibility, filtered_out, hide);
+        }
+    }
+    for (entity, arrow_meta, mut visibility, filtered_out) in arrow_query.iter_mut() {
+        let hide = hidden_nodes.contains(&arrow_meta.start.id)
+            || hidden_nodes.contains(&arrow_meta.end.id);
+        set_filtered_out(&mut commands, entity, &mut visibility, filtered_out, hide);
+    }
+}
+
+fn set_filtered_out(
+    commands: &mut Commands,
+    entity: Entity,
+    visibility: &mut Visibility,
+    filtered_out: Option<&FilteredOut>,
+    hide: bool,
+) {
+    match (filtered_out, hide) {
+        (None, true) => {
+            commands.entity(entity).insert(FilteredOut {
+                was_visible: *visibility == Visibility::Visible,
+            });
+            *visibility = Visibility::Hidden;
+        }
+        (Some(filtered_out), false) => {
+            *visibility = if filtered_out.was_visible {
+                Visibility::Visible
+            } else {
+                Visibility::Hidden
+            };
+            commands.entity(entity).remove::<FilteredOut>();
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_format_node_metadata() {
+        let metadata = parse_node_metadata(
+            "tags: bug, #ui, bug; Owner: alice ; status:done; due: 2023-10-01 12:00; #urgent",
+        );
+        assert_eq!(metadata.tags, vec!["bug", "ui", "urgent"]);
+        assert_eq!(metadata.fields.get("owner").unwrap(), "alice");
+        assert_eq!(metadata.fields.get("status").unwrap(), "done");
+        assert_eq!(metadata.fields.get("due").unwrap(), "2023-10-01 12:00");
+        assert_eq!(
+            format_node_metadata(&metadata),
+            "tags: bug, ui, urgent; due: 2023-10-01 12:00; owner: alice; status: done"
+        );
+        assert_eq!(
+            parse_node_metadata(&format_node_metadata(&metadata)),
+            metadata
+        );
+        assert_eq!(parse_node_metadata(" ; "), NodeMetadata::default());
+    }
+
+    #[test]
+    fn test_node_matches_filter() {
+        let metadata = parse_node_metadata("tags: bug, ui; status: done; owner: alice");
+        assert!(node_matches_filter(&metadata, &NodeMetadata::default()));
+        assert!(node_matches_filter(
+            &metadata,
+            &parse_node_metadata("BUG; status: Done")
+        ));
+        assert!(!node_matches_filter(
+            &metadata,
+            &parse_node_metadata("status: todo")
+        ));
+        assert!(!node_matches_filter(
+            &metadata,
+            &parse_node_metadata("priority: high")
+        ));
+        assert!(!node_matches_filter(
+            &metadata,
+            &parse_node_metadata("tags: bug, docs")
+        ));
+    }
+}
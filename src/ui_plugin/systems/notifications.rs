@@ -0,0 +1,254 @@
+use async_channel::{Receiver, Sender};
+use bevy::prelude::*;
+
+use super::ui_helpers::{GenericButton, Toast, ToastArea};
+use crate::themes::Theme;
+
+/// At most this many toasts are shown, older ones are dropped first.
+const MAX_TOASTS: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NotificationKind {
+    /// Shown until a notification with the same task replaces it
+    Progress,
+    Success,
+    Error,
+}
+
+impl NotificationKind {
+    /// Seconds the toast stays visible.
+    fn duration(&self) -> f64 {
+        match self {
+            NotificationKind::Progress => 60.,
+            NotificationKind::Success => 4.,
+            NotificationKind::Error => 8.,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Notification {
+    /// Operation the notification is about, e.g. "share", newer notifications replace older ones of the same task
+    pub task: &'static str,
+    pub kind: NotificationKind,
+    pub message: String,
+}
+
+/// Sends notifications from systems and async tasks to the toast area.
+#[derive(Clone)]
+pub struct Notifier(Sender<Notification>);
+
+impl Notifier {
+    pub fn notify(&self, task: &'static str, kind: NotificationKind, message: impl Into<String>) {
+        let notification = Notification {
+            task,
+            kind,
+            message: message.into(),
+        };
+        if let Err(e) = self.0.try_send(notification) {
+            error!("Failed to show notification: {}", e.into_inner().message);
+        }
+    }
+
+    pub fn progress(&self, task: &'static str, message: impl Into<String>) {
+        self.notify(task, NotificationKind::Progress, message);
+    }
+
+    pub fn success(&self, task: &'static str, message: impl Into<String>) {
+        self.notify(task, NotificationKind::Success, message);
+    }
+
+    pub fn error(&self, task: &'static str, message: impl Into<String>) {
+        let message = message.into();
+        error!("{}", message);
+        self.notify(task, NotificationKind::Error, message);
+    }
+}
+
+#[derive(Resource)]
+pub struct NotificationChannels {
+    pub notifier: Notifier,
+    pub rx: Receiver<Notification>,
+}
+
+impl Default for NotificationChannels {
+    fn default() -> Self {
+        let (tx, rx) = async_channel::unbounded();
+        NotificationChannels {
+            notifier: Notifier(tx),
+            rx,
+        }
+    }
+}
+
+/// Notifications currently shown, oldest first.
+#[derive(Default)]
+pub struct ToastQueue {
+    toasts: Vec<(Notification, f64)>,
+}
+
+impl ToastQueue {
+    pub fn push(&mut self, notification: Notification, now: f64) {
+        self.toasts
+            .retain(|(toast, _)| toast.task != notification.task);
+        let expires_at = now + notification.kind.duration();
+        self.toasts.push((notification, expires_at));
+        if self.toasts.len() > MAX_TOASTS {
+            self.toasts.remove(0);
+        }
+    }
+
+    /// Drops expired toasts, returns whether any were dropped.
+    pub fn expire(&mut self, now: f64) -> bool {
+        let len = self.toasts.len();
+        self.toasts.retain(|(_, expires_at)| *expires_at > now);
+        len != self.toasts.len()
+    }
+
+    pub fn dismiss(&mut self, index: usize) {
+        if index < self.toasts.len() {
+            self.toasts.remove(index);
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Notification> {
+        self.toasts.iter().map(|(notification, _)| notification)
+    }
+}
+
+fn spawn_toast(
+    commands: &mut Commands,
+    theme: &Theme,
+    index: usize,
+    notification: &Notification,
+) -> Entity {
+    let border_color = match notification.kind {
+        NotificationKind::Progress => theme.btn_border,
+        NotificationKind::Success => theme.node_found_color,
+        NotificationKind::Error => theme.del_button,
+    };
+    commands
+        .spawn((
+            ButtonBundle {
+                border_color: border_color.into(),
+                background_color: theme.modal_bg.into(),
+                style: Style {
+                    border: UiRect::all(Val::Px(2.)),
+                    padding: UiRect::all(Val::Px(6.)),
+                    margin: UiRect::top(Val::Px(5.)),
+                    ..default()
+                },
+                ..default()
+            },
+            GenericButton,
+            Toast { index },
+        ))
+        .with_children(|builder| {
+            builder.spawn(TextBundle::from_section(
+                notification.message.clone(),
+                TextStyle {
+                    font_size: 14.,
+                    color: theme.font,
+                    ..default()
+                },
+            ));
+        })
+        .id()
+}
+
+/// Shows notifications as toasts in the bottom right corner, clicking a toast dismisses it.
+pub fn show_notifications(
+    mut commands: Commands,
+    channels: Res<NotificationChannels>,
+    time: Res<Time>,
+    theme: Res<Theme>,
+    toast_area: Query<Entity, With<ToastArea>>,
+    toast_query: Query<(&Interaction, &Toast), Changed<Interaction>>,
+    mut queue: Local<ToastQueue>,
+) {
+    let Ok(toast_area) = toast_area.get_single() else {
+        return;
+    };
+    let now = time.elapsed_seconds_f64();
+    let mut changed = queue.expire(now);
+    while let Ok(notification) = channels.rx.try_recv() {
+        queue.push(notification, now);
+        changed = true;
+    }
+    for (interaction, toast) in toast_query.iter() {
+        if *interaction == Interaction::Pressed {
+            queue.dismiss(toast.index);
+            changed = true;
+            break;
+        }
+    }
+    if !changed {
+        return;
+    }
+    commands.entity(toast_area).despawn_descendants();
+    for (index, notification) in queue.iter().enumerate() {
+        let toast = spawn_toast(&mut commands, &theme, index, notification);
+        commands.entity(toast_area).add_child(toast);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notification(task: &'static str, kind: NotificationKind) -> Notification {
+        Notification {
+            task,
+            kind,
+            message: format!("{} {:?}", task, kind),
+        }
+    }
+
+    #[test]
+    fn test_toast_queue() {
+        let mut queue = ToastQueue::default();
+        queue.push(notification("share", NotificationKind::Progress), 0.);
+        queue.push(notification("import", NotificationKind::Error), 0.);
+        queue.push(notification("share", NotificationKind::Success), 1.);
+        let tasks: Vec<_> = queue.iter().map(|n| (n.task, n.kind)).collect();
+        assert_eq!(
+            tasks,
+            vec![
+                ("import", NotificationKind::Error),
+                ("share", NotificationKind::Success)
+            ]
+        );
+        assert!(queue.expire(5.5));
+        assert_eq!(queue.iter().next().unwrap().task, "import");
+        assert!(!queue.expire(6.));
+        queue.dismiss(0);
+        assert_eq!(queue.iter().count(), 0);
+
+        for task in ["a", "b", "c", "d", "e"] {
+            queue.push(notification(task, NotificationKind::Success), 0.);
+        }
+        assert_eq!(
+            queue.iter().map(|n| n.task).collect::<Vec<_>>(),
+            ["b", "c", "d", "e"]
+        );
+    }
+
+    #[test]
+    fn test_notifier() {
+        let channels = NotificationChannels::default();
+        channels.notifier.progress("share", "Sharing document...");
+        channels.notifier.error("share", "Sharing failed");
+        assert_eq!(
+            channels.rx.try_recv().unwrap().kind,
+            NotificationKind::Progress
+        );
+        assert_eq!(
+            channels.rx.try_recv().unwrap(),
+            Notification {
+                task: "share",
+                kind: NotificationKind::Error,
+                message: "Sharing failed".to_string(),
+            }
+        );
+    }
+}
@@ -0,0 +1,295 @@
+use bevy::{
+    prelude::*,
+    window::{PrimaryWindow, WindowMode},
+};
+use bevy_cosmic_edit::CosmicEdit;
+use bevy_pancam::PanCam;
+
+use super::ui_helpers::{
+    AddSlide, BottomPanel, ClearSlides, LeftPanel, Menu, RawText, StartPresentation, VeloNode,
+};
+use super::{NotificationChannels, SaveStore, UiState};
+use crate::components::{MainCamera, Slide};
+use crate::resources::AppState;
+use crate::utils::ReflectableUuid;
+
+const SLIDES_TASK: &str = "slides";
+/// Seconds the camera takes to move to the next slide.
+const TRANSITION_DURATION: f32 = 0.8;
+/// Space left around a note fitted to the screen.
+const FIT_MARGIN: f32 = 1.2;
+
+/// Present while slides of the active tab are played.
+#[derive(Resource)]
+pub struct Presentation {
+    slides: Vec<Slide>,
+    index: usize,
+    /// Camera view the current transition started from and seconds since it started
+    transition: Option<(Vec2, f32, f32)>,
+    /// Camera view and window mode restored when the presentation ends
+    restore: (Vec3, f32, WindowMode),
+}
+
+impl Presentation {
+    /// Starts moving the camera from its current view to the slide at `index`.
+    fn go_to(&mut self, index: usize, camera: (Vec2, f32)) {
+        let index = index.min(self.slides.len() - 1);
+        if index == self.index && self.transition.is_some() {
+            return;
+        }
+        self.index = index;
+        self.transition = Some((camera.0, camera.1, 0.));
+    }
+}
+
+fn active_tab_slides(app_state: &mut AppState) -> Option<(ReflectableUuid, &mut Vec<Slide>)> {
+    let doc_id = app_state.current_document?;
+    let tab = app_state
+        .docs
+        .get_mut(&doc_id)?
+        .tabs
+        .iter_mut()
+        .find(|t| t.is_active)?;
+    Some((doc_id, &mut tab.slides))
+}
+
+/// Camera scale showing a note of `size` with some margin, never zooming in past 100%.
+pub fn fit_scale(size: Vec2, window_size: Vec2) -> f32 {
+    if window_size.x <= 0. || window_size.y <= 0. {
+        return 1.;
+    }
+    let scale = (size / window_size).max_element() * FIT_MARGIN;
+    scale.max(1.)
+}
+
+/// Smooth start and stop of camera transitions, `t` goes from 0 to 1.
+pub fn ease_in_out(t: f32) -> f32 {
+    let t = t.clamp(0., 1.);
+    t * t * (3. - 2. * t)
+}
+
+/// Camera position and scale showing the slide, notes are looked up by id.
+fn slide_view(
+    slide: &Slide,
+    window_size: Vec2,
+    raw_text_query: &Query<(&RawText, &CosmicEdit)>,
+    velo_node_query: &Query<(&VeloNode, &GlobalTransform)>,
+) -> (Vec2, f32) {
+    let node = slide.node_id.and_then(|node_id| {
+        let (_, cosmic_edit) = raw_text_query.iter().find(|(raw, _)| raw.id == node_id)?;
+        let (_, transform) = velo_node_query
+            .iter()
+            .find(|(node, _)| node.id == node_id)?;
+        Some((
+            transform.translation().truncate(),
+            Vec2::new(cosmic_edit.width, cosmic_edit.height),
+        ))
+    });
+    match node {
+        Some((position, size)) => (position, fit_scale(size, window_size)),
+        None => (Vec2::new(slide.x, slide.y), slide.scale),
+    }
+}
+
+/// Adds the current camera view as a slide of the active tab, the edited note is fitted to the screen when played.
+pub fn add_slide_handler(
+    mut app_state: ResMut<AppState>,
+    ui_state: Res<UiState>,
+    interaction_query: Query<&Interaction, (Changed<Interaction>, With<AddSlide>)>,
+    camera_query: Query<(&Transform, &OrthographicProjection), With<MainCamera>>,
+    notifications: Res<NotificationChannels>,
+    mut events: EventWriter<SaveStore>,
+) {
+    for interaction in interaction_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let (transform, projection) = camera_query.single();
+        let Some((doc_id, slides)) = active_tab_slides(&mut app_state) else {
+            continue;
+        };
+        slides.push(Slide {
+            x: transform.translation.x,
+            y: transform.translation.y,
+            scale: projection.scale,
+            node_id: ui_state.entity_to_edit,
+        });
+        notifications
+            .notifier
+            .success(SLIDES_TASK, format!("Slide {} added", slides.len()));
+        events.send(SaveStore { doc_id, path: None });
+    }
+}
+
+pub fn clear_slides_handler(
+    mut app_state: ResMut<AppState>,
+    interaction_query: Query<&Interaction, (Changed<Interaction>, With<ClearSlides>)>,
+    notifications: Res<NotificationChannels>,
+    mut events: EventWriter<SaveStore>,
+) {
+    for interaction in interaction_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let Some((doc_id, slides)) = active_tab_slides(&mut app_state) else {
+            continue;
+        };
+        if slides.is_empty() {
+            continue;
+        }
+        slides.clear();
+        notifications
+            .notifier
+            .success(SLIDES_TASK, "Slides of the tab removed");
+        events.send(SaveStore { doc_id, path: None });
+    }
+}
+
+/// Plays slides of the active tab full-screen with the panels hidden.
+pub fn start_presentation(
+    mut commands: Commands,
+    mut app_state: ResMut<AppState>,
+    mut ui_state: ResMut<UiState>,
+    interaction_query: Query<&Interaction, (Changed<Interaction>, With<StartPresentation>)>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+    mut camera_query: Query<(&Transform, &OrthographicProjection, &mut PanCam), With<MainCamera>>,
+    mut panels: Query<&mut Style, Or<(With<Menu>, With<LeftPanel>, With<BottomPanel>)>>,
+    notifications: Res<NotificationChannels>,
+) {
+    for interaction in interaction_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let slides = active_tab_slides(&mut app_state)
+            .map(|(_, slides)| slides.clone())
+            .unwrap_or_default();
+        if slides.is_empty() {
+            notifications
+                .notifier
+                .error(SLIDES_TASK, "Add slides to the tab before presenting");
+            continue;
+        }
+        let mut window = windows.single_mut();
+        let (transform, projection, mut pan_cam) = camera_query.single_mut();
+        let mut presentation = Presentation {
+            slides,
+            index: 0,
+            transition: None,
+            restore: (transform.translation, projection.scale, window.mode),
+        };
+        presentation.go_to(0, (transform.translation.truncate(), projection.scale));
+        commands.insert_resource(presentation);
+        *ui_state = UiState::default();
+        window.mode = WindowMode::BorderlessFullscreen;
+        pan_cam.enabled = false;
+        for mut style in panels.iter_mut() {
+            style.display = Display::None;
+        }
+        notifications.notifier.success(
+            SLIDES_TASK,
+            "Arrow keys or space to change slides, Escape to stop",
+        );
+    }
+}
+
+/// Keyboard navigation between slides, Escape ends the presentation.
+pub fn presentation_navigation(
+    mut commands: Commands,
+    input: Res<Input<KeyCode>>,
+    mut presentation: ResMut<Presentation>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+    mut camera_query: Query<
+        (&mut Transform, &mut OrthographicProjection, &mut PanCam),
+        With<MainCamera>,
+    >,
+    mut panels: Query<&mut Style, Or<(With<Menu>, With<LeftPanel>, With<BottomPanel>)>>,
+) {
+    let (mut transform, mut projection, mut pan_cam) = camera_query.single_mut();
+    let camera = (transform.translation.truncate(), projection.scale);
+    let index = presentation.index;
+    if input.just_pressed(KeyCode::Escape) {
+        let (translation, scale, mode) = presentation.restore;
+        transform.translation = translation;
+        projection.scale = scale;
+        pan_cam.enabled = true;
+        windows.single_mut().mode = mode;
+        for mut style in panels.iter_mut() {
+            style.display = Display::Flex;
+        }
+        commands.remove_resource::<Presentation>();
+    } else if input.any_just_pressed([
+        KeyCode::Right,
+        KeyCode::Down,
+        KeyCode::Space,
+        KeyCode::PageDown,
+        KeyCode::Return,
+    ]) {
+        presentation.go_to(index + 1, camera);
+    } else if input.any_just_pressed([KeyCode::Left, KeyCode::Up, KeyCode::PageUp, KeyCode::Back]) {
+        presentation.go_to(index.saturating_sub(1), camera);
+    } else if input.just_pressed(KeyCode::Home) {
+        presentation.go_to(0, camera);
+    } else if input.just_pressed(KeyCode::End) {
+        presentation.go_to(usize::MAX, camera);
+    }
+}
+
+/// Moves the camera to the current slide.
+pub fn animate_presentation_camera(
+    time: Res<Time>,
+    mut presentation: ResMut<Presentation>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut camera_query: Query<(&mut Transform, &mut OrthographicProjection), With<MainCamera>>,
+    raw_text_query: Query<(&RawText, &CosmicEdit)>,
+    velo_node_query: Query<(&VeloNode, &GlobalTransform)>,
+) {
+    let Some((from, from_scale, elapsed)) = presentation.transition else {
+        return;
+    };
+    let window = windows.single();
+    let window_size = Vec2::new(window.width(), window.height());
+    // the target is looked up every frame, the window is resized when the presentation starts
+    let (to, to_scale) = slide_view(
+        &presentation.slides[presentation.index],
+        window_size,
+        &raw_text_query,
+        &velo_node_query,
+    );
+    let elapsed = elapsed + time.delta_seconds();
+    let t = ease_in_out(elapsed / TRANSITION_DURATION);
+    let (mut transform, mut projection) = camera_query.single_mut();
+    let position = from.lerp(to, t);
+    transform.translation.x = position.x;
+    transform.translation.y = position.y;
+    projection.scale = from_scale + (to_scale - from_scale) * t;
+    presentation.transition = Some((from, from_scale, elapsed));
+}
+
+pub fn is_presenting(presentation: Option<Res<Presentation>>) -> bool {
+    presentation.is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::Tab;
+
+    #[test]
+    fn test_slide_view_helpers() {
+        assert_eq!(ease_in_out(0.), 0.);
+        assert_eq!(ease_in_out(0.5), 0.5);
+        assert_eq!(ease_in_out(2.), 1.);
+        assert_eq!(
+            fit_scale(Vec2::new(2000., 500.), Vec2::new(1000., 1000.)),
+            2.4
+        );
+        assert_eq!(fit_scale(Vec2::new(100., 100.), Vec2::new(1000., 800.)), 1.);
+        assert_eq!(fit_scale(Vec2::new(100., 100.), Vec2::ZERO), 1.);
+
+        let tab: Tab = serde_json::from_str(
+            r#"{"is_active": true, "id": "a2b2c3d4-e5f6-4a7b-8c9d-0e1f2a3b4c5d", "name": "Tab 1", "checkpoints": [], "z_index": 1.0}"#,
+        )
+        .unwrap();
+        assert!(tab.slides.is_empty());
+    }
+}
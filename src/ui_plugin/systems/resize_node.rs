@@ -1,6 +1,6 @@
 use super::{
-    ui_helpers::{ResizeMarker, VeloShape},
-    NodeInteraction, NodeType, RawText, RedrawArrow, VeloNode,
+    ui_helpers::{NodeBadge, ResizeMarker, VeloShape},
+    NodeInteraction, NodeType, RawText, RedrawArrow, ResizeNode, VeloNode,
 };
 use crate::{
     canvas::{arrow::components::ArrowConnect, shadows::systems::Shadow},
@@ -67,6 +67,54 @@ pub fn resize_entity_end(
 pub fn resize_entity_run(
     ui_state: ResMut<UiState>,
     mut cursor_moved_events: EventReader<CursorMoved>,
+    mut resize_events: EventWriter<ResizeNode>,
+    border_query: Query<(&Parent, &VeloShape), With<VeloShape>>,
+    velo_node_query: Query<&Transform, With<VeloNode>>,
+    camera_q: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+) {
+    let (camera, camera_transform) = camera_q.single();
+
+    if let Some(id) = ui_state.entity_to_resize {
+        let event = cursor_moved_events.iter().last();
+        let Some(cursor_pos) =
+            event.and_then(|event| camera.viewport_to_world_2d(camera_transform, event.position))
+        else {
+            return;
+        };
+        for (border_parent, velo_border) in border_query.iter() {
+            if id != velo_border.id {
+                continue;
+            }
+            let velo_transform = velo_node_query.get(border_parent.get()).unwrap();
+            let pos = velo_transform.translation.truncate();
+            let width = f32::max(((cursor_pos.x - pos.x).abs() * 2.).round(), 1.);
+            let height = f32::max(((cursor_pos.y - pos.y).abs() * 2.).round(), 1.);
+            resize_events.send(ResizeNode {
+                id,
+                size: node_size(&velo_border.node_type, width, height),
+            });
+        }
+    }
+}
+
+/// Circles keep equal sides, sizes are rounded up to even numbers to keep the node on whole pixels.
+pub fn node_size(node_type: &NodeType, width: f32, height: f32) -> Vec2 {
+    let (mut width, mut height) = (width, height);
+    if *node_type == NodeType::Circle {
+        width = f32::max(width, height);
+        height = f32::max(width, height);
+    }
+    if width % 2.0 != 0.0 {
+        width += 1.0;
+    }
+    if height % 2.0 != 0.0 {
+        height += 1.0;
+    }
+    Vec2::new(width, height)
+}
+
+pub fn resize_node(
+    mut resize_events: EventReader<ResizeNode>,
     mut events: EventWriter<RedrawArrow>,
     mut resize_marker_query: Query<
         (&ResizeMarker, &Parent, &mut Transform),
@@ -80,133 +128,126 @@ pub fn resize_entity_run(
         (&Parent, &RawText, &mut CosmicEdit, &mut Sprite),
         (With<RawText>, Without<Shadow>),
     >,
-    mut border_query: Query<(&Parent, &VeloShape, &mut Path), With<VeloShape>>,
-    mut velo_node_query: Query<
-        (&mut Transform, &Children),
-        (With<VeloNode>, Without<ResizeMarker>, Without<ArrowConnect>),
+    mut border_query: Query<(&Parent, &VeloShape, &mut Path, &Children), With<VeloShape>>,
+    mut badge_query: Query<
+        &mut Transform,
+        (
+            With<NodeBadge>,
+            Without<VeloNode>,
+            Without<ResizeMarker>,
+            Without<ArrowConnect>,
+        ),
     >,
-    camera_q: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    velo_node_query: Query<&Children, With<VeloNode>>,
     mut shadows_q: Query<(&mut Sprite, &Shadow), (With<Shadow>, Without<RawText>)>,
 ) {
-    let (camera, camera_transform) = camera_q.single();
-
-    if let Some(id) = ui_state.entity_to_resize {
+    for resize_event in resize_events.iter() {
+        let (width, height) = (resize_event.size.x, resize_event.size.y);
         for (raw_text_parent, raw_text, mut cosmic_edit, mut sprite) in
             &mut raw_text_query.iter_mut()
         {
-            if id != raw_text.id {
+            if resize_event.id != raw_text.id {
                 continue;
             }
-            let event = cursor_moved_events.iter().last();
-            if let Some(cursor_pos) = event
-                .and_then(|event| camera.viewport_to_world_2d(camera_transform, event.position))
-            {
-                let (border_parent, velo_border, mut path) =
-                    border_query.get_mut(raw_text_parent.get()).unwrap();
-                let (velo_transform, children) =
-                    velo_node_query.get_mut(border_parent.get()).unwrap();
-                let pos = velo_transform.translation.truncate();
-                let mut width = f32::max(((cursor_pos.x - pos.x).abs() * 2.).round(), 1.);
-                let mut height = f32::max(((cursor_pos.y - pos.y).abs() * 2.).round(), 1.);
-                if velo_border.node_type == NodeType::Circle {
-                    width = f32::max(width, height);
-                    height = f32::max(width, height);
-                }
-                if width % 2.0 != 0.0 {
-                    width += 1.0;
-                }
-                if height % 2.0 != 0.0 {
-                    height += 1.0;
-                }
+            let (border_parent, velo_border, mut path, border_children) =
+                border_query.get_mut(raw_text_parent.get()).unwrap();
+            let children = velo_node_query.get(border_parent.get()).unwrap();
 
-                cosmic_edit.width = width;
-                cosmic_edit.height = height;
-                sprite.custom_size = Some(Vec2::new(width, height));
-                cosmic_edit.editor.buffer_mut().set_redraw(true);
+            cosmic_edit.width = width;
+            cosmic_edit.height = height;
+            sprite.custom_size = Some(Vec2::new(width, height));
+            cosmic_edit.editor.buffer_mut().set_redraw(true);
 
-                for child in children.iter() {
-                    // update shadows sprite
-                    if let Ok((mut sprite, _)) = shadows_q.get_mut(*child) {
-                        sprite.custom_size = Some(Vec2::new(width, height));
-                    }
-                    // update resize markers positions
-                    if let Ok(resize) = resize_marker_query.get_mut(*child) {
-                        let mut resize_transform = resize.2;
-                        match resize.0 {
-                            ResizeMarker::TopLeft => {
-                                resize_transform.translation.x = -width / 2.;
-                                resize_transform.translation.y = height / 2.;
-                            }
-                            ResizeMarker::TopRight => {
-                                resize_transform.translation.x = width / 2.;
-                                resize_transform.translation.y = height / 2.;
-                            }
-                            ResizeMarker::BottomLeft => {
-                                resize_transform.translation.x = -width / 2.;
-                                resize_transform.translation.y = -height / 2.;
-                            }
-                            ResizeMarker::BottomRight => {
-                                resize_transform.translation.x = width / 2.;
-                                resize_transform.translation.y = -height / 2.;
-                            }
+            for child in children.iter() {
+                // update shadows sprite
+                if let Ok((mut sprite, _)) = shadows_q.get_mut(*child) {
+                    sprite.custom_size = Some(Vec2::new(width, height));
+                }
+                // update resize markers positions
+                if let Ok(resize) = resize_marker_query.get_mut(*child) {
+                    let mut resize_transform = resize.2;
+                    match resize.0 {
+                        ResizeMarker::TopLeft => {
+                            resize_transform.translation.x = -width / 2.;
+                            resize_transform.translation.y = height / 2.;
+                        }
+                        ResizeMarker::TopRight => {
+                            resize_transform.translation.x = width / 2.;
+                            resize_transform.translation.y = height / 2.;
+                        }
+                        ResizeMarker::BottomLeft => {
+                            resize_transform.translation.x = -width / 2.;
+                            resize_transform.translation.y = -height / 2.;
+                        }
+                        ResizeMarker::BottomRight => {
+                            resize_transform.translation.x = width / 2.;
+                            resize_transform.translation.y = -height / 2.;
                         }
                     }
-                    // update arrow connectors positions
-                    if let Ok(arrow_connect) = arrow_connector_query.get_mut(*child) {
-                        let mut arrow_transform = arrow_connect.1;
-                        match arrow_connect.0.pos {
-                            crate::canvas::arrow::components::ArrowConnectPos::Top => {
-                                arrow_transform.translation.x = 0.;
-                                arrow_transform.translation.y = height / 2.;
-                            }
-                            crate::canvas::arrow::components::ArrowConnectPos::Bottom => {
-                                arrow_transform.translation.x = 0.;
-                                arrow_transform.translation.y = -height / 2.;
-                            }
-                            crate::canvas::arrow::components::ArrowConnectPos::Left => {
-                                arrow_transform.translation.x = -width / 2.;
-                                arrow_transform.translation.y = 0.;
-                            }
-                            crate::canvas::arrow::components::ArrowConnectPos::Right => {
-                                arrow_transform.translation.x = width / 2.;
-                                arrow_transform.translation.y = 0.;
-                            }
+                }
+                // update arrow connectors positions
+                if let Ok(arrow_connect) = arrow_connector_query.get_mut(*child) {
+                    let mut arrow_transform = arrow_connect.1;
+                    match arrow_connect.0.pos {
+                        crate::canvas::arrow::components::ArrowConnectPos::Top => {
+                            arrow_transform.translation.x = 0.;
+                            arrow_transform.translation.y = height / 2.;
+                        }
+                        crate::canvas::arrow::components::ArrowConnectPos::Bottom => {
+                            arrow_transform.translation.x = 0.;
+                            arrow_transform.translation.y = -height / 2.;
+                        }
+                        crate::canvas::arrow::components::ArrowConnectPos::Left => {
+                            arrow_transform.translation.x = -width / 2.;
+                            arrow_transform.translation.y = 0.;
+                        }
+                        crate::canvas::arrow::components::ArrowConnectPos::Right => {
+                            arrow_transform.translation.x = width / 2.;
+                            arrow_transform.translation.y = 0.;
                         }
                     }
                 }
+            }
 
-                // update size of bevy_lyon node
-                let points = [
-                    Vec2::new(-width / 2., -height / 2.),
-                    Vec2::new(-width / 2., height / 2.),
-                    Vec2::new(width / 2., height / 2.),
-                    Vec2::new(width / 2., -height / 2.),
-                ];
-
-                let new_path = match velo_border.node_type {
-                    NodeType::Rect => bevy_prototype_lyon::prelude::GeometryBuilder::build_as(
-                        &bevy_prototype_lyon::shapes::RoundedPolygon {
-                            points: points.into_iter().collect(),
-                            closed: true,
-                            radius: 10.,
-                        },
-                    ),
-                    NodeType::Paper => bevy_prototype_lyon::prelude::GeometryBuilder::build_as(
-                        &bevy_prototype_lyon::shapes::Polygon {
-                            points: points.into_iter().collect(),
-                            closed: true,
-                        },
-                    ),
-                    NodeType::Circle => bevy_prototype_lyon::prelude::GeometryBuilder::build_as(
-                        &bevy_prototype_lyon::shapes::Circle {
-                            radius: width / 2.,
-                            center: Vec2::new(0., 0.),
-                        },
-                    ),
-                };
-                *path = new_path;
-                events.send(RedrawArrow { id: raw_text.id });
+            // update metadata badge position
+            for child in border_children.iter() {
+                if let Ok(mut badge_transform) = badge_query.get_mut(*child) {
+                    badge_transform.translation.x = -width / 2.;
+                    badge_transform.translation.y = height / 2. + 2.;
+                }
             }
+
+            // update size of bevy_lyon node
+            let points = [
+                Vec2::new(-width / 2., -height / 2.),
+                Vec2::new(-width / 2., height / 2.),
+                Vec2::new(width / 2., height / 2.),
+                Vec2::new(width / 2., -height / 2.),
+            ];
+
+            let new_path = match velo_border.node_type {
+                NodeType::Rect => bevy_prototype_lyon::prelude::GeometryBuilder::build_as(
+                    &bevy_prototype_lyon::shapes::RoundedPolygon {
+                        points: points.into_iter().collect(),
+                        closed: true,
+                        radius: 10.,
+                    },
+                ),
+                NodeType::Paper => bevy_prototype_lyon::prelude::GeometryBuilder::build_as(
+                    &bevy_prototype_lyon::shapes::Polygon {
+                        points: points.into_iter().collect(),
+                        closed: true,
+                    },
+                ),
+                NodeType::Circle => bevy_prototype_lyon::prelude::GeometryBuilder::build_as(
+                    &bevy_prototype_lyon::shapes::Circle {
+                        radius: width / 2.,
+                        center: Vec2::new(0., 0.),
+                    },
+                ),
+            };
+            *path = new_path;
+            events.send(RedrawArrow { id: raw_text.id });
         }
     }
 }
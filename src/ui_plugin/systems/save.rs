@@ -9,13 +9,15 @@ use image::*;
 use serde_json::json;
 use std::{collections::HashMap, io::Cursor};
 
-use super::ui_helpers::{Drawing, VeloNode, VeloShape};
-use super::{DrawingJsonNode, RawText, SaveStore};
+use super::ui_helpers::{AutoSize, Drawing, FilteredOut, NodeMetadata, VeloNode, VeloShape};
+use super::{record_history, record_merge_base, DrawingJsonNode, HistoryBrowser};
+use super::{NodeTextStyle, RawText, SaveStore};
 use crate::canvas::arrow::components::ArrowMeta;
-use crate::components::Doc;
+use crate::components::{Doc, DocSummary};
 use crate::resources::SaveDocRequest;
 use crate::resources::{AppState, SaveTabRequest};
-use crate::utils::{load_doc_to_memory, ReflectableUuid};
+use crate::themes::NodeColor;
+use crate::utils::{dedup_tags, get_timestamp, load_doc_to_memory, ReflectableUuid};
 use crate::{JsonNode, JsonNodeText, MAX_CHECKPOINTS};
 
 pub fn should_save_doc(request: Option<Res<SaveDocRequest>>) -> bool {
@@ -64,9 +66,20 @@ pub fn save_to_store(
     mut pkv: ResMut<PkvStore>,
     mut app_state: ResMut<AppState>,
     mut events: EventReader<SaveStore>,
+    history_browser: Option<Res<HistoryBrowser>>,
 ) {
     for event in events.iter() {
         let doc_id = event.doc_id;
+        // an older version previewed from the history panel is only saved once it's restored
+        if history_browser
+            .as_ref()
+            .is_some_and(|browser| browser.doc_id == doc_id && browser.preview.is_some())
+        {
+            continue;
+        }
+        let doc = app_state.docs.get_mut(&doc_id).unwrap();
+        doc.tags = dedup_tags(doc.tags.drain(..));
+        doc.modified_at = get_timestamp();
         if let Ok(mut docs) = pkv.get::<HashMap<ReflectableUuid, Doc>>("docs") {
             docs.insert(doc_id, app_state.docs.get(&doc_id).unwrap().clone());
             pkv.set("docs", &docs).unwrap();
@@ -77,11 +90,7 @@ pub fn save_to_store(
         }
         if let Ok(mut tags) = pkv.get::<HashMap<ReflectableUuid, Vec<String>>>("tags") {
             let doc = app_state.docs.get(&doc_id).unwrap();
-            if let Some(tags) = tags.get_mut(&doc_id) {
-                tags.append(&mut doc.tags.clone());
-            } else {
-                tags.insert(doc.id, doc.tags.clone());
-            }
+            tags.insert(doc.id, doc.tags.clone());
             pkv.set("tags", &tags).unwrap();
         } else {
             let doc = app_state.docs.get(&doc_id).unwrap();
@@ -99,46 +108,89 @@ pub fn save_to_store(
             names.insert(doc.id, doc.name.clone());
             pkv.set("names", &names).unwrap();
         }
+        if let Ok(mut summaries) = pkv.get::<HashMap<ReflectableUuid, DocSummary>>("doc_summaries")
+        {
+            let doc = app_state.docs.get(&doc_id).unwrap();
+            summaries.insert(doc.id, doc.summary());
+            pkv.set("doc_summaries", &summaries).unwrap();
+        } else {
+            let doc = app_state.docs.get(&doc_id).unwrap();
+            let mut summaries = HashMap::new();
+            summaries.insert(doc.id, doc.summary());
+            pkv.set("doc_summaries", &summaries).unwrap();
+        }
         pkv.set("last_saved", &doc_id).unwrap();
+        // versions shown in the history panel are loaded onto the canvas, they are not new ones
+        let browsed_tab = history_browser
+            .as_ref()
+            .filter(|browser| browser.doc_id == doc_id)
+            .map(|browser| browser.tab_id);
+        record_history(
+            &mut pkv,
+            app_state.docs.get(&doc_id).unwrap(),
+            &app_state.history,
+            browsed_tab,
+        );
 
         if let Some(path) = event.path.clone() {
-            let current_doc = app_state.docs.get(&doc_id).unwrap().clone();
+            let mut current_doc = app_state.docs.get(&doc_id).unwrap().clone();
+            // remote ids and keys of shared copies stay local
+            current_doc.shared = None;
+            record_merge_base(&mut pkv, &current_doc);
             std::fs::write(path, serde_json::to_string_pretty(&current_doc).unwrap())
                 .expect("Error saving current document to file")
         }
-        #[cfg(not(target_arch = "wasm32"))]
-        {
-            if let Some(index) = &mut app_state.search_index {
-                let pool = bevy::tasks::IoTaskPool::get();
-                let tabs_to_delete = std::sync::Arc::new(index.tabs_to_delete.clone());
-                let node_updates = std::sync::Arc::new(index.node_updates.clone());
-                index.tabs_to_delete.clear();
-                index.node_updates.clear();
-                let index = std::sync::Arc::new(index.index.clone());
-                pool.spawn(async move {
-                    let _ = super::clear_tabs_index(&index, &tabs_to_delete);
-                    let _ = super::update_search_index(&index, &node_updates);
-                })
-                .detach();
-            }
+        if let Some(index) = &mut app_state.search_index {
+            let pool = bevy::tasks::IoTaskPool::get();
+            let tabs_to_delete = std::sync::Arc::new(index.tabs_to_delete.clone());
+            let node_updates = std::sync::Arc::new(index.node_updates.clone());
+            index.tabs_to_delete.clear();
+            index.node_updates.clear();
+            let index = index.index.clone();
+            pool.spawn(async move {
+                if let Err(e) = index
+                    .clear_tabs(&tabs_to_delete)
+                    .and_then(|_| index.update(&node_updates))
+                {
+                    error!("Error updating search index {:?}", e);
+                }
+            })
+            .detach();
         }
     }
 }
 
+/// Encodes the image of an image note as base64 PNG, the inverse of `load_node_image`.
+pub fn encode_node_image(images: &Assets<Image>, handle: &Handle<Image>) -> Option<String> {
+    let img = images.get(handle)?.clone().try_into_dynamic().ok()?;
+    let mut image_data: Vec<u8> = Vec::new();
+    img.write_to(&mut Cursor::new(&mut image_data), ImageOutputFormat::Png)
+        .ok()?;
+    Some(general_purpose::STANDARD.encode(image_data))
+}
+
 pub fn save_tab(
     images: Res<Assets<Image>>,
-    arrows: Query<(&ArrowMeta, &Visibility), With<ArrowMeta>>,
+    arrows: Query<(&ArrowMeta, &Visibility, Option<&FilteredOut>), With<ArrowMeta>>,
     request: Res<SaveTabRequest>,
     mut app_state: ResMut<AppState>,
-    raw_text_query: Query<(&RawText, &CosmicEdit, &Parent), With<RawText>>,
-    border_query: Query<(&Parent, &VeloShape), With<VeloShape>>,
-    velo_node_query: Query<(&Transform, &Visibility), With<VeloNode>>,
+    raw_text_query: Query<
+        (
+            &RawText,
+            &CosmicEdit,
+            &Parent,
+            &NodeTextStyle,
+            Option<&AutoSize>,
+        ),
+        With<RawText>,
+    >,
+    border_query: Query<(&Parent, &VeloShape, &NodeMetadata), With<VeloShape>>,
+    velo_node_query: Query<(&Transform, &Visibility, Option<&FilteredOut>), With<VeloNode>>,
     drawing_query: Query<
         (&Transform, &Drawing<(String, Color)>, &Stroke),
         With<Drawing<(String, Color)>>,
     >,
 ) {
-    #[cfg(not(target_arch = "wasm32"))]
     if let Some(index) = &mut app_state.search_index {
         index.tabs_to_delete.insert(request.tab_id.0);
     }
@@ -149,28 +201,28 @@ pub fn save_tab(
         "drawings": []
     });
     let json_images = json["images"].as_object_mut().unwrap();
-    for (raw_text, cosmic_edit, _) in raw_text_query.iter() {
-        if let Some(handle) = cosmic_edit.bg_image.clone() {
-            let image = images.get(&handle).unwrap();
-            if let Ok(img) = image.clone().try_into_dynamic() {
-                let mut image_data: Vec<u8> = Vec::new();
-                img.write_to(&mut Cursor::new(&mut image_data), ImageOutputFormat::Png)
-                    .unwrap();
-                let res_base64 = general_purpose::STANDARD.encode(image_data);
+    for (raw_text, cosmic_edit, ..) in raw_text_query.iter() {
+        if let Some(handle) = &cosmic_edit.bg_image {
+            if let Some(res_base64) = encode_node_image(&images, handle) {
                 json_images.insert(raw_text.id.0.to_string(), json!(res_base64));
             }
         }
     }
 
     let json_nodes = json["nodes"].as_array_mut().unwrap();
-    for (raw_text, cosmic_edit, parent) in raw_text_query.iter() {
-        let (border_parent, border) = border_query.get(parent.get()).unwrap();
-        let (top_transform, top_visibility) = velo_node_query.get(border_parent.get()).unwrap();
+    for (raw_text, cosmic_edit, parent, text_style, auto_size) in raw_text_query.iter() {
+        let (border_parent, border, metadata) = border_query.get(parent.get()).unwrap();
+        let (top_transform, top_visibility, filtered_out) =
+            velo_node_query.get(border_parent.get()).unwrap();
         let x = top_transform.translation.x;
         let y = top_transform.translation.y;
         let z = top_transform.translation.z;
         let (width, height) = (cosmic_edit.width, cosmic_edit.height);
-        let visible = top_visibility == Visibility::Visible;
+        // nodes hidden by metadata filter keep their own visibility
+        let visible = match filtered_out {
+            Some(filtered_out) => filtered_out.was_visible,
+            None => top_visibility == Visibility::Visible,
+        };
         json_nodes.push(json!(JsonNode {
             visible,
             node_type: border.node_type.clone(),
@@ -180,13 +232,18 @@ pub fn save_tab(
             z,
             width,
             height,
-            bg_color: border.pair_color.0.clone(),
+            bg_color: NodeColor::parse(&border.pair_color.0)
+                .with_fallback(border.pair_color.1)
+                .to_string(),
             text: JsonNodeText {
                 text: raw_text.last_text.clone(),
-                pos: cosmic_edit.text_pos.clone().into()
+                pos: cosmic_edit.text_pos.clone().into(),
+                style: text_style.clone(),
             },
+            tags: metadata.tags.clone(),
+            metadata: metadata.fields.clone(),
+            auto_size: auto_size.is_some(),
         }));
-        #[cfg(not(target_arch = "wasm32"))]
         if let Some(index) = &mut app_state.search_index {
             index.node_updates.insert(
                 super::NodeSearchLocation {
@@ -194,15 +251,18 @@ pub fn save_tab(
                     tab_id: request.tab_id.0,
                     node_id: raw_text.id.0,
                 },
-                raw_text.last_text.clone(),
+                super::get_node_search_text(&raw_text.last_text, metadata),
             );
         }
     }
 
     let json_arrows = json["arrows"].as_array_mut().unwrap();
-    for (arrow_meta, visibility) in arrows.iter() {
+    for (arrow_meta, visibility, filtered_out) in arrows.iter() {
         let mut meta = *arrow_meta;
-        meta.visible = visibility == Visibility::Visible;
+        meta.visible = match filtered_out {
+            Some(filtered_out) => filtered_out.was_visible,
+            None => visibility == Visibility::Visible,
+        };
         json_arrows.push(json!(meta));
     }
 
@@ -215,7 +275,9 @@ pub fn save_tab(
             width: stroke.options.line_width,
             id: drawing.id,
             points: drawing.points.clone(),
-            drawing_color: drawing.drawing_color.0.clone()
+            drawing_color: NodeColor::parse(&drawing.drawing_color.0)
+                .with_fallback(drawing.drawing_color.1)
+                .to_string()
         }));
     }
 
@@ -265,7 +327,15 @@ mod tests {
                     name: "Test tab".to_string(),
                     checkpoints: std::collections::VecDeque::new(),
                     z_index: 1.,
+                    slides: vec![],
                 }],
+                shared: Some(crate::components::SharedRemote {
+                    remote_id: "abc".to_string(),
+                    url: "https://example.com/velo#key=secret".to_string(),
+                    key: Some("secret".to_string()),
+                    shared_at: 1.,
+                }),
+                ..Default::default()
             },
         );
         let request = SaveDocRequest {
@@ -293,11 +363,20 @@ mod tests {
         );
         let saved_names: HashMap<ReflectableUuid, String> = pkv.get("names").unwrap();
         assert_eq!(saved_names.get(&doc_id).unwrap(), "test_doc");
+        let saved_summaries: HashMap<ReflectableUuid, DocSummary> =
+            pkv.get("doc_summaries").unwrap();
+        let saved_summary = saved_summaries.get(&doc_id).unwrap();
+        assert!(saved_summary.modified_at > 0.);
+        assert_eq!(saved_summary, &saved_docs.get(&doc_id).unwrap().summary());
         assert_eq!(pkv.get::<ReflectableUuid>("last_saved").unwrap(), doc_id);
         let file_contents = std::fs::read_to_string(temp_file_path).unwrap();
         let saved_doc: Doc = serde_json::from_str(&file_contents).unwrap();
         assert_eq!(saved_doc.name, "test_doc");
         assert!(saved_doc.tabs[0].is_active);
+        // the exported file doesn't give away the share key, the local copy keeps it
+        assert_eq!(saved_doc.shared, None);
+        assert!(!file_contents.contains("secret"));
+        assert!(saved_docs.get(&doc_id).unwrap().shared.is_some());
     }
 
     #[test]
@@ -321,9 +400,11 @@ mod tests {
                     id: tab_id,
                     is_active: true,
                     z_index: 1.,
+                    slides: vec![],
                     name: "Test tab".to_string(),
                     checkpoints: std::collections::VecDeque::new(),
                 }],
+                ..Default::default()
             },
         );
         let request = SaveDocRequest {
@@ -373,20 +454,25 @@ mod tests {
         let doc_id = ReflectableUuid::generate();
         let tab_id = ReflectableUuid::generate();
         let mut app_state = AppState::default();
-        let existing_tags = vec!["test_tag_2".to_string(), "test_tag_1".to_string()];
         app_state.docs.insert(
             doc_id,
             Doc {
                 id: doc_id,
                 name: "test_doc".to_string(),
-                tags: vec!["test_tag_1".to_string()],
+                tags: vec![
+                    "test_tag_1".to_string(),
+                    " test_tag_1 ".to_string(),
+                    "".to_string(),
+                ],
                 tabs: vec![crate::components::Tab {
                     id: tab_id,
                     is_active: true,
                     z_index: 1.,
+                    slides: vec![],
                     name: "Test tab".to_string(),
                     checkpoints: std::collections::VecDeque::new(),
                 }],
+                ..Default::default()
             },
         );
         let request = SaveDocRequest {
@@ -412,10 +498,11 @@ mod tests {
         let saved_docs: HashMap<ReflectableUuid, Doc> = pkv.get("docs").unwrap();
         assert_eq!(saved_docs.get(&doc_id).unwrap().name, "test_doc");
         assert!(saved_docs.get(&doc_id).unwrap().tabs[0].is_active);
-        // Check that the tags were saved to the PKV store
+        // Check that stored tags were replaced by deduplicated document tags
         let saved_tags: HashMap<ReflectableUuid, Vec<String>> = pkv.get("tags").unwrap();
-        let expected_tags = existing_tags;
+        let expected_tags = vec!["test_tag_1".to_string()];
         assert_eq!(saved_tags.get(&doc_id).unwrap(), &expected_tags);
+        assert_eq!(saved_docs.get(&doc_id).unwrap().tags, expected_tags);
         // Check that the name was saved to the PKV store
         let saved_names: HashMap<ReflectableUuid, String> = pkv.get("names").unwrap();
         assert_eq!(saved_names.get(&doc_id).unwrap(), "test_doc");
@@ -427,4 +514,49 @@ mod tests {
         assert_eq!(saved_doc.name, "test_doc");
         assert!(saved_doc.tabs[0].is_active);
     }
+
+    #[test]
+    /// A version previewed from the history panel is not saved
+    fn test_save_history_preview() {
+        let mut app = App::new();
+        app.add_systems(Update, save_to_store);
+        let doc_id = ReflectableUuid::generate();
+        let tab_id = ReflectableUuid::generate();
+        let mut app_state = AppState::default();
+        app_state.docs.insert(
+            doc_id,
+            Doc {
+                id: doc_id,
+                name: "test_doc".to_string(),
+                ..Default::default()
+            },
+        );
+        PkvStore::new("test", "history_preview").clear().unwrap();
+        app.insert_resource(PkvStore::new("test", "history_preview"));
+        app.insert_resource(app_state);
+        app.insert_resource(HistoryBrowser {
+            doc_id,
+            tab_id,
+            tab_name: "Test tab".to_string(),
+            history: Default::default(),
+            preview: Some(0),
+            offset: 0,
+        });
+        app.add_event::<SaveStore>();
+        app.world.send_event(SaveStore { doc_id, path: None });
+        app.update();
+        assert!(app
+            .world
+            .resource::<PkvStore>()
+            .get::<HashMap<ReflectableUuid, Doc>>("docs")
+            .is_err());
+
+        // the newest version is saved as usual
+        app.world.resource_mut::<HistoryBrowser>().preview = None;
+        app.world.send_event(SaveStore { doc_id, path: None });
+        app.update();
+        let saved_docs: HashMap<ReflectableUuid, Doc> =
+            app.world.resource::<PkvStore>().get("docs").unwrap();
+        assert!(saved_docs.contains_key(&doc_id));
+    }
 }
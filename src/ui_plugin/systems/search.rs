@@ -1,4 +1,5 @@
 use bevy::prelude::*;
+use bevy::tasks::IoTaskPool;
 use bevy::window::PrimaryWindow;
 
 use bevy_cosmic_edit::get_cosmic_text;
@@ -7,39 +8,58 @@ use bevy_cosmic_edit::CosmicEdit;
 use bevy_pkv::PkvStore;
 use bevy_prototype_lyon::prelude::Stroke;
 use cosmic_text::Edit;
+use serde_json::Value;
 use std::collections::HashMap;
 use std::collections::HashSet;
-use std::path::Path;
-use std::path::PathBuf;
-use tantivy::collector::TopDocs;
-use tantivy::query::BooleanQuery;
-use tantivy::query::FuzzyTermQuery;
-use tantivy::query::Occur;
-use tantivy::ReloadPolicy;
-
-use tantivy::schema::*;
-use tantivy::Index;
+use std::sync::Arc;
 use uuid::Uuid;
 
+use crate::components::Doc;
 use crate::resources::AppState;
 use crate::themes::Theme;
 use crate::utils::ReflectableUuid;
-use crate::APP_NAME;
-use crate::ORG_NAME;
 
+use super::ui_helpers::NodeMetadata;
+use super::ui_helpers::RebuildSearchIndex;
 use super::ui_helpers::SearchButton;
 use super::ui_helpers::SearchText;
 use super::ui_helpers::VeloShape;
 use super::NodeType;
+use super::NotificationChannels;
+use super::Notifier;
 use super::UiState;
 
+pub const MAX_SEARCH_RESULTS: usize = 1000;
+
+pub type SearchResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+/// Full text index of node texts.
+/// Backed by tantivy on the native target and kept in memory on wasm.
+pub trait SearchBackend: Send + Sync {
+    /// Adds text of provided nodes, replacing previously indexed text of the same nodes.
+    fn update(
+        &self,
+        node_search_locations: &HashMap<NodeSearchLocation, String>,
+    ) -> SearchResult<()>;
+    /// Removes everything from the index and adds provided nodes.
+    fn replace_all(
+        &self,
+        node_search_locations: &HashMap<NodeSearchLocation, String>,
+    ) -> SearchResult<()>;
+    fn clear_tabs(&self, tab_ids: &HashSet<Uuid>) -> SearchResult<()>;
+    fn clear_doc(&self, doc_id: &Uuid) -> SearchResult<()>;
+    fn fuzzy_search(&self, query: &str) -> SearchResult<Vec<NodeSearchLocation>>;
+    /// Returns locations of all indexed nodes.
+    fn locations(&self) -> SearchResult<Vec<NodeSearchLocation>>;
+}
+
 pub struct SearchIndexState {
-    pub index: Index,
+    pub index: Arc<dyn SearchBackend>,
     pub tabs_to_delete: HashSet<Uuid>,
     pub node_updates: HashMap<NodeSearchLocation, String>,
 }
 
-#[derive(Eq, PartialEq, Hash, Clone)]
+#[derive(Eq, PartialEq, Hash, Clone, Debug)]
 pub struct NodeSearchLocation {
     pub doc_id: Uuid,
     pub tab_id: Uuid,
@@ -94,8 +114,7 @@ pub fn search_box_text_changed(
     if str != *previous_search_text {
         if !str.is_empty() {
             if let Some(index) = &app_state.search_index {
-                let index = &index.index;
-                let result = fuzzy_search(index, str.as_str());
+                let result = index.index.fuzzy_search(str.as_str());
                 match result {
                     Ok(docs) => {
                         let node_ids: HashSet<ReflectableUuid> = docs
@@ -128,145 +147,213 @@ pub fn search_box_text_changed(
     }
 }
 
-pub fn init_search_index(mut app_state: ResMut<AppState>) {
-    let dirs = directories::ProjectDirs::from("", ORG_NAME, APP_NAME);
-    let path = match dirs.as_ref() {
-        Some(dirs) => dirs.data_dir(),
-        None => Path::new("."),
-    }
-    .to_path_buf();
+pub fn init_search_index(
+    mut app_state: ResMut<AppState>,
+    pkv: Res<PkvStore>,
+    notifications: Res<NotificationChannels>,
+) {
+    let (index, created) = open_search_index();
+    let docs = pkv
+        .get::<HashMap<ReflectableUuid, Doc>>("docs")
+        .unwrap_or_default();
+    let task_index = index.clone();
+    let notifier = notifications.notifier.clone();
+    IoTaskPool::get()
+        .spawn(async move {
+            if created {
+                info!("Building search index from stored documents");
+                if let Err(e) = rebuild_search_index(task_index.as_ref(), &docs) {
+                    error!("Error rebuilding search index {:?}", e);
+                }
+            } else {
+                report_orphaned_entries(task_index.as_ref(), &docs, &notifier);
+            }
+        })
+        .detach();
     app_state.search_index = Some(SearchIndexState {
-        index: initialize_search_index(path),
+        index,
         node_updates: HashMap::new(),
         tabs_to_delete: HashSet::new(),
     });
 }
 
-pub fn initialize_search_index(dir: PathBuf) -> tantivy::Index {
-    Index::open_in_dir(dir.clone()).unwrap_or_else(|_| {
-        let mut schema_builder = Schema::builder();
-        schema_builder.add_text_field("text", TEXT);
-        schema_builder.add_text_field("full_text", STRING);
-        schema_builder.add_text_field("doc_id", STRING | STORED);
-        schema_builder.add_text_field("tab_id", STRING | STORED);
-        schema_builder.add_text_field("node_id", STRING | STORED);
-        let schema = schema_builder.build();
-        Index::create_in_dir(dir, schema).unwrap()
-    })
-}
-
-pub fn update_search_index(
-    index: &Index,
-    node_search_locations: &HashMap<NodeSearchLocation, String>,
-) -> tantivy::Result<()> {
-    let mut index_writer = index.writer(50_000_000)?;
-
-    for (node_search_location, str) in node_search_locations.iter() {
-        let term = tantivy::Term::from_field_text(
-            index.schema().get_field("node_id").unwrap(),
-            &node_search_location.node_id.to_string(),
-        );
-        index_writer.delete_term(term);
-
-        let mut document = tantivy::Document::new();
-        document.add_text(index.schema().get_field("text").unwrap(), str);
-        document.add_text(index.schema().get_field("full_text").unwrap(), str);
-        document.add_text(
-            index.schema().get_field("doc_id").unwrap(),
-            &node_search_location.doc_id.to_string(),
-        );
-        document.add_text(
-            index.schema().get_field("tab_id").unwrap(),
-            &node_search_location.tab_id.to_string(),
-        );
-        document.add_text(
-            index.schema().get_field("node_id").unwrap(),
-            &node_search_location.node_id.to_string(),
-        );
+#[cfg(not(target_arch = "wasm32"))]
+fn open_search_index() -> (Arc<dyn SearchBackend>, bool) {
+    use crate::{APP_NAME, ORG_NAME};
+    use std::path::Path;
 
-        index_writer.add_document(document)?;
+    let dirs = directories::ProjectDirs::from("", ORG_NAME, APP_NAME);
+    let data_dir = match dirs.as_ref() {
+        Some(dirs) => dirs.data_dir(),
+        None => Path::new("."),
+    };
+    match super::initialize_search_index(data_dir.join(super::SEARCH_INDEX_DIR)) {
+        Ok((index, created)) => {
+            if created {
+                if let Err(e) = super::remove_legacy_search_index(data_dir) {
+                    error!("Error removing outdated search index {:?}", e);
+                }
+            }
+            (Arc::new(index), created)
+        }
+        Err(e) => {
+            // search still works for this session, the index is built from stored documents
+            error!("Error opening search index, keeping it in memory {:?}", e);
+            (Arc::new(super::InMemorySearchIndex::default()), true)
+        }
     }
-
-    index_writer.commit()?;
-
-    Ok(())
 }
 
-const MAX_SEARCH_RESULTS: usize = 1000;
-
-pub fn clear_tabs_index(index: &Index, tab_ids: &HashSet<Uuid>) -> tantivy::Result<()> {
-    let mut index_writer = index.writer(50_000_000)?;
+#[cfg(target_arch = "wasm32")]
+fn open_search_index() -> (Arc<dyn SearchBackend>, bool) {
+    (Arc::new(super::InMemorySearchIndex::default()), true)
+}
 
-    for tab_id in tab_ids {
-        let term = tantivy::Term::from_field_text(
-            index.schema().get_field("tab_id").unwrap(),
-            &tab_id.to_string(),
-        );
-        index_writer.delete_term(term);
+/// Text indexed for a node, tags and metadata fields are searchable along with the node text.
+pub fn get_node_search_text(text: &str, metadata: &NodeMetadata) -> String {
+    let mut search_text = text.to_string();
+    for tag in metadata.tags.iter() {
+        search_text.push('\n');
+        search_text.push_str(tag);
     }
+    for (key, value) in metadata.fields.iter() {
+        search_text.push_str(&format!("\n{} {}", key, value));
+    }
+    search_text
+}
 
-    index_writer.commit()?;
-
-    Ok(())
+/// Collects text of every node saved in the latest checkpoint of each tab.
+pub fn get_doc_search_locations(doc: &Doc) -> HashMap<NodeSearchLocation, String> {
+    let mut locations = HashMap::new();
+    for tab in doc.tabs.iter() {
+        let Some(checkpoint) = tab.checkpoints.back() else {
+            continue;
+        };
+        let Ok(json) = serde_json::from_str::<Value>(checkpoint) else {
+            continue;
+        };
+        let Some(nodes) = json["nodes"].as_array() else {
+            continue;
+        };
+        for node in nodes {
+            let node_id = node["id"].as_str().and_then(|id| Uuid::parse_str(id).ok());
+            let text = node["text"]["text"].as_str();
+            if let (Some(node_id), Some(text)) = (node_id, text) {
+                let metadata = NodeMetadata {
+                    tags: serde_json::from_value(node["tags"].clone()).unwrap_or_default(),
+                    fields: serde_json::from_value(node["metadata"].clone()).unwrap_or_default(),
+                };
+                locations.insert(
+                    NodeSearchLocation {
+                        doc_id: doc.id.0,
+                        tab_id: tab.id.0,
+                        node_id,
+                    },
+                    get_node_search_text(text, &metadata),
+                );
+            }
+        }
+    }
+    locations
 }
 
-pub fn clear_doc_index(index: &Index, doc_id: &Uuid) -> tantivy::Result<()> {
-    let mut index_writer = index.writer(50_000_000)?;
+/// Indexes a document right away, documents opened from a URL in viewer mode are never saved.
+pub fn index_document(app_state: &AppState, doc: &Doc) {
+    let Some(index) = &app_state.search_index else {
+        return;
+    };
+    let result = index
+        .index
+        .clear_doc(&doc.id.0)
+        .and_then(|_| index.index.update(&get_doc_search_locations(doc)));
+    if let Err(e) = result {
+        error!("Error indexing document {:?}", e);
+    }
+}
 
-    let term = tantivy::Term::from_field_text(
-        index.schema().get_field("doc_id").unwrap(),
-        &doc_id.to_string(),
-    );
-    index_writer.delete_term(term);
+/// Drops everything from the index and adds nodes of all provided documents again.
+pub fn rebuild_search_index(
+    index: &dyn SearchBackend,
+    docs: &HashMap<ReflectableUuid, Doc>,
+) -> SearchResult<()> {
+    let mut node_search_locations = HashMap::new();
+    for doc in docs.values() {
+        node_search_locations.extend(get_doc_search_locations(doc));
+    }
+    index.replace_all(&node_search_locations)
+}
 
-    index_writer.commit()?;
+/// Returns indexed nodes which don't exist in any of the provided documents anymore.
+pub fn find_orphaned_entries(
+    index: &dyn SearchBackend,
+    docs: &HashMap<ReflectableUuid, Doc>,
+) -> SearchResult<Vec<NodeSearchLocation>> {
+    let mut known_locations = HashSet::new();
+    for doc in docs.values() {
+        known_locations.extend(get_doc_search_locations(doc).into_keys());
+    }
+    Ok(index
+        .locations()?
+        .into_iter()
+        .filter(|location| !known_locations.contains(location))
+        .collect())
+}
 
-    Ok(())
+fn report_orphaned_entries(
+    index: &dyn SearchBackend,
+    docs: &HashMap<ReflectableUuid, Doc>,
+    notifier: &Notifier,
+) {
+    match find_orphaned_entries(index, docs) {
+        Ok(orphans) if !orphans.is_empty() => notifier.error(
+            "search_index",
+            format!(
+                "Search index contains {} orphaned entries, rebuild the index to remove them",
+                orphans.len()
+            ),
+        ),
+        Ok(_) => {}
+        Err(e) => error!("Error checking search index integrity {:?}", e),
+    }
 }
 
-pub fn fuzzy_search(index: &Index, query: &str) -> tantivy::Result<Vec<NodeSearchLocation>> {
-    let reader = index
-        .reader_builder()
-        .reload_policy(ReloadPolicy::OnCommit)
-        .try_into()?;
-    let searcher = reader.searcher();
-    let normalized_query = query.to_lowercase();
-
-    let schema = index.schema();
-    let text_field = schema.get_field("text").unwrap();
-    let full_text_field = schema.get_field("full_text").unwrap();
-    let doc_id_field = schema.get_field("doc_id").unwrap();
-    let tab_id_field = schema.get_field("tab_id").unwrap();
-    let node_id_field = schema.get_field("node_id").unwrap();
-
-    let text_term = Term::from_field_text(text_field, normalized_query.as_str());
-    let query1 = FuzzyTermQuery::new(text_term, 2, true);
-
-    let full_text_term = Term::from_field_text(full_text_field, normalized_query.as_str());
-    let query2 = FuzzyTermQuery::new(full_text_term, 2, true);
-    let query = BooleanQuery::new(vec![
-        (Occur::Should, Box::new(query1)),
-        (Occur::Should, Box::new(query2)),
-    ]);
-
-    let top_docs = searcher
-        .search(&query, &(TopDocs::with_limit(MAX_SEARCH_RESULTS)))
-        .unwrap();
-    let ids: Vec<NodeSearchLocation> = top_docs
-        .iter()
-        .map(|(_, doc_address)| {
-            let doc = searcher.doc(*doc_address).unwrap();
-            let doc_id_value = doc.get_first(doc_id_field).unwrap();
-            let tab_id_value = doc.get_first(tab_id_field).unwrap();
-            let node_id_value = doc.get_first(node_id_field).unwrap();
-            NodeSearchLocation {
-                doc_id: Uuid::parse_str(doc_id_value.as_text().unwrap()).unwrap(),
-                tab_id: Uuid::parse_str(tab_id_value.as_text().unwrap()).unwrap(),
-                node_id: Uuid::parse_str(node_id_value.as_text().unwrap()).unwrap(),
+pub fn rebuild_search_index_handler(
+    mut query: Query<&Interaction, (Changed<Interaction>, With<RebuildSearchIndex>)>,
+    app_state: Res<AppState>,
+    pkv: Res<PkvStore>,
+    notifications: Res<NotificationChannels>,
+) {
+    for interaction in &mut query.iter_mut() {
+        match *interaction {
+            Interaction::Pressed => {
+                if let Some(index) = &app_state.search_index {
+                    let mut docs = pkv
+                        .get::<HashMap<ReflectableUuid, Doc>>("docs")
+                        .unwrap_or_default();
+                    // documents in memory may be newer than stored ones
+                    for (id, doc) in app_state.docs.iter() {
+                        docs.insert(*id, doc.clone());
+                    }
+                    let index = index.index.clone();
+                    let notifier = notifications.notifier.clone();
+                    notifier.progress("search_index", "Rebuilding search index...");
+                    IoTaskPool::get()
+                        .spawn(async move {
+                            match rebuild_search_index(index.as_ref(), &docs) {
+                                Ok(_) => notifier.success("search_index", "Search index rebuilt"),
+                                Err(e) => notifier.error(
+                                    "search_index",
+                                    format!("Error rebuilding search index {:?}", e),
+                                ),
+                            }
+                        })
+                        .detach();
+                }
             }
-        })
-        .collect();
-    Ok(ids)
+            Interaction::Hovered => {}
+            Interaction::None => {}
+        }
+    }
 }
 
 fn highlight_search_match_nodes(
@@ -295,140 +382,118 @@ fn highlight_search_match_nodes(
 
 #[cfg(test)]
 mod tests {
-    use tempfile::TempDir;
-    use uuid::Uuid;
-
     use super::*;
 
-    #[test]
-    fn test_fuzzy_search() {
-        // Create a temporary directory for the index
-        let temp_dir = TempDir::new().expect("Failed to create temporary directory");
-
-        // Initialize the index using the temporary directory
-        let index = initialize_search_index(temp_dir.path().to_path_buf());
-        let id1 = Uuid::new_v4();
-        let text1 = "apple".to_string();
-        let id2 = Uuid::new_v4();
-        let text2 = "banana".to_string();
-        let mut node_search_locations = HashMap::new();
-        node_search_locations.insert(
-            NodeSearchLocation {
-                doc_id: id1,
-                tab_id: Uuid::new_v4(),
-                node_id: Uuid::new_v4(),
-            },
-            text1,
-        );
-        node_search_locations.insert(
-            NodeSearchLocation {
-                doc_id: id2,
-                tab_id: Uuid::new_v4(),
-                node_id: Uuid::new_v4(),
-            },
-            text2,
-        );
-        update_search_index(&index, &node_search_locations).unwrap();
-        // Perform fuzzy search and assert the results
-        let query = "appla";
-        let result = fuzzy_search(&index, query).unwrap();
+    fn test_doc(text: &str) -> Doc {
+        let node_id = Uuid::new_v4();
+        let checkpoint = serde_json::json!({
+            "images": {},
+            "nodes": [{ "id": node_id, "text": { "text": text, "pos": "Center" } }],
+            "arrows": [],
+            "drawings": [],
+        });
+        Doc {
+            tabs: vec![crate::components::Tab {
+                is_active: true,
+                id: ReflectableUuid::generate(),
+                name: "Tab 1".to_string(),
+                checkpoints: vec![checkpoint.to_string()].into(),
+                z_index: 1.,
+                slides: vec![],
+            }],
+            id: ReflectableUuid::generate(),
+            name: "Doc".to_string(),
+            tags: vec![],
+            ..Default::default()
+        }
+    }
+
+    fn check_rebuild_and_orphaned_entries(index: &dyn SearchBackend) {
+        let doc = test_doc("apple");
+        let mut docs = HashMap::new();
+        docs.insert(doc.id, doc.clone());
 
+        let mut node_search_locations = HashMap::new();
+        let orphan = NodeSearchLocation {
+            doc_id: Uuid::new_v4(),
+            tab_id: Uuid::new_v4(),
+            node_id: Uuid::new_v4(),
+        };
+        node_search_locations.insert(orphan.clone(), "banana".to_string());
+        index.update(&node_search_locations).unwrap();
+
+        assert_eq!(find_orphaned_entries(index, &docs).unwrap(), vec![orphan]);
+        assert_eq!(index.fuzzy_search("apple").unwrap().len(), 0);
+
+        rebuild_search_index(index, &docs).unwrap();
+
+        assert!(find_orphaned_entries(index, &docs).unwrap().is_empty());
+        assert_eq!(index.fuzzy_search("banana").unwrap().len(), 0);
+        let result = index.fuzzy_search("apple").unwrap();
         assert_eq!(result.len(), 1);
-        assert_eq!(result[0].doc_id, id1);
-        // Clean up the temporary directory
-        temp_dir
-            .close()
-            .expect("Failed to remove temporary directory");
+        assert_eq!(result[0].doc_id, doc.id.0);
     }
 
     #[test]
-    fn test_clear_tab() {
-        // Create a temporary directory for the index
-        let temp_dir = TempDir::new().expect("Failed to create temporary directory");
-
-        // Initialize the index using the temporary directory
-        let index = initialize_search_index(temp_dir.path().to_path_buf());
-        let doc_id = Uuid::new_v4();
-        let tab_id = Uuid::new_v4();
-        let text_1 = "example text 1".to_string();
-        let text_2 = "example text 2".to_string();
-        let mut node_search_locations = HashMap::new();
-        node_search_locations.insert(
-            NodeSearchLocation {
-                doc_id,
-                tab_id,
-                node_id: Uuid::new_v4(),
-            },
-            text_1,
-        );
-        node_search_locations.insert(
-            NodeSearchLocation {
-                doc_id,
-                tab_id,
-                node_id: Uuid::new_v4(),
-            },
-            text_2,
-        );
-        update_search_index(&index, &node_search_locations).unwrap();
-
-        let mut tab_ids = HashSet::new();
-        tab_ids.insert(tab_id);
-        // Clear the tab from the index
-        clear_tabs_index(&index, &tab_ids).unwrap();
-
-        // Perform a search and assert that the tab is not found
-        let query = "example";
-        let result = fuzzy_search(&index, query).unwrap();
+    fn test_rebuild_and_orphaned_entries() {
+        let temp_dir = tempfile::TempDir::new().expect("Failed to create temporary directory");
+        let (index, _) =
+            super::super::initialize_search_index(temp_dir.path().to_path_buf()).unwrap();
+        check_rebuild_and_orphaned_entries(&index);
+    }
 
-        assert_eq!(result.len(), 0);
+    #[test]
+    fn test_rebuild_and_orphaned_entries_in_memory() {
+        check_rebuild_and_orphaned_entries(&super::super::InMemorySearchIndex::default());
+    }
 
-        // Clean up the temporary directory
-        temp_dir
-            .close()
-            .expect("Failed to remove temporary directory");
+    #[test]
+    fn test_loaded_doc_is_searchable() {
+        let mut app = App::new();
+        app.add_systems(Update, super::super::load_doc_handler);
+        let (tx, rx) = async_channel::unbounded();
+        let doc = test_doc("apple");
+        tx.try_send(serde_json::to_string(&doc).unwrap()).unwrap();
+        app.insert_resource(super::super::CommChannels { tx, rx });
+        app.insert_resource(super::super::NotificationChannels::default());
+        PkvStore::new("test", "load_doc_handler").clear().unwrap();
+        app.insert_resource(PkvStore::new("test", "load_doc_handler"));
+        app.insert_resource(AppState {
+            search_index: Some(SearchIndexState {
+                index: Arc::new(super::super::InMemorySearchIndex::default()),
+                tabs_to_delete: HashSet::new(),
+                node_updates: HashMap::new(),
+            }),
+            ..default()
+        });
+
+        app.update();
+
+        let app_state = app.world.resource::<AppState>();
+        let index = &app_state.search_index.as_ref().unwrap().index;
+        let result = index.fuzzy_search("apple").unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].doc_id, doc.id.0);
     }
 
     #[test]
-    fn test_clear_doc() {
-        // Create a temporary directory for the index
-        let temp_dir = TempDir::new().expect("Failed to create temporary directory");
-
-        // Initialize the index using the temporary directory
-        let index = initialize_search_index(temp_dir.path().to_path_buf());
-        let doc_id = Uuid::new_v4();
-        let text_1 = "example text 1".to_string();
-        let text_2 = "example text 2".to_string();
-        let mut node_search_locations = HashMap::new();
-        node_search_locations.insert(
-            NodeSearchLocation {
-                doc_id,
-                tab_id: Uuid::new_v4(),
-                node_id: Uuid::new_v4(),
-            },
-            text_1,
+    fn test_node_metadata_is_searchable() {
+        let mut doc = test_doc("apple");
+        let mut checkpoint: Value =
+            serde_json::from_str(doc.tabs[0].checkpoints.back().unwrap()).unwrap();
+        checkpoint["nodes"][0]["tags"] = serde_json::json!(["backend"]);
+        checkpoint["nodes"][0]["metadata"] = serde_json::json!({ "owner": "alice" });
+        doc.tabs[0].checkpoints = vec![checkpoint.to_string()].into();
+
+        let locations = get_doc_search_locations(&doc);
+        assert_eq!(
+            locations.values().next().unwrap(),
+            "apple\nbackend\nowner alice"
         );
-        node_search_locations.insert(
-            NodeSearchLocation {
-                doc_id,
-                tab_id: Uuid::new_v4(),
-                node_id: Uuid::new_v4(),
-            },
-            text_2,
-        );
-        update_search_index(&index, &node_search_locations).unwrap();
-
-        // Clear the document from the index
-        clear_doc_index(&index, &doc_id).unwrap();
-
-        // Perform a search and assert that the document is not found
-        let query = "example";
-        let result = fuzzy_search(&index, query).unwrap();
-
-        assert_eq!(result.len(), 0);
 
-        // Clean up the temporary directory
-        temp_dir
-            .close()
-            .expect("Failed to remove temporary directory");
+        let index = super::super::InMemorySearchIndex::default();
+        index.update(&locations).unwrap();
+        assert_eq!(index.fuzzy_search("alice").unwrap().len(), 1);
+        assert_eq!(index.fuzzy_search("backend").unwrap().len(), 1);
     }
 }
@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::sync::RwLock;
+
+use uuid::Uuid;
+
+use super::{NodeSearchLocation, SearchBackend, SearchResult, MAX_SEARCH_RESULTS};
+
+/// Same edit distance as used by tantivy fuzzy queries on the native target.
+const MAX_EDIT_DISTANCE: usize = 2;
+/// Longer words are dropped by tantivy's default tokenizer, keep results consistent.
+const MAX_WORD_LENGTH: usize = 40;
+
+/// Search index kept in memory, used on the wasm target where no filesystem is available
+/// and on native when the index on disk can't be opened.
+/// Index content is lost on restart, so it is filled from stored documents on startup.
+#[derive(Default)]
+pub struct InMemorySearchIndex {
+    nodes: RwLock<HashMap<NodeSearchLocation, String>>,
+}
+
+impl SearchBackend for InMemorySearchIndex {
+    fn update(
+        &self,
+        node_search_locations: &HashMap<NodeSearchLocation, String>,
+    ) -> SearchResult<()> {
+        let mut nodes = self.nodes.write().map_err(|e| e.to_string())?;
+        let node_ids: HashSet<Uuid> = node_search_locations
+            .keys()
+            .map(|location| location.node_id)
+            .collect();
+        nodes.retain(|location, _| !node_ids.contains(&location.node_id));
+        nodes.extend(
+            node_search_locations
+                .iter()
+                .map(|(location, text)| (location.clone(), text.clone())),
+        );
+        Ok(())
+    }
+
+    fn replace_all(
+        &self,
+        node_search_locations: &HashMap<NodeSearchLocation, String>,
+    ) -> SearchResult<()> {
+        let mut nodes = self.nodes.write().map_err(|e| e.to_string())?;
+        *nodes = node_search_locations.clone();
+        Ok(())
+    }
+
+    fn clear_tabs(&self, tab_ids: &HashSet<Uuid>) -> SearchResult<()> {
+        let mut nodes = self.nodes.write().map_err(|e| e.to_string())?;
+        nodes.retain(|location, _| !tab_ids.contains(&location.tab_id));
+        Ok(())
+    }
+
+    fn clear_doc(&self, doc_id: &Uuid) -> SearchResult<()> {
+        let mut nodes = self.nodes.write().map_err(|e| e.to_string())?;
+        nodes.retain(|location, _| location.doc_id != *doc_id);
+        Ok(())
+    }
+
+    fn fuzzy_search(&self, query: &str) -> SearchResult<Vec<NodeSearchLocation>> {
+        let nodes = self.nodes.read().map_err(|e| e.to_string())?;
+        let query: Vec<char> = query.to_lowercase().chars().collect();
+        let mut matches: Vec<(usize, &NodeSearchLocation)> = nodes
+            .iter()
+            .filter_map(|(location, text)| {
+                let full_text: Vec<char> = text.chars().collect();
+                text.to_lowercase()
+                    .split(|c: char| !c.is_alphanumeric())
+                    .filter(|word| !word.is_empty() && word.len() < MAX_WORD_LENGTH)
+                    .map(|word| word.chars().collect::<Vec<char>>())
+                    .chain(std::iter::once(full_text))
+                    .map(|word| edit_distance(&query, &word))
+                    .min()
+                    .filter(|distance| *distance <= MAX_EDIT_DISTANCE)
+                    .map(|distance| (distance, location))
+            })
+            .collect();
+        matches.sort_by_key(|(distance, location)| (*distance, location.node_id));
+        Ok(matches
+            .into_iter()
+            .take(MAX_SEARCH_RESULTS)
+            .map(|(_, location)| location.clone())
+            .collect())
+    }
+
+    fn locations(&self) -> SearchResult<Vec<NodeSearchLocation>> {
+        let nodes = self.nodes.read().map_err(|e| e.to_string())?;
+        Ok(nodes.keys().cloned().collect())
+    }
+}
+
+/// Levenshtein distance where transposition of two adjacent characters costs one edit.
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    if a.len().abs_diff(b.len()) > MAX_EDIT_DISTANCE {
+        return usize::MAX;
+    }
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, distance) in distances[0].iter_mut().enumerate() {
+        *distance = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+    distances[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chars(s: &str) -> Vec<char> {
+        s.chars().collect()
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance(&chars("apple"), &chars("apple")), 0);
+        assert_eq!(edit_distance(&chars("appla"), &chars("apple")), 1);
+        assert_eq!(edit_distance(&chars("aplpe"), &chars("apple")), 1);
+        assert_eq!(edit_distance(&chars("apple"), &chars("banana")), 5);
+        assert_eq!(edit_distance(&chars("a"), &chars("banana")), usize::MAX);
+    }
+
+    #[test]
+    fn test_fuzzy_search() {
+        let index = InMemorySearchIndex::default();
+        let id1 = Uuid::new_v4();
+        let id2 = Uuid::new_v4();
+        let mut node_search_locations = HashMap::new();
+        node_search_locations.insert(
+            NodeSearchLocation {
+                doc_id: id1,
+                tab_id: Uuid::new_v4(),
+                node_id: Uuid::new_v4(),
+            },
+            "Red apple".to_string(),
+        );
+        node_search_locations.insert(
+            NodeSearchLocation {
+                doc_id: id2,
+                tab_id: Uuid::new_v4(),
+                node_id: Uuid::new_v4(),
+            },
+            "banana".to_string(),
+        );
+        index.update(&node_search_locations).unwrap();
+
+        let result = index.fuzzy_search("appla").unwrap();
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].doc_id, id1);
+    }
+
+    #[test]
+    fn test_clear_tab_and_doc() {
+        let index = InMemorySearchIndex::default();
+        let doc_id = Uuid::new_v4();
+        let tab_id = Uuid::new_v4();
+        let mut node_search_locations = HashMap::new();
+        for tab_id in [tab_id, Uuid::new_v4()] {
+            node_search_locations.insert(
+                NodeSearchLocation {
+                    doc_id,
+                    tab_id,
+                    node_id: Uuid::new_v4(),
+                },
+                "example text".to_string(),
+            );
+        }
+        index.update(&node_search_locations).unwrap();
+
+        index.clear_tabs(&HashSet::from([tab_id])).unwrap();
+        assert_eq!(index.fuzzy_search("example").unwrap().len(), 1);
+
+        index.clear_doc(&doc_id).unwrap();
+        assert_eq!(index.fuzzy_search("example").unwrap().len(), 0);
+    }
+}
@@ -0,0 +1,465 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tantivy::collector::TopDocs;
+use tantivy::query::BooleanQuery;
+use tantivy::query::FuzzyTermQuery;
+use tantivy::query::Occur;
+use tantivy::ReloadPolicy;
+
+use tantivy::schema::*;
+use tantivy::Index;
+use tantivy::IndexWriter;
+use uuid::Uuid;
+
+use super::{NodeSearchLocation, SearchBackend, SearchResult, MAX_SEARCH_RESULTS};
+
+/// Bump this whenever `build_search_schema` changes, existing indexes are rebuilt on startup.
+pub const SEARCH_SCHEMA_VERSION: u32 = 1;
+pub const SEARCH_INDEX_DIR: &str = "search_index";
+const SCHEMA_VERSION_FILE: &str = "schema_version";
+/// Lists every file of a tantivy index, used to find files of the unversioned index.
+const MANAGED_FILES: &str = ".managed.json";
+const LEGACY_INDEX_FILES: [&str; 4] = [
+    "meta.json",
+    MANAGED_FILES,
+    ".tantivy-meta.lock",
+    ".tantivy-writer.lock",
+];
+
+/// Search index stored on disk, used on the native target.
+pub struct TantivySearchIndex {
+    index: Index,
+    /// Tantivy allows a single writer per index, writes from save tasks and rebuilds wait for each other.
+    writer_lock: Mutex<()>,
+}
+
+pub fn build_search_schema() -> Schema {
+    let mut schema_builder = Schema::builder();
+    schema_builder.add_text_field("text", TEXT);
+    schema_builder.add_text_field("full_text", STRING);
+    schema_builder.add_text_field("doc_id", STRING | STORED);
+    schema_builder.add_text_field("tab_id", STRING | STORED);
+    schema_builder.add_text_field("node_id", STRING | STORED);
+    schema_builder.build()
+}
+
+/// Opens the index stored in `dir` or creates a new one.
+/// Index with outdated schema version is removed and created from scratch.
+/// Returned flag is `true` when the index was (re)created and should be filled with documents.
+pub fn initialize_search_index(dir: PathBuf) -> SearchResult<(TantivySearchIndex, bool)> {
+    let version_file = dir.join(SCHEMA_VERSION_FILE);
+    let stored_version = std::fs::read_to_string(&version_file)
+        .ok()
+        .and_then(|version| version.trim().parse::<u32>().ok());
+    if stored_version == Some(SEARCH_SCHEMA_VERSION) {
+        if let Ok(index) = Index::open_in_dir(dir.clone()) {
+            if index.schema() == build_search_schema() {
+                return Ok((TantivySearchIndex::new(index), false));
+            }
+        }
+    }
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir)?;
+    }
+    std::fs::create_dir_all(&dir)?;
+    let index = Index::create_in_dir(dir, build_search_schema())?;
+    std::fs::write(version_file, SEARCH_SCHEMA_VERSION.to_string())?;
+    Ok((TantivySearchIndex::new(index), true))
+}
+
+/// Removes the index created before schema versioning, it was stored right in the data dir
+/// next to other app files, so only files listed by the index itself are deleted.
+pub fn remove_legacy_search_index(data_dir: &Path) -> SearchResult<()> {
+    let managed_files = data_dir.join(MANAGED_FILES);
+    if !managed_files.exists() {
+        return Ok(());
+    }
+    let files: Vec<PathBuf> = serde_json::from_str(&std::fs::read_to_string(managed_files)?)?;
+    for file in files
+        .iter()
+        .map(PathBuf::as_path)
+        .chain(LEGACY_INDEX_FILES.iter().map(Path::new))
+    {
+        // only plain file names, the list must not point outside of the data dir
+        if file.file_name() != Some(file.as_os_str()) {
+            continue;
+        }
+        let path = data_dir.join(file);
+        if path.is_file() {
+            std::fs::remove_file(path)?;
+        }
+    }
+    Ok(())
+}
+
+impl TantivySearchIndex {
+    fn new(index: Index) -> Self {
+        TantivySearchIndex {
+            index,
+            writer_lock: Mutex::new(()),
+        }
+    }
+
+    /// Runs `write` with the only writer of the index and commits the changes.
+    fn write(&self, write: impl FnOnce(&IndexWriter) -> tantivy::Result<()>) -> SearchResult<()> {
+        let _guard = self
+            .writer_lock
+            .lock()
+            .map_err(|_| "search index writer lock is poisoned")?;
+        let mut index_writer = self.index.writer(50_000_000)?;
+        write(&index_writer)?;
+        index_writer.commit()?;
+        Ok(())
+    }
+
+    fn add_nodes(
+        &self,
+        index_writer: &tantivy::IndexWriter,
+        node_search_locations: &HashMap<NodeSearchLocation, String>,
+    ) -> tantivy::Result<()> {
+        let schema = self.index.schema();
+        for (node_search_location, str) in node_search_locations.iter() {
+            let term = tantivy::Term::from_field_text(
+                schema.get_field("node_id").unwrap(),
+                &node_search_location.node_id.to_string(),
+            );
+            index_writer.delete_term(term);
+
+            let mut document = tantivy::Document::new();
+            document.add_text(schema.get_field("text").unwrap(), str);
+            document.add_text(schema.get_field("full_text").unwrap(), str);
+            document.add_text(
+                schema.get_field("doc_id").unwrap(),
+                node_search_location.doc_id.to_string(),
+            );
+            document.add_text(
+                schema.get_field("tab_id").unwrap(),
+                node_search_location.tab_id.to_string(),
+            );
+            document.add_text(
+                schema.get_field("node_id").unwrap(),
+                node_search_location.node_id.to_string(),
+            );
+
+            index_writer.add_document(document)?;
+        }
+        Ok(())
+    }
+
+    fn get_node_search_location(&self, doc: &tantivy::Document) -> NodeSearchLocation {
+        let schema = self.index.schema();
+        let doc_id_value = doc.get_first(schema.get_field("doc_id").unwrap()).unwrap();
+        let tab_id_value = doc.get_first(schema.get_field("tab_id").unwrap()).unwrap();
+        let node_id_value = doc.get_first(schema.get_field("node_id").unwrap()).unwrap();
+        NodeSearchLocation {
+            doc_id: Uuid::parse_str(doc_id_value.as_text().unwrap()).unwrap(),
+            tab_id: Uuid::parse_str(tab_id_value.as_text().unwrap()).unwrap(),
+            node_id: Uuid::parse_str(node_id_value.as_text().unwrap()).unwrap(),
+        }
+    }
+}
+
+impl SearchBackend for TantivySearchIndex {
+    fn update(
+        &self,
+        node_search_locations: &HashMap<NodeSearchLocation, String>,
+    ) -> SearchResult<()> {
+        self.write(|index_writer| self.add_nodes(index_writer, node_search_locations))
+    }
+
+    fn replace_all(
+        &self,
+        node_search_locations: &HashMap<NodeSearchLocation, String>,
+    ) -> SearchResult<()> {
+        self.write(|index_writer| {
+            index_writer.delete_all_documents()?;
+            self.add_nodes(index_writer, node_search_locations)
+        })
+    }
+
+    fn clear_tabs(&self, tab_ids: &HashSet<Uuid>) -> SearchResult<()> {
+        self.write(|index_writer| {
+            for tab_id in tab_ids {
+                let term = tantivy::Term::from_field_text(
+                    self.index.schema().get_field("tab_id").unwrap(),
+                    &tab_id.to_string(),
+                );
+                index_writer.delete_term(term);
+            }
+            Ok(())
+        })
+    }
+
+    fn clear_doc(&self, doc_id: &Uuid) -> SearchResult<()> {
+        self.write(|index_writer| {
+            let term = tantivy::Term::from_field_text(
+                self.index.schema().get_field("doc_id").unwrap(),
+                &doc_id.to_string(),
+            );
+            index_writer.delete_term(term);
+            Ok(())
+        })
+    }
+
+    fn fuzzy_search(&self, query: &str) -> SearchResult<Vec<NodeSearchLocation>> {
+        let reader = self
+            .index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::OnCommit)
+            .try_into()?;
+        let searcher = reader.searcher();
+        let normalized_query = query.to_lowercase();
+
+        let schema = self.index.schema();
+        let text_field = schema.get_field("text").unwrap();
+        let full_text_field = schema.get_field("full_text").unwrap();
+
+        let text_term = Term::from_field_text(text_field, normalized_query.as_str());
+        let query1 = FuzzyTermQuery::new(text_term, 2, true);
+
+        let full_text_term = Term::from_field_text(full_text_field, normalized_query.as_str());
+        let query2 = FuzzyTermQuery::new(full_text_term, 2, true);
+        let query = BooleanQuery::new(vec![
+            (Occur::Should, Box::new(query1)),
+            (Occur::Should, Box::new(query2)),
+        ]);
+
+        let top_docs = searcher.search(&query, &(TopDocs::with_limit(MAX_SEARCH_RESULTS)))?;
+        let mut ids = vec![];
+        for (_, doc_address) in top_docs {
+            let doc = searcher.doc(doc_address)?;
+            ids.push(self.get_node_search_location(&doc));
+        }
+        Ok(ids)
+    }
+
+    fn locations(&self) -> SearchResult<Vec<NodeSearchLocation>> {
+        let reader = self.index.reader()?;
+        let searcher = reader.searcher();
+        let mut locations = vec![];
+        for segment_reader in searcher.segment_readers() {
+            let store_reader = segment_reader.get_store_reader(1)?;
+            for doc in store_reader.iter(segment_reader.alive_bitset()) {
+                locations.push(self.get_node_search_location(&doc?));
+            }
+        }
+        Ok(locations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+    use uuid::Uuid;
+
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_search() {
+        // Create a temporary directory for the index
+        let temp_dir = TempDir::new().expect("Failed to create temporary directory");
+
+        // Initialize the index using the temporary directory
+        let (index, _) = initialize_search_index(temp_dir.path().to_path_buf()).unwrap();
+        let id1 = Uuid::new_v4();
+        let text1 = "apple".to_string();
+        let id2 = Uuid::new_v4();
+        let text2 = "banana".to_string();
+        let mut node_search_locations = HashMap::new();
+        node_search_locations.insert(
+            NodeSearchLocation {
+                doc_id: id1,
+                tab_id: Uuid::new_v4(),
+                node_id: Uuid::new_v4(),
+            },
+            text1,
+        );
+        node_search_locations.insert(
+            NodeSearchLocation {
+                doc_id: id2,
+                tab_id: Uuid::new_v4(),
+                node_id: Uuid::new_v4(),
+            },
+            text2,
+        );
+        index.update(&node_search_locations).unwrap();
+        // Perform fuzzy search and assert the results
+        let query = "appla";
+        let result = index.fuzzy_search(query).unwrap();
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].doc_id, id1);
+        // Clean up the temporary directory
+        temp_dir
+            .close()
+            .expect("Failed to remove temporary directory");
+    }
+
+    #[test]
+    fn test_clear_tab() {
+        // Create a temporary directory for the index
+        let temp_dir = TempDir::new().expect("Failed to create temporary directory");
+
+        // Initialize the index using the temporary directory
+        let (index, _) = initialize_search_index(temp_dir.path().to_path_buf()).unwrap();
+        let doc_id = Uuid::new_v4();
+        let tab_id = Uuid::new_v4();
+        let text_1 = "example text 1".to_string();
+        let text_2 = "example text 2".to_string();
+        let mut node_search_locations = HashMap::new();
+        node_search_locations.insert(
+            NodeSearchLocation {
+                doc_id,
+                tab_id,
+                node_id: Uuid::new_v4(),
+            },
+            text_1,
+        );
+        node_search_locations.insert(
+            NodeSearchLocation {
+                doc_id,
+                tab_id,
+                node_id: Uuid::new_v4(),
+            },
+            text_2,
+        );
+        index.update(&node_search_locations).unwrap();
+
+        let mut tab_ids = HashSet::new();
+        tab_ids.insert(tab_id);
+        // Clear the tab from the index
+        index.clear_tabs(&tab_ids).unwrap();
+
+        // Perform a search and assert that the tab is not found
+        let query = "example";
+        let result = index.fuzzy_search(query).unwrap();
+
+        assert_eq!(result.len(), 0);
+
+        // Clean up the temporary directory
+        temp_dir
+            .close()
+            .expect("Failed to remove temporary directory");
+    }
+
+    #[test]
+    fn test_clear_doc() {
+        // Create a temporary directory for the index
+        let temp_dir = TempDir::new().expect("Failed to create temporary directory");
+
+        // Initialize the index using the temporary directory
+        let (index, _) = initialize_search_index(temp_dir.path().to_path_buf()).unwrap();
+        let doc_id = Uuid::new_v4();
+        let text_1 = "example text 1".to_string();
+        let text_2 = "example text 2".to_string();
+        let mut node_search_locations = HashMap::new();
+        node_search_locations.insert(
+            NodeSearchLocation {
+                doc_id,
+                tab_id: Uuid::new_v4(),
+                node_id: Uuid::new_v4(),
+            },
+            text_1,
+        );
+        node_search_locations.insert(
+            NodeSearchLocation {
+                doc_id,
+                tab_id: Uuid::new_v4(),
+                node_id: Uuid::new_v4(),
+            },
+            text_2,
+        );
+        index.update(&node_search_locations).unwrap();
+
+        // Clear the document from the index
+        index.clear_doc(&doc_id).unwrap();
+
+        // Perform a search and assert that the document is not found
+        let query = "example";
+        let result = index.fuzzy_search(query).unwrap();
+
+        assert_eq!(result.len(), 0);
+
+        // Clean up the temporary directory
+        temp_dir
+            .close()
+            .expect("Failed to remove temporary directory");
+    }
+
+    #[test]
+    fn test_outdated_schema_version() {
+        let temp_dir = TempDir::new().expect("Failed to create temporary directory");
+        let path = temp_dir.path().to_path_buf();
+
+        let (_, created) = initialize_search_index(path.clone()).unwrap();
+        assert!(created);
+        let (_, created) = initialize_search_index(path.clone()).unwrap();
+        assert!(!created);
+
+        std::fs::write(path.join(SCHEMA_VERSION_FILE), "0").unwrap();
+        let (_, created) = initialize_search_index(path.clone()).unwrap();
+        assert!(created);
+        assert_eq!(
+            std::fs::read_to_string(path.join(SCHEMA_VERSION_FILE)).unwrap(),
+            SEARCH_SCHEMA_VERSION.to_string()
+        );
+    }
+
+    #[test]
+    fn test_remove_legacy_search_index() {
+        let temp_dir = TempDir::new().expect("Failed to create temporary directory");
+        let data_dir = temp_dir.path().to_path_buf();
+        let index = TantivySearchIndex::new(
+            Index::create_in_dir(&data_dir, build_search_schema()).unwrap(),
+        );
+        let mut node_search_locations = HashMap::new();
+        node_search_locations.insert(
+            NodeSearchLocation {
+                doc_id: Uuid::new_v4(),
+                tab_id: Uuid::new_v4(),
+                node_id: Uuid::new_v4(),
+            },
+            "example".to_string(),
+        );
+        index.update(&node_search_locations).unwrap();
+        drop(index);
+        std::fs::write(data_dir.join("settings.json"), "{}").unwrap();
+        std::fs::create_dir(data_dir.join(SEARCH_INDEX_DIR)).unwrap();
+
+        remove_legacy_search_index(&data_dir).unwrap();
+
+        let mut remaining: Vec<_> = std::fs::read_dir(&data_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        remaining.sort();
+        assert_eq!(remaining, vec!["search_index", "settings.json"]);
+    }
+
+    #[test]
+    fn test_concurrent_writes() {
+        let temp_dir = TempDir::new().expect("Failed to create temporary directory");
+        let (index, _) = initialize_search_index(temp_dir.path().to_path_buf()).unwrap();
+        let doc_id = Uuid::new_v4();
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    let mut node_search_locations = HashMap::new();
+                    node_search_locations.insert(
+                        NodeSearchLocation {
+                            doc_id,
+                            tab_id: Uuid::new_v4(),
+                            node_id: Uuid::new_v4(),
+                        },
+                        "example".to_string(),
+                    );
+                    index.update(&node_search_locations).unwrap();
+                });
+            }
+        });
+        assert_eq!(index.fuzzy_search("example").unwrap().len(), 4);
+    }
+}
@@ -1,12 +1,13 @@
 use bevy::{prelude::*, window::PrimaryWindow};
 
-use super::{ui_helpers::RawText, NodeInteraction, UiState};
+use super::{ui_helpers::RawText, NodeInteraction, UiState, ViewerMode};
 
 pub fn set_focused_entity(
     mut windows: Query<&mut Window, With<PrimaryWindow>>,
     mut node_interaction_events: EventReader<NodeInteraction>,
     mut ui_state: ResMut<UiState>,
     velo: Query<&RawText, With<RawText>>,
+    viewer_mode: Option<Res<ViewerMode>>,
 ) {
     let mut primary_window = windows.single_mut();
 
@@ -26,6 +27,10 @@ pub fn set_focused_entity(
                     }
                 }
                 crate::ui_plugin::NodeInteractionType::LeftClick => {}
+                // nodes can't be edited or moved in viewer mode
+                crate::ui_plugin::NodeInteractionType::LeftDoubleClick
+                | crate::ui_plugin::NodeInteractionType::LeftMouseHoldAndDrag
+                    if viewer_mode.is_some() => {}
                 crate::ui_plugin::NodeInteractionType::LeftDoubleClick => {
                     *ui_state = UiState::default();
                     ui_state.entity_to_edit = Some(velo_node.id);
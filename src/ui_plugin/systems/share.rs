@@ -0,0 +1,589 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use async_channel::{Receiver, Sender};
+use bevy::prelude::*;
+use bevy_pkv::PkvStore;
+use serde::Deserialize;
+
+use super::ui_helpers::{RevokeShare, ShareDoc, Tooltip};
+use super::{
+    encrypt_document, generate_key, key_fragment, record_merge_base, NotificationChannels,
+};
+use super::{GistShareProvider, GitlabShareProvider, HttpPutShareProvider, LocalDirShareProvider};
+use crate::components::{Doc, SharedRemote};
+use crate::resources::AppState;
+use crate::utils::{get_timestamp, load_doc_to_memory, ReflectableUuid};
+
+pub const DEFAULT_VIEWER_URL: &str = "https://staffengineer.github.io/velo";
+/// File name of the document in gists and snippets.
+pub const SHARED_FILE_NAME: &str = "velo.json";
+const SHARE_TASK: &str = "share";
+/// Updating a copy that was deleted remotely can't succeed, sharing again uploads a new one.
+const UPDATE_FAILED_HINT: &str = "revoke the share to upload a new copy";
+
+pub type ShareResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+/// Receives the uploaded document.
+pub type ShareCallback = Box<dyn FnOnce(ShareResult<RemoteDocument>) + Send>;
+pub type RevokeCallback = Box<dyn FnOnce(ShareResult<()>) + Send>;
+
+/// Document uploaded by a share provider.
+#[derive(Debug, PartialEq)]
+pub struct RemoteDocument {
+    /// Provider specific id used to update or delete the document, e.g. gist id
+    pub id: String,
+    /// URL the document can be downloaded from, stays the same after updates
+    pub url: String,
+}
+
+pub struct SharedDocument {
+    pub id: ReflectableUuid,
+    /// Left out for encrypted documents, the name would give away what they are about
+    pub name: Option<String>,
+    /// Document serialized to json
+    pub contents: String,
+}
+
+impl SharedDocument {
+    /// Gist description or snippet title.
+    pub fn title(&self) -> String {
+        match &self.name {
+            Some(name) => format!("Velo Document: {}", name),
+            None => "Velo Document".to_string(),
+        }
+    }
+}
+
+/// Serializes the document for upload, encrypted with the key if there is one.
+pub fn shared_document(doc: &Doc, key: Option<&str>) -> Result<SharedDocument, String> {
+    let contents = serde_json::to_string_pretty(doc)
+        .map_err(|e| format!("Failed to serialize document: {}", e))?;
+    let (name, contents) = match key {
+        Some(key) => (
+            None,
+            encrypt_document(&contents, key)
+                .map_err(|e| format!("Failed to encrypt document: {}", e))?,
+        ),
+        None => (Some(doc.name.clone()), contents),
+    };
+    Ok(SharedDocument {
+        id: doc.id,
+        name,
+        contents,
+    })
+}
+
+/// Place documents are uploaded to, so they can be opened in the viewer by URL.
+pub trait ShareProvider: Send + Sync {
+    fn share(&self, doc: SharedDocument, on_done: ShareCallback);
+    /// Replaces the contents of a document shared before, links to it keep working.
+    fn update(&self, remote_id: &str, doc: SharedDocument, on_done: ShareCallback);
+    /// Deletes the remote copy, links to it stop working.
+    fn revoke(&self, remote_id: &str, on_done: RevokeCallback);
+}
+
+pub struct ShareState {
+    pub provider: Arc<dyn ShareProvider>,
+    /// Shared documents are opened with `<viewer_url>?document=<document url>`
+    pub viewer_url: String,
+    /// Upload encrypted documents, the key is only added to the share link
+    pub encrypt: bool,
+}
+
+/// `[share]` section of `.velo.toml`.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct ShareConfig {
+    #[serde(default = "default_viewer_url")]
+    pub viewer_url: String,
+    #[serde(default)]
+    pub encrypt: bool,
+    #[serde(flatten)]
+    pub provider: ShareProviderConfig,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "provider", rename_all = "kebab-case")]
+pub enum ShareProviderConfig {
+    GithubGist {
+        token: String,
+        /// Secret gists are not listed on the profile but anyone with the URL can open them
+        #[serde(default = "default_true")]
+        public: bool,
+    },
+    GitlabSnippet {
+        #[serde(default = "default_gitlab_url")]
+        url: String,
+        token: String,
+        /// Only public snippets can be opened in the viewer without signing in
+        #[serde(default = "default_gitlab_visibility")]
+        visibility: String,
+    },
+    /// Generic HTTP endpoint accepting PUT requests, e.g. a WebDAV folder
+    HttpPut {
+        url: String,
+        /// Where uploaded documents can be downloaded from, `url` by default
+        public_url: Option<String>,
+        username: Option<String>,
+        password: Option<String>,
+        /// Sent as a bearer token
+        token: Option<String>,
+    },
+    /// Copies documents to a folder, useful for testing
+    LocalDir { path: PathBuf },
+}
+
+fn default_viewer_url() -> String {
+    DEFAULT_VIEWER_URL.to_string()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_gitlab_url() -> String {
+    "https://gitlab.com".to_string()
+}
+
+fn default_gitlab_visibility() -> String {
+    "public".to_string()
+}
+
+impl ShareConfig {
+    /// Public gist shared with the token from `github_access_token`, used before the `[share]` section existed.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn github_gist(token: String) -> Self {
+        ShareConfig {
+            viewer_url: default_viewer_url(),
+            encrypt: false,
+            provider: ShareProviderConfig::GithubGist {
+                token,
+                public: true,
+            },
+        }
+    }
+}
+
+impl From<ShareConfig> for ShareState {
+    fn from(config: ShareConfig) -> Self {
+        let provider: Arc<dyn ShareProvider> = match config.provider {
+            ShareProviderConfig::GithubGist { token, public } => {
+                Arc::new(GistShareProvider { token, public })
+            }
+            ShareProviderConfig::GitlabSnippet {
+                url,
+                token,
+                visibility,
+            } => Arc::new(GitlabShareProvider {
+                url,
+                token,
+                visibility,
+            }),
+            ShareProviderConfig::HttpPut {
+                url,
+                public_url,
+                username,
+                password,
+                token,
+            } => Arc::new(HttpPutShareProvider {
+                url,
+                public_url,
+                username,
+                password,
+                token,
+            }),
+            ShareProviderConfig::LocalDir { path } => Arc::new(LocalDirShareProvider { path }),
+        };
+        ShareState {
+            provider,
+            viewer_url: config.viewer_url,
+            encrypt: config.encrypt,
+        }
+    }
+}
+
+/// Link opening the document in the viewer, the key of encrypted documents goes to the fragment.
+pub fn get_viewer_url(viewer_url: &str, document_url: &str, key: Option<&str>) -> String {
+    let fragment = key.map(key_fragment).unwrap_or_default();
+    format!("{}?document={}{}", viewer_url, document_url, fragment)
+}
+
+/// Sends the request, `parse` gets the uploaded document out of a successful response.
+pub fn fetch_remote_document(
+    request: ehttp::Request,
+    parse: impl FnOnce(&ehttp::Response) -> ShareResult<RemoteDocument> + Send + 'static,
+    on_done: ShareCallback,
+) {
+    ehttp::fetch(request, move |result| {
+        let remote = match result {
+            Ok(response) if response.ok => parse(&response),
+            Ok(response) => Err(format!("{} {}", response.status, response.status_text).into()),
+            Err(e) => Err(e.into()),
+        };
+        on_done(remote);
+    });
+}
+
+/// Sends the delete request, a document that is already gone counts as revoked.
+pub fn fetch_revoke(mut request: ehttp::Request, on_done: RevokeCallback) {
+    request.method = "DELETE".to_string();
+    ehttp::fetch(request, move |result| {
+        on_done(match result {
+            Ok(response) if response.ok || response.status == 404 => Ok(()),
+            Ok(response) => Err(format!("{} {}", response.status, response.status_text).into()),
+            Err(e) => Err(e.into()),
+        });
+    });
+}
+
+/// Remote copy of a document changed, `None` when it was revoked.
+pub struct ShareUpdate {
+    pub doc_id: ReflectableUuid,
+    pub shared: Option<SharedRemote>,
+    /// Uploaded version, collaborators edit the shared copy so merging it back starts from here
+    pub merge_base: Option<Doc>,
+}
+
+/// Share callbacks run outside of the ECS, results are applied by `apply_share_updates`.
+#[derive(Resource)]
+pub struct ShareChannels {
+    pub tx: Sender<ShareUpdate>,
+    pub rx: Receiver<ShareUpdate>,
+}
+
+impl Default for ShareChannels {
+    fn default() -> Self {
+        let (tx, rx) = async_channel::unbounded();
+        ShareChannels { tx, rx }
+    }
+}
+
+fn send_share_update(
+    tx: &Sender<ShareUpdate>,
+    doc_id: ReflectableUuid,
+    shared: Option<SharedRemote>,
+    merge_base: Option<Doc>,
+) {
+    let update = ShareUpdate {
+        doc_id,
+        shared,
+        merge_base,
+    };
+    if tx.try_send(update).is_err() {
+        error!("Failed to record share state of document {}", doc_id.0);
+    }
+}
+
+/// "5 minutes ago" for timestamps in milliseconds since unix epoch.
+pub fn format_share_age(now: f64, shared_at: f64) -> String {
+    let seconds = ((now - shared_at) / 1000.).max(0.) as u64;
+    let (count, unit) = match seconds {
+        0..=59 => return "just now".to_string(),
+        60..=3599 => (seconds / 60, "minute"),
+        3600..=86399 => (seconds / 3600, "hour"),
+        _ => (seconds / 86400, "day"),
+    };
+    let plural = if count == 1 { "" } else { "s" };
+    format!("{} {}{} ago", count, unit, plural)
+}
+
+/// Shares the current document, or updates its remote copy if it was shared before so earlier links keep working.
+pub fn shared_doc_handler(
+    mut app_state: ResMut<AppState>,
+    mut query: Query<&Interaction, (Changed<Interaction>, With<ShareDoc>)>,
+    mut pkv: ResMut<PkvStore>,
+    notifications: Res<NotificationChannels>,
+    share_channels: Res<ShareChannels>,
+) {
+    for interaction in &mut query.iter_mut() {
+        match *interaction {
+            Interaction::Pressed => {
+                let notifier = notifications.notifier.clone();
+                let Some(doc_id) = app_state.current_document else {
+                    continue;
+                };
+                load_doc_to_memory(doc_id, &mut app_state, &mut pkv);
+                let Some(mut current_doc) = app_state.docs.get(&doc_id).cloned() else {
+                    notifier.error(SHARE_TASK, "Document to share is not loaded");
+                    continue;
+                };
+                let Some(share) = &app_state.share else {
+                    notifier.error(
+                        SHARE_TASK,
+                        "Sharing is not configured, add a [share] section to ~/.velo.toml",
+                    );
+                    continue;
+                };
+                // remote ids and keys stay local
+                let previous = current_doc.shared.take();
+                let key = share.encrypt.then(|| {
+                    previous
+                        .as_ref()
+                        .and_then(|shared| shared.key.clone())
+                        .unwrap_or_else(generate_key)
+                });
+                let doc = match shared_document(&current_doc, key.as_deref()) {
+                    Ok(doc) => doc,
+                    Err(e) => {
+                        notifier.error(SHARE_TASK, e);
+                        continue;
+                    }
+                };
+                let viewer_url = share.viewer_url.clone();
+                let tx = share_channels.tx.clone();
+                let updating = previous.is_some();
+                let merge_base = current_doc.clone();
+                notifier.progress(
+                    SHARE_TASK,
+                    if updating {
+                        format!("Updating shared \"{}\"...", current_doc.name)
+                    } else {
+                        format!("Sharing \"{}\"...", current_doc.name)
+                    },
+                );
+                let on_done: ShareCallback = Box::new(move |result| match result {
+                    Ok(remote) => {
+                        let url = get_viewer_url(&viewer_url, &remote.url, key.as_deref());
+                        info!("Document shared: {}", url);
+                        let shared = SharedRemote {
+                            remote_id: remote.id,
+                            url: url.clone(),
+                            key,
+                            shared_at: get_timestamp(),
+                        };
+                        send_share_update(&tx, doc_id, Some(shared), Some(merge_base));
+                        let done = if updating {
+                            "Shared document updated"
+                        } else {
+                            "Document shared"
+                        };
+                        #[cfg(not(target_arch = "wasm32"))]
+                        match arboard::Clipboard::new().and_then(|mut c| c.set_text(&url)) {
+                            Ok(()) => notifier
+                                .success(SHARE_TASK, format!("{}, link copied to clipboard", done)),
+                            Err(e) => notifier.success(
+                                SHARE_TASK,
+                                format!("{}: {} (clipboard unavailable: {})", done, url, e),
+                            ),
+                        }
+                        #[cfg(target_arch = "wasm32")]
+                        notifier.success(SHARE_TASK, format!("{}: {}", done, url));
+                    }
+                    Err(e) if updating => notifier.error(
+                        SHARE_TASK,
+                        format!(
+                            "Updating shared document failed: {}, {}",
+                            e, UPDATE_FAILED_HINT
+                        ),
+                    ),
+                    Err(e) => notifier.error(SHARE_TASK, format!("Sharing failed: {}", e)),
+                });
+                match previous {
+                    Some(shared) => share.provider.update(&shared.remote_id, doc, on_done),
+                    None => share.provider.share(doc, on_done),
+                }
+            }
+            Interaction::Hovered => {}
+            Interaction::None => {}
+        }
+    }
+}
+
+/// Deletes the remote copy of the current document, links sent earlier stop working.
+pub fn revoke_share_handler(
+    app_state: Res<AppState>,
+    mut query: Query<&Interaction, (Changed<Interaction>, With<RevokeShare>)>,
+    notifications: Res<NotificationChannels>,
+    share_channels: Res<ShareChannels>,
+) {
+    for interaction in &mut query.iter_mut() {
+        match *interaction {
+            Interaction::Pressed => {
+                let notifier = notifications.notifier.clone();
+                let Some(doc_id) = app_state.current_document else {
+                    continue;
+                };
+                let Some(shared) = app_state
+                    .docs
+                    .get(&doc_id)
+                    .and_then(|doc| doc.shared.clone())
+                else {
+                    notifier.error(SHARE_TASK, "Document is not shared");
+                    continue;
+                };
+                let Some(share) = &app_state.share else {
+                    continue;
+                };
+                notifier.progress(SHARE_TASK, "Revoking shared document...");
+                let tx = share_channels.tx.clone();
+                share.provider.revoke(
+                    &shared.remote_id,
+                    Box::new(move |result| match result {
+                        Ok(()) => {
+                            send_share_update(&tx, doc_id, None, None);
+                            notifier.success(
+                                SHARE_TASK,
+                                "Shared copy deleted, the link no longer works",
+                            );
+                        }
+                        Err(e) => notifier.error(SHARE_TASK, format!("Revoking failed: {}", e)),
+                    }),
+                );
+            }
+            Interaction::Hovered => {}
+            Interaction::None => {}
+        }
+    }
+}
+
+/// Remembers remote copies in memory and in the store, without touching the modification time.
+/// The uploaded version becomes the merge base only once the upload succeeded.
+pub fn apply_share_updates(
+    mut app_state: ResMut<AppState>,
+    mut pkv: ResMut<PkvStore>,
+    share_channels: Res<ShareChannels>,
+) {
+    while let Ok(update) = share_channels.rx.try_recv() {
+        if let Some(merge_base) = &update.merge_base {
+            record_merge_base(&mut pkv, merge_base);
+        }
+        if let Some(doc) = app_state.docs.get_mut(&update.doc_id) {
+            doc.shared = update.shared.clone();
+        }
+        if let Ok(mut docs) = pkv.get::<HashMap<ReflectableUuid, Doc>>("docs") {
+            if let Some(doc) = docs.get_mut(&update.doc_id) {
+                doc.shared = update.shared;
+                if let Err(e) = pkv.set("docs", &docs) {
+                    error!("Failed to save share state: {}", e);
+                }
+            }
+        }
+    }
+}
+
+/// Tooltip of the share button tells whether pressing it shares or updates the document.
+pub fn update_share_tooltip(
+    app_state: Res<AppState>,
+    share_buttons: Query<(&Interaction, &Children), (Changed<Interaction>, With<ShareDoc>)>,
+    mut tooltips: Query<&mut Text, With<Tooltip>>,
+) {
+    for (_, children) in share_buttons.iter() {
+        let shared = app_state
+            .current_document
+            .and_then(|doc_id| app_state.docs.get(&doc_id))
+            .and_then(|doc| doc.shared.as_ref());
+        let label = match shared {
+            Some(shared) => format!(
+                "Update Shared Document (shared {})",
+                format_share_age(get_timestamp(), shared.shared_at)
+            ),
+            None => "Share Document (copy URL to clipboard)".to_string(),
+        };
+        for child in children.iter() {
+            if let Ok(mut text) = tooltips.get_mut(*child) {
+                text.sections[0].value = label.clone();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_share_config() {
+        let config: ShareConfig = toml::from_str(
+            r#"
+            provider = "gitlab-snippet"
+            token = "secret"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config,
+            ShareConfig {
+                viewer_url: DEFAULT_VIEWER_URL.to_string(),
+                encrypt: false,
+                provider: ShareProviderConfig::GitlabSnippet {
+                    url: "https://gitlab.com".to_string(),
+                    token: "secret".to_string(),
+                    visibility: "public".to_string(),
+                },
+            }
+        );
+        let config: ShareConfig = toml::from_str(
+            r#"
+            provider = "github-gist"
+            token = "secret"
+            public = false
+            encrypt = true
+            viewer_url = "https://example.com/velo"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.viewer_url, "https://example.com/velo");
+        assert!(config.encrypt);
+        assert_eq!(
+            config.provider,
+            ShareProviderConfig::GithubGist {
+                token: "secret".to_string(),
+                public: false,
+            }
+        );
+        assert!(toml::from_str::<ShareConfig>(r#"provider = "ftp""#).is_err());
+        assert_eq!(
+            get_viewer_url(DEFAULT_VIEWER_URL, "https://example.com/velo.json", None),
+            "https://staffengineer.github.io/velo?document=https://example.com/velo.json"
+        );
+        assert_eq!(
+            get_viewer_url(
+                DEFAULT_VIEWER_URL,
+                "https://example.com/velo.json",
+                Some("abc")
+            ),
+            "https://staffengineer.github.io/velo?document=https://example.com/velo.json#key=abc"
+        );
+    }
+
+    #[test]
+    fn test_format_share_age() {
+        let now = 1_000_000_000.;
+        assert_eq!(format_share_age(now, now - 10_000.), "just now");
+        assert_eq!(format_share_age(now, now + 10_000.), "just now");
+        assert_eq!(format_share_age(now, now - 60_000.), "1 minute ago");
+        assert_eq!(format_share_age(now, now - 7_200_000.), "2 hours ago");
+        assert_eq!(format_share_age(now, now - 3. * 86_400_000.), "3 days ago");
+    }
+
+    #[test]
+    /// Only a successful upload becomes the common ancestor of later merges
+    fn test_share_update_records_merge_base() {
+        let mut app = App::new();
+        app.add_systems(Update, apply_share_updates);
+        PkvStore::new("test", "share_merge_base").clear().unwrap();
+        app.insert_resource(PkvStore::new("test", "share_merge_base"));
+        app.insert_resource(AppState::default());
+        app.init_resource::<ShareChannels>();
+        let doc = Doc {
+            id: ReflectableUuid::generate(),
+            name: "Doc".to_string(),
+            ..Default::default()
+        };
+        let tx = app.world.resource::<ShareChannels>().tx.clone();
+
+        send_share_update(&tx, doc.id, None, None);
+        app.update();
+        assert!(app
+            .world
+            .resource::<PkvStore>()
+            .get::<HashMap<ReflectableUuid, Doc>>("merge_bases")
+            .is_err());
+
+        send_share_update(&tx, doc.id, None, Some(doc.clone()));
+        app.update();
+        let bases: HashMap<ReflectableUuid, Doc> =
+            app.world.resource::<PkvStore>().get("merge_bases").unwrap();
+        assert_eq!(bases[&doc.id].name, "Doc");
+    }
+}
@@ -0,0 +1,134 @@
+use aes_gcm::aead::{Aead, KeyInit, OsRng};
+use aes_gcm::{AeadCore, Aes256Gcm, Key, Nonce};
+use base64::{engine::general_purpose, Engine};
+use serde::{Deserialize, Serialize};
+
+/// Name of the share link fragment parameter holding the key, browsers never send fragments to servers.
+pub const KEY_FRAGMENT: &str = "key";
+
+/// What gets uploaded instead of the document when sharing is encrypted.
+#[derive(Serialize, Deserialize)]
+struct EncryptedDocument {
+    /// AES-256-GCM, the only supported algorithm for now
+    velo_encrypted: u8,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// New random key encoded for the share link.
+pub fn generate_key() -> String {
+    general_purpose::URL_SAFE_NO_PAD.encode(Aes256Gcm::generate_key(OsRng))
+}
+
+fn decode_key(key: &str) -> Result<Vec<u8>, String> {
+    general_purpose::URL_SAFE_NO_PAD
+        .decode(key)
+        .ok()
+        .filter(|key| key.len() == 32)
+        .ok_or_else(|| "invalid key".to_string())
+}
+
+/// Returns the payload to upload instead of the document, the key is reused when the shared document is updated.
+pub fn encrypt_document(contents: &str, key: &str) -> Result<String, String> {
+    let key = decode_key(key)?;
+    let nonce = Aes256Gcm::generate_nonce(OsRng);
+    let ciphertext = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key))
+        .encrypt(&nonce, contents.as_bytes())
+        .map_err(|e| e.to_string())?;
+    let payload = EncryptedDocument {
+        velo_encrypted: 1,
+        nonce: general_purpose::STANDARD.encode(nonce),
+        ciphertext: general_purpose::STANDARD.encode(ciphertext),
+    };
+    serde_json::to_string(&payload).map_err(|e| e.to_string())
+}
+
+pub fn is_encrypted(payload: &str) -> bool {
+    serde_json::from_str::<EncryptedDocument>(payload).is_ok()
+}
+
+/// Returns the document json, fails when the key doesn't match or the payload was tampered with.
+pub fn decrypt_document(payload: &str, key: &str) -> Result<String, String> {
+    let payload: EncryptedDocument =
+        serde_json::from_str(payload).map_err(|e| format!("invalid encrypted document: {}", e))?;
+    let key = decode_key(key)?;
+    let nonce = general_purpose::STANDARD
+        .decode(&payload.nonce)
+        .ok()
+        .filter(|nonce| nonce.len() == 12)
+        .ok_or("invalid nonce")?;
+    let ciphertext = general_purpose::STANDARD
+        .decode(&payload.ciphertext)
+        .map_err(|e| e.to_string())?;
+    let contents = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key))
+        .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+        .map_err(|_| "wrong key or corrupted document")?;
+    String::from_utf8(contents).map_err(|e| e.to_string())
+}
+
+/// Share link fragment carrying the key, e.g. `#key=...`.
+pub fn key_fragment(key: &str) -> String {
+    format!("#{}={}", KEY_FRAGMENT, key)
+}
+
+/// Splits `<url>#key=<key>` into the URL and the key.
+pub fn split_key_fragment(url: &str) -> (&str, Option<&str>) {
+    match url.split_once('#') {
+        Some((url, fragment)) => (url, key_from_fragment(fragment)),
+        None => (url, None),
+    }
+}
+
+/// Finds the key in a URL fragment without the leading `#`.
+pub fn key_from_fragment(fragment: &str) -> Option<&str> {
+    fragment
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(name, _)| *name == KEY_FRAGMENT)
+        .map(|(_, key)| key)
+        .filter(|key| !key.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_document() {
+        let key = generate_key();
+        let payload = encrypt_document(r#"{"name": "Internal"}"#, &key).unwrap();
+        assert!(is_encrypted(&payload));
+        assert!(!payload.contains("Internal"));
+        assert!(!is_encrypted(r#"{"name": "Internal"}"#));
+        assert_eq!(
+            decrypt_document(&payload, &key).unwrap(),
+            r#"{"name": "Internal"}"#
+        );
+
+        assert_ne!(
+            encrypt_document(r#"{"name": "Internal"}"#, &key).unwrap(),
+            payload
+        );
+        assert!(decrypt_document(&payload, &generate_key()).is_err());
+        assert!(decrypt_document(&payload, "short").is_err());
+        assert!(encrypt_document("", "short").is_err());
+    }
+
+    #[test]
+    fn test_split_key_fragment() {
+        assert_eq!(
+            split_key_fragment("https://example.com/velo.json#key=abc"),
+            ("https://example.com/velo.json", Some("abc"))
+        );
+        assert_eq!(
+            split_key_fragment("https://example.com/velo.json#x=1&key=abc"),
+            ("https://example.com/velo.json", Some("abc"))
+        );
+        assert_eq!(
+            split_key_fragment("https://example.com/velo.json"),
+            ("https://example.com/velo.json", None)
+        );
+        assert_eq!(key_from_fragment("key="), None);
+        assert_eq!(key_fragment("abc"), "#key=abc");
+    }
+}
@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use super::{
+    fetch_remote_document, fetch_revoke, RemoteDocument, RevokeCallback, ShareCallback,
+    ShareProvider, ShareResult, SharedDocument, SHARED_FILE_NAME,
+};
+
+const GISTS_URL: &str = "https://api.github.com/gists";
+
+#[derive(Serialize)]
+struct GistFile {
+    content: String,
+}
+
+#[derive(Serialize)]
+struct GistRequest {
+    description: String,
+    /// Can't be changed after the gist is created
+    #[serde(skip_serializing_if = "Option::is_none")]
+    public: Option<bool>,
+    files: HashMap<String, GistFile>,
+}
+
+/// Shares documents as GitHub gists, the token needs the "gist" scope.
+pub struct GistShareProvider {
+    pub token: String,
+    pub public: bool,
+}
+
+impl GistShareProvider {
+    fn request(&self, method: &str, url: String, body: Option<GistRequest>) -> ehttp::Request {
+        let body = body
+            .map(|body| serde_json::to_string_pretty(&body).unwrap())
+            .unwrap_or_default();
+        let mut request = ehttp::Request::post(url, body);
+        request.method = method.to_string();
+        request.headers.insert(
+            "Accept".to_string(),
+            "application/vnd.github.v3+json".to_string(),
+        );
+        request
+            .headers
+            .insert("Authorization".to_string(), format!("token {}", self.token));
+        request
+    }
+
+    fn body(&self, doc: SharedDocument, public: Option<bool>) -> GistRequest {
+        let description = doc.title();
+        let mut files = HashMap::new();
+        files.insert(
+            SHARED_FILE_NAME.to_string(),
+            GistFile {
+                content: doc.contents,
+            },
+        );
+        GistRequest {
+            description,
+            public,
+            files,
+        }
+    }
+}
+
+/// Raw URLs of gist files point to a revision, without it they always serve the latest one.
+fn latest_revision_url(raw_url: &str) -> String {
+    let parts: Vec<&str> = raw_url.split('/').collect();
+    match parts.iter().position(|part| *part == "raw") {
+        Some(raw) if raw + 2 < parts.len() => {
+            let mut parts = parts.clone();
+            parts.remove(raw + 1);
+            parts.join("/")
+        }
+        _ => raw_url.to_string(),
+    }
+}
+
+fn parse_gist_response(response: &str) -> ShareResult<RemoteDocument> {
+    let json: Value = serde_json::from_str(response)?;
+    let id = json["id"].as_str().ok_or("gist has no id")?;
+    let raw_url = json["files"][SHARED_FILE_NAME]["raw_url"]
+        .as_str()
+        .ok_or("gist has no raw url")?;
+    Ok(RemoteDocument {
+        id: id.to_string(),
+        url: latest_revision_url(raw_url),
+    })
+}
+
+impl ShareProvider for GistShareProvider {
+    fn share(&self, doc: SharedDocument, on_done: ShareCallback) {
+        let body = self.body(doc, Some(self.public));
+        fetch_remote_document(
+            self.request("POST", GISTS_URL.to_string(), Some(body)),
+            |response| parse_gist_response(&response.text().unwrap_or_default()),
+            on_done,
+        );
+    }
+
+    fn update(&self, remote_id: &str, doc: SharedDocument, on_done: ShareCallback) {
+        let body = self.body(doc, None);
+        fetch_remote_document(
+            self.request("PATCH", format!("{}/{}", GISTS_URL, remote_id), Some(body)),
+            |response| parse_gist_response(&response.text().unwrap_or_default()),
+            on_done,
+        );
+    }
+
+    fn revoke(&self, remote_id: &str, on_done: RevokeCallback) {
+        fetch_revoke(
+            self.request("DELETE", format!("{}/{}", GISTS_URL, remote_id), None),
+            on_done,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::{Doc, Tab};
+    use crate::ui_plugin::{generate_key, shared_document};
+    use crate::utils::ReflectableUuid;
+
+    #[test]
+    fn test_gist_request() {
+        let provider = GistShareProvider {
+            token: "secret".to_string(),
+            public: false,
+        };
+        let doc = SharedDocument {
+            id: ReflectableUuid::generate(),
+            name: Some("Plans".to_string()),
+            contents: "{}".to_string(),
+        };
+        let body = provider.body(doc, Some(false));
+        let request = provider.request("POST", GISTS_URL.to_string(), Some(body));
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.headers["Authorization"], "token secret");
+        let body: Value = serde_json::from_slice(&request.body).unwrap();
+        assert_eq!(body["public"], false);
+        assert_eq!(body["files"]["velo.json"]["content"], "{}");
+
+        let doc = SharedDocument {
+            id: ReflectableUuid::generate(),
+            name: Some("Plans".to_string()),
+            contents: "{}".to_string(),
+        };
+        let update = provider.body(doc, None);
+        let body: Value = serde_json::to_value(update).unwrap();
+        assert!(body.get("public").is_none());
+
+        let response = r#"{"id": "abc", "files": {"velo.json": {"raw_url": "https://gist.githubusercontent.com/user/abc/raw/0123/velo.json"}}}"#;
+        assert_eq!(
+            parse_gist_response(response).unwrap(),
+            RemoteDocument {
+                id: "abc".to_string(),
+                url: "https://gist.githubusercontent.com/user/abc/raw/velo.json".to_string()
+            }
+        );
+        assert!(parse_gist_response("{}").is_err());
+    }
+
+    #[test]
+    fn test_encrypted_gist_request() {
+        let provider = GistShareProvider {
+            token: "secret".to_string(),
+            public: true,
+        };
+        let doc = Doc {
+            name: "Secret plans".to_string(),
+            tabs: vec![Tab {
+                is_active: true,
+                id: ReflectableUuid::generate(),
+                name: "Tab 1".to_string(),
+                checkpoints: [r#"{"nodes": [{"text": {"text": "launch codes"}}]}"#.to_string()]
+                    .into(),
+                z_index: 1.,
+                slides: vec![],
+            }],
+            ..Default::default()
+        };
+        let shared = shared_document(&doc, Some(&generate_key())).unwrap();
+        let body = provider.body(shared, Some(true));
+        let request = provider.request("POST", GISTS_URL.to_string(), Some(body));
+        let body = String::from_utf8(request.body).unwrap();
+        assert!(body.contains("\"description\": \"Velo Document\""));
+        assert!(!body.contains("Secret plans"));
+        assert!(!body.contains("launch codes"));
+    }
+}
@@ -0,0 +1,157 @@
+use serde_json::{json, Value};
+
+use super::{
+    fetch_remote_document, fetch_revoke, RemoteDocument, RevokeCallback, ShareCallback,
+    ShareProvider, ShareResult, SharedDocument, SHARED_FILE_NAME,
+};
+
+/// Shares documents as snippets on gitlab.com or a self-hosted GitLab, the token needs the "api" scope.
+pub struct GitlabShareProvider {
+    pub url: String,
+    pub token: String,
+    /// "public", "internal" or "private"
+    pub visibility: String,
+}
+
+impl GitlabShareProvider {
+    fn request(&self, method: &str, path: &str, body: Option<Value>) -> ehttp::Request {
+        let body = body.map(|body| body.to_string()).unwrap_or_default();
+        let mut request = ehttp::Request::post(
+            format!("{}/api/v4/snippets{}", self.url.trim_end_matches('/'), path),
+            body,
+        );
+        request.method = method.to_string();
+        request
+            .headers
+            .insert("Content-Type".to_string(), "application/json".to_string());
+        request
+            .headers
+            .insert("PRIVATE-TOKEN".to_string(), self.token.clone());
+        request
+    }
+
+    /// Snippets are created with a visibility, updates replace the file in place.
+    fn body(&self, doc: SharedDocument, create: bool) -> Value {
+        let title = doc.title();
+        if create {
+            json!({
+                "title": title,
+                "visibility": self.visibility,
+                "files": [{ "file_path": SHARED_FILE_NAME, "content": doc.contents }],
+            })
+        } else {
+            json!({
+                "title": title,
+                "files": [{ "action": "update", "file_path": SHARED_FILE_NAME, "content": doc.contents }],
+            })
+        }
+    }
+}
+
+fn parse_snippet_response(response: &str) -> ShareResult<RemoteDocument> {
+    let json: Value = serde_json::from_str(response)?;
+    let id = match &json["id"] {
+        Value::Number(id) => id.to_string(),
+        _ => return Err("snippet has no id".into()),
+    };
+    let url = json["files"][0]["raw_url"]
+        .as_str()
+        .or(json["raw_url"].as_str())
+        .ok_or("snippet has no raw url")?;
+    Ok(RemoteDocument {
+        id,
+        url: url.to_string(),
+    })
+}
+
+impl ShareProvider for GitlabShareProvider {
+    fn share(&self, doc: SharedDocument, on_done: ShareCallback) {
+        let body = self.body(doc, true);
+        fetch_remote_document(
+            self.request("POST", "", Some(body)),
+            |response| parse_snippet_response(&response.text().unwrap_or_default()),
+            on_done,
+        );
+    }
+
+    fn update(&self, remote_id: &str, doc: SharedDocument, on_done: ShareCallback) {
+        let body = self.body(doc, false);
+        fetch_remote_document(
+            self.request("PUT", &format!("/{}", remote_id), Some(body)),
+            |response| parse_snippet_response(&response.text().unwrap_or_default()),
+            on_done,
+        );
+    }
+
+    fn revoke(&self, remote_id: &str, on_done: RevokeCallback) {
+        fetch_revoke(
+            self.request("DELETE", &format!("/{}", remote_id), None),
+            on_done,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::{Doc, Tab};
+    use crate::ui_plugin::{generate_key, shared_document};
+    use crate::utils::ReflectableUuid;
+
+    #[test]
+    fn test_snippet_request() {
+        let provider = GitlabShareProvider {
+            url: "https://gitlab.example.com/".to_string(),
+            token: "secret".to_string(),
+            visibility: "internal".to_string(),
+        };
+        let request = provider.request("PUT", "/42", Some(json!({ "title": "Plans" })));
+        assert_eq!(request.method, "PUT");
+        assert_eq!(request.url, "https://gitlab.example.com/api/v4/snippets/42");
+        assert_eq!(request.headers["PRIVATE-TOKEN"], "secret");
+        let body: Value = serde_json::from_slice(&request.body).unwrap();
+        assert_eq!(body["title"], "Plans");
+
+        let response = r#"{"id": 42, "raw_url": "https://gitlab.example.com/-/snippets/42/raw",
+            "files": [{"raw_url": "https://gitlab.example.com/-/snippets/42/raw/main/velo.json"}]}"#;
+        assert_eq!(
+            parse_snippet_response(response).unwrap(),
+            RemoteDocument {
+                id: "42".to_string(),
+                url: "https://gitlab.example.com/-/snippets/42/raw/main/velo.json".to_string()
+            }
+        );
+        assert!(parse_snippet_response("[]").is_err());
+    }
+
+    #[test]
+    fn test_encrypted_snippet_request() {
+        let provider = GitlabShareProvider {
+            url: "https://gitlab.com".to_string(),
+            token: "secret".to_string(),
+            visibility: "public".to_string(),
+        };
+        let doc = Doc {
+            name: "Secret plans".to_string(),
+            tabs: vec![Tab {
+                is_active: true,
+                id: ReflectableUuid::generate(),
+                name: "Tab 1".to_string(),
+                checkpoints: [r#"{"nodes": [{"text": {"text": "launch codes"}}]}"#.to_string()]
+                    .into(),
+                z_index: 1.,
+                slides: vec![],
+            }],
+            ..Default::default()
+        };
+        let key = generate_key();
+        for create in [true, false] {
+            let shared = shared_document(&doc, Some(&key)).unwrap();
+            let request = provider.request("POST", "", Some(provider.body(shared, create)));
+            let body = String::from_utf8(request.body).unwrap();
+            assert!(body.contains("\"title\":\"Velo Document\""));
+            assert!(!body.contains("Secret plans"));
+            assert!(!body.contains("launch codes"));
+        }
+    }
+}
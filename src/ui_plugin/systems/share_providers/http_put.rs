@@ -0,0 +1,107 @@
+use base64::{engine::general_purpose, Engine};
+
+use super::{
+    fetch_remote_document, fetch_revoke, RemoteDocument, RevokeCallback, ShareCallback,
+    ShareProvider, SharedDocument,
+};
+
+/// Uploads documents with `PUT <url>/<document id>.json`, works with WebDAV servers and object storage.
+pub struct HttpPutShareProvider {
+    pub url: String,
+    pub public_url: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub token: Option<String>,
+}
+
+impl HttpPutShareProvider {
+    fn file_name(doc: &SharedDocument) -> String {
+        format!("{}.json", doc.id.0)
+    }
+
+    fn request(&self, method: &str, file_name: &str, body: String) -> ehttp::Request {
+        let url = format!("{}/{}", self.url.trim_end_matches('/'), file_name);
+        let mut request = ehttp::Request::post(url, body);
+        request.method = method.to_string();
+        request
+            .headers
+            .insert("Content-Type".to_string(), "application/json".to_string());
+        if let Some(token) = &self.token {
+            request
+                .headers
+                .insert("Authorization".to_string(), format!("Bearer {}", token));
+        } else if let Some(username) = &self.username {
+            let credentials = format!(
+                "{}:{}",
+                username,
+                self.password.as_deref().unwrap_or_default()
+            );
+            request.headers.insert(
+                "Authorization".to_string(),
+                format!("Basic {}", general_purpose::STANDARD.encode(credentials)),
+            );
+        }
+        request
+    }
+
+    fn document_url(&self, file_name: &str) -> String {
+        let base = self.public_url.as_ref().unwrap_or(&self.url);
+        format!("{}/{}", base.trim_end_matches('/'), file_name)
+    }
+
+    /// The file name doubles as the remote id, so updates overwrite the same file.
+    fn upload(&self, file_name: String, doc: SharedDocument, on_done: ShareCallback) {
+        let remote = RemoteDocument {
+            url: self.document_url(&file_name),
+            id: file_name,
+        };
+        fetch_remote_document(
+            self.request("PUT", &remote.id, doc.contents),
+            move |_| Ok(remote),
+            on_done,
+        );
+    }
+}
+
+impl ShareProvider for HttpPutShareProvider {
+    fn share(&self, doc: SharedDocument, on_done: ShareCallback) {
+        self.upload(Self::file_name(&doc), doc, on_done);
+    }
+
+    fn update(&self, remote_id: &str, doc: SharedDocument, on_done: ShareCallback) {
+        self.upload(remote_id.to_string(), doc, on_done);
+    }
+
+    fn revoke(&self, remote_id: &str, on_done: RevokeCallback) {
+        fetch_revoke(self.request("DELETE", remote_id, String::new()), on_done);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_http_put_request() {
+        let mut provider = HttpPutShareProvider {
+            url: "https://dav.example.com/velo/".to_string(),
+            public_url: Some("https://example.com/shared".to_string()),
+            username: Some("user".to_string()),
+            password: Some("pass".to_string()),
+            token: None,
+        };
+        assert_eq!(
+            provider.document_url("doc.json"),
+            "https://example.com/shared/doc.json"
+        );
+        let request = provider.request("PUT", "doc.json", "{}".to_string());
+        assert_eq!(request.method, "PUT");
+        assert_eq!(request.url, "https://dav.example.com/velo/doc.json");
+        assert_eq!(request.headers["Authorization"], "Basic dXNlcjpwYXNz");
+        assert_eq!(request.body, b"{}");
+
+        provider.token = Some("secret".to_string());
+        let request = provider.request("DELETE", "doc.json", String::new());
+        assert_eq!(request.headers["Authorization"], "Bearer secret");
+    }
+}
@@ -0,0 +1,89 @@
+use std::path::PathBuf;
+
+use super::{
+    RemoteDocument, RevokeCallback, ShareCallback, ShareProvider, ShareResult, SharedDocument,
+};
+
+/// "Shares" documents by writing them to a folder, for testing and for folders synced by other tools.
+pub struct LocalDirShareProvider {
+    pub path: PathBuf,
+}
+
+impl LocalDirShareProvider {
+    fn write(&self, file_name: String, doc: &SharedDocument) -> ShareResult<RemoteDocument> {
+        std::fs::create_dir_all(&self.path)?;
+        let path = self.path.join(&file_name);
+        std::fs::write(&path, &doc.contents)?;
+        Ok(RemoteDocument {
+            id: file_name,
+            url: format!("file://{}", path.canonicalize()?.display()),
+        })
+    }
+
+    fn remove(&self, file_name: &str) -> ShareResult<()> {
+        match std::fs::remove_file(self.path.join(file_name)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl ShareProvider for LocalDirShareProvider {
+    fn share(&self, doc: SharedDocument, on_done: ShareCallback) {
+        on_done(self.write(format!("{}.json", doc.id.0), &doc));
+    }
+
+    fn update(&self, remote_id: &str, doc: SharedDocument, on_done: ShareCallback) {
+        on_done(self.write(remote_id.to_string(), &doc));
+    }
+
+    fn revoke(&self, remote_id: &str, on_done: RevokeCallback) {
+        on_done(self.remove(remote_id));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use super::*;
+    use crate::utils::ReflectableUuid;
+
+    #[test]
+    fn test_local_dir_share() {
+        let dir = tempfile::tempdir().unwrap();
+        let provider = LocalDirShareProvider {
+            path: dir.path().join("shared"),
+        };
+        let doc = SharedDocument {
+            id: ReflectableUuid::generate(),
+            name: Some("Plans".to_string()),
+            contents: "{}".to_string(),
+        };
+        let (tx, rx) = mpsc::channel();
+        provider.share(doc, Box::new(move |result| tx.send(result).unwrap()));
+        let remote = rx.recv().unwrap().unwrap();
+        assert!(remote.url.starts_with("file://") && remote.url.ends_with(".json"));
+        let path = provider.path.join(&remote.id);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "{}");
+
+        let doc = SharedDocument {
+            id: ReflectableUuid::generate(),
+            name: Some("Plans".to_string()),
+            contents: "[]".to_string(),
+        };
+        let (tx, rx) = mpsc::channel();
+        provider.update(
+            &remote.id,
+            doc,
+            Box::new(move |result| tx.send(result).unwrap()),
+        );
+        assert_eq!(rx.recv().unwrap().unwrap(), remote);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "[]");
+
+        let (tx, rx) = mpsc::channel();
+        provider.revoke(&remote.id, Box::new(move |result| tx.send(result).unwrap()));
+        assert!(rx.recv().unwrap().is_ok());
+        assert!(!path.exists());
+    }
+}
@@ -92,6 +92,7 @@ pub fn add_tab_handler(
                     checkpoints: VecDeque::new(),
                     is_active: true,
                     z_index: 1.,
+                    slides: vec![],
                 });
                 commands.insert_resource(LoadDocRequest {
                     doc_id: app_state.current_document.unwrap(),
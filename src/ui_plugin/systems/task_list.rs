@@ -0,0 +1,81 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_markdown::count_tasks;
+use serde_json::Value;
+
+use super::ui_helpers::TabTaskCount;
+use crate::resources::AppState;
+use crate::utils::ReflectableUuid;
+
+/// "3/5 done" for checked and all task list items, nothing when there are no tasks.
+pub fn task_progress_text((done, total): (usize, usize)) -> Option<String> {
+    (total > 0).then(|| format!("{}/{} done", done, total))
+}
+
+/// Tasks in every note saved in the checkpoint.
+pub fn count_checkpoint_tasks(checkpoint: &str) -> (usize, usize) {
+    let Ok(json) = serde_json::from_str::<Value>(checkpoint) else {
+        return (0, 0);
+    };
+    let Some(nodes) = json["nodes"].as_array() else {
+        return (0, 0);
+    };
+    nodes
+        .iter()
+        .filter_map(|node| node["text"]["text"].as_str())
+        .map(count_tasks)
+        .fold((0, 0), |acc, tasks| (acc.0 + tasks.0, acc.1 + tasks.1))
+}
+
+/// Shows aggregated task counts next to tab names, counted from the latest checkpoint of each tab.
+pub fn update_tab_task_counts(
+    app_state: Res<AppState>,
+    mut counts: Query<(&mut Text, Ref<TabTaskCount>)>,
+    added_counts: Query<(), Added<TabTaskCount>>,
+    mut last_checkpoints: Local<HashMap<ReflectableUuid, String>>,
+) {
+    if !app_state.is_changed() && added_counts.is_empty() {
+        return;
+    }
+    let Some(doc) = app_state
+        .current_document
+        .and_then(|doc_id| app_state.docs.get(&doc_id))
+    else {
+        return;
+    };
+    for (mut text, task_count) in counts.iter_mut() {
+        let Some(tab) = doc.tabs.iter().find(|tab| tab.id == task_count.id) else {
+            continue;
+        };
+        let checkpoint = tab.checkpoints.back();
+        if !task_count.is_added() && last_checkpoints.get(&tab.id) == checkpoint {
+            continue;
+        }
+        let checkpoint = checkpoint.cloned().unwrap_or_default();
+        text.sections[0].value =
+            task_progress_text(count_checkpoint_tasks(&checkpoint)).unwrap_or_default();
+        last_checkpoints.insert(tab.id, checkpoint);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_checkpoint_tasks() {
+        let checkpoint = serde_json::json!({
+            "nodes": [
+                { "text": { "text": "- [x] one\n- [ ] two" } },
+                { "text": { "text": "no tasks" } },
+                { "text": { "text": "1. [X] three" } },
+            ]
+        })
+        .to_string();
+        let tasks = count_checkpoint_tasks(&checkpoint);
+        assert_eq!(tasks, (2, 3));
+        assert_eq!(task_progress_text(tasks), Some("2/3 done".to_string()));
+        assert_eq!(task_progress_text(count_checkpoint_tasks("")), None);
+    }
+}
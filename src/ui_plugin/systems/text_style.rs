@@ -0,0 +1,405 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use bevy_cosmic_edit::{
+    cosmic_edit_set_text, get_cosmic_text, CosmicEdit, CosmicFont, CosmicText, CosmicTextPos,
+};
+use cosmic_text::{Align, Attrs, AttrsOwned, Edit, Family, Metrics, Weight};
+
+use super::ui_helpers::{
+    spawn_modal, BevyMarkdownView, ButtonAction, ButtonTypes, MainPanel, RawText, VeloShape,
+};
+use super::{ModalAction, NodeTextStyle, TextAlign, TextPos, UiState, VerticalAlign};
+use crate::resources::FontSystemState;
+use crate::themes::{NodeColor, Theme};
+use crate::utils::{bevy_color_to_cosmic, get_markdown_lines, ReflectableUuid};
+
+impl NodeTextStyle {
+    /// Alignment set on the node replaces the `TextPos` preset.
+    pub fn has_layout(&self) -> bool {
+        self.align.is_some() || self.vertical_align.is_some()
+    }
+
+    /// Fills the alignment missing in a partial layout from the `TextPos` preset.
+    pub fn fill_layout(&mut self, text_pos: &TextPos) {
+        if !self.has_layout() {
+            return;
+        }
+        self.align.get_or_insert(match text_pos {
+            TextPos::Center => TextAlign::Center,
+            TextPos::TopLeft => TextAlign::Left,
+        });
+        self.vertical_align.get_or_insert(match text_pos {
+            TextPos::Center => VerticalAlign::Middle,
+            TextPos::TopLeft => VerticalAlign::Top,
+        });
+    }
+}
+
+/// Parses text like `size: 18; font: Victor Mono; weight: bold; align: right; valign: bottom; color: #ff0000`.
+/// Keys are case insensitive, unknown keys and invalid values are skipped.
+pub fn parse_node_text_style(text: &str) -> NodeTextStyle {
+    let mut style = NodeTextStyle::default();
+    for part in text.split(';').filter(|part| !part.trim().is_empty()) {
+        let Some((key, value)) = part.split_once(':') else {
+            warn!("Text style should be `key: value`, got {}", part.trim());
+            continue;
+        };
+        let key = key.trim().to_lowercase();
+        let value = value.trim();
+        let parsed = match key.as_str() {
+            "size" => value
+                .parse::<f32>()
+                .ok()
+                .filter(|size| *size > 0.)
+                .map(|size| style.font_size = Some(size)),
+            "font" if !value.is_empty() => {
+                style.font_family = Some(value.to_string());
+                Some(())
+            }
+            "weight" => parse_font_weight(value).map(|weight| style.font_weight = Some(weight)),
+            "align" => parse_text_align(value).map(|align| style.align = Some(align)),
+            "valign" => parse_vertical_align(value).map(|align| style.vertical_align = Some(align)),
+            "color" if !value.is_empty() => {
+                style.color = Some(NodeColor::parse(value).to_string());
+                Some(())
+            }
+            _ => None,
+        };
+        if parsed.is_none() {
+            warn!("Invalid text style {}: {}", key, value);
+        }
+    }
+    style
+}
+
+/// Inverse of `parse_node_text_style`.
+pub fn format_node_text_style(style: &NodeTextStyle) -> String {
+    let mut parts = vec![];
+    if let Some(size) = style.font_size {
+        parts.push(format!("size: {}", size));
+    }
+    if let Some(family) = &style.font_family {
+        parts.push(format!("font: {}", family));
+    }
+    if let Some(weight) = style.font_weight {
+        parts.push(format!("weight: {}", weight));
+    }
+    if let Some(align) = style.align {
+        let align = match align {
+            TextAlign::Left => "left",
+            TextAlign::Center => "center",
+            TextAlign::Right => "right",
+            TextAlign::Justify => "justify",
+        };
+        parts.push(format!("align: {}", align));
+    }
+    if let Some(align) = style.vertical_align {
+        let align = match align {
+            VerticalAlign::Top => "top",
+            VerticalAlign::Middle => "middle",
+            VerticalAlign::Bottom => "bottom",
+        };
+        parts.push(format!("valign: {}", align));
+    }
+    if let Some(color) = &style.color {
+        parts.push(format!("color: {}", color));
+    }
+    parts.join("; ")
+}
+
+fn parse_font_weight(value: &str) -> Option<u16> {
+    match value.to_lowercase().as_str() {
+        "thin" => Some(100),
+        "light" => Some(300),
+        "regular" | "normal" => Some(400),
+        "medium" => Some(500),
+        "semibold" => Some(600),
+        "bold" => Some(700),
+        "black" => Some(900),
+        value => value
+            .parse::<u16>()
+            .ok()
+            .filter(|weight| (1..=1000).contains(weight)),
+    }
+}
+
+fn parse_text_align(value: &str) -> Option<TextAlign> {
+    match value.to_lowercase().as_str() {
+        "left" => Some(TextAlign::Left),
+        "center" => Some(TextAlign::Center),
+        "right" => Some(TextAlign::Right),
+        "justify" => Some(TextAlign::Justify),
+        _ => None,
+    }
+}
+
+fn parse_vertical_align(value: &str) -> Option<VerticalAlign> {
+    match value.to_lowercase().as_str() {
+        "top" => Some(VerticalAlign::Top),
+        "middle" | "center" => Some(VerticalAlign::Middle),
+        "bottom" => Some(VerticalAlign::Bottom),
+        _ => None,
+    }
+}
+
+/// Generic CSS family names map to the system fonts, anything else is a font name
+/// like the bundled `Victor Mono`.
+fn font_family(name: &str) -> Family<'_> {
+    match name.to_lowercase().as_str() {
+        "serif" => Family::Serif,
+        "sans-serif" => Family::SansSerif,
+        "monospace" => Family::Monospace,
+        "cursive" => Family::Cursive,
+        "fantasy" => Family::Fantasy,
+        _ => Family::Name(name),
+    }
+}
+
+pub fn node_text_attrs(theme: &Theme, style: &NodeTextStyle) -> AttrsOwned {
+    let family = style.font_family.as_deref().unwrap_or(&theme.font_name);
+    let color = style
+        .color
+        .as_deref()
+        .and_then(|color| NodeColor::parse(color).resolve(theme))
+        .unwrap_or(theme.font);
+    let mut attrs = Attrs::new()
+        .family(font_family(family))
+        .color(bevy_color_to_cosmic(color));
+    if let Some(weight) = style.font_weight {
+        attrs = attrs.weight(Weight(weight));
+    }
+    AttrsOwned::new(attrs)
+}
+
+/// Font size and line height, line height keeps the theme's proportion.
+pub fn node_text_metrics(theme: &Theme, style: &NodeTextStyle, is_transparent: bool) -> (f32, f32) {
+    let default_size = if is_transparent {
+        3. * theme.font_size
+    } else {
+        theme.font_size
+    };
+    let font_size = style.font_size.unwrap_or(default_size);
+    (font_size, font_size * theme.line_height / theme.font_size)
+}
+
+pub fn node_text_pos(text_pos: &TextPos, style: &NodeTextStyle) -> CosmicTextPos {
+    if style.has_layout() {
+        CosmicTextPos::TopLeft
+    } else {
+        text_pos.clone().into()
+    }
+}
+
+fn cosmic_align(align: TextAlign) -> Align {
+    match align {
+        TextAlign::Left => Align::Left,
+        TextAlign::Center => Align::Center,
+        TextAlign::Right => Align::Right,
+        TextAlign::Justify => Align::Justified,
+    }
+}
+
+pub fn text_style_handler(
+    mut commands: Commands,
+    mut interaction_query: Query<
+        (&Interaction, &ButtonAction),
+        (Changed<Interaction>, With<ButtonAction>),
+    >,
+    style_query: Query<(&RawText, &NodeTextStyle), With<RawText>>,
+    mut ui_state: ResMut<UiState>,
+    main_panel_query: Query<Entity, With<MainPanel>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut cosmic_fonts: ResMut<Assets<CosmicFont>>,
+    font_system_state: ResMut<FontSystemState>,
+    theme: Res<Theme>,
+) {
+    let window = windows.single();
+    for (interaction, button_action) in &mut interaction_query.iter_mut() {
+        if *interaction != Interaction::Pressed
+            || button_action.button_type != ButtonTypes::EditTextStyle
+        {
+            continue;
+        }
+        let Some(id) = ui_state.entity_to_edit else {
+            continue;
+        };
+        let Some((_, style)) = style_query.iter().find(|(raw_text, _)| raw_text.id == id) else {
+            continue;
+        };
+        let action = ModalAction::EditTextStyle(id, format_node_text_style(style));
+        let modal_id = ReflectableUuid::generate();
+        *ui_state = UiState::default();
+        commands.insert_resource(bevy_cosmic_edit::ActiveEditor { entity: None });
+        ui_state.modal_id = Some(modal_id);
+        let entity = spawn_modal(
+            &mut commands,
+            &theme,
+            &mut cosmic_fonts,
+            font_system_state.0.clone().unwrap(),
+            window,
+            modal_id,
+            action,
+        );
+        commands.entity(main_panel_query.single()).add_child(entity);
+    }
+}
+
+/// Rebuilds attributes and metrics of edited styles, and of all styled nodes when the theme changes.
+/// Newly spawned nodes already have their style applied.
+pub fn apply_node_text_style(
+    mut commands: Commands,
+    theme: Res<Theme>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut cosmic_fonts: ResMut<Assets<CosmicFont>>,
+    mut editors: Query<
+        (
+            Entity,
+            &mut CosmicEdit,
+            &RawText,
+            Ref<NodeTextStyle>,
+            &Parent,
+        ),
+        With<RawText>,
+    >,
+    shapes: Query<&VeloShape>,
+) {
+    let scale_factor = windows.single().scale_factor() as f32;
+    for (entity, mut cosmic_edit, raw_text, style, parent) in editors.iter_mut() {
+        let style_changed = style.is_changed() && !style.is_added();
+        let theme_changed = theme.is_changed() && *style != NodeTextStyle::default();
+        if !style_changed && !theme_changed {
+            continue;
+        }
+        let Ok(shape) = shapes.get(parent.get()) else {
+            continue;
+        };
+        let cosmic_edit = cosmic_edit.as_mut();
+        let (font_size, line_height) =
+            node_text_metrics(&theme, &style, shape.pair_color.1 == Color::NONE);
+        cosmic_edit.attrs = node_text_attrs(&theme, &style);
+        cosmic_edit.font_size = font_size;
+        cosmic_edit.line_height = line_height;
+        if style.has_layout() {
+            cosmic_edit.text_pos = CosmicTextPos::TopLeft;
+        }
+        let text = if cosmic_edit.readonly {
+            let markdown_lines =
+                get_markdown_lines(&theme, &raw_text.last_text, cosmic_edit.attrs.clone());
+            commands.entity(entity).insert(BevyMarkdownView {
+                id: raw_text.id,
+                span_metadata: markdown_lines.span_metadata,
+            });
+            CosmicText::MultiStyle(markdown_lines.lines)
+        } else {
+            CosmicText::OneStyle(get_cosmic_text(cosmic_edit.editor.buffer()))
+        };
+        let font_system = cosmic_fonts.get_mut(&cosmic_edit.font_system).unwrap();
+        let metrics = Metrics::new(font_size, line_height).scale(scale_factor);
+        cosmic_edit
+            .editor
+            .buffer_mut()
+            .set_metrics(&mut font_system.0, metrics);
+        cosmic_edit_set_text(
+            text,
+            cosmic_edit.attrs.clone(),
+            &mut cosmic_edit.editor,
+            &mut font_system.0,
+        );
+        cosmic_edit.editor.buffer_mut().set_redraw(true);
+    }
+}
+
+/// Alignment is kept on buffer lines, which are recreated whenever text is set or split,
+/// so it's reapplied every frame. Vertical alignment moves the editor sprite inside the node.
+pub fn sync_node_text_layout(
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut editors: Query<(&mut CosmicEdit, &NodeTextStyle, &mut Transform), With<RawText>>,
+) {
+    let scale_factor = windows.single().scale_factor() as f32;
+    for (mut cosmic_edit, style, mut transform) in editors.iter_mut() {
+        let align = style
+            .has_layout()
+            .then(|| cosmic_align(style.align.unwrap_or(TextAlign::Left)));
+        let buffer = cosmic_edit.editor.buffer();
+        if buffer.lines.iter().any(|line| line.align() != align) {
+            let buffer = cosmic_edit.editor.buffer_mut();
+            for line in buffer.lines.iter_mut() {
+                line.set_align(align);
+            }
+            buffer.set_redraw(true);
+            // wait for the lines to be laid out again before measuring
+            continue;
+        }
+        let buffer = cosmic_edit.editor.buffer();
+        // lines below the node might never be laid out, only wait for a fresh buffer
+        if buffer.lines.iter().all(|line| line.layout_opt().is_none()) {
+            continue;
+        }
+        let offset = match style.vertical_align {
+            Some(vertical_align) if style.has_layout() && cosmic_edit.bg_image.is_none() => {
+                let text_height = buffer.layout_runs().count() as f32
+                    * buffer.metrics().line_height
+                    / scale_factor;
+                let free_space = (cosmic_edit.height - text_height).max(0.);
+                match vertical_align {
+                    VerticalAlign::Top => 0.,
+                    VerticalAlign::Middle => free_space / 2.,
+                    VerticalAlign::Bottom => free_space,
+                }
+            }
+            _ => 0.,
+        };
+        if transform.translation.y != -offset {
+            transform.translation.y = -offset;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_format_node_text_style() {
+        let style = parse_node_text_style(
+            "Size: 18; font: Victor Mono; weight: semibold; align: Right; valign: bottom; color: #ff0000",
+        );
+        assert_eq!(style.font_size, Some(18.));
+        assert_eq!(style.font_family.as_deref(), Some("Victor Mono"));
+        assert_eq!(style.font_weight, Some(600));
+        assert_eq!(style.align, Some(TextAlign::Right));
+        assert_eq!(style.vertical_align, Some(VerticalAlign::Bottom));
+        assert_eq!(style.color.as_deref(), Some("#ff0000ff"));
+        assert_eq!(
+            format_node_text_style(&style),
+            "size: 18; font: Victor Mono; weight: 600; align: right; valign: bottom; color: #ff0000ff"
+        );
+        assert_eq!(
+            parse_node_text_style(&format_node_text_style(&style)),
+            style
+        );
+        assert_eq!(
+            parse_node_text_style("size: -1; weight: heavy; align: up; color: font"),
+            NodeTextStyle {
+                color: Some("font".to_string()),
+                ..default()
+            }
+        );
+        assert_eq!(parse_node_text_style(" ; "), NodeTextStyle::default());
+    }
+
+    #[test]
+    fn test_fill_layout() {
+        let mut style = NodeTextStyle {
+            vertical_align: Some(VerticalAlign::Bottom),
+            ..default()
+        };
+        style.fill_layout(&TextPos::Center);
+        assert_eq!(style.align, Some(TextAlign::Center));
+        assert_eq!(style.vertical_align, Some(VerticalAlign::Bottom));
+
+        let mut style = NodeTextStyle::default();
+        style.fill_layout(&TextPos::TopLeft);
+        assert_eq!(style, NodeTextStyle::default());
+    }
+}
@@ -0,0 +1,235 @@
+use bevy::{core_pipeline::clear_color::ClearColorConfig, prelude::*};
+use bevy_cosmic_edit::{cosmic_edit_set_text, get_cosmic_text, CosmicEdit, CosmicFont, CosmicText};
+use bevy_pkv::PkvStore;
+use bevy_prototype_lyon::prelude::{Fill, Stroke};
+use cosmic_text::{Edit, FamilyOwned};
+
+use super::ui_helpers::{
+    BevyMarkdownView, ChangeTheme, Drawing, GenericButton, RawText, ThemeButton, ThemePicker,
+    VeloShape,
+};
+use super::{NodeType, UiState};
+use crate::canvas::arrow::components::ArrowMeta;
+use crate::components::MainCamera;
+use crate::themes::{remap_color, theme_color_map, AvailableThemes, NodeColor, Theme};
+use crate::utils::{bevy_color_to_cosmic, get_markdown_lines, get_theme_key, UserPreferences};
+
+pub fn change_theme(
+    mut commands: Commands,
+    change_theme_button: Query<
+        (&Interaction, &GlobalTransform, &Node),
+        (Changed<Interaction>, With<ChangeTheme>),
+    >,
+    theme_picker: Query<Entity, With<ThemePicker>>,
+    themes: Res<AvailableThemes>,
+    theme: Res<Theme>,
+    pkv: Res<PkvStore>,
+) {
+    for (interaction, transform, node) in change_theme_button.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        if let Ok(entity) = theme_picker.get_single() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        let position = transform.translation().truncate() - node.size() / 2.;
+        let current_theme = get_theme_key(&pkv);
+        let picker = commands
+            .spawn((
+                NodeBundle {
+                    z_index: ZIndex::Global(1),
+                    background_color: theme.menu_bg.into(),
+                    border_color: theme.btn_border.into(),
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        flex_direction: FlexDirection::Column,
+                        left: Val::Px(position.x),
+                        top: Val::Px(position.y + node.size().y + 5.),
+                        border: UiRect::all(Val::Px(1.)),
+                        padding: UiRect::all(Val::Px(3.)),
+                        ..default()
+                    },
+                    ..default()
+                },
+                ThemePicker,
+            ))
+            .id();
+        for (name, _) in themes.0.iter() {
+            let label = if *name == current_theme {
+                format!("* {}", name)
+            } else {
+                name.clone()
+            };
+            let button = commands
+                .spawn((
+                    ButtonBundle {
+                        background_color: theme.menu_bg.into(),
+                        style: Style {
+                            padding: UiRect::all(Val::Px(5.)),
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    GenericButton,
+                    ThemeButton { name: name.clone() },
+                ))
+                .with_children(|builder| {
+                    builder.spawn(TextBundle::from_section(
+                        label,
+                        TextStyle {
+                            font_size: 16.,
+                            color: theme.font,
+                            ..default()
+                        },
+                    ));
+                })
+                .id();
+            commands.entity(picker).add_child(button);
+        }
+    }
+}
+
+pub fn theme_button_click(
+    mut commands: Commands,
+    interaction_query: Query<(&Interaction, &ThemeButton), Changed<Interaction>>,
+    theme_picker: Query<Entity, With<ThemePicker>>,
+    themes: Res<AvailableThemes>,
+    mut theme: ResMut<Theme>,
+    mut pkv: ResMut<PkvStore>,
+) {
+    for (interaction, theme_button) in interaction_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        if let Some(new_theme) = themes.get(&theme_button.name) {
+            *theme = new_theme.clone();
+            let _ = pkv.set(
+                "user_preferences",
+                &UserPreferences {
+                    theme_name: Some(theme_button.name.clone()),
+                },
+            );
+        }
+        for entity in theme_picker.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+/// Re-colors existing entities when `Theme` resource changes.
+/// Canvas entities get colors of the new theme directly, colors of UI elements
+/// are replaced according to `theme_color_map`.
+pub fn apply_theme(
+    mut commands: Commands,
+    theme: Res<Theme>,
+    mut previous_theme: Local<Option<Theme>>,
+    ui_state: Res<UiState>,
+    mut ui_colors: Query<(Option<&mut BackgroundColor>, Option<&mut BorderColor>)>,
+    mut texts: Query<&mut Text>,
+    mut shapes: Query<(&mut VeloShape, &mut Fill, &mut Stroke)>,
+    mut arrows: Query<&mut Stroke, (With<ArrowMeta>, Without<VeloShape>)>,
+    mut drawings: Query<
+        (&mut Drawing<(String, Color)>, &mut Stroke),
+        (Without<ArrowMeta>, Without<VeloShape>),
+    >,
+    mut cameras: Query<&mut Camera2d, With<MainCamera>>,
+    mut editors: Query<(Entity, &mut CosmicEdit, Option<&RawText>)>,
+    mut cosmic_fonts: ResMut<Assets<CosmicFont>>,
+) {
+    if !theme.is_changed() {
+        return;
+    }
+    let Some(old_theme) = previous_theme.replace(theme.clone()) else {
+        return;
+    };
+    let color_map = theme_color_map(&old_theme, &theme);
+
+    for (background_color, border_color) in ui_colors.iter_mut() {
+        if let Some(mut background_color) = background_color {
+            background_color.0 = remap_color(&color_map, background_color.0);
+        }
+        if let Some(mut border_color) = border_color {
+            border_color.0 = remap_color(&color_map, border_color.0);
+        }
+    }
+    for mut text in texts.iter_mut() {
+        for section in text.sections.iter_mut() {
+            section.style.color = remap_color(&color_map, section.style.color);
+        }
+    }
+
+    for (mut shape, mut fill, mut stroke) in shapes.iter_mut() {
+        if let Some(color) = NodeColor::parse(&shape.pair_color.0).resolve(&theme) {
+            shape.pair_color.1 = color;
+            fill.color = color;
+        }
+        let has_border = shape.node_type != NodeType::Paper;
+        stroke.color = if ui_state.entity_to_edit == Some(shape.id) {
+            theme.selected_node_border
+        } else if has_border && fill.color != Color::NONE {
+            theme.node_border
+        } else {
+            Color::NONE
+        };
+    }
+    for mut stroke in arrows.iter_mut() {
+        stroke.color = theme.arrow;
+    }
+    for (mut drawing, mut stroke) in drawings.iter_mut() {
+        if let Some(color) = NodeColor::parse(&drawing.drawing_color.0).resolve(&theme) {
+            drawing.drawing_color.1 = color;
+        }
+        stroke.color = if ui_state.entity_to_draw_selected == Some(drawing.id) {
+            theme.drawing_selected
+        } else {
+            drawing.drawing_color.1
+        };
+    }
+    for mut camera in cameras.iter_mut() {
+        camera.clear_color =
+            ClearColorConfig::Custom(theme.canvas_bg_color.unwrap_or(Color::WHITE.with_a(0.1)));
+    }
+
+    let cosmic_color_map: Vec<_> = color_map
+        .iter()
+        .map(|(old, new)| (bevy_color_to_cosmic(*old), bevy_color_to_cosmic(*new)))
+        .collect();
+    for (entity, mut cosmic_edit, raw_text) in editors.iter_mut() {
+        let cosmic_edit = cosmic_edit.as_mut();
+        if let Some(color) = cosmic_edit.attrs.color_opt {
+            cosmic_edit.attrs.color_opt = cosmic_color_map
+                .iter()
+                .find(|(old, _)| *old == color)
+                .map(|(_, new)| *new)
+                .or(Some(color));
+        }
+        if cosmic_edit.attrs.family_owned == FamilyOwned::Name(old_theme.font_name.clone()) {
+            cosmic_edit.attrs.family_owned = FamilyOwned::Name(theme.font_name.clone());
+        }
+        // notes stay transparent over their shape, its fill is resolved by name above
+        if raw_text.is_none() {
+            cosmic_edit.bg = remap_color(&color_map, cosmic_edit.bg);
+        }
+        let text = match raw_text {
+            Some(raw_text) if cosmic_edit.readonly => {
+                let markdown_lines =
+                    get_markdown_lines(&theme, &raw_text.last_text, cosmic_edit.attrs.clone());
+                commands.entity(entity).insert(BevyMarkdownView {
+                    id: raw_text.id,
+                    span_metadata: markdown_lines.span_metadata,
+                });
+                CosmicText::MultiStyle(markdown_lines.lines)
+            }
+            _ => CosmicText::OneStyle(get_cosmic_text(cosmic_edit.editor.buffer())),
+        };
+        let font_system = cosmic_fonts.get_mut(&cosmic_edit.font_system).unwrap();
+        cosmic_edit_set_text(
+            text,
+            cosmic_edit.attrs.clone(),
+            &mut cosmic_edit.editor,
+            &mut font_system.0,
+        );
+        cosmic_edit.editor.buffer_mut().set_redraw(true);
+    }
+}
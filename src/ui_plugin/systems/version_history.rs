@@ -0,0 +1,738 @@
+use std::collections::BTreeMap;
+
+use bevy::{prelude::*, window::PrimaryWindow};
+use bevy_cosmic_edit::CosmicFont;
+use bevy_pkv::PkvStore;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use velo_collab::ElementKind;
+
+use super::ui_helpers::{spawn_modal, HistoryButton, HistoryPanel, MainPanel, VersionHistory};
+use super::{active_tab, format_share_age, push_checkpoint, spawn_panel_button};
+use super::{DiffOverlay, ModalAction, NotificationChannels, SaveStore, TabDiff, TabSnapshot};
+use crate::components::Doc;
+use crate::resources::{AppState, FontSystemState, LoadTabRequest};
+use crate::themes::Theme;
+use crate::utils::{get_timestamp, ReflectableUuid};
+use crate::UiState;
+
+const HISTORY_TASK: &str = "history";
+/// Prefix of the store keys of version histories, followed by the tab id.
+const HISTORY_KEY: &str = "history";
+/// Versions listed in the history panel at once.
+const HISTORY_ROWS: usize = 10;
+const DAY_MS: f64 = 86_400_000.;
+
+/// `[history]` section of `.velo.toml`.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct HistoryConfig {
+    /// Unnamed versions kept per tab besides the newest one
+    pub max_versions: usize,
+    /// Unnamed versions older than this are dropped, 0 keeps them regardless of age
+    pub max_age_days: u32,
+    /// Named versions are kept regardless of the limits above
+    pub keep_named: bool,
+    /// Saves closer together than this replace the previous unnamed version
+    pub min_interval_secs: u64,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        HistoryConfig {
+            max_versions: 200,
+            max_age_days: 90,
+            keep_named: true,
+            min_interval_secs: 60,
+        }
+    }
+}
+
+/// Changes turning one snapshot into another, only changed fields of elements are kept.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct SnapshotDelta {
+    /// Elements removed or replaced as a whole, applied before `set`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    removed: Vec<String>,
+    /// Changed fields of elements, all fields of added ones
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    set: BTreeMap<String, (ElementKind, Map<String, Value>)>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    removed_images: Vec<String>,
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    images: Map<String, Value>,
+}
+
+impl SnapshotDelta {
+    pub fn between(from: &TabSnapshot, to: &TabSnapshot) -> Self {
+        let mut delta = SnapshotDelta::default();
+        for (id, (kind, fields)) in &to.elements {
+            match from.elements.get(id) {
+                Some((old_kind, old_fields))
+                    if old_kind == kind && old_fields.keys().all(|f| fields.contains_key(f)) =>
+                {
+                    let changed: Map<String, Value> = fields
+                        .iter()
+                        .filter(|(field, value)| old_fields.get(*field) != Some(*value))
+                        .map(|(field, value)| (field.clone(), value.clone()))
+                        .collect();
+                    if !changed.is_empty() {
+                        delta.set.insert(id.clone(), (*kind, changed));
+                    }
+                }
+                old => {
+                    if old.is_some() {
+                        delta.removed.push(id.clone());
+                    }
+                    delta.set.insert(id.clone(), (*kind, fields.clone()));
+                }
+            }
+        }
+        delta.removed.extend(
+            from.elements
+                .keys()
+                .filter(|id| !to.elements.contains_key(*id))
+                .cloned(),
+        );
+        for (id, image) in &to.images {
+            if from.images.get(id) != Some(image) {
+                delta.images.insert(id.clone(), image.clone());
+            }
+        }
+        delta.removed_images = from
+            .images
+            .keys()
+            .filter(|id| !to.images.contains_key(*id))
+            .cloned()
+            .collect();
+        delta
+    }
+
+    /// Delta applying `self` and then `next`, so versions between two others can be dropped
+    /// without rebuilding their snapshots.
+    fn followed_by(mut self, next: &SnapshotDelta) -> Self {
+        for id in &next.removed {
+            self.set.remove(id);
+            if !self.removed.contains(id) {
+                self.removed.push(id.clone());
+            }
+        }
+        for (id, (kind, fields)) in &next.set {
+            let (_, element) = self.set.entry(id.clone()).or_insert((*kind, Map::new()));
+            element.extend(fields.clone());
+        }
+        for id in &next.removed_images {
+            self.images.remove(id);
+            if !self.removed_images.contains(id) {
+                self.removed_images.push(id.clone());
+            }
+        }
+        self.images.extend(next.images.clone());
+        self
+    }
+
+    pub fn apply(&self, snapshot: &mut TabSnapshot) {
+        for id in &self.removed {
+            snapshot.elements.remove(id);
+        }
+        for (id, (kind, fields)) in &self.set {
+            let (_, element) = snapshot
+                .elements
+                .entry(id.clone())
+                .or_insert((*kind, Map::new()));
+            element.extend(fields.clone());
+        }
+        for id in &self.removed_images {
+            snapshot.images.remove(id);
+        }
+        snapshot.images.extend(self.images.clone());
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct HistoryVersion {
+    /// Milliseconds since unix epoch
+    pub timestamp: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Turns the next version back into this one, empty for the newest
+    #[serde(default)]
+    undo: SnapshotDelta,
+}
+
+/// Versions of a tab stored as its newest checkpoint and deltas back to the older ones.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct TabHistory {
+    /// Checkpoint of the newest version
+    latest: String,
+    /// Oldest first
+    pub versions: Vec<HistoryVersion>,
+}
+
+impl TabHistory {
+    pub fn latest_snapshot(&self) -> TabSnapshot {
+        TabSnapshot::parse(&self.latest).unwrap_or_default()
+    }
+
+    pub fn snapshot(&self, index: usize) -> Option<TabSnapshot> {
+        if index >= self.versions.len() {
+            return None;
+        }
+        let mut snapshot = self.latest_snapshot();
+        for version in self.versions[index..].iter().rev().skip(1) {
+            version.undo.apply(&mut snapshot);
+        }
+        Some(snapshot)
+    }
+
+    /// Adds the checkpoint as the newest version, or replaces the newest one if it is unnamed
+    /// and was recorded less than `min_interval_secs` ago. Returns whether anything changed.
+    pub fn record(&mut self, json: &str, now: f64, config: &HistoryConfig) -> bool {
+        let Some(new) = TabSnapshot::parse(json) else {
+            return false;
+        };
+        let count = self.versions.len();
+        let recent = match self.versions.last() {
+            Some(last) => {
+                last.name.is_none()
+                    && now - last.timestamp < config.min_interval_secs as f64 * 1000.
+            }
+            None => false,
+        };
+        if !recent || self.latest_snapshot() == new {
+            return self.push(json, now, None, config);
+        }
+        if count > 1 {
+            let mut previous = self.latest_snapshot();
+            self.versions[count - 2].undo.apply(&mut previous);
+            self.versions[count - 2].undo = SnapshotDelta::between(&new, &previous);
+        }
+        self.versions[count - 1].timestamp = now;
+        self.latest = json.to_string();
+        self.prune(now, config);
+        true
+    }
+
+    /// Adds the checkpoint as the newest version unless it has not changed.
+    pub fn push(
+        &mut self,
+        json: &str,
+        now: f64,
+        name: Option<String>,
+        config: &HistoryConfig,
+    ) -> bool {
+        let Some(new) = TabSnapshot::parse(json) else {
+            return false;
+        };
+        if let Some(last) = self.versions.last_mut() {
+            let latest = TabSnapshot::parse(&self.latest).unwrap_or_default();
+            if latest == new {
+                return false;
+            }
+            last.undo = SnapshotDelta::between(&new, &latest);
+        }
+        self.versions.push(HistoryVersion {
+            timestamp: now,
+            name,
+            undo: SnapshotDelta::default(),
+        });
+        self.latest = json.to_string();
+        self.prune(now, config);
+        true
+    }
+
+    /// Drops the oldest unnamed versions over the configured count or age, the newest is always
+    /// kept. Undo deltas around a dropped version are merged, older snapshots are never rebuilt.
+    fn prune(&mut self, now: f64, config: &HistoryConfig) {
+        let protected = |version: &HistoryVersion| config.keep_named && version.name.is_some();
+        let newest = self.versions.len().saturating_sub(1);
+        let mut excess = self.versions[..newest]
+            .iter()
+            .filter(|version| !protected(version))
+            .count()
+            .saturating_sub(config.max_versions);
+        let mut index = 0;
+        // versions are oldest first, pruning stops at the first one within the limits
+        while index + 1 < self.versions.len() {
+            let version = &self.versions[index];
+            if protected(version) {
+                index += 1;
+                continue;
+            }
+            let expired = config.max_age_days > 0
+                && now - version.timestamp > config.max_age_days as f64 * DAY_MS;
+            if excess == 0 && !expired {
+                break;
+            }
+            excess = excess.saturating_sub(1);
+            let removed = self.versions.remove(index);
+            if let Some(previous) = index.checked_sub(1) {
+                let undo = std::mem::take(&mut self.versions[previous].undo);
+                self.versions[previous].undo = removed.undo.followed_by(&undo);
+            }
+        }
+    }
+}
+
+fn history_key(tab_id: ReflectableUuid) -> String {
+    format!("{}/{}", HISTORY_KEY, tab_id.0)
+}
+
+/// Versions of a tab kept in the store.
+pub fn load_history(pkv: &PkvStore, tab_id: ReflectableUuid) -> TabHistory {
+    pkv.get(history_key(tab_id)).unwrap_or_default()
+}
+
+fn save_history(pkv: &mut PkvStore, tab_id: ReflectableUuid, history: &TabHistory) {
+    if let Err(e) = pkv.set(history_key(tab_id), history) {
+        error!("Failed to save version history: {}", e);
+    }
+}
+
+/// Records the latest checkpoint of every tab of the document except the one which history is browsed.
+pub fn record_history(
+    pkv: &mut PkvStore,
+    doc: &Doc,
+    config: &HistoryConfig,
+    skip_tab: Option<ReflectableUuid>,
+) {
+    let now = get_timestamp();
+    for tab in doc.tabs.iter().filter(|tab| Some(tab.id) != skip_tab) {
+        if let Some(json) = tab.checkpoints.back() {
+            let mut history = load_history(pkv, tab.id);
+            if history.record(json, now, config) {
+                save_history(pkv, tab.id, &history);
+            }
+        }
+    }
+}
+
+/// Empties the histories of the tabs, the store can't remove single keys.
+pub fn remove_history(pkv: &mut PkvStore, tab_ids: impl IntoIterator<Item = ReflectableUuid>) {
+    for tab_id in tab_ids {
+        if pkv.get::<TabHistory>(history_key(tab_id)).is_ok() {
+            save_history(pkv, tab_id, &TabHistory::default());
+        }
+    }
+}
+
+/// History of a tab shown in the history panel.
+#[derive(Resource)]
+pub struct HistoryBrowser {
+    pub doc_id: ReflectableUuid,
+    pub tab_id: ReflectableUuid,
+    pub tab_name: String,
+    pub history: TabHistory,
+    /// Older version shown on the canvas instead of the newest one
+    pub preview: Option<usize>,
+    /// Newest versions scrolled past in the list
+    pub offset: usize,
+}
+
+impl HistoryBrowser {
+    /// Version the name and restore buttons apply to.
+    fn selected(&self) -> usize {
+        self.preview
+            .unwrap_or(self.history.versions.len().saturating_sub(1))
+    }
+}
+
+/// Names the selected version, empty text removes the name.
+pub fn name_version(pkv: &mut PkvStore, browser: Option<&mut HistoryBrowser>, text: &str) {
+    let Some(browser) = browser else {
+        return;
+    };
+    let Some(timestamp) = browser
+        .history
+        .versions
+        .get(browser.selected())
+        .map(|version| version.timestamp)
+    else {
+        return;
+    };
+    let name = Some(text.trim().to_string()).filter(|name| !name.is_empty());
+    let mut history = load_history(pkv, browser.tab_id);
+    if let Some(version) = history
+        .versions
+        .iter_mut()
+        .find(|version| version.timestamp == timestamp)
+    {
+        version.name = name;
+        save_history(pkv, browser.tab_id, &history);
+        browser.history = history;
+    }
+}
+
+/// Opens the history of the active tab, its current state is recorded first.
+pub fn version_history_handler(
+    mut commands: Commands,
+    query: Query<&Interaction, (Changed<Interaction>, With<VersionHistory>)>,
+    app_state: Res<AppState>,
+    mut pkv: ResMut<PkvStore>,
+) {
+    for interaction in query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let Some((doc_id, tab)) = active_tab(&app_state) else {
+            continue;
+        };
+        let mut history = load_history(&pkv, tab.id);
+        let recorded = tab
+            .checkpoints
+            .back()
+            .is_some_and(|json| history.record(json, get_timestamp(), &app_state.history));
+        if recorded {
+            save_history(&mut pkv, tab.id, &history);
+        }
+        commands.insert_resource(HistoryBrowser {
+            doc_id,
+            tab_id: tab.id,
+            tab_name: tab.name.clone(),
+            history,
+            preview: None,
+            offset: 0,
+        });
+        commands.remove_resource::<DiffOverlay>();
+    }
+}
+
+/// Shows the snapshot on the canvas as the latest checkpoint of the browsed tab.
+fn show_snapshot(
+    commands: &mut Commands,
+    app_state: &mut AppState,
+    browser: &HistoryBrowser,
+    snapshot: &TabSnapshot,
+) -> bool {
+    let Some(tab) = app_state
+        .docs
+        .get_mut(&browser.doc_id)
+        .and_then(|doc| doc.tabs.iter_mut().find(|tab| tab.id == browser.tab_id))
+    else {
+        return false;
+    };
+    push_checkpoint(tab, snapshot);
+    commands.insert_resource(LoadTabRequest {
+        doc_id: browser.doc_id,
+        tab_id: browser.tab_id,
+        drop_last_checkpoint: false,
+    });
+    true
+}
+
+pub fn history_button_handler(
+    mut commands: Commands,
+    interaction_query: Query<(&Interaction, &HistoryButton), Changed<Interaction>>,
+    mut app_state: ResMut<AppState>,
+    browser: Option<ResMut<HistoryBrowser>>,
+    mut pkv: ResMut<PkvStore>,
+    notifications: Res<NotificationChannels>,
+    mut events: EventWriter<SaveStore>,
+    (mut ui_state, main_panel_query, windows, mut cosmic_fonts, font_system_state, theme): (
+        ResMut<UiState>,
+        Query<Entity, With<MainPanel>>,
+        Query<&Window, With<PrimaryWindow>>,
+        ResMut<Assets<CosmicFont>>,
+        Res<FontSystemState>,
+        Res<Theme>,
+    ),
+) {
+    let Some(mut browser) = browser else {
+        return;
+    };
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let newest = browser.history.versions.len().saturating_sub(1);
+        match button {
+            HistoryButton::Preview(index) => {
+                let Some(snapshot) = browser.history.snapshot(*index) else {
+                    continue;
+                };
+                if !show_snapshot(&mut commands, &mut app_state, &browser, &snapshot) {
+                    commands.remove_resource::<HistoryBrowser>();
+                    continue;
+                }
+                if *index == newest {
+                    browser.preview = None;
+                    commands.remove_resource::<DiffOverlay>();
+                } else {
+                    browser.preview = Some(*index);
+                    // highlights what restoring the version changes
+                    let mut overlay = DiffOverlay {
+                        doc_id: browser.doc_id,
+                        ..default()
+                    };
+                    overlay.tabs.insert(
+                        browser.tab_id,
+                        TabDiff::new(browser.history.latest_snapshot(), snapshot),
+                    );
+                    commands.insert_resource(overlay);
+                }
+            }
+            HistoryButton::Newer => {
+                browser.offset = browser.offset.saturating_sub(HISTORY_ROWS);
+            }
+            HistoryButton::Older => {
+                browser.offset += HISTORY_ROWS;
+            }
+            HistoryButton::Name => {
+                let name = browser
+                    .history
+                    .versions
+                    .get(browser.selected())
+                    .and_then(|version| version.name.clone())
+                    .unwrap_or_default();
+                let id = ReflectableUuid::generate();
+                *ui_state = UiState::default();
+                commands.insert_resource(bevy_cosmic_edit::ActiveEditor { entity: None });
+                ui_state.modal_id = Some(id);
+                let entity = spawn_modal(
+                    &mut commands,
+                    &theme,
+                    &mut cosmic_fonts,
+                    font_system_state.0.clone().unwrap(),
+                    windows.single(),
+                    id,
+                    ModalAction::NameVersion(name),
+                );
+                commands.entity(main_panel_query.single()).add_child(entity);
+            }
+            HistoryButton::Restore => {
+                let Some(index) = browser.preview else {
+                    continue;
+                };
+                let Some(snapshot) = browser.history.snapshot(index) else {
+                    continue;
+                };
+                // the restored version becomes the newest one, the versions after it are kept
+                let mut history = load_history(&pkv, browser.tab_id);
+                history.push(
+                    &snapshot.to_json(),
+                    get_timestamp(),
+                    None,
+                    &app_state.history,
+                );
+                save_history(&mut pkv, browser.tab_id, &history);
+                let age =
+                    format_share_age(get_timestamp(), browser.history.versions[index].timestamp);
+                notifications
+                    .notifier
+                    .success(HISTORY_TASK, format!("Restored the version from {}", age));
+                // saves are held back only while a version is previewed
+                browser.preview = None;
+                events.send(SaveStore {
+                    doc_id: browser.doc_id,
+                    path: None,
+                });
+                commands.remove_resource::<HistoryBrowser>();
+                commands.remove_resource::<DiffOverlay>();
+            }
+            HistoryButton::Close => {
+                if browser.preview.is_some() {
+                    let latest = browser.history.latest_snapshot();
+                    show_snapshot(&mut commands, &mut app_state, &browser, &latest);
+                }
+                commands.remove_resource::<HistoryBrowser>();
+                commands.remove_resource::<DiffOverlay>();
+            }
+        }
+    }
+}
+
+/// Lists versions of the browsed tab newest first, hidden while no history is browsed.
+pub fn update_history_panel(
+    mut commands: Commands,
+    browser: Option<Res<HistoryBrowser>>,
+    theme: Res<Theme>,
+    mut panel_query: Query<(Entity, &mut Style), With<HistoryPanel>>,
+) {
+    let Ok((panel, mut style)) = panel_query.get_single_mut() else {
+        return;
+    };
+    let Some(browser) = browser else {
+        if style.display != Display::None {
+            style.display = Display::None;
+            commands.entity(panel).despawn_descendants();
+        }
+        return;
+    };
+    if !browser.is_changed() {
+        return;
+    }
+    style.display = Display::Flex;
+    commands.entity(panel).despawn_descendants();
+    let text_style = TextStyle {
+        font_size: 14.,
+        color: theme.font,
+        ..default()
+    };
+    let now = get_timestamp();
+    let count = browser.history.versions.len();
+    let row = Style {
+        align_items: AlignItems::Center,
+        justify_content: JustifyContent::SpaceBetween,
+        margin: UiRect::top(Val::Px(5.)),
+        ..default()
+    };
+    commands.entity(panel).with_children(|builder| {
+        builder.spawn(TextBundle::from_section(
+            format!("History of \"{}\"", browser.tab_name),
+            TextStyle {
+                font_size: 16.,
+                ..text_style.clone()
+            },
+        ));
+        for index in (0..count).rev().skip(browser.offset).take(HISTORY_ROWS) {
+            let version = &browser.history.versions[index];
+            let mut label = if index + 1 == count {
+                "current".to_string()
+            } else {
+                format_share_age(now, version.timestamp)
+            };
+            if let Some(name) = &version.name {
+                label = format!("{}: {}", label, name);
+            }
+            builder
+                .spawn(NodeBundle {
+                    style: row.clone(),
+                    ..default()
+                })
+                .with_children(|builder| {
+                    builder.spawn(TextBundle::from_section(label, text_style.clone()));
+                    spawn_panel_button(
+                        builder,
+                        &theme,
+                        "Show",
+                        HistoryButton::Preview(index),
+                        browser.selected() == index,
+                    );
+                });
+        }
+        builder
+            .spawn(NodeBundle {
+                style: Style {
+                    justify_content: JustifyContent::End,
+                    margin: UiRect::top(Val::Px(8.)),
+                    ..default()
+                },
+                ..default()
+            })
+            .with_children(|builder| {
+                if browser.offset > 0 {
+                    spawn_panel_button(builder, &theme, "Newer", HistoryButton::Newer, false);
+                }
+                if browser.offset + HISTORY_ROWS < count {
+                    spawn_panel_button(builder, &theme, "Older", HistoryButton::Older, false);
+                }
+                spawn_panel_button(builder, &theme, "Name", HistoryButton::Name, false);
+                if browser.preview.is_some() {
+                    spawn_panel_button(builder, &theme, "Restore", HistoryButton::Restore, true);
+                }
+                spawn_panel_button(builder, &theme, "Close", HistoryButton::Close, false);
+            });
+    });
+}
+
+pub fn is_browsing_history(browser: Option<Res<HistoryBrowser>>) -> bool {
+    browser.is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn checkpoint(texts: &[&str]) -> String {
+        let nodes: Vec<Value> = texts
+            .iter()
+            .enumerate()
+            .map(|(i, text)| json!({"id": format!("n{}", i), "x": i, "text": {"text": text}}))
+            .collect();
+        json!({"images": {}, "nodes": nodes, "arrows": [], "drawings": []}).to_string()
+    }
+
+    #[test]
+    fn test_tab_history() {
+        let config = HistoryConfig {
+            max_versions: 2,
+            max_age_days: 0,
+            keep_named: true,
+            min_interval_secs: 60,
+        };
+        let versions = [
+            checkpoint(&["a"]),
+            checkpoint(&["a", "b"]),
+            checkpoint(&["c", "b"]),
+            checkpoint(&["c"]),
+        ];
+        let mut history = TabHistory::default();
+        assert!(history.record(&versions[0], 0., &config));
+        assert!(!history.record(&versions[0], 100_000., &config));
+        history.versions[0].name = Some("before retro".to_string());
+        for (i, json) in versions.iter().enumerate().skip(1) {
+            assert!(history.record(json, i as f64 * 100_000., &config));
+        }
+        let snapshots: Vec<TabSnapshot> = (0..history.versions.len())
+            .map(|i| history.snapshot(i).unwrap())
+            .collect();
+        assert_eq!(
+            snapshots,
+            versions
+                .iter()
+                .map(|v| TabSnapshot::parse(v).unwrap())
+                .collect::<Vec<_>>()
+        );
+
+        // saves within a minute replace the newest version
+        assert!(history.record(&checkpoint(&["d"]), 310_000., &config));
+        assert_eq!(history.versions.len(), 4);
+        assert_eq!(history.snapshot(2), TabSnapshot::parse(&versions[2]));
+
+        // the oldest unnamed version is dropped, the named one is kept
+        assert!(history.record(&checkpoint(&["e"]), 500_000., &config));
+        assert_eq!(
+            history
+                .versions
+                .iter()
+                .map(|v| v.timestamp)
+                .collect::<Vec<_>>(),
+            vec![0., 200_000., 310_000., 500_000.]
+        );
+        assert_eq!(history.versions[0].name.as_deref(), Some("before retro"));
+        assert_eq!(history.snapshot(0), TabSnapshot::parse(&versions[0]));
+        assert_eq!(history.snapshot(1), TabSnapshot::parse(&versions[2]));
+        assert_eq!(history.snapshot(2), TabSnapshot::parse(&checkpoint(&["d"])));
+    }
+
+    #[test]
+    fn test_history_store() {
+        PkvStore::new("test", "version_history").clear().unwrap();
+        let mut pkv = PkvStore::new("test", "version_history");
+        let tab = |text: &str| crate::components::Tab {
+            id: ReflectableUuid::generate(),
+            name: text.to_string(),
+            checkpoints: [checkpoint(&[text])].into(),
+            is_active: false,
+            z_index: 1.,
+            slides: vec![],
+        };
+        let doc = Doc {
+            tabs: vec![tab("a"), tab("b")],
+            ..Default::default()
+        };
+        let config = HistoryConfig::default();
+        record_history(&mut pkv, &doc, &config, Some(doc.tabs[1].id));
+        assert_eq!(load_history(&pkv, doc.tabs[0].id).versions.len(), 1);
+        assert!(load_history(&pkv, doc.tabs[1].id).versions.is_empty());
+
+        // each tab has its own entry in the store
+        record_history(&mut pkv, &doc, &config, None);
+        let history = load_history(&pkv, doc.tabs[1].id);
+        assert_eq!(history.snapshot(0), TabSnapshot::parse(&checkpoint(&["b"])));
+        remove_history(&mut pkv, [doc.tabs[0].id]);
+        assert!(load_history(&pkv, doc.tabs[0].id).versions.is_empty());
+        assert_eq!(load_history(&pkv, doc.tabs[1].id).versions.len(), 1);
+    }
+}
@@ -0,0 +1,53 @@
+use bevy::prelude::*;
+
+/// Command line flag enabling viewer mode on native builds.
+#[cfg(not(target_arch = "wasm32"))]
+pub const VIEWER_FLAG: &str = "--viewer";
+
+/// Present when documents are opened read-only, editing systems don't run.
+#[derive(Resource)]
+pub struct ViewerMode;
+
+/// `?viewer`, `?viewer=1` or `?viewer=true` in the page URL enables viewer mode.
+#[cfg(any(target_arch = "wasm32", test))]
+pub fn is_viewer_query(query: &str) -> bool {
+    query
+        .trim_start_matches('?')
+        .split('&')
+        .map(|pair| pair.split_once('=').unwrap_or((pair, "")))
+        .any(|(name, value)| name == "viewer" && matches!(value, "" | "1" | "true"))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn viewer_mode_requested() -> bool {
+    std::env::args().any(|arg| arg == VIEWER_FLAG)
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn viewer_mode_requested() -> bool {
+    web_sys::window()
+        .and_then(|window| window.location().search().ok())
+        .map_or(false, |query| is_viewer_query(&query))
+}
+
+/// Run condition for systems that modify documents.
+pub fn editing_enabled(viewer_mode: Option<Res<ViewerMode>>) -> bool {
+    viewer_mode.is_none()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_viewer_query() {
+        assert!(is_viewer_query("?viewer"));
+        assert!(is_viewer_query(
+            "?document=https://example.com/velo.json&viewer=1"
+        ));
+        assert!(is_viewer_query("viewer=true"));
+        assert!(!is_viewer_query("?viewer=0"));
+        assert!(!is_viewer_query("?document=https://example.com/viewer"));
+        assert!(!is_viewer_query(""));
+    }
+}
@@ -10,7 +10,7 @@ use crate::{
     utils::{bevy_color_to_cosmic, ReflectableUuid},
 };
 
-use super::{DeleteTab, EditableText, GenericButton, TabButton, TabContainer};
+use super::{DeleteTab, EditableText, GenericButton, TabButton, TabContainer, TabTaskCount};
 
 pub fn add_tab(
     commands: &mut Commands,
@@ -120,7 +120,22 @@ pub fn add_tab(
         ))
         .id();
     commands.entity(del_button).add_child(del_label);
+    let task_count = commands
+        .spawn((
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font_size: 12.,
+                    color: theme.font,
+                    ..default()
+                },
+            ),
+            Label,
+            TabTaskCount { id },
+        ))
+        .id();
     commands.entity(root).add_child(cosmic_edit);
+    commands.entity(root).add_child(task_count);
     commands.entity(root).add_child(del_button);
     root
 }
@@ -1,6 +1,7 @@
 use crate::{ui_plugin::NodeType, utils::ReflectableUuid};
 use bevy::prelude::*;
 use bevy_markdown::TextSpanMetadata;
+use std::collections::BTreeMap;
 
 use crate::TextPos;
 
@@ -64,12 +65,75 @@ pub struct ImportFromFile;
 #[derive(Component, Clone)]
 pub struct ImportFromUrl;
 
+#[derive(Component, Clone)]
+pub struct RebuildSearchIndex;
+
+#[derive(Component, Clone)]
+pub struct EditDocTags;
+
+#[derive(Component, Clone)]
+pub struct PinDoc;
+
+#[derive(Component, Clone)]
+pub struct MoveDocToFolder;
+
+#[derive(Component)]
+pub struct DocSortButton;
+
+/// Doc list entry which is not a document item: section header, folder or recent document.
+#[derive(Component)]
+pub struct DocListSection;
+
+#[derive(Component)]
+pub struct DocListFolder {
+    pub folder: String,
+}
+
+#[derive(Component)]
+pub struct RecentDocButton {
+    pub id: ReflectableUuid,
+}
+
+#[derive(Component)]
+pub struct TagFacetList;
+
+#[derive(Component)]
+pub struct TagFacet {
+    pub tag: String,
+}
+
 #[derive(Component, Clone)]
 pub struct ShareDoc;
 
+#[derive(Component, Clone)]
+pub struct RevokeShare;
+
+#[derive(Component, Clone)]
+pub struct AddSlide;
+
+#[derive(Component, Clone)]
+pub struct ClearSlides;
+
+#[derive(Component, Clone)]
+pub struct StartPresentation;
+
+#[derive(Component, Clone)]
+pub struct CompareVersions;
+
+#[derive(Component, Clone)]
+pub struct VersionHistory;
+
 #[derive(Component, Clone)]
 pub struct ChangeTheme;
 
+#[derive(Component)]
+pub struct ThemePicker;
+
+#[derive(Component)]
+pub struct ThemeButton {
+    pub name: String,
+}
+
 #[derive(Component)]
 pub struct DeleteDoc {
     pub id: ReflectableUuid,
@@ -84,6 +148,12 @@ pub struct TabContainer {
     pub id: ReflectableUuid,
 }
 
+/// Checked and all task list items in the notes of the tab.
+#[derive(Component)]
+pub struct TabTaskCount {
+    pub id: ReflectableUuid,
+}
+
 #[derive(Component)]
 pub struct SearchButton {
     pub id: ReflectableUuid,
@@ -114,6 +184,9 @@ pub struct ChangeColor {
     pub pair_color: (String, Color),
 }
 
+#[derive(Component)]
+pub struct PickColor;
+
 #[derive(Component)]
 pub struct TextPosMode {
     pub text_pos: TextPos,
@@ -146,6 +219,83 @@ pub struct VeloNode {
     pub id: ReflectableUuid,
 }
 
+/// Tags and key/value fields (owner, status, priority, due date...) of a node.
+/// Stored next to `VeloShape`.
+#[derive(Component, Clone, Default, Debug, PartialEq, Eq)]
+pub struct NodeMetadata {
+    pub tags: Vec<String>,
+    pub fields: BTreeMap<String, String>,
+}
+
+#[derive(Component)]
+pub struct NodeBadge;
+
+/// Link suggestions for the edited note or backlinks of the selected one.
+#[derive(Component)]
+pub struct LinksPanel;
+
+/// Container of notification toasts.
+#[derive(Component)]
+pub struct ToastArea;
+
+/// Conflicts of the document merge, filled by `update_merge_panel`.
+#[derive(Component)]
+pub struct MergePanel;
+
+#[derive(Component)]
+pub enum MergeButton {
+    /// Picks a side for the conflict at the index
+    Choose(usize, crate::ui_plugin::MergeSide),
+    Apply,
+    Cancel,
+}
+
+/// Versions of the browsed tab, filled by `update_history_panel`.
+#[derive(Component)]
+pub struct HistoryPanel;
+
+#[derive(Component)]
+pub enum HistoryButton {
+    /// Shows the version at the index on the canvas
+    Preview(usize),
+    Newer,
+    Older,
+    Name,
+    Restore,
+    Close,
+}
+
+/// Highlight of a changed note drawn by `draw_diff_overlay`.
+#[derive(Component)]
+pub struct DiffMark;
+
+#[derive(Component)]
+pub struct Toast {
+    /// Position in the toast queue
+    pub index: usize,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum LinkPanelAction {
+    /// Replaces the typed part of the wiki link with the suggested one
+    Complete {
+        typed: String,
+        link: String,
+    },
+    Open(String),
+}
+
+#[derive(Component)]
+pub struct LinkPanelItem {
+    pub action: LinkPanelAction,
+}
+
+/// Node or arrow hidden by the metadata filter.
+#[derive(Component)]
+pub struct FilteredOut {
+    pub was_visible: bool,
+}
+
 #[derive(PartialEq, Eq, Clone)]
 pub enum ButtonTypes {
     AddRec,
@@ -158,6 +308,11 @@ pub enum ButtonTypes {
     ShowChildren,
     HideChildren,
     ShowRandom,
+    EditNodeMetadata,
+    FilterNodes,
+    EditTextStyle,
+    AutoSize,
+    ShrinkToFit,
 }
 #[derive(Component, Clone)]
 pub struct ButtonAction {
@@ -204,6 +359,14 @@ pub enum ModalAction {
     LoadFromUrl,
     DeleteDocument,
     DeleteTab,
+    EditTags(Vec<String>),
+    EditNodeMetadata(ReflectableUuid, String),
+    FilterNodes(String),
+    MoveToFolder(String),
+    PickColor(Option<ReflectableUuid>, String),
+    EditTextStyle(ReflectableUuid, String),
+    CompareVersions(String),
+    NameVersion(String),
 }
 
 impl std::fmt::Display for ModalAction {
@@ -214,6 +377,23 @@ impl std::fmt::Display for ModalAction {
             ModalAction::LoadFromFile => write!(f, "Load from file:"),
             ModalAction::LoadFromUrl => write!(f, "Load from URL:"),
             ModalAction::SaveToFile => write!(f, "Save to file:"),
+            ModalAction::EditTags(_) => write!(f, "Tags (comma separated):"),
+            ModalAction::EditNodeMetadata(..) => {
+                write!(f, "Note metadata (tags: a, b; status: done):")
+            }
+            ModalAction::FilterNodes(_) => write!(f, "Show notes matching (empty shows all):"),
+            ModalAction::MoveToFolder(_) => write!(f, "Folder (empty for none):"),
+            ModalAction::PickColor(..) => write!(f, "Color (#rrggbb[aa] or theme color):"),
+            ModalAction::CompareVersions(_) => {
+                write!(
+                    f,
+                    "Compare checkpoints (1 is the oldest) or document files:"
+                )
+            }
+            ModalAction::NameVersion(_) => write!(f, "Version name (empty removes it):"),
+            ModalAction::EditTextStyle(..) => {
+                write!(f, "Text style (size: 18; font: Victor Mono; weight: bold; align: right; valign: bottom; color: #ff0000):")
+            }
         }
     }
 }
@@ -232,6 +412,10 @@ pub struct ModalCancel {
 #[derive(Component)]
 pub struct InteractiveNode;
 
+/// Note grows to fit its text.
+#[derive(Component)]
+pub struct AutoSize;
+
 #[derive(Component, Clone)]
 pub struct Drawing<T> {
     pub id: ReflectableUuid,
@@ -31,6 +31,14 @@ pub fn spawn_modal(
         ModalAction::SaveToFile => "./velo.json".to_string(),
         ModalAction::LoadFromFile => "./velo.json".to_string(),
         ModalAction::LoadFromUrl => "https://gist..".to_string(),
+        ModalAction::EditTags(ref tags) => tags.join(", "),
+        ModalAction::EditNodeMetadata(_, ref metadata) => metadata.clone(),
+        ModalAction::FilterNodes(ref filter) => filter.clone(),
+        ModalAction::MoveToFolder(ref folder) => folder.clone(),
+        ModalAction::PickColor(_, ref color) => color.clone(),
+        ModalAction::EditTextStyle(_, ref style) => style.clone(),
+        ModalAction::CompareVersions(ref versions) => versions.clone(),
+        ModalAction::NameVersion(ref name) => name.clone(),
         _ => "".to_string(),
     };
     let top = commands
@@ -140,7 +148,17 @@ pub fn spawn_modal(
     commands.entity(modal_static).add_child(cancel_button);
 
     let modal_dynamic = match modal_action {
-        ModalAction::SaveToFile | ModalAction::LoadFromFile | ModalAction::LoadFromUrl => {
+        ModalAction::SaveToFile
+        | ModalAction::LoadFromFile
+        | ModalAction::LoadFromUrl
+        | ModalAction::EditTags(_)
+        | ModalAction::EditNodeMetadata(..)
+        | ModalAction::FilterNodes(_)
+        | ModalAction::MoveToFolder(_)
+        | ModalAction::PickColor(..)
+        | ModalAction::EditTextStyle(..)
+        | ModalAction::CompareVersions(_)
+        | ModalAction::NameVersion(_) => {
             let top = commands
                 .spawn(NodeBundle {
                     style: Style {
@@ -2,21 +2,26 @@ use bevy_cosmic_edit::{
     spawn_cosmic_edit, ActiveEditor, CosmicEditMeta, CosmicEditSprite, CosmicFont, CosmicMetrics,
     CosmicNode, CosmicText,
 };
-use bevy_markdown::{generate_markdown_lines, BevyMarkdown, BevyMarkdownTheme};
+use bevy_markdown::count_tasks;
 use bevy_prototype_lyon::prelude::{Fill, Path, Stroke};
 
 use bevy::prelude::*;
-use cosmic_text::AttrsOwned;
+use bevy::sprite::Anchor;
 
 use crate::canvas::shadows::systems::spawn_shadow;
 use crate::canvas::shadows::CustomShadowMaterial;
 use crate::themes::Theme;
-use crate::ui_plugin::NodeType;
+use crate::ui_plugin::{
+    node_badge_text, node_text_attrs, node_text_metrics, node_text_pos, NodeTextStyle, NodeType,
+};
 use crate::TextPos;
 
-use super::{BevyMarkdownView, InteractiveNode, RawText, ResizeMarker, VeloNode, VeloShape};
+use super::{
+    AutoSize, BevyMarkdownView, InteractiveNode, NodeBadge, NodeMetadata, RawText, ResizeMarker,
+    VeloNode, VeloShape,
+};
 use crate::canvas::arrow::components::{ArrowConnect, ArrowConnectPos};
-use crate::utils::{bevy_color_to_cosmic, ReflectableUuid};
+use crate::utils::{get_markdown_lines, ReflectableUuid};
 
 #[derive(Clone)]
 pub struct NodeMeta {
@@ -28,8 +33,11 @@ pub struct NodeMeta {
     pub pair_bg_color: (String, Color),
     pub image: Option<Handle<Image>>,
     pub text_pos: TextPos,
+    pub text_style: NodeTextStyle,
+    pub auto_size: bool,
     pub is_active: bool,
     pub visible: bool,
+    pub metadata: NodeMetadata,
 }
 
 pub fn spawn_sprite_node(
@@ -126,25 +134,23 @@ pub fn spawn_sprite_node(
             },
         ))
         .id();
+    let badge = spawn_node_badge(
+        commands,
+        theme,
+        &node_badge_text(&item_meta.metadata, count_tasks(&item_meta.text)),
+        width,
+        height,
+    );
+    commands
+        .entity(shape)
+        .insert(item_meta.metadata)
+        .add_child(badge);
 
-    let mut attrs = cosmic_text::Attrs::new();
-    attrs = attrs.family(cosmic_text::Family::Name(theme.font_name.as_str()));
-    attrs = attrs.color(bevy_color_to_cosmic(theme.font));
+    let attrs = node_text_attrs(theme, &item_meta.text_style);
     let (text, span_metadata) = match item_meta.is_active {
         true => (CosmicText::OneStyle(item_meta.text.clone()), vec![]),
         false => {
-            let markdown_theme = BevyMarkdownTheme {
-                code_theme: theme.code_theme.clone(),
-                code_default_lang: theme.code_default_lang.clone(),
-                link: bevy_color_to_cosmic(theme.link),
-                inline_code: bevy_color_to_cosmic(theme.inline_code),
-            };
-            let markdown_lines = generate_markdown_lines(BevyMarkdown {
-                text: item_meta.text.clone(),
-                attrs: AttrsOwned::new(attrs),
-                markdown_theme,
-            })
-            .expect("should handle markdown convertion");
+            let markdown_lines = get_markdown_lines(theme, &item_meta.text, attrs.clone());
             (
                 CosmicText::MultiStyle(markdown_lines.lines),
                 markdown_lines.span_metadata,
@@ -152,10 +158,11 @@ pub fn spawn_sprite_node(
         }
     };
 
+    let (font_size, line_height) = node_text_metrics(theme, &item_meta.text_style, is_transparent);
     let cosmic_edit_meta = CosmicEditMeta {
         text,
         font_system_handle: cosmic_font_handle,
-        text_pos: item_meta.text_pos.clone().into(),
+        text_pos: node_text_pos(&item_meta.text_pos, &item_meta.text_style),
         size: Some((width, height)),
         node: CosmicNode::Sprite(CosmicEditSprite {
             transform: Transform {
@@ -164,22 +171,14 @@ pub fn spawn_sprite_node(
             },
         }),
         metrics: CosmicMetrics {
-            font_size: if is_transparent {
-                3. * theme.font_size
-            } else {
-                theme.font_size
-            },
-            line_height: if is_transparent {
-                3. * theme.line_height
-            } else {
-                theme.line_height
-            },
+            font_size,
+            line_height,
             scale_factor,
         },
         bg: Color::NONE,
         bg_image: item_meta.image,
         readonly: !item_meta.is_active,
-        attrs: AttrsOwned::new(attrs),
+        attrs,
     };
     let cosmic_edit = spawn_cosmic_edit(commands, cosmic_fonts, cosmic_edit_meta);
     commands
@@ -188,7 +187,11 @@ pub fn spawn_sprite_node(
             id: item_meta.id,
             last_text: item_meta.text.clone(),
         })
+        .insert(item_meta.text_style)
         .insert(InteractiveNode);
+    if item_meta.auto_size {
+        commands.entity(cosmic_edit).insert(AutoSize);
+    }
 
     match item_meta.is_active {
         true => {
@@ -265,6 +268,33 @@ pub fn spawn_sprite_node(
     top
 }
 
+fn spawn_node_badge(
+    commands: &mut Commands,
+    theme: &Res<Theme>,
+    badge_text: &str,
+    width: f32,
+    height: f32,
+) -> Entity {
+    commands
+        .spawn((
+            Text2dBundle {
+                text: Text::from_section(
+                    badge_text,
+                    TextStyle {
+                        font_size: 12.,
+                        color: theme.font,
+                        ..default()
+                    },
+                ),
+                text_anchor: Anchor::BottomLeft,
+                transform: Transform::from_xyz(-width / 2., height / 2. + 2., 0.003),
+                ..default()
+            },
+            NodeBadge,
+        ))
+        .id()
+}
+
 fn spawn_resize_marker(
     commands: &mut Commands,
     theme: &Res<Theme>,
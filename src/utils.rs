@@ -1,10 +1,15 @@
 use bevy::prelude::*;
 
 use bevy_cosmic_edit::CosmicTextPos;
+use bevy_markdown::{
+    generate_markdown_lines, BevyMarkdown, BevyMarkdownLines, BevyMarkdownTheme, TextSpanMetadata,
+};
+use cosmic_text::AttrsOwned;
 use serde::{Deserialize, Serialize};
 
 use crate::resources::AppState;
-use crate::ui_plugin::TextPos;
+use crate::themes::Theme;
+use crate::ui_plugin::{HistoryConfig, ShareConfig, TextPos};
 
 use std::collections::HashMap;
 use std::{fs, path::PathBuf};
@@ -66,9 +71,27 @@ pub fn load_doc_to_memory(
     }
 }
 
+/// Splits comma separated tags, drops empty ones and duplicates keeping the first occurrence.
+pub fn parse_tags(text: &str) -> Vec<String> {
+    dedup_tags(text.split(',').map(|tag| tag.to_string()))
+}
+
+pub fn dedup_tags(tags: impl IntoIterator<Item = String>) -> Vec<String> {
+    let mut result: Vec<String> = vec![];
+    for tag in tags {
+        let tag = tag.trim();
+        if !tag.is_empty() && !result.iter().any(|t| t == tag) {
+            result.push(tag.to_string());
+        }
+    }
+    result
+}
+
 #[derive(Debug, Default)]
 pub struct Config {
     pub github_access_token: Option<String>,
+    pub share: Option<ShareConfig>,
+    pub history: Option<HistoryConfig>,
 }
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -83,6 +106,18 @@ pub fn read_config_file() -> Option<Config> {
             config.github_access_token = Some(token_str.to_owned());
         }
     }
+    if let Some(share) = config_value.get("share") {
+        match share.clone().try_into::<ShareConfig>() {
+            Ok(share) => config.share = Some(share),
+            Err(e) => error!("Invalid [share] section in .velo.toml: {}", e),
+        }
+    }
+    if let Some(history) = config_value.get("history") {
+        match history.clone().try_into::<HistoryConfig>() {
+            Ok(history) => config.history = Some(history),
+            Err(e) => error!("Invalid [history] section in .velo.toml: {}", e),
+        }
+    }
     Some(config)
 }
 
@@ -113,6 +148,31 @@ pub fn bevy_color_to_cosmic(color: bevy::prelude::Color) -> cosmic_text::Color {
     )
 }
 
+/// Renders markdown with code and link colors of the theme, text that can't be rendered is shown as plain text.
+pub fn get_markdown_lines(theme: &Theme, text: &str, attrs: AttrsOwned) -> BevyMarkdownLines {
+    let markdown_theme = BevyMarkdownTheme {
+        code_theme: theme.code_theme.clone(),
+        code_default_lang: theme.code_default_lang.clone(),
+        link: bevy_color_to_cosmic(theme.link),
+        inline_code: bevy_color_to_cosmic(theme.inline_code),
+    };
+    generate_markdown_lines(BevyMarkdown {
+        text: text.to_string(),
+        markdown_theme,
+        attrs: attrs.clone(),
+    })
+    .unwrap_or_else(|errors| {
+        warn!("Failed to render markdown: {:?}", errors);
+        BevyMarkdownLines {
+            lines: text
+                .split('\n')
+                .map(|line| vec![(line.to_string(), attrs.clone())])
+                .collect(),
+            span_metadata: vec![TextSpanMetadata::default()],
+        }
+    })
+}
+
 pub fn get_theme_key(pkv: &PkvStore) -> String {
     if let Ok(user_preferences) = pkv.get::<UserPreferences>("user_preferences") {
         if let Some(theme_name) = user_preferences.theme_name {
@@ -124,6 +184,3 @@ pub fn get_theme_key(pkv: &PkvStore) -> String {
         "light".to_string()
     }
 }
-
-pub static DARK_THEME_ICON_CODE: &str = "\u{e51c}";
-pub static LIGHT_THEME_ICON_CODE: &str = "\u{e518}";